@@ -1,8 +1,13 @@
-use egui_glow::glow::{NativeBuffer, NativeProgram, NativeUniformLocation, NativeVertexArray};
+use egui_glow::glow::{
+    NativeBuffer, NativeProgram, NativeTexture, NativeUniformLocation, NativeVertexArray,
+};
 use ezgl::{gl, gl::HasContext};
 use powdermilk_biscuits::{
     bytemuck,
-    graphics::{PixelPos, StrokePoint},
+    clip::ScissorRect,
+    graphics::{Color, PixelPos, StrokePoint},
+    stroke::LinePrimitive,
+    tess::CapStyle,
     ui::widget::SketchWidget,
     winit::dpi::PhysicalSize,
     CoordinateSystem, Sketch,
@@ -82,6 +87,122 @@ pub fn view_matrix(
     )
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pipeline {
+    Line,
+    Mesh,
+}
+
+/// one step of drawing the strokes. `T` is whatever identifies a stroke
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawCommand<T> {
+    UseProgram(Pipeline),
+    SetView,
+    SetColor(Color),
+    SetCrisp(bool),
+    SetScissor(Option<ScissorRect>),
+    DrawLine(T),
+    DrawPoints(T),
+    DrawMesh(T),
+}
+
+impl<T> DrawCommand<T> {
+    pub fn changes_state(&self) -> bool {
+        matches!(
+            self,
+            DrawCommand::UseProgram(_)
+                | DrawCommand::SetView
+                | DrawCommand::SetColor(_)
+                | DrawCommand::SetCrisp(_)
+                | DrawCommand::SetScissor(_)
+        )
+    }
+}
+
+/// `(id, color, line primitive, draw_tesselated, scissor, crisp)`
+pub type DrawnStroke<T> = (
+    T,
+    Color,
+    Option<LinePrimitive>,
+    bool,
+    Option<ScissorRect>,
+    bool,
+);
+
+/// the commands to draw each stroke's line and then its mesh, in the order the strokes are in.
+/// the program only changes between strokes that need different ones, the view is set the first
+/// time each program is used, and the color, crispness, and scissor are only set when they're
+/// different from what was set last.
+///
+/// strokes are in the order they should be drawn.
+pub fn draw_commands<T: Copy>(strokes: &[DrawnStroke<T>]) -> Vec<DrawCommand<T>> {
+    let mut commands = Vec::new();
+    let mut program = None;
+    let mut scissor = None;
+    // uniforms belong to the program they were set in, so each remembers its own
+    let mut viewed = [false; 2];
+    let mut color = [None; 2];
+    let mut crisp = [None; 2];
+
+    for &(id, stroke_color, line, draw_tesselated, stroke_scissor, stroke_crisp) in strokes {
+        // clipped out entirely
+        if stroke_scissor.map(|rect| rect.width == 0 || rect.height == 0) == Some(true) {
+            continue;
+        }
+
+        let line = match line {
+            Some(LinePrimitive::Strip) => Some(DrawCommand::DrawLine(id)),
+            Some(LinePrimitive::Points) => Some(DrawCommand::DrawPoints(id)),
+            None => None,
+        };
+        let mesh = draw_tesselated.then_some(DrawCommand::DrawMesh(id));
+
+        // the line goes under the stroke's own mesh
+        for (pipeline, draw) in [
+            line.map(|draw| (Pipeline::Line, draw)),
+            mesh.map(|draw| (Pipeline::Mesh, draw)),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let i = pipeline as usize;
+            if program != Some(pipeline) {
+                commands.push(DrawCommand::UseProgram(pipeline));
+                program = Some(pipeline);
+            }
+
+            if !viewed[i] {
+                commands.push(DrawCommand::SetView);
+                viewed[i] = true;
+            }
+
+            if color[i] != Some(stroke_color) {
+                commands.push(DrawCommand::SetColor(stroke_color));
+                color[i] = Some(stroke_color);
+            }
+
+            if crisp[i] != Some(stroke_crisp) {
+                commands.push(DrawCommand::SetCrisp(stroke_crisp));
+                crisp[i] = Some(stroke_crisp);
+            }
+
+            if scissor != stroke_scissor {
+                commands.push(DrawCommand::SetScissor(stroke_scissor));
+                scissor = stroke_scissor;
+            }
+
+            commands.push(draw);
+        }
+    }
+
+    // leave the scissor test off for whatever gets drawn next
+    if scissor.is_some() {
+        commands.push(DrawCommand::SetScissor(None));
+    }
+
+    commands
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct GlPos {
     pub x: f32,
@@ -142,22 +263,42 @@ pub unsafe fn compile_program(
 
 pub struct Renderer {
     msaa_fbo: gl::Framebuffer,
+    /// what `msaa_fbo` draws into, replaced when the window is resized
+    msaa_texture: NativeTexture,
     line_strokes_program: NativeProgram,
     mesh_strokes_program: NativeProgram,
     pen_cursor_program: NativeProgram,
     strokes_view: NativeUniformLocation,
     strokes_color: NativeUniformLocation,
+    strokes_crisp: NativeUniformLocation,
+    strokes_screen_size: NativeUniformLocation,
     pen_cursor_view: NativeUniformLocation,
     pen_cursor_erasing: NativeUniformLocation,
     pen_cursor_pen_down: NativeUniformLocation,
     cursor_vao: NativeVertexArray,
     cursor_buffer: NativeBuffer,
+    /// what the cursor buffer holds and how many vertices that is
+    cursor_cap: CapStyle,
+    cursor_len: i32,
+}
+
+/// fill the bound array buffer with the cursor for `cap`, returning how many vertices it has
+unsafe fn buffer_cursor(gl: &gl::Context, cap: CapStyle) -> i32 {
+    let lines = powdermilk_biscuits::graphics::cursor_geometry(cap, 1., 50);
+    gl.buffer_data_u8_slice(
+        gl::ARRAY_BUFFER,
+        bytemuck::cast_slice(lines.as_slice()),
+        gl::STATIC_DRAW,
+    );
+    lines.len() as i32 / 2
 }
 
 impl Renderer {
     pub fn new(gl: &gl::Context, width: u32, height: u32) -> Self {
         unsafe {
-            gl.enable(gl::SRGB8_ALPHA8);
+            // colors are linear. writes to the srgb multisample texture get encoded and the
+            // resolve in render averages the samples in linear space, so edges don't get darker
+            // than they should
             gl.enable(gl::FRAMEBUFFER_SRGB);
             gl.enable(gl::MULTISAMPLE);
             gl.enable(gl::VERTEX_PROGRAM_POINT_SIZE);
@@ -214,6 +355,12 @@ impl Renderer {
             let strokes_color = gl
                 .get_uniform_location(line_strokes_program, "strokeColor")
                 .unwrap();
+            let strokes_crisp = gl
+                .get_uniform_location(line_strokes_program, "crisp")
+                .unwrap();
+            let strokes_screen_size = gl
+                .get_uniform_location(line_strokes_program, "screenSize")
+                .unwrap();
 
             let cursor_vao = gl.create_vertex_array().unwrap();
             gl.bind_vertex_array(Some(cursor_vao));
@@ -221,35 +368,15 @@ impl Renderer {
             gl.bind_buffer(gl::ARRAY_BUFFER, Some(cursor_buffer));
 
             let float_size = std::mem::size_of::<f32>();
-            let circle = powdermilk_biscuits::graphics::cursor_geometry(1., 50);
-            let bytes =
-                std::slice::from_raw_parts(circle.as_ptr() as *const u8, circle.len() * float_size);
-
-            gl.buffer_data_u8_slice(gl::ARRAY_BUFFER, bytes, gl::STATIC_DRAW);
+            let cursor_cap = CapStyle::default();
+            let cursor_len = buffer_cursor(gl, cursor_cap);
             gl.enable_vertex_attrib_array(0);
             gl.vertex_attrib_pointer_f32(0, 2, gl::FLOAT, false, 2 * float_size as i32, 0);
 
             let msaa_fbo = gl.create_framebuffer().unwrap();
             gl.bind_framebuffer(gl::FRAMEBUFFER, Some(msaa_fbo));
 
-            let tex = gl.create_texture().unwrap();
-            gl.bind_texture(gl::TEXTURE_2D_MULTISAMPLE, Some(tex));
-            gl.tex_image_2d_multisample(
-                gl::TEXTURE_2D_MULTISAMPLE,
-                4,
-                gl::RGBA8 as i32,
-                width as i32,
-                height as i32,
-                true,
-            );
-            gl.bind_texture(gl::TEXTURE_2D_MULTISAMPLE, None);
-            gl.framebuffer_texture_2d(
-                gl::FRAMEBUFFER,
-                gl::COLOR_ATTACHMENT0,
-                gl::TEXTURE_2D_MULTISAMPLE,
-                Some(tex),
-                0,
-            );
+            let msaa_texture = Self::make_msaa_texture(gl, width, height);
 
             assert_eq!(
                 gl.check_framebuffer_status(gl::FRAMEBUFFER),
@@ -260,48 +387,63 @@ impl Renderer {
 
             Self {
                 msaa_fbo,
+                msaa_texture,
                 line_strokes_program,
                 mesh_strokes_program,
                 pen_cursor_program,
                 strokes_view,
                 strokes_color,
+                strokes_crisp,
+                strokes_screen_size,
                 pen_cursor_view,
                 pen_cursor_erasing,
                 pen_cursor_pen_down,
                 cursor_vao,
                 cursor_buffer,
+                cursor_cap,
+                cursor_len,
             }
         }
     }
 
-    pub fn resize(&self, new_size: PhysicalSize<u32>, gl: &gl::Context) {
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>, gl: &gl::Context) {
         unsafe {
             gl.viewport(0, 0, new_size.width as i32, new_size.height as i32);
             gl.bind_framebuffer(gl::FRAMEBUFFER, Some(self.msaa_fbo));
 
-            let tex = gl.create_texture().unwrap();
-            gl.bind_texture(gl::TEXTURE_2D_MULTISAMPLE, Some(tex));
-            gl.tex_image_2d_multisample(
-                gl::TEXTURE_2D_MULTISAMPLE,
-                4,
-                gl::RGBA8 as i32,
-                new_size.width as i32,
-                new_size.height as i32,
-                true,
-            );
-            gl.bind_texture(gl::TEXTURE_2D_MULTISAMPLE, None);
-            gl.framebuffer_texture_2d(
-                gl::FRAMEBUFFER,
-                gl::COLOR_ATTACHMENT0,
-                gl::TEXTURE_2D_MULTISAMPLE,
-                Some(tex),
-                0,
+            let old = std::mem::replace(
+                &mut self.msaa_texture,
+                Self::make_msaa_texture(gl, new_size.width, new_size.height),
             );
+            gl.delete_texture(old);
         }
     }
 
+    /// a multisample texture to draw into, attached to the bound framebuffer
+    unsafe fn make_msaa_texture(gl: &gl::Context, width: u32, height: u32) -> NativeTexture {
+        let texture = gl.create_texture().unwrap();
+        gl.bind_texture(gl::TEXTURE_2D_MULTISAMPLE, Some(texture));
+        gl.tex_image_2d_multisample(
+            gl::TEXTURE_2D_MULTISAMPLE,
+            SAMPLE_COUNT,
+            gl::SRGB8_ALPHA8 as i32,
+            width as i32,
+            height as i32,
+            true,
+        );
+        gl.bind_texture(gl::TEXTURE_2D_MULTISAMPLE, None);
+        gl.framebuffer_texture_2d(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D_MULTISAMPLE,
+            Some(texture),
+            0,
+        );
+        texture
+    }
+
     pub fn render(
-        &self,
+        &mut self,
         gl: &gl::Context,
         sketch: &mut Sketch<GlStrokeBackend>,
         widget: &SketchWidget<GlCoords>,
@@ -339,9 +481,10 @@ impl Renderer {
 
                     let line_vbo = gl.create_buffer().unwrap();
                     gl.bind_buffer(gl::ARRAY_BUFFER, Some(line_vbo));
+                    let line_points = stroke.line_points();
                     gl.buffer_data_u8_slice(
                         gl::ARRAY_BUFFER,
-                        bytemuck::cast_slice(&stroke.points),
+                        bytemuck::cast_slice(&line_points),
                         gl::STATIC_DRAW,
                     );
 
@@ -361,7 +504,7 @@ impl Renderer {
                     let mut mesh_lens = Vec::new();
                     let mut mesh_vbos = Vec::new();
                     let mut mesh_ebos = Vec::new();
-                    for mesh in stroke.meshes.iter() {
+                    for mesh in stroke.drawn_meshes() {
                         let mesh_vao = gl.create_vertex_array().unwrap();
                         gl.bind_vertex_array(Some(mesh_vao));
 
@@ -392,7 +535,7 @@ impl Renderer {
                     GlStrokeBackend {
                         line_vao,
                         line_vbo,
-                        line_len: stroke.points.len() as i32,
+                        line_len: line_points.len() as i32,
                         mesh_vaos,
                         mesh_vbos,
                         mesh_ebos,
@@ -413,54 +556,114 @@ impl Renderer {
             gl.clear(gl::COLOR_BUFFER_BIT);
         }
 
-        sketch.visible_strokes().for_each(|stroke| unsafe {
-            gl.use_program(Some(self.line_strokes_program));
-            let view = view_matrix(sketch.zoom, sketch.zoom, size, sketch.origin);
-            gl.uniform_matrix_4_f32_slice(Some(&self.strokes_view), false, &view.to_cols_array());
-            gl.uniform_3_f32(
-                Some(&self.strokes_color),
-                stroke.color[0],
-                stroke.color[1],
-                stroke.color[2],
-            );
+        let view = view_matrix(sketch.zoom, sketch.zoom, size, sketch.origin);
+        let mut strokes = sketch
+            .visible_strokes()
+            .map(|stroke| {
+                let scissor = stroke.clip.map(|clip| {
+                    clip.scissor::<GlCoords>(size.width, size.height, sketch.zoom, sketch.origin)
+                });
+                (
+                    stroke,
+                    stroke.color,
+                    stroke.line_primitive(),
+                    stroke.draw_tesselated,
+                    scissor,
+                    stroke.crisp,
+                )
+            })
+            .collect::<Vec<_>>();
+        // there's no depth buffer, so draw the lowest depth first. the sort is stable so strokes at
+        // the same depth stay in order
+        strokes.sort_by(|(a, ..), (b, ..)| a.depth.total_cmp(&b.depth));
+
+        for command in draw_commands(&strokes) {
+            unsafe {
+                match command {
+                    DrawCommand::UseProgram(Pipeline::Line) => {
+                        gl.use_program(Some(self.line_strokes_program));
+                    }
 
-            let GlStrokeBackend {
-                line_vao, line_len, ..
-            } = stroke.backend().unwrap();
-            gl.bind_vertex_array(Some(*line_vao));
-            gl.draw_arrays(gl::LINE_STRIP, 0, *line_len);
+                    DrawCommand::UseProgram(Pipeline::Mesh) => {
+                        gl.use_program(Some(self.mesh_strokes_program));
+                    }
 
-            if stroke.draw_tesselated {
-                gl.use_program(Some(self.mesh_strokes_program));
-                gl.uniform_matrix_4_f32_slice(
-                    Some(&self.strokes_view),
-                    false,
-                    &view.to_cols_array(),
-                );
-                gl.uniform_3_f32(
-                    Some(&self.strokes_color),
-                    stroke.color[0],
-                    stroke.color[1],
-                    stroke.color[2],
-                );
+                    DrawCommand::SetView => {
+                        gl.uniform_matrix_4_f32_slice(
+                            Some(&self.strokes_view),
+                            false,
+                            &view.to_cols_array(),
+                        );
+                        gl.uniform_2_f32(
+                            Some(&self.strokes_screen_size),
+                            size.width as f32,
+                            size.height as f32,
+                        );
+                    }
+
+                    DrawCommand::SetColor(color) => {
+                        gl.uniform_3_f32(Some(&self.strokes_color), color[0], color[1], color[2]);
+                    }
+
+                    DrawCommand::SetCrisp(crisp) => {
+                        gl.uniform_1_i32(Some(&self.strokes_crisp), crisp as i32);
+                    }
+
+                    DrawCommand::SetScissor(None) => {
+                        gl.disable(gl::SCISSOR_TEST);
+                    }
+
+                    DrawCommand::SetScissor(Some(rect)) => {
+                        // gl counts up from the bottom of the window
+                        gl.enable(gl::SCISSOR_TEST);
+                        gl.scissor(
+                            rect.x as i32,
+                            size.height as i32 - (rect.y + rect.height) as i32,
+                            rect.width as i32,
+                            rect.height as i32,
+                        );
+                    }
+
+                    DrawCommand::DrawLine(stroke) => {
+                        let GlStrokeBackend {
+                            line_vao, line_len, ..
+                        } = stroke.backend().unwrap();
+                        gl.bind_vertex_array(Some(*line_vao));
+                        gl.draw_arrays(gl::LINE_STRIP, 0, *line_len);
+                    }
 
-                let GlStrokeBackend {
-                    mesh_vaos,
-                    mesh_lens,
-                    ..
-                } = stroke.backend().unwrap();
-                for (mesh_vao, mesh_len) in mesh_vaos.iter().zip(mesh_lens.iter()) {
-                    gl.bind_vertex_array(Some(*mesh_vao));
-                    gl.draw_elements(gl::TRIANGLES, *mesh_len, gl::UNSIGNED_SHORT, 0);
+                    DrawCommand::DrawPoints(stroke) => {
+                        let GlStrokeBackend {
+                            line_vao, line_len, ..
+                        } = stroke.backend().unwrap();
+                        gl.bind_vertex_array(Some(*line_vao));
+                        gl.draw_arrays(gl::POINTS, 0, *line_len);
+                    }
+
+                    DrawCommand::DrawMesh(stroke) => {
+                        let GlStrokeBackend {
+                            mesh_vaos,
+                            mesh_lens,
+                            ..
+                        } = stroke.backend().unwrap();
+                        for (mesh_vao, mesh_len) in mesh_vaos.iter().zip(mesh_lens.iter()) {
+                            gl.bind_vertex_array(Some(*mesh_vao));
+                            gl.draw_elements(gl::TRIANGLES, *mesh_len, gl::UNSIGNED_SHORT, 0);
+                        }
+                    }
                 }
             }
-        });
+        }
 
         if !cursor_visible {
             unsafe {
                 gl.use_program(Some(self.pen_cursor_program));
                 gl.bind_vertex_array(Some(self.cursor_vao));
                 gl.bind_buffer(gl::ARRAY_BUFFER, Some(self.cursor_buffer));
+                if widget.line_cap != self.cursor_cap {
+                    self.cursor_cap = widget.line_cap;
+                    self.cursor_len = buffer_cursor(gl, self.cursor_cap);
+                }
 
                 gl.uniform_1_f32(
                     Some(&self.pen_cursor_erasing),
@@ -488,7 +691,7 @@ impl Renderer {
                     &view.to_cols_array(),
                 );
 
-                gl.draw_arrays(gl::LINES, 0, 50 * 2);
+                gl.draw_arrays(gl::LINES, 0, self.cursor_len);
             }
         }
 
@@ -510,3 +713,215 @@ impl Renderer {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // what render used to do: switch programs and set every uniform for every stroke
+    fn naive_commands(strokes: &[DrawnStroke<usize>]) -> Vec<DrawCommand<usize>> {
+        strokes
+            .iter()
+            .flat_map(|(id, color, line, draw_tesselated, _, crisp)| {
+                let mut commands = Vec::new();
+                if line.is_some() {
+                    commands.extend([
+                        DrawCommand::UseProgram(Pipeline::Line),
+                        DrawCommand::SetView,
+                        DrawCommand::SetColor(*color),
+                        DrawCommand::SetCrisp(*crisp),
+                        DrawCommand::DrawLine(*id),
+                    ]);
+                }
+                if *draw_tesselated {
+                    commands.extend([
+                        DrawCommand::UseProgram(Pipeline::Mesh),
+                        DrawCommand::SetView,
+                        DrawCommand::SetColor(*color),
+                        DrawCommand::SetCrisp(*crisp),
+                        DrawCommand::DrawMesh(*id),
+                    ]);
+                }
+                commands
+            })
+            .collect()
+    }
+
+    fn draws<T: Copy>(commands: &[DrawCommand<T>]) -> Vec<DrawCommand<T>> {
+        commands
+            .iter()
+            .filter(|command| !command.changes_state())
+            .copied()
+            .collect()
+    }
+
+    fn state_changes<T>(commands: &[DrawCommand<T>]) -> usize {
+        commands
+            .iter()
+            .filter(|command| command.changes_state())
+            .count()
+    }
+
+    #[test]
+    fn coords_round_trip() {
+        powdermilk_biscuits::coords::assert_round_trips::<GlCoords>();
+    }
+
+    #[test]
+    fn fewer_state_changes() {
+        let black = [0.; 3];
+        let red = [1., 0., 0.];
+        let strokes = (0..100)
+            .map(|i| {
+                (
+                    i,
+                    if i < 60 { black } else { red },
+                    (i % 10 == 0).then_some(LinePrimitive::Strip),
+                    i % 10 != 0,
+                    None,
+                    false,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let naive = naive_commands(&strokes);
+        let batched = draw_commands(&strokes);
+
+        // the same things get drawn in the same order
+        assert_eq!(draws(&naive), draws(&batched));
+
+        // a program for every run of lines or meshes, a view, two colors, and one crispness for
+        // each program
+        assert_eq!(state_changes(&batched), 20 + 2 + 4 + 2);
+        assert_eq!(state_changes(&naive), 100 * 4);
+    }
+
+    #[test]
+    fn strokes_stay_in_order() {
+        let strokes = [
+            (0, [0.; 3], Some(LinePrimitive::Strip), false, None, false),
+            (1, [0.; 3], None, true, None, false),
+            (2, [0.; 3], Some(LinePrimitive::Strip), false, None, false),
+        ];
+
+        let commands = draw_commands(&strokes);
+        assert_eq!(
+            commands,
+            vec![
+                DrawCommand::UseProgram(Pipeline::Line),
+                DrawCommand::SetView,
+                DrawCommand::SetColor([0.; 3]),
+                DrawCommand::SetCrisp(false),
+                DrawCommand::DrawLine(0),
+                DrawCommand::UseProgram(Pipeline::Mesh),
+                DrawCommand::SetView,
+                DrawCommand::SetColor([0.; 3]),
+                DrawCommand::SetCrisp(false),
+                DrawCommand::DrawMesh(1),
+                // the line program still has its uniforms
+                DrawCommand::UseProgram(Pipeline::Line),
+                DrawCommand::DrawLine(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_to_draw() {
+        assert!(draw_commands::<usize>(&[]).is_empty());
+
+        // no tessellated strokes, no mesh program
+        let commands = draw_commands(&[
+            (0, [0.; 3], Some(LinePrimitive::Strip), false, None, false),
+            (1, [0.; 3], Some(LinePrimitive::Strip), false, None, false),
+        ]);
+        assert!(!commands.contains(&DrawCommand::UseProgram(Pipeline::Mesh)));
+        assert_eq!(state_changes(&commands), 4);
+    }
+
+    #[test]
+    fn scissor_only_when_clipped() {
+        let clip = Some(ScissorRect {
+            x: 10,
+            y: 10,
+            width: 20,
+            height: 20,
+        });
+        let empty = Some(ScissorRect {
+            x: 10,
+            y: 10,
+            width: 0,
+            height: 20,
+        });
+        let strokes = [
+            (0, [0.; 3], Some(LinePrimitive::Strip), true, None, false),
+            (1, [0.; 3], Some(LinePrimitive::Strip), true, clip, false),
+            (2, [0.; 3], Some(LinePrimitive::Strip), true, clip, false),
+            (3, [0.; 3], Some(LinePrimitive::Strip), true, empty, false),
+        ];
+
+        let commands = draw_commands(&strokes);
+        let scissors = commands
+            .iter()
+            .filter(|command| matches!(command, DrawCommand::SetScissor(_)))
+            .collect::<Vec<_>>();
+
+        // on from the first clipped stroke, and off at the end
+        assert_eq!(
+            scissors,
+            vec![
+                &DrawCommand::SetScissor(clip),
+                &DrawCommand::SetScissor(None),
+            ]
+        );
+
+        // a stroke clipped to nothing isn't drawn at all
+        assert!(!commands.contains(&DrawCommand::DrawLine(3)));
+        assert!(!commands.contains(&DrawCommand::DrawMesh(3)));
+    }
+
+    #[test]
+    fn crisp_only_when_it_changes() {
+        let strokes = [
+            (0, [0.; 3], Some(LinePrimitive::Strip), true, None, false),
+            (1, [0.; 3], Some(LinePrimitive::Strip), true, None, true),
+            (2, [0.; 3], Some(LinePrimitive::Strip), true, None, true),
+            (3, [0.; 3], Some(LinePrimitive::Strip), false, None, false),
+        ];
+
+        let crisp = draw_commands(&strokes)
+            .into_iter()
+            .filter_map(|command| match command {
+                DrawCommand::SetCrisp(crisp) => Some(crisp),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // each program keeps its own, and only the line program draws the last stroke
+        assert_eq!(crisp, vec![false, false, true, true, false]);
+    }
+
+    #[test]
+    fn points_and_circles() {
+        let strokes = [
+            (0, [0.; 3], Some(LinePrimitive::Points), false, None, false),
+            (1, [0.; 3], None, true, None, false),
+            (2, [0.; 3], Some(LinePrimitive::Strip), true, None, false),
+        ];
+
+        let draws = draw_commands(&strokes)
+            .into_iter()
+            .filter(|command| !command.changes_state())
+            .collect::<Vec<_>>();
+
+        // circles are only a mesh, and points are only points
+        assert_eq!(
+            draws,
+            vec![
+                DrawCommand::DrawPoints(0),
+                DrawCommand::DrawMesh(1),
+                DrawCommand::DrawLine(2),
+                DrawCommand::DrawMesh(2),
+            ]
+        );
+    }
+}
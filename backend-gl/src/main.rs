@@ -4,12 +4,9 @@ use backend_gl::{GlCoords, GlStrokeBackend, Renderer};
 use egui_glow::EguiGlow;
 use ezgl::Ezgl;
 use powdermilk_biscuits::{
-    config::Config,
     egui::Context as EguiContext,
-    loop_::{loop_, LoopContext, LoopEvent, PerEvent, RenderResult},
-    ui::widget::SketchWidget,
+    prelude::*,
     winit::{dpi::PhysicalSize, event::Event as WinitEvent, event_loop::EventLoop, window::Window},
-    Sketch,
 };
 
 fn no_winit_ezgl(window: &Window, size: PhysicalSize<u32>) -> Ezgl {
@@ -44,7 +41,18 @@ struct GlLoop {
 }
 
 impl LoopContext<GlStrokeBackend, GlCoords> for GlLoop {
-    fn setup(ev: &EventLoop<LoopEvent>, window: &Window, _: &mut Sketch<GlStrokeBackend>) -> Self {
+    fn setup(
+        ev: &EventLoop<LoopEvent>,
+        window: &Window,
+        _: &mut Sketch<GlStrokeBackend>,
+        config: &Config,
+    ) -> Self {
+        if !matches!(config.renderer, RendererKind::Auto | RendererKind::Gl) {
+            tracing::warn!(
+                "{:?} renderer isn't available here, using opengl",
+                config.renderer
+            );
+        }
         let gl = no_winit_ezgl(window, window.inner_size());
         let size = window.inner_size();
         GlLoop {
@@ -5,6 +5,8 @@ layout (location=1) in float pressure;
 
 layout (location=0) uniform mat4 view;
 layout (location=1) uniform vec3 strokeColor;
+layout (location=2) uniform bool crisp;
+layout (location=3) uniform vec2 screenSize;
 
 layout (location=0) out vec4 fragPos;
 layout (location=1) out float fragPressure;
@@ -12,6 +14,10 @@ layout (location=2) out vec3 fragStrokeColor;
 
 void main() {
   vec4 pos = view * vec4(strokePos, 0.0, 1.0);
+  if (crisp) {
+    // snap to whole pixels so the edges don't get smoothed
+    pos.xy = round((pos.xy * 0.5 + 0.5) * screenSize) / screenSize * 2.0 - 1.0;
+  }
   gl_Position = pos;
 
   fragPos = pos;
@@ -1,32 +1,39 @@
 use egui_wgpu::renderer::ScreenDescriptor;
 use powdermilk_biscuits::{
     bytemuck, egui,
-    graphics::{PixelPos, StrokePoint},
-    stroke::Stroke,
+    graphics::{self, AntiAliasing, PixelPos, RendererAttempt, StrokePoint},
+    stroke::{LinePrimitive, Stroke, StrokeElement, StrokeStyle},
+    tess::CapStyle,
     ui::widget::SketchWidget,
     winit::{self, dpi::PhysicalSize, window::Window},
     CoordinateSystem, Sketch, Tool,
 };
-use std::mem::size_of;
+use std::{mem::size_of, num::NonZeroU32};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    Backends, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    Adapter, Backends, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingType, BlendState, Buffer, BufferAddress, BufferBindingType,
-    BufferUsages, Color as WgpuColor, ColorTargetState, ColorWrites, CommandEncoder,
-    CommandEncoderDescriptor, CompositeAlphaMode, Device, DeviceDescriptor, Face, Features,
-    FragmentState, FrontFace, IndexFormat, Instance, Limits, LoadOp, MultisampleState, Operations,
-    PipelineLayoutDescriptor, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
-    PrimitiveTopology, PushConstantRange, Queue, RenderPassColorAttachment, RenderPassDescriptor,
-    RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions, ShaderStages, Surface,
-    SurfaceConfiguration, SurfaceError, TextureFormat, TextureUsages, TextureView,
+    BufferDescriptor, BufferUsages, Color as WgpuColor, ColorTargetState, ColorWrites,
+    CommandEncoder, CommandEncoderDescriptor, CompareFunction, CompositeAlphaMode, DepthBiasState,
+    DepthStencilState, Device, DeviceDescriptor, Extent3d, Face, Features, FragmentState,
+    FrontFace, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, IndexFormat, Instance, Limits,
+    LoadOp, Maintain, MapMode, MultisampleState, Operations, Origin3d, PipelineLayoutDescriptor,
+    PolygonMode, PowerPreference, PresentMode, PrimitiveState, PrimitiveTopology,
+    PushConstantRange, Queue, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions,
+    RequestDeviceError, ShaderStages, StencilState, Surface, SurfaceConfiguration, SurfaceError,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
     TextureViewDescriptor, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState,
-    VertexStepMode,
+    VertexStepMode, COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 
 pub type WgpuStroke = Stroke<WgpuStrokeBackend>;
 
 const NUM_SEGMENTS: usize = 50;
 
+/// the fewest points a points buffer has room for
+const MIN_POINTS_CAPACITY: usize = 64;
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct WgpuCoords;
 
@@ -81,6 +88,13 @@ pub fn view_matrix(
 pub struct WgpuStrokeBackend {
     pub points: Buffer,
     pub points_len: usize,
+    /// how many points fit in `points`
+    pub points_capacity: usize,
+    /// how many of the stroke's points `points` was made from, the last of them, and the style it
+    /// was drawn in, to tell whether the stroke has only had points added since
+    pub buffered_len: usize,
+    pub buffered_last: Option<StrokeElement>,
+    pub buffered_style: StrokeStyle,
     pub meshes: Vec<Buffer>,
     pub indices: Vec<Buffer>,
     pub num_indices: Vec<usize>,
@@ -152,15 +166,50 @@ impl<T> EventExt for winit::event::Event<'_, T> {
     }
 }
 
+/// the `StrokeConstants` push constants in the stroke shaders: color, whether the stroke is crisp,
+/// the size of the window, the stroke's depth, and padding
+type StrokeConstants = [f32; 8];
+
+fn stroke_constants(stroke: &WgpuStroke, size: Size) -> StrokeConstants {
+    [
+        stroke.color[0],
+        stroke.color[1],
+        stroke.color[2],
+        if stroke.crisp { 1. } else { 0. },
+        size.width as f32,
+        size.height as f32,
+        graphics::depth_to_ndc(stroke.depth),
+        0.,
+    ]
+}
+
+const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// images are drawn in srgb like the window, one byte each of red, green, blue, and alpha
+const IMAGE_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// strokes with a higher depth are closer, so they're drawn on top of the ones with a lower depth no
+/// matter which order they're drawn in
+fn stroke_depth_stencil() -> Option<DepthStencilState> {
+    Some(DepthStencilState {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: true,
+        depth_compare: CompareFunction::LessEqual,
+        stencil: StencilState::default(),
+        bias: DepthBiasState::default(),
+    })
+}
+
 struct StrokeRenderer {
     triangle_pipeline: RenderPipeline,
     line_pipeline: RenderPipeline,
+    point_pipeline: RenderPipeline,
     view_bind_group: BindGroup,
     view_uniform_buffer: Buffer,
 }
 
 impl StrokeRenderer {
-    fn new(device: &Device, format: TextureFormat) -> Self {
+    fn new(device: &Device, format: TextureFormat, sample_count: u32) -> Self {
         let line_shader =
             device.create_shader_module(wgpu::include_wgsl!("shaders/stroke_line.wgsl"));
         let mesh_shader =
@@ -200,7 +249,7 @@ impl StrokeRenderer {
             bind_group_layouts: &[&view_bind_layout],
             push_constant_ranges: &[PushConstantRange {
                 stages: ShaderStages::VERTEX,
-                range: 0..12,
+                range: 0..size_of::<StrokeConstants>() as u32,
             }],
         });
 
@@ -240,9 +289,9 @@ impl StrokeRenderer {
                 unclipped_depth: false,
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil: stroke_depth_stencil(),
             multisample: MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -281,9 +330,9 @@ impl StrokeRenderer {
                 polygon_mode: PolygonMode::Fill,
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil: stroke_depth_stencil(),
             multisample: MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -298,18 +347,52 @@ impl StrokeRenderer {
         let triangle_pipeline = device.create_render_pipeline(&triangle_pipeline_desc);
         let line_pipeline = device.create_render_pipeline(&line_pipeline_desc);
 
+        let point_pipeline_desc = RenderPipelineDescriptor {
+            label: Some("stroke point pipeline"),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::PointList,
+                ..line_pipeline_desc.primitive
+            },
+            ..line_pipeline_desc
+        };
+        let point_pipeline = device.create_render_pipeline(&point_pipeline_desc);
+
         StrokeRenderer {
             triangle_pipeline,
             line_pipeline,
+            point_pipeline,
             view_bind_group,
             view_uniform_buffer,
         }
     }
 
+    fn vertices(device: &Device, cap: CapStyle) -> (Buffer, u32) {
+        let cursor_points = graphics::cursor_geometry(cap, 1., NUM_SEGMENTS);
+
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("cursor points"),
+            contents: bytemuck::cast_slice(cursor_points.as_slice()),
+            usage: BufferUsages::VERTEX,
+        });
+
+        (vertex_buffer, cursor_points.len() as u32 / 2)
+    }
+
+    /// draw the end of a line with `cap` from now on
+    fn use_cap(&mut self, device: &Device, cap: CapStyle) {
+        if cap != self.cap {
+            (self.vertex_buffer, self.num_vertices) = CursorRenderer::vertices(device, cap);
+            self.cap = cap;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn render(
         &self,
         queue: &Queue,
         frame: &TextureView,
+        resolve_target: Option<&TextureView>,
+        depth: &TextureView,
         encoder: &mut CommandEncoder,
         sketch: &Sketch<WgpuStrokeBackend>,
         size: Size,
@@ -328,7 +411,7 @@ impl StrokeRenderer {
             label: None,
             color_attachments: &[Some(RenderPassColorAttachment {
                 view: frame,
-                resolve_target: None,
+                resolve_target,
                 ops: Operations {
                     load: LoadOp::Clear(WgpuColor {
                         r: bg_color[0] as f64,
@@ -339,30 +422,58 @@ impl StrokeRenderer {
                     store: true,
                 },
             })],
-            depth_stencil_attachment: None,
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(1.),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
         });
 
+        let mut scissor = None;
         sketch.visible_strokes().for_each(|stroke| {
-            pass.set_pipeline(&self.line_pipeline);
+            let stroke_scissor = stroke.clip.map(|clip| {
+                clip.scissor::<WgpuCoords>(size.width, size.height, sketch.zoom, sketch.origin)
+            });
+
+            // clipped out entirely
+            if stroke_scissor.map(|rect| rect.width == 0 || rect.height == 0) == Some(true) {
+                return;
+            }
 
-            pass.set_bind_group(0, &self.view_bind_group, &[]);
-            pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::cast_slice(&stroke.color));
+            if scissor != stroke_scissor {
+                match stroke_scissor {
+                    Some(rect) => pass.set_scissor_rect(rect.x, rect.y, rect.width, rect.height),
+                    None => pass.set_scissor_rect(0, 0, size.width, size.height),
+                }
+                scissor = stroke_scissor;
+            }
+
+            let constants = stroke_constants(stroke, size);
 
-            let WgpuStrokeBackend {
-                points, points_len, ..
-            } = stroke.backend().unwrap();
-            pass.set_vertex_buffer(0, points.slice(..));
-            pass.draw(0..(*points_len as u32), 0..1);
+            if let Some(line) = stroke.line_primitive() {
+                pass.set_pipeline(match line {
+                    LinePrimitive::Strip => &self.line_pipeline,
+                    LinePrimitive::Points => &self.point_pipeline,
+                });
+
+                pass.set_bind_group(0, &self.view_bind_group, &[]);
+                pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::cast_slice(&constants));
+
+                let WgpuStrokeBackend {
+                    points, points_len, ..
+                } = stroke.backend().unwrap();
+                pass.set_vertex_buffer(0, points.slice(..));
+                pass.draw(0..(*points_len as u32), 0..1);
+            }
 
             if stroke.draw_tesselated {
                 pass.set_pipeline(&self.triangle_pipeline);
 
                 pass.set_bind_group(0, &self.view_bind_group, &[]);
-                pass.set_push_constants(
-                    ShaderStages::VERTEX,
-                    0,
-                    bytemuck::cast_slice(&stroke.color),
-                );
+                pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::cast_slice(&constants));
 
                 let WgpuStrokeBackend {
                     meshes,
@@ -385,6 +496,9 @@ impl StrokeRenderer {
 
 struct CursorRenderer {
     vertex_buffer: Buffer,
+    /// what the vertex buffer holds and how many vertices that is
+    cap: CapStyle,
+    num_vertices: u32,
     pipeline: RenderPipeline,
     bind_group: BindGroup,
     view_uniform_buffer: Buffer,
@@ -392,14 +506,9 @@ struct CursorRenderer {
 }
 
 impl CursorRenderer {
-    fn new(device: &Device, format: TextureFormat) -> Self {
-        let cursor_points = powdermilk_biscuits::graphics::cursor_geometry(1., NUM_SEGMENTS);
-
-        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("cursor points"),
-            contents: bytemuck::cast_slice(cursor_points.as_slice()),
-            usage: BufferUsages::VERTEX,
-        });
+    fn new(device: &Device, format: TextureFormat, sample_count: u32) -> Self {
+        let cap = CapStyle::default();
+        let (vertex_buffer, num_vertices) = CursorRenderer::vertices(device, cap);
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/cursor.wgsl"));
 
@@ -504,7 +613,7 @@ impl CursorRenderer {
             },
             depth_stencil: None,
             multisample: MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -515,6 +624,8 @@ impl CursorRenderer {
 
         CursorRenderer {
             vertex_buffer,
+            cap,
+            num_vertices,
             pipeline,
             bind_group,
             view_uniform_buffer,
@@ -522,10 +633,12 @@ impl CursorRenderer {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn render(
         &self,
         queue: &Queue,
         frame: &TextureView,
+        resolve_target: Option<&TextureView>,
         encoder: &mut CommandEncoder,
         widget: &SketchWidget<WgpuCoords>,
         zoom: f32,
@@ -557,7 +670,7 @@ impl CursorRenderer {
             label: Some("cursor render pass"),
             color_attachments: &[Some(RenderPassColorAttachment {
                 view: frame,
-                resolve_target: None,
+                resolve_target,
                 ops: Operations {
                     load: LoadOp::Load,
                     store: true,
@@ -569,12 +682,356 @@ impl CursorRenderer {
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &self.bind_group, &[]);
         pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.draw(0..(NUM_SEGMENTS * 2) as u32, 0..1);
+        pass.draw(0..self.num_vertices, 0..1);
     }
 }
 
 pub type Size = PhysicalSize<u32>;
 
+/// the texture to draw into before resolving to the surface, if there's more than one sample
+fn msaa_target(
+    device: &Device,
+    format: TextureFormat,
+    size: Size,
+    sample_count: u32,
+) -> Option<TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("msaa target"),
+        size: Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    Some(texture.create_view(&TextureViewDescriptor::default()))
+}
+
+/// the depth buffer for the strokes, with as many samples as the color target
+fn depth_target(device: &Device, size: Size, sample_count: u32) -> TextureView {
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("depth target"),
+        size: Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    texture.create_view(&TextureViewDescriptor::default())
+}
+
+/// why there's nothing to draw with
+#[derive(Debug)]
+pub enum GraphicsError {
+    /// no adapter for any backend wgpu knows about, which usually means no gpu or no drivers
+    NoAdapter,
+    NoDevice(RequestDeviceError),
+}
+
+impl std::fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphicsError::NoAdapter => write!(f, "no graphics adapter available"),
+            GraphicsError::NoDevice(err) => write!(f, "couldn't get a graphics device: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphicsError {}
+
+impl From<RequestDeviceError> for GraphicsError {
+    fn from(err: RequestDeviceError) -> Self {
+        GraphicsError::NoDevice(err)
+    }
+}
+
+/// an adapter that can draw to `surface`, and a device and queue from it. a `software` adapter
+/// draws on the cpu
+pub async fn request_device(
+    instance: &Instance,
+    surface: Option<&Surface>,
+    software: bool,
+) -> Result<(Adapter, Device, Queue), GraphicsError> {
+    tracing::debug!("requesting adapter");
+    let adapter = instance
+        .request_adapter(&RequestAdapterOptions {
+            power_preference: PowerPreference::LowPower,
+            compatible_surface: surface,
+            force_fallback_adapter: software,
+        })
+        .await
+        .ok_or(GraphicsError::NoAdapter)?;
+
+    let limits = Limits {
+        max_push_constant_size: adapter.limits().max_push_constant_size,
+        ..Default::default()
+    };
+
+    tracing::debug!("requesting device");
+    let (device, queue) = adapter
+        .request_device(
+            &DeviceDescriptor {
+                label: Some("device descriptor"),
+                features: Features::PUSH_CONSTANTS,
+                limits,
+            },
+            None,
+        )
+        .await?;
+
+    Ok((adapter, device, queue))
+}
+
+/// upload whatever changed about `stroke` since it was last buffered
+fn buffer_stroke(device: &Device, queue: &Queue, stroke: &mut Stroke<WgpuStrokeBackend>) {
+    let (meshes, (indices, num_indices)) = stroke
+        .drawn_meshes()
+        .map(|mesh| {
+            (
+                device.create_buffer_init(&BufferInitDescriptor {
+                    label: Some("mesh buffer"),
+                    contents: bytemuck::cast_slice(mesh.vertices()),
+                    usage: BufferUsages::VERTEX,
+                }),
+                (
+                    device.create_buffer_init(&BufferInitDescriptor {
+                        label: Some("index buffer"),
+                        contents: bytemuck::cast_slice(mesh.indices()),
+                        usage: BufferUsages::INDEX,
+                    }),
+                    mesh.indices().len(),
+                ),
+            )
+        })
+        .unzip();
+
+    let old = stroke.backend.take();
+    let line_points = stroke.line_points();
+
+    // while drawing, the stroke only gets points added to the end, so only the line points
+    // that changed need uploading, as long as they still fit
+    let (points, points_capacity) = match old {
+        Some(old)
+            if old.buffered_style == stroke.drawn_style()
+                && old.buffered_len <= stroke.points().len()
+                && old.buffered_len.checked_sub(1).map(|i| &stroke.points()[i])
+                    == old.buffered_last.as_ref()
+                && line_points.len() <= old.points_capacity =>
+        {
+            let settled = stroke
+                .settled_line_points(old.buffered_len)
+                .min(old.points_len);
+            if settled < line_points.len() {
+                queue.write_buffer(
+                    &old.points,
+                    (settled * size_of::<StrokeElement>()) as BufferAddress,
+                    bytemuck::cast_slice(&line_points[settled..]),
+                );
+            }
+            (old.points, old.points_capacity)
+        }
+
+        // anything else, like undoing or moving it, starts over. the buffer grows by doubling
+        // so a stroke being drawn doesn't need a new one for every point
+        _ => {
+            let capacity = line_points
+                .len()
+                .next_power_of_two()
+                .max(MIN_POINTS_CAPACITY);
+            let points = device.create_buffer(&BufferDescriptor {
+                label: Some("points buffer"),
+                size: (capacity * size_of::<StrokeElement>()) as BufferAddress,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            if !line_points.is_empty() {
+                queue.write_buffer(&points, 0, bytemuck::cast_slice(&line_points));
+            }
+            (points, capacity)
+        }
+    };
+
+    let points_len = line_points.len();
+    drop(line_points);
+    stroke.backend.replace(WgpuStrokeBackend {
+        points,
+        points_len,
+        points_capacity,
+        buffered_len: stroke.points().len(),
+        buffered_last: stroke.points().last().copied(),
+        buffered_style: stroke.drawn_style(),
+        meshes,
+        indices,
+        num_indices,
+        dirty: false,
+    });
+}
+
+fn buffer_all_strokes(device: &Device, queue: &Queue, sketch: &mut Sketch<WgpuStrokeBackend>) {
+    for stroke in sketch.strokes.values_mut() {
+        if stroke.is_dirty() {
+            buffer_stroke(device, queue, stroke);
+        }
+    }
+}
+
+/// how far apart rows of an image `width` pixels wide are in a buffer copied from a texture,
+/// which has to be a multiple of [COPY_BYTES_PER_ROW_ALIGNMENT]
+fn padded_row_len(width: u32) -> u32 {
+    let row_len = width * 4;
+    (row_len + COPY_BYTES_PER_ROW_ALIGNMENT - 1) / COPY_BYTES_PER_ROW_ALIGNMENT
+        * COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+/// the pixels of an image `width` pixels wide without the padding at the end of each row
+fn unpad_rows(padded: &[u8], width: u32) -> Vec<u8> {
+    let row_len = width as usize * 4;
+    padded
+        .chunks_exact(padded_row_len(width) as usize)
+        .flat_map(|row| &row[..row_len])
+        .copied()
+        .collect()
+}
+
+/// draw the strokes of `sketch` into a texture `size` pixels big and read it back. the strokes
+/// are marked visible or not for that size. smaa is only done in the window, so it's the same as
+/// none here
+fn render_to_image(
+    device: &Device,
+    queue: &Queue,
+    sketch: &mut Sketch<WgpuStrokeBackend>,
+    size: Size,
+    anti_aliasing: AntiAliasing,
+) -> Vec<u8> {
+    let size = Size::new(size.width.max(1), size.height.max(1));
+    sketch.update_visible_strokes::<WgpuCoords>(size.width, size.height);
+    buffer_all_strokes(device, queue, sketch);
+
+    let extent = Extent3d {
+        width: size.width,
+        height: size.height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("image target"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: IMAGE_FORMAT,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+    });
+    let target = texture.create_view(&TextureViewDescriptor::default());
+    let sample_count = anti_aliasing.sample_count();
+    let multisampled = msaa_target(device, IMAGE_FORMAT, size, sample_count);
+    let depth = depth_target(device, size, sample_count);
+
+    let padded_row_len = padded_row_len(size.width);
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("image buffer"),
+        size: (padded_row_len * size.height) as BufferAddress,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    // the window's pipelines are for the surface's format and sample count
+    let renderer = StrokeRenderer::new(device, IMAGE_FORMAT, sample_count);
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("image encoder"),
+    });
+    renderer.render(
+        queue,
+        multisampled.as_ref().unwrap_or(&target),
+        multisampled.as_ref().map(|_| &target),
+        &depth,
+        &mut encoder,
+        sketch,
+        size,
+        sketch.bg_color,
+    );
+    encoder.copy_texture_to_buffer(
+        ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_row_len),
+                rows_per_image: None,
+            },
+        },
+        extent,
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(Maintain::Wait);
+    receiver
+        .recv()
+        .expect("image buffer dropped before it was mapped")
+        .expect("couldn't map the image buffer");
+
+    let pixels = unpad_rows(&slice.get_mapped_range(), size.width);
+    buffer.unmap();
+    pixels
+}
+
+/// draws sketches into images, without a window
+pub struct ImageRenderer {
+    device: Device,
+    queue: Queue,
+    anti_aliasing: AntiAliasing,
+}
+
+impl ImageRenderer {
+    /// a `software` renderer draws on the cpu
+    pub async fn new(software: bool) -> Result<Self, GraphicsError> {
+        let instance = Instance::new(Backends::all());
+        let (_, device, queue) = request_device(&instance, None, software).await?;
+        Ok(ImageRenderer {
+            device,
+            queue,
+            anti_aliasing: AntiAliasing::None,
+        })
+    }
+
+    pub fn with_anti_aliasing(self, anti_aliasing: AntiAliasing) -> Self {
+        ImageRenderer {
+            anti_aliasing,
+            ..self
+        }
+    }
+
+    /// the strokes of `sketch` as it's seen in a window `size` pixels big, as rows of RGBA bytes
+    /// from the top. the strokes need meshes already
+    pub fn render_to_image(&self, sketch: &mut Sketch<WgpuStrokeBackend>, size: Size) -> Vec<u8> {
+        render_to_image(&self.device, &self.queue, sketch, size, self.anti_aliasing)
+    }
+}
+
 pub struct Graphics {
     pub surface: Surface,
     pub surface_format: TextureFormat,
@@ -582,53 +1039,41 @@ pub struct Graphics {
     queue: Queue,
     config: SurfaceConfiguration,
     pub size: Size,
-    pub aa: bool,
+    anti_aliasing: AntiAliasing,
     smaa_target: smaa::SmaaTarget,
+    msaa_target: Option<TextureView>,
+    depth_target: TextureView,
     stroke_renderer: StrokeRenderer,
     cursor_renderer: CursorRenderer,
 }
 
 impl Graphics {
-    pub async fn new(window: &Window) -> Self {
-        tracing::info!("setting up wgpu");
+    pub async fn new(window: &Window, attempt: RendererAttempt) -> Result<Self, GraphicsError> {
+        tracing::info!("setting up wgpu ({attempt:?})");
         let size = window.inner_size();
-        let instance = Instance::new(Backends::all());
+        let instance = Instance::new(match attempt {
+            RendererAttempt::Gl => Backends::GL,
+            RendererAttempt::Gpu | RendererAttempt::Software => Backends::all(),
+        });
         let surface = unsafe { instance.create_surface(window) };
-
-        tracing::debug!("requesting adapter");
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::LowPower,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-
-        let limits = Limits {
-            max_push_constant_size: adapter.limits().max_push_constant_size,
-            ..Default::default()
-        };
-
-        tracing::debug!("requesting device");
-        let (device, queue) = adapter
-            .request_device(
-                &DeviceDescriptor {
-                    label: Some("device descriptor"),
-                    features: Features::PUSH_CONSTANTS,
-                    limits,
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        let (adapter, device, queue) = request_device(
+            &instance,
+            Some(&surface),
+            attempt == RendererAttempt::Software,
+        )
+        .await?;
 
         tracing::debug!("setting up pipeline stuff");
         let formats = surface.get_supported_formats(&adapter);
 
+        // stroke colors are linear, so the surface has to be srgb for antialiased edges to be
+        // blended in linear space and encoded afterwards
         let surface_format = if formats.contains(&TextureFormat::Rgba8UnormSrgb) {
             TextureFormat::Rgba8UnormSrgb
+        } else if let Some(srgb) = formats.iter().find(|format| format.describe().srgb) {
+            *srgb
         } else {
+            tracing::warn!("no srgb surface format, edges will be too dark");
             formats[0]
         };
 
@@ -663,9 +1108,13 @@ impl Graphics {
         );
 
         tracing::info!("done!");
-        Graphics {
-            stroke_renderer: StrokeRenderer::new(&device, surface_format),
-            cursor_renderer: CursorRenderer::new(&device, surface_format),
+        let anti_aliasing = AntiAliasing::default();
+        let sample_count = anti_aliasing.sample_count();
+        Ok(Graphics {
+            stroke_renderer: StrokeRenderer::new(&device, surface_format, sample_count),
+            cursor_renderer: CursorRenderer::new(&device, surface_format, sample_count),
+            msaa_target: msaa_target(&device, surface_format, size, sample_count),
+            depth_target: depth_target(&device, size, sample_count),
 
             surface,
             surface_format,
@@ -673,8 +1122,33 @@ impl Graphics {
             queue,
             config,
             size,
-            aa: true,
+            anti_aliasing,
             smaa_target,
+        })
+    }
+
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.anti_aliasing
+    }
+
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) {
+        if anti_aliasing == self.anti_aliasing {
+            return;
+        }
+
+        tracing::info!("switching anti-aliasing to {:?}", anti_aliasing);
+        let new_pipelines = self.anti_aliasing.needs_new_pipelines(anti_aliasing);
+        self.anti_aliasing = anti_aliasing;
+
+        if new_pipelines {
+            let sample_count = anti_aliasing.sample_count();
+            self.stroke_renderer =
+                StrokeRenderer::new(&self.device, self.surface_format, sample_count);
+            self.cursor_renderer =
+                CursorRenderer::new(&self.device, self.surface_format, sample_count);
+            self.msaa_target =
+                msaa_target(&self.device, self.surface_format, self.size, sample_count);
+            self.depth_target = depth_target(&self.device, self.size, sample_count);
         }
     }
 
@@ -692,54 +1166,35 @@ impl Graphics {
                 self.surface_format,
                 smaa::SmaaMode::Smaa1X,
             );
+            self.msaa_target = msaa_target(
+                &self.device,
+                self.surface_format,
+                new_size,
+                self.anti_aliasing.sample_count(),
+            );
+            self.depth_target =
+                depth_target(&self.device, new_size, self.anti_aliasing.sample_count());
         }
     }
 
     pub fn buffer_stroke(&mut self, stroke: &mut Stroke<WgpuStrokeBackend>) {
-        stroke.backend.replace({
-            let (meshes, (indices, num_indices)) = stroke
-                .meshes
-                .iter()
-                .map(|mesh| {
-                    (
-                        self.device.create_buffer_init(&BufferInitDescriptor {
-                            label: Some("mesh buffer"),
-                            contents: bytemuck::cast_slice(mesh.vertices()),
-                            usage: BufferUsages::VERTEX,
-                        }),
-                        (
-                            self.device.create_buffer_init(&BufferInitDescriptor {
-                                label: Some("index buffer"),
-                                contents: bytemuck::cast_slice(mesh.indices()),
-                                usage: BufferUsages::INDEX,
-                            }),
-                            mesh.indices().len(),
-                        ),
-                    )
-                })
-                .unzip();
-
-            WgpuStrokeBackend {
-                points: self.device.create_buffer_init(&BufferInitDescriptor {
-                    label: Some("points buffer"),
-                    contents: bytemuck::cast_slice(&stroke.points),
-                    usage: BufferUsages::VERTEX,
-                }),
-                points_len: stroke.points.len(),
-                meshes,
-                indices,
-                num_indices,
-                dirty: false,
-            }
-        });
+        buffer_stroke(&self.device, &self.queue, stroke);
     }
 
     pub fn buffer_all_strokes(&mut self, sketch: &mut Sketch<WgpuStrokeBackend>) {
-        for stroke in sketch.strokes.values_mut() {
-            if stroke.is_dirty() {
-                self.buffer_stroke(stroke);
-            }
-        }
+        buffer_all_strokes(&self.device, &self.queue, sketch);
+    }
+
+    /// draw the strokes into an image `size` pixels big instead of the window, and return its
+    /// pixels as rows of RGBA bytes from the top
+    pub fn render_to_image(
+        &mut self,
+        sketch: &mut Sketch<WgpuStrokeBackend>,
+        size: Size,
+    ) -> Vec<u8> {
+        let pixels = render_to_image(&self.device, &self.queue, sketch, size, self.anti_aliasing);
+        sketch.update_visible_strokes::<WgpuCoords>(self.size.width, self.size.height);
+        pixels
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -753,9 +1208,10 @@ impl Graphics {
         egui_painter: &mut egui_wgpu::Renderer,
     ) -> Result<(), SurfaceError> {
         self.buffer_all_strokes(sketch);
+        self.cursor_renderer.use_cap(&self.device, widget.line_cap);
 
         macro_rules! render {
-            ($frame:expr) => {
+            ($frame:expr, $resolve_target:expr) => {
                 let mut encoder = self
                     .device
                     .create_command_encoder(&CommandEncoderDescriptor {
@@ -765,6 +1221,8 @@ impl Graphics {
                 self.stroke_renderer.render(
                     &self.queue,
                     $frame,
+                    $resolve_target,
+                    &self.depth_target,
                     &mut encoder,
                     sketch,
                     self.size,
@@ -775,6 +1233,7 @@ impl Graphics {
                     self.cursor_renderer.render(
                         &self.queue,
                         $frame,
+                        $resolve_target,
                         &mut encoder,
                         widget,
                         sketch.zoom,
@@ -791,16 +1250,24 @@ impl Graphics {
             .texture
             .create_view(&TextureViewDescriptor::default());
 
-        if self.aa {
-            let smaa_frame = self
-                .smaa_target
-                .start_frame(&self.device, &self.queue, &surface_view);
+        match (self.anti_aliasing, self.msaa_target.as_ref()) {
+            (AntiAliasing::Smaa, _) => {
+                let smaa_frame =
+                    self.smaa_target
+                        .start_frame(&self.device, &self.queue, &surface_view);
 
-            render!(&smaa_frame);
+                render!(&smaa_frame, None);
 
-            smaa_frame.resolve();
-        } else {
-            render!(&surface_view);
+                smaa_frame.resolve();
+            }
+
+            (AntiAliasing::Msaa, Some(msaa_target)) => {
+                render!(msaa_target, Some(&surface_view));
+            }
+
+            _ => {
+                render!(&surface_view, None);
+            }
         }
 
         let mut encoder = self
@@ -850,3 +1317,185 @@ impl Graphics {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use powdermilk_biscuits::{
+        config::Config,
+        graphics::{Color, ColorExt},
+        lyon::lyon_tessellation::{StrokeOptions, StrokeTessellator},
+        tess,
+    };
+
+    const SIZE: u32 = 64;
+    const BRUSH_SIZE: f32 = 6.;
+
+    /// something to draw with, or None without any adapter at all, like on CI without a gpu
+    fn renderer() -> Option<ImageRenderer> {
+        let renderer = futures::executor::block_on(ImageRenderer::new(false))
+            .or_else(|_| futures::executor::block_on(ImageRenderer::new(true)));
+        if let Err(err) = &renderer {
+            eprintln!("not rendering: {err}");
+        }
+        renderer.ok()
+    }
+
+    /// strokes of each color from one pixel to another on black, meshed the way the app would
+    fn sketch(strokes: &[(Color, [f32; 2], [f32; 2])]) -> Sketch<WgpuStrokeBackend> {
+        // one stroke unit is one pixel
+        let zoom = 2.;
+        let point = |[x, y]: [f32; 2]| {
+            let pos = WgpuCoords::pixel_to_pos(
+                SIZE,
+                SIZE,
+                zoom,
+                StrokePoint::default(),
+                PixelPos { x, y },
+            );
+            StrokeElement {
+                x: pos.x,
+                y: pos.y,
+                pressure: 1.,
+            }
+        };
+        let mut sketch = Sketch::new(
+            strokes
+                .iter()
+                .map(|&(color, from, to)| {
+                    let mut stroke = Stroke::with_points(vec![point(from), point(to)], color);
+                    stroke.brush_size = BRUSH_SIZE;
+                    stroke
+                })
+                .collect(),
+        );
+        sketch.zoom = zoom;
+        sketch.bg_color = Color::BLACK;
+
+        let config = Config::new();
+        let options = StrokeOptions::default()
+            .with_line_cap(config.line_cap.into())
+            .with_line_join(config.line_join.into())
+            .with_tolerance(tess::level_tolerance(
+                tess::zoom_level(sketch.zoom),
+                config.mesh_pixel_tolerance,
+            ))
+            .with_variable_line_width(0);
+        sketch.force_update::<WgpuCoords>(SIZE, SIZE, &mut StrokeTessellator::new(), &options);
+        sketch
+    }
+
+    /// the red channel of the pixel at `x`, `y`
+    fn red(pixels: &[u8], x: u32, y: u32) -> u8 {
+        pixels[((y * SIZE + x) * 4) as usize]
+    }
+
+    #[test]
+    fn coords_round_trip() {
+        powdermilk_biscuits::coords::assert_round_trips::<WgpuCoords>();
+    }
+
+    #[test]
+    fn image_rows() {
+        assert_eq!(padded_row_len(1), COPY_BYTES_PER_ROW_ALIGNMENT);
+        assert_eq!(padded_row_len(64), 256);
+        assert_eq!(padded_row_len(65), 512);
+
+        // three pixels a row, padded out to 256 bytes
+        let padded = (0..2)
+            .flat_map(|row| (0..256).map(move |i| if i < 12 { row * 12 + i } else { 255 }))
+            .map(|byte| byte as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(unpad_rows(&padded, 3), (0..24).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn gradient_is_encoded() {
+        let renderer = match renderer() {
+            Some(renderer) => renderer,
+            None => return,
+        };
+
+        // a row of greys going from black to white, which are linear
+        let levels = (0..8).map(|i| i as f32 / 7.).collect::<Vec<_>>();
+        let rows = levels
+            .iter()
+            .enumerate()
+            .map(|(i, &level)| {
+                let y = 4. + 8. * i as f32;
+                (Color::grey(level), [8., y], [56., y])
+            })
+            .collect::<Vec<_>>();
+        let pixels = renderer.render_to_image(&mut sketch(&rows), Size::new(SIZE, SIZE));
+        assert_eq!(pixels.len(), (SIZE * SIZE * 4) as usize);
+
+        // and come out encoded for an srgb display, not as they are
+        for (i, level) in levels.into_iter().enumerate() {
+            let expected = (graphics::linear_to_srgb(level) * 255.).round();
+            let drawn = red(&pixels, SIZE / 2, 4 + 8 * i as u32);
+            assert!(
+                (drawn as f32 - expected).abs() <= 1.,
+                "grey {level} drew {drawn}, not {expected}"
+            );
+        }
+        assert_eq!(red(&pixels, 2, 2), 0);
+    }
+
+    #[test]
+    fn msaa_smooths_edges() {
+        let renderer = match renderer() {
+            Some(renderer) => renderer,
+            None => return,
+        };
+
+        let diagonal = [(Color::WHITE, [12., 12.], [52., 52.])];
+        let size = Size::new(SIZE, SIZE);
+        let none = renderer.render_to_image(&mut sketch(&diagonal), size);
+        let renderer = renderer.with_anti_aliasing(AntiAliasing::Msaa);
+        let msaa = renderer.render_to_image(&mut sketch(&diagonal), size);
+
+        // only the pixels along the edges change, the rest are all in or all out either way
+        let mut smoothed = 0;
+        for y in 2..SIZE - 2 {
+            for x in 2..SIZE - 2 {
+                let drawn = red(&none, x, y);
+                let edge = (y - 2..=y + 2)
+                    .flat_map(|ny| (x - 2..=x + 2).map(move |nx| (nx, ny)))
+                    .any(|(nx, ny)| red(&none, nx, ny) != drawn);
+                if !edge {
+                    assert_eq!(red(&msaa, x, y), drawn, "{x}, {y} isn't on the edge");
+                } else if red(&msaa, x, y) != drawn {
+                    smoothed += 1;
+                }
+            }
+        }
+        assert!(smoothed > 0);
+
+        // and the edges are blended in linear space, some number of samples covered
+        let resolved = (0..=AntiAliasing::MSAA_SAMPLES)
+            .map(|covered| {
+                let coverage = covered as f32 / AntiAliasing::MSAA_SAMPLES as f32;
+                graphics::resolve_edge(Color::WHITE, Color::BLACK, coverage)[0] * 255.
+            })
+            .collect::<Vec<_>>();
+        for pixel in msaa.chunks_exact(4) {
+            assert!(
+                resolved
+                    .iter()
+                    .any(|value| (pixel[0] as f32 - value).abs() <= 2.),
+                "{} isn't a resolved edge",
+                pixel[0]
+            );
+        }
+    }
+
+    #[test]
+    fn no_adapter() {
+        // without any backends there's never an adapter, gpu or not
+        let instance = Instance::new(Backends::empty());
+        assert!(matches!(
+            futures::executor::block_on(request_device(&instance, None, false)),
+            Err(GraphicsError::NoAdapter)
+        ));
+    }
+}
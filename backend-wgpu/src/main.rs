@@ -2,12 +2,9 @@
 
 use backend_wgpu::{Graphics, WgpuCoords, WgpuStrokeBackend};
 use powdermilk_biscuits::{
-    config::Config,
     egui::Context as EguiContext,
-    loop_::{loop_, LoopContext, LoopEvent, PerEvent, RenderResult},
-    ui::widget::SketchWidget,
+    prelude::*,
     winit::{dpi::PhysicalSize, event::Event as WinitEvent, event_loop::EventLoop, window::Window},
-    Sketch,
 };
 
 fn main() {
@@ -27,8 +24,22 @@ impl LoopContext<WgpuStrokeBackend, WgpuCoords> for WgpuLoop {
         ev: &EventLoop<LoopEvent>,
         window: &Window,
         sketch: &mut Sketch<WgpuStrokeBackend>,
+        config: &Config,
     ) -> WgpuLoop {
-        let mut graphics = futures::executor::block_on(Graphics::new(window));
+        let graphics = config
+            .renderer
+            .select(|attempt| futures::executor::block_on(Graphics::new(window, attempt)));
+        let mut graphics = match graphics {
+            Ok(graphics) => graphics,
+            Err(err) => {
+                tracing::error!("{err}");
+                powdermilk_biscuits::ui::error(&format!(
+                    "{}\n\n{err}",
+                    powdermilk_biscuits::s!(&MboxMessageNoGraphics)
+                ));
+                std::process::exit(1);
+            }
+        };
         graphics.buffer_all_strokes(sketch);
 
         WgpuLoop {
@@ -82,7 +93,12 @@ impl LoopContext<WgpuStrokeBackend, WgpuCoords> for WgpuLoop {
                 powdermilk_biscuits::ui::egui(ctx, sketch, widget, config);
             });
 
+        // cursor icon, copied text, links to open, and where the input method's window goes
+        self.egui_winit
+            .handle_platform_output(window, &self.egui_ctx, egui_data.platform_output);
+
         let egui_tris = self.egui_ctx.tessellate(egui_data.shapes);
+        self.graphics.set_anti_aliasing(config.anti_aliasing);
 
         match self.graphics.render(
             sketch,
@@ -103,7 +119,8 @@ impl LoopContext<WgpuStrokeBackend, WgpuCoords> for WgpuLoop {
         if egui_data.repaint_after.is_zero() {
             RenderResult::Redraw
         } else {
-            RenderResult::Nothing
+            // egui says Duration::MAX when it doesn't need to repaint
+            RenderResult::RedrawAfter(egui_data.repaint_after)
         }
     }
 
@@ -9,17 +9,36 @@ struct Frag {
   @location(1) color: vec3<f32>,
 };
 
+struct StrokeConstants {
+  color: vec3<f32>,
+  // 1 to snap to whole pixels
+  crisp: f32,
+  screen_size: vec2<f32>,
+  // already squished into 0 to 1, lower is closer
+  depth: f32,
+};
+
 @group(0) @binding(0) var<uniform> view: mat4x4<f32>;
 
 // non-standard xd
-var<push_constant> color: vec3<f32>;
+var<push_constant> stroke: StrokeConstants;
+
+fn snap(pos: vec4<f32>) -> vec4<f32> {
+  if stroke.crisp == 0.0 {
+    return pos;
+  }
+
+  let pixel = round((pos.xy * 0.5 + 0.5) * stroke.screen_size);
+  return vec4<f32>(pixel / stroke.screen_size * 2.0 - 1.0, pos.zw);
+}
 
 @vertex fn vmain(in: Vert) -> Frag {
   var out: Frag;
 
-  out.pos = view * vec4<f32>(in.pos, 0.0, 1.0);
+  out.pos = snap(view * vec4<f32>(in.pos, 0.0, 1.0));
+  out.pos.z = stroke.depth;
   out.pressure = in.pressure;
-  out.color = color;
+  out.color = stroke.color;
 
   return out;
 }
@@ -0,0 +1,145 @@
+//! how bloated a sketch is, before simplifying it
+
+use powdermilk_biscuits::{graphics::StrokePos, simplify, Sketch};
+
+/// how far a point can be from the simplified stroke before it counts as needed, in stroke units
+pub const DEFAULT_TOLERANCE: f32 = 0.01;
+
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    pub tolerance: f32,
+    pub strokes: usize,
+    pub points: usize,
+    pub max_points: usize,
+    /// points that simplifying at `tolerance` would remove
+    pub redundant_points: usize,
+    pub arc_length: f32,
+    /// top left and bottom right of every point, if there are any
+    pub bounds: Option<(StrokePos, StrokePos)>,
+}
+
+impl Report {
+    pub fn new(sketch: &Sketch<()>, tolerance: f32) -> Self {
+        let mut report = Report {
+            tolerance,
+            strokes: 0,
+            points: 0,
+            max_points: 0,
+            redundant_points: 0,
+            arc_length: 0.,
+            bounds: None,
+        };
+
+        for stroke in sketch.strokes.values().filter(|stroke| !stroke.erased()) {
+            let points = stroke.points();
+            report.strokes += 1;
+            report.points += points.len();
+            report.max_points = report.max_points.max(points.len());
+            report.redundant_points += simplify::rdp_keep(points, tolerance)
+                .into_iter()
+                .filter(|keep| !keep)
+                .count();
+            report.arc_length += points
+                .windows(2)
+                .map(|pair| (pair[1].x - pair[0].x).hypot(pair[1].y - pair[0].y))
+                .sum::<f32>();
+
+            for point in points {
+                let (top_left, bottom_right) =
+                    report.bounds.get_or_insert((point.into(), point.into()));
+                top_left.x = top_left.x.min(point.x);
+                top_left.y = top_left.y.max(point.y);
+                bottom_right.x = bottom_right.x.max(point.x);
+                bottom_right.y = bottom_right.y.min(point.y);
+            }
+        }
+
+        report
+    }
+
+    pub fn average_points(&self) -> f32 {
+        if self.strokes == 0 {
+            0.
+        } else {
+            self.points as f32 / self.strokes as f32
+        }
+    }
+
+    pub fn show(&self) {
+        println!(
+            "{} strokes, {} points ({:.02} per stroke, at most {})",
+            self.strokes,
+            self.points,
+            self.average_points(),
+            self.max_points
+        );
+        println!(
+            "{} points ({:.01}%) could be removed at tolerance {}",
+            self.redundant_points,
+            if self.points == 0 {
+                0.
+            } else {
+                100. * self.redundant_points as f32 / self.points as f32
+            },
+            self.tolerance
+        );
+        println!("total length {:.02}", self.arc_length);
+        if let Some((top_left, bottom_right)) = self.bounds {
+            println!(
+                "bounds ({:.02}, {:.02}) to ({:.02}, {:.02})",
+                top_left.x, top_left.y, bottom_right.x, bottom_right.y
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use powdermilk_biscuits::{
+        graphics::{Color, ColorExt},
+        stroke::{Stroke, StrokeElement},
+    };
+
+    fn stroke(xy: &[(f32, f32)]) -> Stroke<()> {
+        Stroke::with_points(
+            xy.iter()
+                .map(|&(x, y)| StrokeElement { x, y, pressure: 1. })
+                .collect(),
+            Color::WHITE,
+        )
+    }
+
+    #[test]
+    fn fixture() {
+        let mut erased = stroke(&[(100., 100.), (200., 200.)]);
+        erased.erase();
+        let sketch = Sketch::new(vec![
+            // a straight line sampled every unit, only the ends are needed
+            stroke(&[(0., 0.), (1., 0.), (2., 0.), (3., 0.), (4., 0.)]),
+            // a right angle with extra points along each side
+            stroke(&[(0., 1.), (0., 2.), (0., 3.), (1., 3.), (2., 3.)]),
+            stroke(&[(-1., -1.)]),
+            erased,
+        ]);
+
+        let report = Report::new(&sketch, DEFAULT_TOLERANCE);
+        assert_eq!(
+            report,
+            Report {
+                tolerance: DEFAULT_TOLERANCE,
+                strokes: 3,
+                points: 11,
+                max_points: 5,
+                redundant_points: 5,
+                arc_length: 8.,
+                bounds: Some((StrokePos { x: -1., y: 3. }, StrokePos { x: 4., y: -1. })),
+            }
+        );
+        assert_eq!(report.average_points(), 11. / 3.);
+
+        let empty = Report::new(&Sketch::new(Vec::new()), DEFAULT_TOLERANCE);
+        assert_eq!(empty.average_points(), 0.);
+        assert_eq!(empty.bounds, None);
+    }
+}
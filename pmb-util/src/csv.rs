@@ -0,0 +1,128 @@
+//! strokes from a list of points
+//!
+//! each line is `stroke,x,y,pressure`, where points with the same stroke id go in the same stroke
+//! in the order they're listed. pressure can be left off, and lines that are empty or start with
+//! `#` are skipped, as is a header line like `stroke,x,y,pressure`.
+
+use anyhow::{anyhow, Result};
+use powdermilk_biscuits::{
+    graphics::Color,
+    stroke::{Stroke, StrokeElement},
+};
+
+pub fn parse(text: &str, color: Color, brush_size: f32) -> Result<Vec<Stroke<()>>> {
+    let mut strokes: Vec<(&str, Vec<StrokeElement>)> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if i == 0 && fields.get(1).map(|x| x.parse::<f32>().is_err()) == Some(true) {
+            continue;
+        }
+
+        if !(3..=4).contains(&fields.len()) {
+            return Err(anyhow!(
+                "line {}: expected stroke,x,y,pressure but there are {} fields",
+                line_number,
+                fields.len()
+            ));
+        }
+
+        let number = |name: &str, field: &str| -> Result<f32> {
+            match field.parse::<f32>() {
+                Ok(number) if number.is_finite() => Ok(number),
+                _ => Err(anyhow!(
+                    "line {}: {} should be a number, not {:?}",
+                    line_number,
+                    name,
+                    field
+                )),
+            }
+        };
+
+        let id = fields[0];
+        if id.is_empty() {
+            return Err(anyhow!("line {}: missing stroke id", line_number));
+        }
+
+        let point = StrokeElement {
+            x: number("x", fields[1])?,
+            y: number("y", fields[2])?,
+            pressure: match fields.get(3) {
+                Some(pressure) => number("pressure", pressure)?,
+                None => 1.,
+            },
+        };
+
+        match strokes.iter_mut().find(|(other, _)| *other == id) {
+            Some((_, points)) => points.push(point),
+            None => strokes.push((id, vec![point])),
+        }
+    }
+
+    Ok(strokes
+        .into_iter()
+        .map(|(_, points)| {
+            let mut stroke = Stroke::with_points(points, color);
+            stroke.brush_size = brush_size;
+            stroke
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use powdermilk_biscuits::graphics::ColorExt;
+
+    #[test]
+    fn grouped_by_id() {
+        let text = "stroke,x,y,pressure
+a,0,0,1
+a,1,0,0.5
+# a comment
+b,5,5
+
+a,2,1,0.25
+b,6,5,1
+c,-1,-1,1
+";
+        let strokes = parse(text, Color::WHITE, 0.1).unwrap();
+        assert_eq!(
+            strokes
+                .iter()
+                .map(|stroke| stroke.points().len())
+                .collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+        assert_eq!(strokes[0].points()[2].x, 2.);
+        assert_eq!(strokes[1].points()[0].pressure, 1.);
+        assert!(strokes.iter().all(|stroke| stroke.brush_size() == 0.1));
+    }
+
+    #[test]
+    fn malformed() {
+        let error = |text| match parse(text, Color::WHITE, 0.1) {
+            Ok(_) => panic!("{:?} should be an error", text),
+            Err(err) => err.to_string(),
+        };
+        assert_eq!(
+            error("a,0,0\na,1\n"),
+            "line 2: expected stroke,x,y,pressure but there are 2 fields"
+        );
+        assert_eq!(
+            error("a,0,0\na,1,up\n"),
+            "line 2: y should be a number, not \"up\""
+        );
+        assert_eq!(
+            error("a,0,0,NaN\n"),
+            "line 1: pressure should be a number, not \"NaN\""
+        );
+        assert_eq!(error("a,0,0\n,1,1\n"), "line 2: missing stroke id");
+    }
+}
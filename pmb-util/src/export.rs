@@ -0,0 +1,72 @@
+use anyhow::Result;
+use powdermilk_biscuits::graphics::StrokePos;
+use std::path::Path;
+
+/// how big the image is if no size is given
+pub const DEFAULT_WIDTH: u32 = 1920;
+pub const DEFAULT_HEIGHT: u32 = 1080;
+
+/// draw the sketch at `path` the way it looks in a window `width` by `height` pixels big, and save
+/// it as a PNG at `out`. with `select`, only the strokes inside that rectangle, zoomed to fit
+#[cfg(feature = "png")]
+pub fn to_png(
+    path: &Path,
+    about: &dyn crate::About,
+    out: &Path,
+    width: u32,
+    height: u32,
+    select: Option<(StrokePos, StrokePos)>,
+) -> Result<()> {
+    use backend_wgpu::{ImageRenderer, Size, WgpuCoords, WgpuStrokeBackend};
+    use powdermilk_biscuits::{
+        config::Config,
+        lyon::lyon_tessellation::{StrokeOptions, StrokeTessellator},
+        tess,
+    };
+
+    // meshed the way the app would with the default settings
+    let config = Config::new();
+    let mut sketch = crate::read_latest::<WgpuStrokeBackend>(path, about)?;
+    if let Some((a, b)) = select {
+        let keys = sketch.select_rect(a, b);
+        sketch = sketch.only_strokes::<WgpuCoords>(&keys, width, height);
+    }
+    let options = StrokeOptions::default()
+        .with_line_cap(config.line_cap.into())
+        .with_line_join(config.line_join.into())
+        .with_tolerance(tess::level_tolerance(
+            tess::zoom_level(sketch.zoom),
+            config.mesh_pixel_tolerance,
+        ))
+        .with_variable_line_width(0);
+    sketch.use_tessellator(config.tessellator);
+    sketch.force_update::<WgpuCoords>(width, height, &mut StrokeTessellator::new(), &options);
+
+    let renderer = futures::executor::block_on(ImageRenderer::new(false))
+        .or_else(|_| futures::executor::block_on(ImageRenderer::new(true)))?;
+    let pixels = renderer.render_to_image(&mut sketch, Size::new(width, height));
+
+    let mut encoder = png::Encoder::new(
+        std::io::BufWriter::new(std::fs::File::create(out)?),
+        width,
+        height,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "png"))]
+pub fn to_png(
+    _: &Path,
+    _: &dyn crate::About,
+    _: &Path,
+    _: u32,
+    _: u32,
+    _: Option<(StrokePos, StrokePos)>,
+) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Built without PNG export, rebuild with --features png"
+    ))
+}
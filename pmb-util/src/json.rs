@@ -0,0 +1,439 @@
+//! sketches as JSON, for scripts and other tools
+//!
+//! these types are separate from the ones in the file format so the JSON doesn't change every time
+//! the file format does. only add fields to them, never change or remove them.
+
+use anyhow::{anyhow, Result};
+use powdermilk_biscuits::{
+    clip::ClipRect,
+    graphics::{Color, ColorExt, StrokePoint, StrokePos},
+    layer::{from_saved, SavedLayer},
+    null::NullCoords,
+    stroke::{Stroke, StrokeElement, StrokeStyle},
+    Sketch,
+};
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SketchJson {
+    pub zoom: f32,
+    pub origin: [f32; 2],
+    pub bg_color: [f32; 3],
+    pub fg_color: [f32; 3],
+    /// every layer's strokes one after the other, bottom layer first, in the order they're drawn
+    pub strokes: Vec<StrokeJson>,
+    /// bottom to top. with none, all the strokes are on one layer
+    #[serde(default)]
+    pub layers: Vec<LayerJson>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LayerJson {
+    pub name: String,
+    #[serde(default = "visible")]
+    pub visible: bool,
+    /// how many of the strokes are on this layer
+    pub len: usize,
+}
+
+fn visible() -> bool {
+    true
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StrokeJson {
+    pub color: [f32; 3],
+    pub brush_size: f32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub group_id: Option<u32>,
+    /// top left and bottom right corners
+    #[serde(default)]
+    pub clip: Option<[[f32; 2]; 2]>,
+    #[serde(default)]
+    pub crisp: bool,
+    #[serde(default)]
+    pub depth: f32,
+    #[serde(default)]
+    pub style: StrokeStyle,
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub closed: bool,
+    /// x, y, and pressure
+    pub points: Vec<[f32; 3]>,
+}
+
+impl From<&Sketch<()>> for SketchJson {
+    fn from(sketch: &Sketch<()>) -> Self {
+        SketchJson {
+            zoom: sketch.zoom,
+            origin: [sketch.origin.x, sketch.origin.y],
+            bg_color: sketch.bg_color.serialize_floats(),
+            fg_color: sketch.fg_color.serialize_floats(),
+            strokes: sketch
+                .layers
+                .iter()
+                .flat_map(|layer| sketch.saved_strokes(layer))
+                .map(StrokeJson::from)
+                .collect(),
+            layers: sketch
+                .layers
+                .iter()
+                .map(|layer| LayerJson {
+                    name: layer.name.clone(),
+                    visible: layer.visible,
+                    len: sketch.saved_strokes(layer).count(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&Stroke<()>> for StrokeJson {
+    fn from(stroke: &Stroke<()>) -> Self {
+        StrokeJson {
+            color: stroke.color.serialize_floats(),
+            brush_size: stroke.brush_size(),
+            tags: stroke.tags.clone(),
+            group_id: stroke.group_id,
+            clip: stroke.clip.map(|clip| {
+                [
+                    [clip.top_left.x, clip.top_left.y],
+                    [clip.bottom_right.x, clip.bottom_right.y],
+                ]
+            }),
+            crisp: stroke.crisp,
+            depth: stroke.depth,
+            style: stroke.style,
+            locked: stroke.locked,
+            closed: stroke.closed,
+            points: stroke
+                .points()
+                .iter()
+                .map(|point| [point.x, point.y, point.pressure])
+                .collect(),
+        }
+    }
+}
+
+impl SketchJson {
+    /// the sketch as JSON. with `selection_only`, just the selected strokes, zoomed to fit a
+    /// window `width` by `height` pixels big
+    pub fn export(sketch: &Sketch<()>, selection_only: bool, width: u32, height: u32) -> Self {
+        if selection_only {
+            SketchJson::from(&sketch.only_strokes::<NullCoords>(&sketch.selection, width, height))
+        } else {
+            SketchJson::from(sketch)
+        }
+    }
+
+    pub fn into_sketch(self) -> Result<Sketch<()>> {
+        if !(self.zoom.is_finite() && self.zoom > 0.) {
+            return Err(anyhow!("zoom should be more than 0, not {}", self.zoom));
+        }
+
+        let on_layers = self.layers.iter().map(|layer| layer.len).sum::<usize>();
+        if !self.layers.is_empty() && on_layers != self.strokes.len() {
+            return Err(anyhow!(
+                "the layers have {} strokes, but there are {}",
+                on_layers,
+                self.strokes.len()
+            ));
+        }
+
+        let strokes = self
+            .strokes
+            .into_iter()
+            .enumerate()
+            .map(|(i, stroke)| {
+                stroke
+                    .into_stroke()
+                    .map_err(|err| anyhow!("stroke {}: {}", i, err))
+            })
+            .collect::<Result<_>>()?;
+
+        let mut sketch = Sketch::new(strokes);
+        if !self.layers.is_empty() {
+            sketch.layers = from_saved(
+                self.layers
+                    .into_iter()
+                    .map(|layer| SavedLayer {
+                        name: layer.name,
+                        visible: layer.visible,
+                        len: layer.len as u64,
+                    })
+                    .collect(),
+            );
+        }
+        sketch.zoom = self.zoom;
+        sketch.origin = StrokePoint {
+            x: self.origin[0],
+            y: self.origin[1],
+        };
+        sketch.bg_color = Color::from_floats(self.bg_color);
+        sketch.fg_color = Color::from_floats(self.fg_color);
+        Ok(sketch)
+    }
+}
+
+impl StrokeJson {
+    pub fn into_stroke(self) -> Result<Stroke<()>> {
+        if !(self.brush_size.is_finite() && self.brush_size > 0.) {
+            return Err(anyhow!(
+                "brush size should be more than 0, not {}",
+                self.brush_size
+            ));
+        }
+
+        let points = self
+            .points
+            .into_iter()
+            .map(|[x, y, pressure]| StrokeElement { x, y, pressure })
+            .collect();
+
+        let mut stroke = Stroke::with_points(points, Color::from_floats(self.color));
+        stroke.brush_size = self.brush_size;
+        stroke.tags = self.tags;
+        stroke.group_id = self.group_id;
+        stroke.clip = self.clip.map(|[[left, top], [right, bottom]]| {
+            ClipRect::new(
+                StrokePos { x: left, y: top },
+                StrokePos {
+                    x: right,
+                    y: bottom,
+                },
+            )
+        });
+        stroke.crisp = self.crisp;
+        stroke.depth = self.depth;
+        stroke.style = self.style;
+        stroke.locked = self.locked;
+        stroke.closed = self.closed;
+        Ok(stroke)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use powdermilk_biscuits::layer::Layer;
+
+    #[test]
+    fn round_trip() {
+        let mut first = Stroke::with_points(
+            vec![
+                StrokeElement {
+                    x: 1.,
+                    y: 2.,
+                    pressure: 0.5,
+                },
+                StrokeElement {
+                    x: -3.25,
+                    y: 4.,
+                    pressure: 1.,
+                },
+            ],
+            Color::WHITE,
+        );
+        first.brush_size = 0.2;
+        first.add_tag("ink");
+        first.crisp = true;
+        first.depth = -2.5;
+        first.style = StrokeStyle::CirclesPressure;
+        first.locked = true;
+        first.closed = true;
+        first.clip = Some(ClipRect::new(
+            StrokePos { x: -5., y: 5. },
+            StrokePos { x: 5., y: -5. },
+        ));
+        let mut erased = Stroke::with_points(Vec::new(), Color::BLACK);
+        erased.erase();
+
+        let mut sketch = Sketch::new(vec![first, Stroke::with_points(Vec::new(), Color::BLACK)]);
+        sketch.add_stroke(erased);
+        sketch.group(&sketch.strokes.keys().take(2).collect::<Vec<_>>());
+        sketch.zoom = 12.5;
+        sketch.origin = StrokePoint { x: 3., y: -1. };
+
+        let json = serde_json::to_string(&SketchJson::from(&sketch)).unwrap();
+        let back = serde_json::from_str::<SketchJson>(&json)
+            .unwrap()
+            .into_sketch()
+            .unwrap();
+
+        // erased strokes aren't part of the sketch anymore
+        assert_eq!(back.strokes.len(), 2);
+        assert_eq!(back.zoom, sketch.zoom);
+        assert_eq!(back.origin, sketch.origin);
+        assert_eq!(back.bg_color, sketch.bg_color);
+        assert_eq!(back.fg_color, sketch.fg_color);
+        for (a, b) in sketch.strokes.values().zip(back.strokes.values()) {
+            assert_eq!(a.points(), b.points());
+            assert_eq!(a.color, b.color);
+            assert_eq!(a.brush_size(), b.brush_size());
+            assert_eq!(a.tags, b.tags);
+            assert_eq!(a.group_id, b.group_id);
+            assert_eq!(a.clip, b.clip);
+            assert_eq!(a.crisp, b.crisp);
+            assert_eq!(a.depth, b.depth);
+            assert_eq!(a.style, b.style);
+            assert_eq!(a.locked, b.locked);
+            assert_eq!(a.closed, b.closed);
+        }
+
+        // and dumping it again gives the same JSON
+        assert_eq!(
+            serde_json::to_string(&SketchJson::from(&back)).unwrap(),
+            json
+        );
+    }
+
+    #[test]
+    fn layers_and_order() {
+        let dot = |at: f32| {
+            Stroke::with_points(
+                vec![StrokeElement {
+                    x: at,
+                    y: at,
+                    pressure: 1.,
+                }],
+                Color::WHITE,
+            )
+        };
+        let mut sketch = Sketch::new(vec![dot(0.), dot(1.), dot(2.)]);
+        let keys = sketch.strokes.keys().collect::<Vec<_>>();
+        // drawn in a different order than they're in the map, with the first one on its own layer
+        sketch.layers[0].strokes = vec![keys[2], keys[1]];
+        sketch.layers.push(Layer {
+            name: String::from("on top"),
+            visible: false,
+            strokes: vec![keys[0]],
+        });
+        let order = |sketch: &Sketch<()>| {
+            sketch
+                .layers
+                .iter()
+                .flat_map(|layer| sketch.saved_strokes(layer))
+                .map(|stroke| stroke.points()[0].x)
+                .collect::<Vec<_>>()
+        };
+        let layers = |sketch: &Sketch<()>| {
+            sketch
+                .layers
+                .iter()
+                .map(|layer| (layer.name.clone(), layer.visible, layer.strokes.len()))
+                .collect::<Vec<_>>()
+        };
+
+        let json = serde_json::to_string(&SketchJson::from(&sketch)).unwrap();
+        let back = serde_json::from_str::<SketchJson>(&json)
+            .unwrap()
+            .into_sketch()
+            .unwrap();
+        assert_eq!(order(&back), vec![2., 1., 0.]);
+        assert_eq!(layers(&back), layers(&sketch));
+
+        // a selection stays in drawing order on its layers, not the order it was selected in
+        let selected = sketch.only_strokes::<NullCoords>(&[keys[0], keys[2]], 800, 600);
+        assert_eq!(order(&selected), vec![2., 0.]);
+        assert_eq!(
+            layers(&selected),
+            vec![
+                (String::from("Layer 1"), true, 1),
+                (String::from("on top"), false, 1)
+            ]
+        );
+
+        // the layers have to add up to the strokes
+        let mut short = serde_json::from_str::<SketchJson>(&json).unwrap();
+        short.layers[1].len = 0;
+        let Err(error) = short.into_sketch() else {
+            panic!("layers with too few strokes should be an error");
+        };
+        assert_eq!(
+            error.to_string(),
+            "the layers have 2 strokes, but there are 3"
+        );
+    }
+
+    #[test]
+    fn import() {
+        let json = r#"{
+            "zoom": 50.0,
+            "origin": [0.0, 0.0],
+            "bg_color": [1.0, 1.0, 1.0],
+            "fg_color": [0.0, 0.0, 0.0],
+            "strokes": [
+                { "color": [1.0, 0.0, 0.0], "brush_size": 0.1, "points": [[0, 0, 1], [1, 1, 1]] },
+                { "color": [0.0, 0.0, 1.0], "brush_size": 0.2, "points": [[5, 5, 0.5]] }
+            ]
+        }"#;
+        let sketch = serde_json::from_str::<SketchJson>(json)
+            .unwrap()
+            .into_sketch()
+            .unwrap();
+        assert_eq!(
+            sketch
+                .strokes
+                .values()
+                .map(|stroke| stroke.points().len())
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+
+        // points need all three numbers
+        let missing_pressure = json.replace("[5, 5, 0.5]", "[5, 5]");
+        assert!(serde_json::from_str::<SketchJson>(&missing_pressure).is_err());
+
+        let no_brush = json.replace("0.2", "0");
+        let Err(error) = serde_json::from_str::<SketchJson>(&no_brush)
+            .unwrap()
+            .into_sketch()
+        else {
+            panic!("a brush size of 0 should be an error");
+        };
+        assert_eq!(
+            error.to_string(),
+            "stroke 1: brush size should be more than 0, not 0"
+        );
+    }
+
+    #[test]
+    fn selection_only() {
+        let strokes = (0..5)
+            .map(|i| {
+                let at = i as f32 * 10.;
+                Stroke::with_points(
+                    vec![StrokeElement {
+                        x: at,
+                        y: at,
+                        pressure: 1.,
+                    }],
+                    Color::WHITE,
+                )
+            })
+            .collect();
+        let mut sketch = Sketch::new(strokes);
+        sketch.select_rect(StrokePos { x: 5., y: 5. }, StrokePos { x: 25., y: 25. });
+        assert_eq!(sketch.selection.len(), 2);
+
+        let everything = serde_json::to_string(&SketchJson::export(&sketch, false, 800, 600));
+        let everything = serde_json::from_str::<SketchJson>(&everything.unwrap()).unwrap();
+        assert_eq!(everything.strokes.len(), 5);
+        assert_eq!(everything.zoom, sketch.zoom);
+
+        let selected = serde_json::to_string(&SketchJson::export(&sketch, true, 800, 600));
+        let selected = serde_json::from_str::<SketchJson>(&selected.unwrap()).unwrap();
+        let points = selected
+            .strokes
+            .iter()
+            .map(|stroke| stroke.points.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(points, vec![vec![[10., 10., 1.]], vec![[20., 20., 1.]]]);
+
+        // fit to the two strokes
+        assert_eq!(selected.origin, [-15., -15.]);
+        assert!(selected.zoom > sketch.zoom);
+    }
+}
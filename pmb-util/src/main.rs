@@ -2,14 +2,24 @@ use anyhow::Result;
 use gumdrop::Options;
 use powdermilk_biscuits::{
     config::Config,
-    migrate::{self, v1, v2, v3, v4, v5, v6, v7, v8, Version},
-    Sketch,
+    graphics::StrokePos,
+    migrate::{
+        self, v1, v10, v11, v12, v13, v14, v15, v16, v17, v2, v3, v4, v5, v6, v7, v8, v9, Version,
+    },
+    session::SessionLog,
+    Sketch, StrokeBackend,
 };
 use std::{
     io::Read,
     path::{Path, PathBuf},
 };
 
+mod analyze;
+mod csv;
+mod export;
+mod json;
+mod replay;
+
 #[derive(gumdrop::Options, Debug)]
 pub struct Args {
     #[options(help = "Show this message")]
@@ -45,20 +55,110 @@ pub struct Args {
     )]
     dry_run: bool,
 
+    #[options(
+        help = "Write only the strokes with this tag to a new file",
+        no_short,
+        meta = "TAG"
+    )]
+    extract_tag: Option<String>,
+
+    #[options(help = "Print the sketch as JSON", no_short)]
+    dump_json: bool,
+
+    #[options(
+        help = "Only export the strokes entirely inside the rectangle X1,Y1,X2,Y2, zoomed to fit them. Requires --dump-json or --export-png",
+        no_short,
+        meta = "RECT"
+    )]
+    select: Option<String>,
+
+    #[options(
+        help = "Read a sketch from JSON made by --dump-json and save it as a PMB file",
+        no_short
+    )]
+    from_json: bool,
+
+    #[options(
+        help = "Read strokes from CSV with stroke,x,y,pressure on each line and save them as a PMB file",
+        no_short
+    )]
+    from_csv: bool,
+
+    #[options(
+        help = "Report how many points each stroke has and how many could be simplified away"
+    )]
+    analyze: bool,
+
+    #[options(
+        help = "How far simplifying can move a stroke. Requires --analyze",
+        no_short,
+        meta = "TOL"
+    )]
+    tolerance: Option<f32>,
+
+    #[options(
+        help = "Draw a session recorded in PMB with the default settings and save the sketch it ends with",
+        no_short
+    )]
+    replay: bool,
+
+    #[options(help = "Draw the sketch and save it as a PNG", no_short)]
+    export_png: bool,
+
+    #[options(
+        help = "How wide the PNG is in pixels. Requires --export-png or --select",
+        no_short,
+        meta = "PIXELS"
+    )]
+    width: Option<u32>,
+
+    #[options(
+        help = "How tall the PNG is in pixels. Requires --export-png or --select",
+        no_short,
+        meta = "PIXELS"
+    )]
+    height: Option<u32>,
+
     #[options(free, help = "File to analyze")]
     path: Option<PathBuf>,
+
+    #[options(
+        free,
+        help = "Where to save the file made by --from-json, --from-csv, --replay, or --export-png"
+    )]
+    out: Option<PathBuf>,
 }
 
 fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
+    // stdout is for what was asked for, like --dump-json
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
     let args = Args::parse_args_default_or_exit();
 
-    if 1 < [args.version, args.print_default_config, args.migrate]
-        .into_iter()
-        .fold(0, |acc, b| if b { acc + 1 } else { acc })
+    if 1 < [
+        args.version,
+        args.print_default_config,
+        args.migrate,
+        args.extract_tag.is_some(),
+        args.dump_json,
+        args.from_json,
+        args.from_csv,
+        args.analyze,
+        args.replay,
+        args.export_png,
+    ]
+    .into_iter()
+    .fold(0, |acc, b| if b { acc + 1 } else { acc })
         || (!args.migrate && (args.migrate_in_place || args.dry_run))
         || (args.migrate_in_place && args.dry_run)
         || (args.print_default_config_debug && !args.print_default_config)
+        || (args.out.is_some()
+            && !(args.from_json || args.from_csv || args.replay || args.export_png))
+        || (args.tolerance.is_some() && !args.analyze)
+        || ((args.width.is_some() || args.height.is_some())
+            && !(args.export_png || args.select.is_some()))
+        || (args.select.is_some() && !(args.dump_json || args.export_png))
     {
         println!("{}", Args::usage());
         return Err(anyhow::anyhow!("Invalid usage"));
@@ -83,9 +183,125 @@ fn main() -> Result<()> {
     }
 
     if let Some(path) = args.path.as_ref() {
+        if args.from_json || args.from_csv {
+            let sketch = if args.from_json {
+                let json =
+                    serde_json::from_reader::<_, json::SketchJson>(std::fs::File::open(path)?)?;
+                json.into_sketch()?
+            } else {
+                let defaults = Sketch::<()>::default();
+                let brush_size = powdermilk_biscuits::DEFAULT_BRUSH as f32 / defaults.zoom;
+                let strokes = csv::parse(
+                    &std::fs::read_to_string(path)?,
+                    defaults.fg_color,
+                    brush_size,
+                )?;
+                Sketch::new(strokes)
+            };
+
+            let write_path = args
+                .out
+                .clone()
+                .unwrap_or_else(|| path.with_extension("pmb"));
+            if write_path.exists() {
+                return Err(anyhow::anyhow!("{} already exists", write_path.display()));
+            }
+
+            println!(
+                "Saving {} strokes as {}",
+                sketch.strokes.len(),
+                write_path.display()
+            );
+            migrate::write(write_path, &sketch)?;
+            return Ok(());
+        }
+
+        if args.replay {
+            let log = SessionLog::read(path)?;
+            let sketch = replay::replay(&Config::new(), &log);
+
+            let write_path = args
+                .out
+                .clone()
+                .unwrap_or_else(|| path.with_extension("pmb"));
+            if write_path.exists() {
+                return Err(anyhow::anyhow!("{} already exists", write_path.display()));
+            }
+
+            println!(
+                "Replayed {} events, saving {} strokes as {}",
+                log.events.len(),
+                sketch.strokes.len(),
+                write_path.display()
+            );
+            migrate::write(write_path, &sketch)?;
+            return Ok(());
+        }
+
+        let width = args.width.unwrap_or(export::DEFAULT_WIDTH);
+        let height = args.height.unwrap_or(export::DEFAULT_HEIGHT);
+        let select = args.select.as_deref().map(parse_rect).transpose()?;
+
+        // stdout is only the JSON so it can be piped somewhere
+        if args.dump_json {
+            let mut sketch = read_latest::<()>(path, look_at(path)?.as_ref())?;
+            if let Some((a, b)) = select {
+                sketch.select_rect(a, b);
+            }
+            let json = json::SketchJson::export(&sketch, select.is_some(), width, height);
+            println!("{}", serde_json::to_string_pretty(&json)?);
+            return Ok(());
+        }
+
         println!("Analyzing {}", path.display());
         let about = look_at(path)?;
 
+        if args.analyze {
+            let sketch = read_latest::<()>(path, about.as_ref())?;
+            let tolerance = args.tolerance.unwrap_or(analyze::DEFAULT_TOLERANCE);
+            analyze::Report::new(&sketch, tolerance).show();
+            return Ok(());
+        }
+
+        if args.export_png {
+            let write_path = args
+                .out
+                .clone()
+                .unwrap_or_else(|| path.with_extension("png"));
+            if write_path.exists() {
+                return Err(anyhow::anyhow!("{} already exists", write_path.display()));
+            }
+
+            println!(
+                "Saving a {}x{} image as {}",
+                width,
+                height,
+                write_path.display()
+            );
+            export::to_png(path, about.as_ref(), &write_path, width, height, select)?;
+            return Ok(());
+        }
+
+        if let Some(tag) = args.extract_tag.as_ref() {
+            let sketch = read_latest::<()>(path, about.as_ref())?;
+
+            let tagged = sketch.only_tagged(tag);
+            let write_path = PathBuf::from(format!(
+                "{}_{}.pmb",
+                path.file_stem().unwrap().to_str().unwrap(),
+                tag,
+            ));
+
+            println!(
+                "Saving {} strokes tagged {:?} as {}",
+                tagged.strokes.len(),
+                tag,
+                write_path.display()
+            );
+            migrate::write(write_path, &tagged)?;
+            return Ok(());
+        }
+
         if args.migrate {
             if about.version() == Version::CURRENT {
                 println!("{} already up to date", path.display());
@@ -123,6 +339,32 @@ fn main() -> Result<()> {
     }
 }
 
+/// the corners of a rectangle written X1,Y1,X2,Y2
+fn parse_rect(rect: &str) -> Result<(StrokePos, StrokePos)> {
+    let numbers = rect
+        .split(',')
+        .map(|number| number.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| anyhow::anyhow!("{:?} isn't a rectangle: {}", rect, err))?;
+
+    match numbers[..] {
+        [x1, y1, x2, y2] => Ok((StrokePos { x: x1, y: y1 }, StrokePos { x: x2, y: y2 })),
+        _ => Err(anyhow::anyhow!(
+            "{:?} isn't a rectangle, it should be X1,Y1,X2,Y2",
+            rect
+        )),
+    }
+}
+
+/// read the file, upgrading it to the current version if it's older
+fn read_latest<S: StrokeBackend>(path: &Path, about: &dyn About) -> Result<Sketch<S>> {
+    if about.version() == Version::CURRENT {
+        Ok(migrate::read::<S>(std::fs::File::open(path)?)?)
+    } else {
+        Ok(migrate::from::<S>(about.version(), path)?)
+    }
+}
+
 pub fn look_at(path: &Path) -> Result<Box<dyn About>> {
     let mut file = std::fs::File::open(path)?;
     let mut magic = [0; 3];
@@ -159,7 +401,7 @@ pub fn look_at(path: &Path) -> Result<Box<dyn About>> {
         };
     }
 
-    read!(1, 2, 3, 4, 5, 6, 7, 8)
+    read!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17)
 }
 
 pub trait About {
@@ -222,7 +464,7 @@ pub trait About {
 
 impl About for Sketch<()> {
     fn changes(&self) -> &'static str {
-        "Added foreground color, removed erased strokes"
+        "Added layers"
     }
 
     fn version(&self) -> Version {
@@ -250,6 +492,276 @@ impl About for Sketch<()> {
     }
 }
 
+impl About for v17::SketchV17 {
+    fn changes(&self) -> &'static str {
+        "Added closed strokes"
+    }
+
+    fn version(&self) -> Version {
+        Version(17)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
+impl About for v16::SketchV16 {
+    fn changes(&self) -> &'static str {
+        "Added locked strokes"
+    }
+
+    fn version(&self) -> Version {
+        Version(16)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
+impl About for v15::SketchV15 {
+    fn changes(&self) -> &'static str {
+        "Added stroke styles"
+    }
+
+    fn version(&self) -> Version {
+        Version(15)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
+impl About for v14::SketchV14 {
+    fn changes(&self) -> &'static str {
+        "Added stroke depth"
+    }
+
+    fn version(&self) -> Version {
+        Version(14)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
+impl About for v13::SketchV13 {
+    fn changes(&self) -> &'static str {
+        "Added crisp strokes"
+    }
+
+    fn version(&self) -> Version {
+        Version(13)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
+impl About for v12::SketchV12 {
+    fn changes(&self) -> &'static str {
+        "Added stroke clipping"
+    }
+
+    fn version(&self) -> Version {
+        Version(12)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
+impl About for v11::SketchV11 {
+    fn changes(&self) -> &'static str {
+        "Added stroke groups"
+    }
+
+    fn version(&self) -> Version {
+        Version(11)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
+impl About for v10::SketchV10 {
+    fn changes(&self) -> &'static str {
+        "Added stroke tags"
+    }
+
+    fn version(&self) -> Version {
+        Version(10)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
+impl About for v9::SketchV9 {
+    fn changes(&self) -> &'static str {
+        "Added foreground color, removed erased strokes"
+    }
+
+    fn version(&self) -> Version {
+        Version(9)
+    }
+
+    fn num_strokes(&self) -> usize {
+        self.strokes.len()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    fn origin(&self) -> (f32, f32) {
+        (self.origin.x, self.origin.y)
+    }
+
+    fn bg_color(&self) -> Option<[f32; 3]> {
+        Some(self.bg_color)
+    }
+
+    fn fg_color(&self) -> Option<[f32; 3]> {
+        Some(self.fg_color)
+    }
+}
+
 impl About for v8::SketchV8 {
     fn changes(&self) -> &'static str {
         "Identical to v7"
@@ -0,0 +1,96 @@
+//! draw a recorded session again, without a window
+
+use powdermilk_biscuits::{
+    config::Config,
+    null::{NullCoords, NullStrokeBackend},
+    session::SessionLog,
+    ui::widget::SketchWidget,
+    Sketch,
+};
+
+/// the sketch the session ends with, if it started from an empty one
+pub fn replay(config: &Config, log: &SessionLog) -> Sketch<NullStrokeBackend> {
+    let mut sketch = Sketch::empty();
+    let mut widget = SketchWidget::<NullCoords>::headless(log.width, log.height);
+    for event in log.events() {
+        widget.next(config, &mut sketch, event);
+    }
+    sketch
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use powdermilk_biscuits::{
+        event::Event,
+        winit::{
+            dpi::PhysicalPosition,
+            event::{DeviceId, Force, PenInfo, Touch, TouchPhase},
+        },
+    };
+
+    fn pen(phase: TouchPhase, x: f64, y: f64) -> Touch {
+        Touch {
+            device_id: unsafe { DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition { x, y },
+            force: Some(Force::Normalized(x / 1000.)),
+            id: 0,
+            pen_info: Some(PenInfo {
+                barrel: false,
+                inverted: false,
+                eraser: false,
+            }),
+        }
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+
+        assert!(widget.stop_recording().events.is_empty());
+
+        widget.start_recording();
+        for stroke in 0..3 {
+            let y = 100. + stroke as f64 * 50.;
+            widget.next(
+                &config,
+                &mut sketch,
+                Event::PenDown(pen(TouchPhase::Started, 100., y)),
+            );
+            for i in 1..10 {
+                let x = 100. + i as f64 * 15.;
+                widget.next(
+                    &config,
+                    &mut sketch,
+                    Event::PenMove(pen(TouchPhase::Moved, x, y)),
+                );
+            }
+            widget.next(
+                &config,
+                &mut sketch,
+                Event::PenUp(pen(TouchPhase::Ended, 250., y)),
+            );
+        }
+        let log = widget.stop_recording();
+        assert!(!widget.is_recording());
+        assert_eq!(log.events.len(), 33);
+        assert!(log.events.windows(2).all(|pair| pair[0].ms <= pair[1].ms));
+
+        let dir = powdermilk_biscuits::scratch::dir("session");
+        let path = dir.join("session.ron");
+        log.write(&path).unwrap();
+        let read = SessionLog::read(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read, log);
+
+        let replayed = replay(&config, &read);
+        assert_eq!(replayed.strokes.len(), 3);
+        assert_eq!(replayed.content_hash(), sketch.content_hash());
+        for (a, b) in sketch.strokes.values().zip(replayed.strokes.values()) {
+            assert_eq!(a.points(), b.points());
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! drawing a long spline stroke one point at a time, rebuilding the spline every time versus
+//! keeping it up to date. run with `cargo bench -p powdermilk-biscuits --bench spline`
+
+use powdermilk_biscuits::{spline::Spline, stroke::StrokeElement};
+use std::time::{Duration, Instant};
+
+/// the best of a few runs, so the first one warming up doesn't count. `f` returns how many samples
+/// it made so the work can't be optimized out
+fn time(f: impl Fn() -> usize) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            assert_ne!(f(), 0);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    for len in [100, 1000, 5000] {
+        let points = (0..len)
+            .map(|i| StrokeElement {
+                x: i as f32 * 0.01,
+                y: (i as f32 * 0.3).sin(),
+                pressure: 0.5,
+            })
+            .collect::<Vec<_>>();
+
+        let rebuild = time(|| {
+            (1..=points.len())
+                .map(|end| Spline::new(&points[..end]).samples().len())
+                .sum()
+        });
+
+        let incremental = time(|| {
+            let mut spline = Spline::default();
+            (1..=points.len())
+                .map(|end| {
+                    spline.update(&points[..end]);
+                    spline.samples().len()
+                })
+                .sum()
+        });
+
+        println!(
+            "{len:>5} points: rebuild {rebuild:>12?}, incremental {incremental:>12?} ({:.1}x)",
+            rebuild.as_secs_f64() / incremental.as_secs_f64()
+        );
+    }
+}
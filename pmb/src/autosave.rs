@@ -0,0 +1,177 @@
+//! copies of modified sketches written next to their files
+//!
+//! once a sketch with unsaved changes goes a while without changing, it's written to
+//! `<file>.autosave`, or a file in the temp directory if it doesn't have one yet. saving the sketch
+//! removes it. opening a file with an autosave newer than it asks whether to use the autosave.
+
+use crate::{error::PmbError, migrate, Sketch, StrokeBackend};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// where sketches that haven't been saved anywhere get autosaved
+pub const UNTITLED_FILE_NAME: &str = "untitled.pmb.autosave";
+
+/// decides when to write the autosave
+#[derive(Debug, Default, Clone)]
+pub struct Autosave {
+    /// how many changes the undo stack had the last time it changed, and when. None if there
+    /// aren't any unsaved changes
+    pending: Option<(u64, Instant)>,
+    /// how many changes there were when the autosave was written and where it went, if there is
+    /// one
+    written: Option<(u64, PathBuf)>,
+
+    /// how long the sketch has to go without changing. None never autosaves
+    pub interval: Option<Duration>,
+}
+
+impl Autosave {
+    pub fn new(interval: Option<Duration>) -> Self {
+        Autosave {
+            pending: None,
+            written: None,
+            interval,
+        }
+    }
+
+    /// the sketch's undo stack is at `changes` now, and whether that's been saved
+    pub fn update(&mut self, modified: bool, changes: u64, now: Instant) {
+        if !modified {
+            self.pending = None;
+        } else if self.pending.map(|(pending, _)| pending) != Some(changes) {
+            self.pending = Some((changes, now));
+        }
+    }
+
+    /// when the autosave should be written
+    pub fn deadline(&self) -> Option<Instant> {
+        let (changes, at) = self.pending?;
+        if matches!(self.written, Some((written, _)) if written == changes) {
+            return None;
+        }
+        Some(at + self.interval?)
+    }
+
+    /// where to write the autosave of `file`, if it's time to. only says so once per change
+    pub fn take(&mut self, now: Instant, file: Option<&Path>) -> Option<PathBuf> {
+        match (self.deadline(), self.pending) {
+            (Some(deadline), Some((changes, _))) if deadline <= now => {
+                let path = path_for(file);
+                self.written = Some((changes, path.clone()));
+                Some(path)
+            }
+            _ => None,
+        }
+    }
+
+    /// the autosave to remove, if the sketch was saved after it was written
+    pub fn discard(&mut self) -> Option<PathBuf> {
+        if self.pending.is_none() {
+            self.forget()
+        } else {
+            None
+        }
+    }
+
+    /// the autosave to remove no matter what, like when the sketch is closed
+    pub fn forget(&mut self) -> Option<PathBuf> {
+        self.written.take().map(|(_, path)| path)
+    }
+}
+
+/// where the autosave of `file` goes
+pub fn path_for(file: Option<&Path>) -> PathBuf {
+    match file {
+        Some(file) => {
+            let mut name = OsString::from(file.as_os_str());
+            name.push(".autosave");
+            PathBuf::from(name)
+        }
+        None => std::env::temp_dir().join(UNTITLED_FILE_NAME),
+    }
+}
+
+/// the autosave of `file`, if it was written after `file` was
+pub fn newer_than(file: &Path) -> Option<PathBuf> {
+    let autosave = path_for(Some(file));
+    let modified = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    };
+    (modified(&autosave)? > modified(file)?).then_some(autosave)
+}
+
+pub fn write<S: StrokeBackend>(path: &Path, sketch: &Sketch<S>) -> Result<(), PmbError> {
+    migrate::write_atomic(path, |file| migrate::encode(file, sketch))
+}
+
+/// remove an autosave that isn't needed anymore
+pub fn remove(path: &Path) {
+    if let Err(err) = std::fs::remove_file(path) {
+        tracing::error!("couldn't remove {}: {}", path.display(), err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn waits_for_quiet() {
+        let start = Instant::now();
+        let secs = Duration::from_secs;
+        let mut autosave = Autosave::new(Some(secs(10)));
+
+        // nothing to save
+        autosave.update(false, 0, start);
+        assert_eq!(autosave.deadline(), None);
+
+        // still drawing
+        for (at, changes) in [(0, 1), (4, 2), (13, 3)] {
+            autosave.update(true, changes, start + secs(at));
+            assert_eq!(autosave.take(start + secs(at), None), None);
+        }
+        autosave.update(true, 3, start + secs(20));
+        assert_eq!(autosave.deadline(), Some(start + secs(23)));
+        assert_eq!(autosave.take(start + secs(22), None), None);
+        let untitled = std::env::temp_dir().join(UNTITLED_FILE_NAME);
+        assert_eq!(
+            autosave.take(start + secs(23), None),
+            Some(untitled.clone())
+        );
+        assert_eq!(autosave.take(start + secs(30), None), None);
+        assert_eq!(autosave.discard(), None);
+
+        // saved, so the autosave goes away
+        autosave.update(false, 3, start + secs(40));
+        assert_eq!(autosave.discard(), Some(untitled));
+        assert_eq!(autosave.discard(), None);
+
+        let mut never = Autosave::new(None);
+        never.update(true, 1, start);
+        assert_eq!(never.deadline(), None);
+        assert_eq!(never.take(start + secs(1000), None), None);
+    }
+
+    #[test]
+    fn newer_autosave() {
+        let dir = crate::scratch::dir("autosave");
+        let file = dir.join("sketch.pmb");
+        assert_eq!(path_for(Some(&file)), dir.join("sketch.pmb.autosave"));
+
+        migrate::write(&file, &Sketch::<()>::default()).unwrap();
+        assert_eq!(newer_than(&file), None);
+
+        std::thread::sleep(Duration::from_millis(20));
+        let autosave = path_for(Some(&file));
+        write(&autosave, &Sketch::<()>::default()).unwrap();
+        assert_eq!(newer_than(&file), Some(autosave.clone()));
+        assert!(migrate::read::<()>(std::fs::File::open(&autosave).unwrap()).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
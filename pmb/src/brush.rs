@@ -0,0 +1,260 @@
+//! what turns the points of a stroke into a mesh
+//!
+//! each stroke style has a [`BrushEngine`] that builds its meshes. the built-in ones cover every
+//! style, and [`Brushes`] can swap any of them out for another engine. strokes keep a copy of the
+//! brushes the same way they keep the tessellator, so they can rebuild their meshes on their own.
+
+use crate::{
+    stroke::{MeshBuffer, Stroke, StrokeElement, StrokeStyle},
+    tess::{native, TessellatorKind},
+};
+use lyon::lyon_tessellation::{StrokeOptions, StrokeTessellator, TessellationError};
+use std::{collections::HashMap, sync::Arc};
+
+/// everything about a stroke a brush engine might need besides its points
+pub struct BrushConfig<'a> {
+    pub tessellator: &'a mut StrokeTessellator,
+    pub stroke_options: &'a StrokeOptions,
+    pub kind: TessellatorKind,
+    pub brush_size: f32,
+    /// brush sizes between stamps, or 0 for one on every point
+    pub stamp_spacing: f32,
+    /// whether the last point joins back up with the first
+    pub closed: bool,
+}
+
+pub trait BrushEngine: Send + Sync {
+    fn build_mesh(
+        &self,
+        points: &[StrokeElement],
+        config: &mut BrushConfig<'_>,
+    ) -> Result<MeshBuffer, TessellationError>;
+
+    /// the same mesh, split into as many as it takes for strokes too big for one. engines that
+    /// never get near [`MAX_VERTICES`](crate::mesh::MAX_VERTICES) don't need to split anything
+    fn build_chunks(
+        &self,
+        points: &[StrokeElement],
+        config: &mut BrushConfig<'_>,
+    ) -> Result<Vec<MeshBuffer>, TessellationError> {
+        self.build_mesh(points, config).map(|mesh| vec![mesh])
+    }
+}
+
+/// a line through every point, as wide as the brush times the pressure
+pub struct LineBrush;
+
+impl BrushEngine for LineBrush {
+    fn build_mesh(
+        &self,
+        points: &[StrokeElement],
+        config: &mut BrushConfig<'_>,
+    ) -> Result<MeshBuffer, TessellationError> {
+        crate::tess::tessellate(
+            config.kind,
+            config.tessellator,
+            config.stroke_options,
+            config.brush_size,
+            points,
+            config.closed,
+        )
+    }
+
+    fn build_chunks(
+        &self,
+        points: &[StrokeElement],
+        config: &mut BrushConfig<'_>,
+    ) -> Result<Vec<MeshBuffer>, TessellationError> {
+        let wide = crate::tess::tessellate_wide(
+            config.kind,
+            config.tessellator,
+            config.stroke_options,
+            config.brush_size,
+            points,
+            config.closed,
+        )?;
+        Ok(crate::tess::chunks(config.kind, &wide))
+    }
+}
+
+/// a line along the smoothed stroke, or through the points if there are too few to smooth
+pub struct SplineBrush;
+
+impl SplineBrush {
+    fn points(points: &[StrokeElement]) -> std::borrow::Cow<'_, [StrokeElement]> {
+        if points.len() >= Stroke::<()>::DEGREE {
+            std::borrow::Cow::Owned(crate::spline::samples(points))
+        } else {
+            std::borrow::Cow::Borrowed(points)
+        }
+    }
+}
+
+impl BrushEngine for SplineBrush {
+    fn build_mesh(
+        &self,
+        points: &[StrokeElement],
+        config: &mut BrushConfig<'_>,
+    ) -> Result<MeshBuffer, TessellationError> {
+        LineBrush.build_mesh(&SplineBrush::points(points), config)
+    }
+
+    fn build_chunks(
+        &self,
+        points: &[StrokeElement],
+        config: &mut BrushConfig<'_>,
+    ) -> Result<Vec<MeshBuffer>, TessellationError> {
+        LineBrush.build_chunks(&SplineBrush::points(points), config)
+    }
+}
+
+/// circles every `stamp_spacing` brush sizes along the stroke. always native, since lyon would
+/// only join them up again
+pub struct CircleBrush {
+    /// whether the circles get smaller with less pressure
+    pub pressure: bool,
+}
+
+impl CircleBrush {
+    fn tolerance(config: &BrushConfig<'_>) -> f32 {
+        if config.kind == TessellatorKind::Deterministic {
+            native::DETERMINISTIC_TOLERANCE
+        } else {
+            config.stroke_options.tolerance
+        }
+    }
+}
+
+impl BrushEngine for CircleBrush {
+    fn build_mesh(
+        &self,
+        points: &[StrokeElement],
+        config: &mut BrushConfig<'_>,
+    ) -> Result<MeshBuffer, TessellationError> {
+        let mut mesh = native::circles(
+            CircleBrush::tolerance(config),
+            config.brush_size,
+            config.stamp_spacing * config.brush_size,
+            &crate::tess::looped(points, config.closed),
+            self.pressure,
+        )?;
+        if config.kind == TessellatorKind::Deterministic {
+            native::snap(&mut mesh);
+        }
+        Ok(mesh)
+    }
+
+    fn build_chunks(
+        &self,
+        points: &[StrokeElement],
+        config: &mut BrushConfig<'_>,
+    ) -> Result<Vec<MeshBuffer>, TessellationError> {
+        let wide = native::circles_wide(
+            CircleBrush::tolerance(config),
+            config.brush_size,
+            config.stamp_spacing * config.brush_size,
+            &crate::tess::looped(points, config.closed),
+            self.pressure,
+        )?;
+        Ok(crate::tess::chunks(config.kind, &wide))
+    }
+}
+
+/// the engine every style uses unless it's been swapped out
+pub fn builtin(style: StrokeStyle) -> &'static dyn BrushEngine {
+    match style {
+        // points aren't drawn with their mesh, but it's still there for erasing
+        StrokeStyle::Lines | StrokeStyle::Points => &LineBrush,
+        StrokeStyle::Spline => &SplineBrush,
+        StrokeStyle::Circles => &CircleBrush { pressure: false },
+        StrokeStyle::CirclesPressure => &CircleBrush { pressure: true },
+    }
+}
+
+/// which engine draws each style
+#[derive(Clone, Default)]
+pub struct Brushes {
+    engines: HashMap<StrokeStyle, Arc<dyn BrushEngine>>,
+}
+
+impl Brushes {
+    /// draw strokes in `style` with `engine` from now on
+    pub fn register(&mut self, style: StrokeStyle, engine: impl BrushEngine + 'static) {
+        self.engines.insert(style, Arc::new(engine));
+    }
+
+    pub fn engine(&self, style: StrokeStyle) -> &dyn BrushEngine {
+        match self.engines.get(&style) {
+            Some(engine) => engine.as_ref(),
+            None => builtin(style),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graphics::{Color, ColorExt};
+    use lyon::math::point;
+
+    /// one triangle under the first point, no matter what
+    struct Triangle;
+
+    impl BrushEngine for Triangle {
+        fn build_mesh(
+            &self,
+            points: &[StrokeElement],
+            config: &mut BrushConfig<'_>,
+        ) -> Result<MeshBuffer, TessellationError> {
+            let first = points[0];
+            let size = config.brush_size;
+            let mut mesh = MeshBuffer::new();
+            mesh.vertices = vec![
+                point(first.x, first.y),
+                point(first.x + size, first.y),
+                point(first.x, first.y + size),
+            ];
+            mesh.indices = vec![0, 1, 2];
+            Ok(mesh)
+        }
+    }
+
+    #[test]
+    fn custom_engine() {
+        let mut brushes = Brushes::default();
+        brushes.register(StrokeStyle::Lines, Triangle);
+
+        let mut stroke = Stroke::<()>::with_points(
+            vec![
+                StrokeElement {
+                    x: 1.,
+                    y: 2.,
+                    pressure: 1.,
+                },
+                StrokeElement {
+                    x: 5.,
+                    y: 5.,
+                    pressure: 1.,
+                },
+            ],
+            Color::WHITE,
+        );
+        stroke.brush_size = 0.5;
+        stroke.brushes = brushes;
+
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default();
+        stroke.remesh(&mut tessellator, &options);
+        assert_eq!(stroke.meshes.len(), 1);
+        assert_eq!(
+            stroke.meshes[0].vertices(),
+            [point(1., 2.), point(1.5, 2.), point(1., 2.5)]
+        );
+        assert_eq!(stroke.meshes[0].indices(), [0, 1, 2]);
+
+        // other styles are still built in
+        stroke.style = StrokeStyle::Circles;
+        stroke.remesh(&mut tessellator, &options);
+        assert!(stroke.meshes[0].vertices().len() > 3);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    graphics::{PixelPos, StrokePoint, StrokePos},
+    stroke::Stroke,
+    CoordinateSystem, StrokeBackend,
+};
+
+/// a rectangle in stroke space that strokes are cut off at
+#[derive(Default, Debug, Clone, Copy, PartialEq, pmb_macros::Disk)]
+pub struct ClipRect {
+    pub top_left: StrokePos,
+    pub bottom_right: StrokePos,
+}
+
+/// a clip rectangle in pixels from the top left of the window, for a scissor test
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ClipRect {
+    /// the rectangle with any two opposite corners
+    pub fn new(a: StrokePos, b: StrokePos) -> Self {
+        ClipRect {
+            top_left: StrokePos {
+                x: a.x.min(b.x),
+                y: a.y.max(b.y),
+            },
+            bottom_right: StrokePos {
+                x: a.x.max(b.x),
+                y: a.y.min(b.y),
+            },
+        }
+    }
+
+    /// clip to the area a stroke covers. only its bounding box counts, not its exact shape
+    pub fn from_stroke<S: StrokeBackend>(stroke: &Stroke<S>) -> Option<Self> {
+        if stroke.meshes.is_empty() {
+            return None;
+        }
+
+        Some(ClipRect::new(stroke.top_left, stroke.bottom_right))
+    }
+
+    pub fn contains(&self, pos: StrokePos) -> bool {
+        self.top_left.x <= pos.x
+            && pos.x <= self.bottom_right.x
+            && self.bottom_right.y <= pos.y
+            && pos.y <= self.top_left.y
+    }
+
+    /// where the clip is on screen, cut down to fit in the window
+    pub fn scissor<C: CoordinateSystem>(
+        &self,
+        width: u32,
+        height: u32,
+        zoom: f32,
+        origin: StrokePoint,
+    ) -> ScissorRect {
+        let clamp = |pixel: PixelPos| {
+            (
+                pixel.x.round().clamp(0., width as f32) as u32,
+                pixel.y.round().clamp(0., height as f32) as u32,
+            )
+        };
+
+        let (left, top) = clamp(C::pos_to_pixel(width, height, zoom, origin, self.top_left));
+        let (right, bottom) = clamp(C::pos_to_pixel(
+            width,
+            height,
+            zoom,
+            origin,
+            self.bottom_right,
+        ));
+
+        ScissorRect {
+            x: left,
+            y: top,
+            width: right.saturating_sub(left),
+            height: bottom.saturating_sub(top),
+        }
+    }
+}
+
+impl ScissorRect {
+    pub fn contains(&self, pixel: PixelPos) -> bool {
+        self.x as f32 <= pixel.x
+            && pixel.x < (self.x + self.width) as f32
+            && self.y as f32 <= pixel.y
+            && pixel.y < (self.y + self.height) as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        graphics::{Color, ColorExt},
+        null::NullCoords as Coords,
+        stroke::StrokeElement,
+    };
+
+    fn pos(x: f32, y: f32) -> StrokePos {
+        StrokePos { x, y }
+    }
+
+    #[test]
+    fn corners() {
+        let clip = ClipRect::new(pos(3., -2.), pos(-1., 4.));
+        assert_eq!(clip.top_left, pos(-1., 4.));
+        assert_eq!(clip.bottom_right, pos(3., -2.));
+        assert!(clip.contains(pos(0., 0.)));
+        assert!(clip.contains(pos(3., 4.)));
+        assert!(!clip.contains(pos(3.5, 0.)));
+        assert!(!clip.contains(pos(0., -3.)));
+
+        // a stroke without a mesh doesn't cover anything yet
+        let stroke = Stroke::<()>::with_points(Vec::new(), Color::WHITE);
+        assert!(ClipRect::from_stroke(&stroke).is_none());
+    }
+
+    #[test]
+    fn stroke_cut_at_boundary() {
+        let (width, height, zoom, origin) = (200, 100, 10., StrokePoint::default());
+
+        // the right half of the window
+        let clip = ClipRect::new(pos(0., 10.), pos(100., -10.));
+        let scissor = clip.scissor::<Coords>(width, height, zoom, origin);
+        assert_eq!(
+            scissor,
+            ScissorRect {
+                x: 100,
+                y: 0,
+                width: 100,
+                height: 100,
+            }
+        );
+
+        // a line across the whole window only shows up right of the middle
+        let line = (-9..9).map(|x| StrokeElement {
+            x: x as f32,
+            y: 1.,
+            pressure: 1.,
+        });
+        for point in line {
+            let pixel = Coords::pos_to_pixel(width, height, zoom, origin, (&point).into());
+            assert_eq!(scissor.contains(pixel), point.x >= 0., "{}", point);
+        }
+
+        // a clip entirely off screen doesn't let anything through
+        let off_screen = ClipRect::new(pos(50., 50.), pos(60., 60.));
+        let scissor = off_screen.scissor::<Coords>(width, height, zoom, origin);
+        assert_eq!(scissor.width * scissor.height, 0);
+    }
+
+    #[test]
+    fn moves_with_stroke() {
+        use lyon::{
+            lyon_tessellation::{StrokeOptions, StrokeTessellator},
+            math::Transform,
+        };
+
+        let mut stroke = Stroke::<()>::with_points(
+            vec![StrokeElement {
+                x: 1.,
+                y: 1.,
+                pressure: 1.,
+            }],
+            Color::WHITE,
+        );
+        stroke.clip = Some(ClipRect::new(pos(0., 0.), pos(2., 2.)));
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default();
+
+        stroke.translate(3., -1., &mut tessellator, &options);
+        assert_eq!(stroke.clip, Some(ClipRect::new(pos(3., -1.), pos(5., 1.))));
+
+        // the same as moving it with a transform
+        stroke.transform(&Transform::translation(-3., 1.), &mut tessellator, &options);
+        assert_eq!(stroke.clip, Some(ClipRect::new(pos(0., 0.), pos(2., 2.))));
+    }
+}
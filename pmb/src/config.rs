@@ -1,11 +1,90 @@
 use crate::{
+    eraser::EraserMode,
     error::{PmbError, PmbErrorExt},
     event::Combination,
-    s, Tool,
+    graphics::{AntiAliasing, RendererKind},
+    grid::GridConfig,
+    migrate, s,
+    select::SelectMode,
+    stroke::StrokeStyle,
+    tess::{CapStyle, JoinStyle, TessellatorKind},
+    Tool,
+};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
 };
-use std::path::{Path, PathBuf};
 use winit::event::{MouseButton, VirtualKeyCode as Keycode};
 
+/// where a monitor is on the desktop, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// how much of the window has to be on a monitor to be able to grab it and move it back
+const MIN_VISIBLE: i64 = 32;
+
+impl MonitorRect {
+    /// whether enough of the window is on this monitor to grab it
+    pub fn shows(&self, x: i32, y: i32, width: u32, height: u32) -> bool {
+        let overlap = |start: i32, size: u32, monitor_start: i32, monitor_size: u32| {
+            let end = start as i64 + size as i64;
+            let monitor_end = monitor_start as i64 + monitor_size as i64;
+            end.min(monitor_end) - (start as i64).max(monitor_start as i64)
+        };
+
+        overlap(x, width, self.x, self.width) >= MIN_VISIBLE.min(width as i64)
+            && overlap(y, height, self.y, self.height) >= MIN_VISIBLE.min(height as i64)
+    }
+}
+
+/// what touching the screen with one finger does. more fingers are gestures, which use the
+/// `tool_for_gesture` settings
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum FingerPolicy {
+    /// whichever tool the pen is using
+    #[default]
+    UseTool,
+    Draw,
+    Pan,
+    Ignore,
+}
+
+impl FingerPolicy {
+    pub fn next(self) -> Self {
+        match self {
+            FingerPolicy::UseTool => FingerPolicy::Draw,
+            FingerPolicy::Draw => FingerPolicy::Pan,
+            FingerPolicy::Pan => FingerPolicy::Ignore,
+            FingerPolicy::Ignore => FingerPolicy::UseTool,
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// environment variable with the directory to keep the config file in
+pub const CONFIG_DIR_VAR: &str = "PMB_CONFIG_DIR";
+
+/// the system's config directory, or the working directory when there isn't one
+fn config_dir() -> PathBuf {
+    #[cfg(feature = "desktop")]
+    if let Some(dir) = dirs::config_dir() {
+        return dir;
+    }
+
+    PathBuf::new()
+}
+
 macro_rules! config {
     ($($field:ident : $ty:ty $default:block),* $(,)?) => {
         paste::paste! {
@@ -46,20 +125,58 @@ config!(
     pan_key: Keycode { LShift },
     pen_zoom_key: Keycode { LControl },
     toggle_eraser_pen: Combination { E.into() },
+    cycle_eraser_mode: Combination { Combination::from(LAlt) | E },
+    toggle_lock: Combination { L.into() },
+    recolor_selection: Combination { Combination::from(LAlt) | C },
     brush_increase: Combination { Combination::from(RBracket).repeatable() },
     brush_decrease: Combination { Combination::from(LBracket).repeatable() },
     undo: Combination { Combination::from(LControl).repeatable() | Z },
     redo: Combination { Combination::from(LControl).repeatable() | LShift | Z },
+    redo_alt: Combination { Combination::from(LControl).repeatable() | Y },
     save: Combination { Combination::from(LControl) | S },
     new: Combination { Combination::from(LControl) | N },
     reset_view: Combination { Z.into() },
     open: Combination { Combination::from(LControl) | O },
+    open_document: Combination { Combination::from(LControl) | T },
+    next_document: Combination { Combination::from(LControl) | Tab },
     zoom_out: Combination { Combination::from(LControl) | NumpadSubtract },
     zoom_in: Combination { Combination::from(LControl) | NumpadAdd },
+    finger_policy: FingerPolicy { FingerPolicy::UseTool },
     tool_for_gesture_2: Tool { Tool::Pan },
     tool_for_gesture_3: Tool { Tool::Pan },
     tool_for_gesture_4: Tool { Tool::Pan },
+    two_finger_tap_undo: bool { true },
+    palm_rejection_ms: f32 { 500.0 },
+    eraser_mode: EraserMode { EraserMode::WholeStroke },
+    select_mode: SelectMode { SelectMode::Rectangle },
+    tessellator: TessellatorKind { TessellatorKind::Lyon },
+    mesh_pixel_tolerance: f32 { crate::tess::PIXEL_TOLERANCE },
+    line_cap: CapStyle { CapStyle::Round },
+    line_join: JoinStyle { JoinStyle::Round },
+    default_style: StrokeStyle { StrokeStyle::Lines },
+    use_individual_style: bool { true },
+    stamp_spacing: f32 { 0.5 },
+    anti_aliasing: AntiAliasing { AntiAliasing::Smaa },
+    renderer: RendererKind { RendererKind::Auto },
     max_points_before_split_stroke: Option<usize> { Some(750) },
+    lazy_brush_radius: f32 { 0.0 },
+    pressure_curve: [f32; 4] { crate::pressure::IDENTITY },
+    pressure_min: f32 { 0.0 },
+    pressure_max: f32 { 1.0 },
+    prediction_ms: f32 { 0.0 },
+    auto_straighten: bool { false },
+    auto_shapes: bool { false },
+    shape_tolerance: f32 { 0.05 },
+    resize_debounce_ms: f32 { 50.0 },
+    max_fps: f32 { 0.0 },
+    stroke_idle_ms: f32 { 0.0 },
+    autosave_secs: f32 { 60.0 },
+    require_movement_to_commit: bool { false },
+    commit_movement_px: f32 { 3.0 },
+    template: Option<PathBuf> { None },
+    ruler_units_per_stroke_unit: Option<f32> { None },
+    ruler_unit_name: String { String::from("cm") },
+    grid: GridConfig { GridConfig::default() },
 
     window_start_x: Option<i32> { None },
     window_start_y: Option<i32> { None },
@@ -76,6 +193,8 @@ config!(
     debug_print_strokes: Combination { Combination::INACTIVE },
     debug_dirty_all_strokes: Combination { Combination::INACTIVE },
     debug_toggle_show_info: Combination { Combination::INACTIVE },
+    debug_toggle_language: Combination { Combination::INACTIVE },
+    debug_quit: Combination { Combination::INACTIVE },
     debug_show_info: bool { false },
 );
 
@@ -108,17 +227,31 @@ impl Config {
             debug_print_strokes: Combination::from(LShift) | D,
             debug_dirty_all_strokes: Combination::from(LControl) | D,
             debug_toggle_show_info: Combination::from(LAlt) | D,
+            debug_toggle_language: Combination::from(LAlt) | L,
+            debug_quit: Escape.into(),
             debug_show_info: true,
             ..Config::new()
         }
     }
 
+    /// where the config file goes. PMB_CONFIG_DIR overrides the usual config directory, for
+    /// portable installs and tests
     pub fn config_path() -> Result<PathBuf, PmbError> {
-        let mut path = dirs::config_dir().unwrap();
-        path.push("powdermilk-biscuits");
+        match std::env::var_os(CONFIG_DIR_VAR) {
+            Some(dir) if !dir.is_empty() => Config::config_path_in(dir),
+            _ => {
+                let mut path = config_dir();
+                path.push("powdermilk-biscuits");
+                Config::config_path_in(path)
+            }
+        }
+    }
 
+    /// the config file in `dir`, creating `dir` if it doesn't exist
+    pub fn config_path_in(dir: impl Into<PathBuf>) -> Result<PathBuf, PmbError> {
+        let mut path = dir.into();
         if !path.exists() {
-            std::fs::create_dir(&path)?;
+            std::fs::create_dir_all(&path)?;
         }
 
         path.push("config.ron");
@@ -128,26 +261,41 @@ impl Config {
     // TODO registry/gsettings or something, this is dumb
     pub fn from_disk(path: &Path) -> Config {
         tracing::info!("load config from {}", path.display());
+        let err = match Config::read(path) {
+            Ok(Some(config)) => return config,
+            Ok(None) => return Config::default(),
+            Err(err) => err,
+        };
+
+        tracing::warn!("couldn't read config {}: {}", path.display(), err);
+        let backup = Config::backup_path(path);
+        if let Ok(Some(config)) = Config::read(&backup) {
+            tracing::warn!("using backup config {}", backup.display());
+            // leave the broken one alone so it can be fixed by hand
+            return config.with_error();
+        }
+
+        err.display_with(s!(MboxMessageCouldNotOpenConfigFile));
+        Config::default().with_error()
+    }
+
+    /// None if there's no config file
+    fn read(path: &Path) -> Result<Option<Config>, PmbError> {
         let file = match std::fs::read_to_string(path) {
             Ok(contents) => contents,
-            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
-                return Config::default();
-            }
-            Err(err) => {
-                PmbError::from(err).display_with(s!(MboxMessageCouldNotOpenConfigFile));
-                return Config::default().with_error();
-            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
         };
 
-        match ron::from_str(&file) {
-            Ok(config) => config,
-            Err(err) => {
-                PmbError::from(err).display_with(s!(MboxMessageCouldNotOpenConfigFile));
-                Config::default().with_error()
-            }
-        }
+        Ok(Some(ron::from_str(&file)?))
+    }
+
+    /// the previous config, from before the last save
+    pub fn backup_path(path: &Path) -> PathBuf {
+        with_suffix(path, ".bak")
     }
 
+    /// write the config so it's never half written, keeping the old one as a backup
     pub fn save(&self, path: &Path) {
         tracing::info!("save config to {}", path.display());
 
@@ -157,12 +305,21 @@ impl Config {
             return;
         }
 
-        let contents = self.to_ron_string();
-        if let Err(err) = std::fs::write(path, contents) {
-            PmbError::from(err).display_with(s!(MboxMessageCouldNotOpenConfigFile));
+        if let Err(err) = self.write(path) {
+            err.display_with(s!(MboxMessageCouldNotOpenConfigFile));
         }
     }
 
+    fn write(&self, path: &Path) -> Result<(), PmbError> {
+        if path.exists() {
+            std::fs::copy(path, Config::backup_path(path))?;
+        }
+        migrate::write_atomic(path, |file| {
+            file.write_all(self.to_ron_string().as_bytes())?;
+            Ok(())
+        })
+    }
+
     pub fn to_ron_string(&self) -> String {
         let contents = ron::ser::to_string_pretty(
             self,
@@ -176,6 +333,22 @@ impl Config {
         format!("// this file generated automatically.\n// do not edit while pmb is running!!\n{contents}")
     }
 
+    /// how long a stroke can go without input before it's finished anyway, if at all
+    pub fn stroke_idle_timeout(&self) -> Option<Duration> {
+        (self.stroke_idle_ms > 0.).then(|| Duration::from_secs_f32(self.stroke_idle_ms / 1000.))
+    }
+
+    /// how long after the pen was last seen that fingers touching are ignored, if at all
+    pub fn palm_rejection_window(&self) -> Option<Duration> {
+        (self.palm_rejection_ms > 0.)
+            .then(|| Duration::from_secs_f32(self.palm_rejection_ms / 1000.))
+    }
+
+    /// how long a modified sketch can go without changing before it's autosaved, if at all
+    pub fn autosave_interval(&self) -> Option<Duration> {
+        (self.autosave_secs > 0.).then(|| Duration::from_secs_f32(self.autosave_secs))
+    }
+
     pub fn start_pos(&self) -> (Option<i32>, Option<i32>) {
         (self.window_start_x, self.window_start_y)
     }
@@ -184,12 +357,53 @@ impl Config {
         (self.window_start_width, self.window_start_height)
     }
 
-    pub fn tool_for_gesture(&self, active_tool: Tool, i: u8) -> Tool {
+    /// the style every stroke should be drawn in, if they don't each have their own
+    pub fn style_override(&self) -> Option<StrokeStyle> {
+        (!self.use_individual_style).then_some(self.default_style)
+    }
+
+    /// the tool for a touch with `i` fingers, or `None` if it should be ignored
+    pub fn tool_for_gesture(&self, active_tool: Tool, i: u8) -> Option<Tool> {
         match i {
-            2 => self.tool_for_gesture_2,
-            3 => self.tool_for_gesture_3,
-            4 => self.tool_for_gesture_4,
-            _ => active_tool,
+            1 => match self.finger_policy {
+                FingerPolicy::UseTool => Some(active_tool),
+                FingerPolicy::Draw => Some(Tool::Pen),
+                FingerPolicy::Pan => Some(Tool::Pan),
+                FingerPolicy::Ignore => None,
+            },
+            2 => Some(self.tool_for_gesture_2),
+            3 => Some(self.tool_for_gesture_3),
+            4 => Some(self.tool_for_gesture_4),
+            _ => Some(active_tool),
+        }
+    }
+
+    /// forget where the window was if it wouldn't show up on any of the monitors, like after
+    /// unplugging one. it goes wherever the window manager puts it instead
+    pub fn forget_offscreen_window(&mut self, monitors: &[MonitorRect]) {
+        // can't tell where the monitors are, so don't guess
+        if monitors.is_empty() {
+            return;
+        }
+
+        let (Some(x), Some(y)) = self.start_pos() else {
+            return;
+        };
+
+        let (width, height) = match self.start_size() {
+            (Some(width), Some(height)) => (width, height),
+            _ => (MIN_VISIBLE as u32, MIN_VISIBLE as u32),
+        };
+
+        if !monitors
+            .iter()
+            .any(|monitor| monitor.shows(x, y, width, height))
+        {
+            tracing::warn!("window at {x},{y} {width}x{height} is off screen");
+            self.window_start_x = None;
+            self.window_start_y = None;
+            self.window_start_width = None;
+            self.window_start_height = None;
         }
     }
 
@@ -203,3 +417,138 @@ impl Config {
         self.window_start_y.replace(y);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn config_dir_from_env() {
+        let scratch = crate::scratch::dir("config-dir");
+        let dir = scratch.join("config");
+
+        std::env::set_var(CONFIG_DIR_VAR, &dir);
+        let path = Config::config_path().unwrap();
+        std::env::remove_var(CONFIG_DIR_VAR);
+
+        // the directory gets made if it isn't there
+        assert_eq!(path, dir.join("config.ron"));
+        assert!(dir.is_dir());
+
+        let mut config = Config::new();
+        config.move_window(12, 34);
+        config.save(&path);
+        assert!(path.exists());
+        assert_eq!(Config::from_disk(&path).start_pos(), (Some(12), Some(34)));
+
+        std::fs::remove_dir_all(&scratch).unwrap();
+    }
+
+    #[test]
+    fn atomic_save() {
+        let dir = crate::scratch::dir("atomic-save");
+        let path = dir.join("config.ron");
+
+        let mut config = Config::new();
+        config.move_window(1, 1);
+        config.save(&path);
+        assert!(!Config::backup_path(&path).exists());
+
+        config.move_window(2, 2);
+        config.save(&path);
+
+        // the new config replaced the old one, which is now the backup
+        assert_eq!(Config::from_disk(&path).start_pos(), (Some(2), Some(2)));
+        assert_eq!(
+            Config::from_disk(&Config::backup_path(&path)).start_pos(),
+            (Some(1), Some(1))
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_uses_backup() {
+        let dir = crate::scratch::dir("corrupt-config");
+        let path = dir.join("config.ron");
+
+        let mut config = Config::new();
+        config.move_window(5, 6);
+        config.save(&path);
+        config.save(&path);
+
+        // cut off in the middle of writing
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, &contents[..contents.len() / 2]).unwrap();
+
+        let loaded = Config::from_disk(&path);
+        assert_eq!(loaded.start_pos(), (Some(5), Some(6)));
+
+        // and the broken config doesn't get overwritten
+        loaded.save(&path);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            contents[..contents.len() / 2]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn partial_keybinds() {
+        // bindings left out of the file keep their defaults
+        let config: Config =
+            ron::from_str("(debug_quit: (keys: [LControl, Q], repeatable: false))").unwrap();
+        let keys = |combo: &Combination| format!("{:?}", combo);
+        assert_eq!(keys(&config.debug_quit), "[LControl, Q]");
+        assert_eq!(keys(&config.toggle_lock), "[L]");
+        assert_eq!(keys(&config.debug_toggle_language), "[]");
+    }
+
+    #[test]
+    fn offscreen_window() {
+        // a 1080p monitor with a smaller one to the left of it, a bit lower
+        let monitors = [
+            MonitorRect {
+                x: 0,
+                y: 0,
+                width: 1920,
+                height: 1080,
+            },
+            MonitorRect {
+                x: -1280,
+                y: 200,
+                width: 1280,
+                height: 1024,
+            },
+        ];
+        let at = |x, y, width, height| {
+            let mut config = Config::new();
+            config.move_window(x, y);
+            config.resize_window(width, height);
+            config.forget_offscreen_window(&monitors);
+            config.start_pos() != (None, None)
+        };
+
+        assert!(at(100, 100, 800, 600));
+        assert!(at(-1000, 300, 800, 600));
+        // mostly off the edge but there's enough to grab
+        assert!(at(1880, 1000, 800, 600));
+        assert!(at(-900, 100, 800, 600));
+
+        // in the gap above the left monitor
+        assert!(!at(-1000, 0, 800, 150));
+        // on a monitor that's not there anymore
+        assert!(!at(2500, 100, 800, 600));
+        assert!(!at(0, -2000, 800, 600));
+        // only a sliver is showing
+        assert!(!at(1910, 100, 800, 600));
+
+        // don't do anything if the monitors are unknown
+        let mut config = Config::new();
+        config.move_window(5000, 5000);
+        config.forget_offscreen_window(&[]);
+        assert_eq!(config.start_pos(), (Some(5000), Some(5000)));
+    }
+}
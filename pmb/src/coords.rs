@@ -0,0 +1,135 @@
+//! checks that a [`CoordinateSystem`] gets back to where it started
+//!
+//! each backend writes its own conversions, and it's easy for one direction to forget a flip or a
+//! scale the other has. [`assert_round_trips`] throws a few thousand made up windows, zooms,
+//! origins, and cursor positions at one and makes sure converting there and back again lands on
+//! the same pixel. backends call it from their own tests.
+
+use crate::{
+    graphics::{PixelPos, StrokePoint},
+    CoordinateSystem,
+};
+
+/// how many made up inputs each check gets
+pub const ROUND_TRIPS: usize = 4096;
+
+/// how far off a round trip can land, in pixels
+pub const PIXEL_TOLERANCE: f32 = 0.01;
+
+/// xorshift, so the inputs are the same every run without pulling in a crate for it
+struct Inputs(u64);
+
+impl Inputs {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn between(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next()
+    }
+}
+
+/// panics with the inputs if `C` doesn't convert pixels to ndc, stroke points, or stroke
+/// positions and back again
+pub fn assert_round_trips<C: CoordinateSystem>() {
+    let name = std::any::type_name::<C>();
+    let mut inputs = Inputs(0x5eed_cafe_f00d_d00d);
+
+    for _ in 0..ROUND_TRIPS {
+        let width = inputs.between(1., 4096.) as u32;
+        let height = inputs.between(1., 4096.) as u32;
+        let zoom = inputs
+            .between(crate::MIN_ZOOM.ln(), crate::MAX_ZOOM.ln())
+            .exp();
+        // a few windows away from the middle at most, and the cursor can be outside the window
+        let screens = 4. * width.max(height) as f32 / zoom;
+        let origin = StrokePoint {
+            x: inputs.between(-screens, screens),
+            y: inputs.between(-screens, screens),
+        };
+        let pixel = PixelPos {
+            x: inputs.between(-(width as f32), 2. * width as f32),
+            y: inputs.between(-(height as f32), 2. * height as f32),
+        };
+
+        let check = |what: &str, back: PixelPos| {
+            let off = (back.x - pixel.x).hypot(back.y - pixel.y);
+            assert!(
+                off <= PIXEL_TOLERANCE,
+                "{name}: {what} took {pixel} to {back} ({off} pixels off) in a {width}x{height} \
+                 window at zoom {zoom} with origin {origin}",
+            );
+        };
+
+        check(
+            "pixel → ndc → pixel",
+            C::ndc_to_pixel(width, height, C::pixel_to_ndc(width, height, pixel)),
+        );
+        check(
+            "pixel → stroke → pixel",
+            C::stroke_to_pixel(
+                width,
+                height,
+                zoom,
+                C::pixel_to_stroke(width, height, zoom, pixel),
+            ),
+        );
+        check(
+            "pixel → pos → pixel",
+            C::pos_to_pixel(
+                width,
+                height,
+                zoom,
+                origin,
+                C::pixel_to_pos(width, height, zoom, origin, pixel),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::null::NullCoords;
+
+    #[test]
+    fn null_round_trips() {
+        assert_round_trips::<NullCoords>();
+    }
+
+    /// flips y going to ndc but not coming back
+    #[derive(Debug, Default, Clone, Copy)]
+    struct Unflipped;
+
+    impl CoordinateSystem for Unflipped {
+        type Ndc = PixelPos;
+
+        fn pixel_to_ndc(width: u32, height: u32, pos: PixelPos) -> PixelPos {
+            NullCoords::pixel_to_ndc(width, height, pos)
+        }
+
+        fn ndc_to_pixel(width: u32, height: u32, pos: PixelPos) -> PixelPos {
+            PixelPos {
+                x: (pos.x + 1.) * width as f32 / 2.,
+                y: (pos.y + 1.) * height as f32 / 2.,
+            }
+        }
+
+        fn ndc_to_stroke(width: u32, height: u32, zoom: f32, ndc: PixelPos) -> StrokePoint {
+            NullCoords::ndc_to_stroke(width, height, zoom, ndc)
+        }
+
+        fn stroke_to_ndc(width: u32, height: u32, zoom: f32, point: StrokePoint) -> PixelPos {
+            NullCoords::stroke_to_ndc(width, height, zoom, point)
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel → ndc → pixel")]
+    fn catches_missing_flip() {
+        assert_round_trips::<Unflipped>();
+    }
+}
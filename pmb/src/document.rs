@@ -0,0 +1,146 @@
+//! more than one sketch open at once
+//!
+//! everything that belongs to one sketch lives in its [`Document`]: the strokes, the widget drawing
+//! on them, and through the widget the path, whether it's modified, and the undo history. the
+//! config and the window are shared, and only the focused document gets input and gets drawn.
+
+use crate::{ui::widget::SketchWidget, CoordinateSystem, Sketch, StrokeBackend};
+
+pub struct Document<S: StrokeBackend, C: CoordinateSystem> {
+    pub widget: SketchWidget<C>,
+    pub sketch: Sketch<S>,
+}
+
+impl<S: StrokeBackend, C: CoordinateSystem> Document<S, C> {
+    pub fn new(widget: SketchWidget<C>, sketch: Sketch<S>) -> Self {
+        Document { widget, sketch }
+    }
+}
+
+/// the open documents and which one is focused. there's always at least one
+pub struct Documents<S: StrokeBackend, C: CoordinateSystem> {
+    documents: Vec<Document<S, C>>,
+    focused: usize,
+}
+
+impl<S: StrokeBackend, C: CoordinateSystem> Documents<S, C> {
+    pub fn new(first: Document<S, C>) -> Self {
+        Documents {
+            documents: vec![first],
+            focused: 0,
+        }
+    }
+
+    pub fn focused(&self) -> &Document<S, C> {
+        &self.documents[self.focused]
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Document<S, C> {
+        &mut self.documents[self.focused]
+    }
+
+    /// which one is focused, counting from 0
+    pub fn focused_index(&self) -> usize {
+        self.focused
+    }
+
+    pub fn count(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// open another document and focus it
+    pub fn push(&mut self, document: Document<S, C>) {
+        self.documents.push(document);
+        self.focused = self.documents.len() - 1;
+    }
+
+    /// focus the document after the focused one, going back around to the first
+    pub fn focus_next(&mut self) {
+        self.focused = (self.focused + 1) % self.documents.len();
+    }
+
+    pub fn focus(&mut self, index: usize) {
+        if index < self.documents.len() {
+            self.focused = index;
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Document<S, C>> {
+        self.documents.iter_mut()
+    }
+
+    /// the documents with changes that haven't been saved
+    pub fn modified(&self) -> impl Iterator<Item = usize> + '_ {
+        self.documents
+            .iter()
+            .enumerate()
+            .filter(|(_, document)| document.widget.modified)
+            .map(|(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        event::Event,
+        null::{NullCoords, NullStrokeBackend},
+    };
+    use winit::{
+        dpi::PhysicalPosition,
+        event::{DeviceId, PenInfo, Touch, TouchPhase},
+    };
+
+    fn document() -> Document<NullStrokeBackend, NullCoords> {
+        Document::new(SketchWidget::headless(800, 600), Sketch::empty())
+    }
+
+    fn pen(phase: TouchPhase, x: f64) -> Touch {
+        Touch {
+            device_id: unsafe { DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition { x, y: 100. },
+            force: None,
+            id: 0,
+            pen_info: Some(PenInfo {
+                barrel: false,
+                inverted: false,
+                eraser: false,
+            }),
+        }
+    }
+
+    #[test]
+    fn documents_independent() {
+        let config = Config::new();
+        let mut documents = Documents::new(document());
+        documents.push(document());
+        assert_eq!(documents.count(), 2);
+        assert_eq!(documents.focused_index(), 1);
+
+        let Document { widget, sketch } = documents.focused_mut();
+        widget.next(
+            &config,
+            sketch,
+            Event::PenDown(pen(TouchPhase::Started, 100.)),
+        );
+        widget.next(
+            &config,
+            sketch,
+            Event::PenMove(pen(TouchPhase::Moved, 200.)),
+        );
+        widget.next(&config, sketch, Event::PenUp(pen(TouchPhase::Ended, 200.)));
+        assert_eq!(sketch.strokes.len(), 1);
+        assert!(widget.modified);
+
+        documents.focus_next();
+        assert_eq!(documents.focused_index(), 0);
+        assert!(documents.focused().sketch.strokes.is_empty());
+        assert!(!documents.focused().widget.modified);
+        assert_eq!(documents.modified().collect::<Vec<_>>(), vec![1]);
+
+        documents.focus(5);
+        assert_eq!(documents.focused_index(), 0);
+    }
+}
@@ -0,0 +1,494 @@
+use crate::{
+    graphics::StrokePos,
+    stroke::Stroke,
+    ui::undo::{Action, UndoStack},
+    Sketch, StrokeBackend,
+};
+use lyon::lyon_tessellation::{StrokeOptions, StrokeTessellator};
+
+/// what happens to a stroke when the eraser touches it
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum EraserMode {
+    /// the whole stroke goes away
+    #[default]
+    WholeStroke,
+
+    /// only the points under the eraser go away, splitting the stroke into pieces
+    PointSplit,
+
+    /// the end of the most recent stroke under the eraser is pulled back, like undoing the stroke
+    /// as it was drawn
+    Rewind,
+}
+
+impl EraserMode {
+    pub fn next(self) -> Self {
+        match self {
+            EraserMode::WholeStroke => EraserMode::PointSplit,
+            EraserMode::PointSplit => EraserMode::Rewind,
+            EraserMode::Rewind => EraserMode::WholeStroke,
+        }
+    }
+}
+
+/// erase whatever `hit` says is under the eraser, in the cursor's bounding box. returns whether
+/// anything was erased
+#[allow(clippy::too_many_arguments)]
+pub fn erase<S: StrokeBackend>(
+    mode: EraserMode,
+    sketch: &mut Sketch<S>,
+    undo_stack: &mut UndoStack,
+    tessellator: &mut StrokeTessellator,
+    options: &StrokeOptions,
+    top_left: StrokePos,
+    bottom_right: StrokePos,
+    hit: impl Fn(StrokePos) -> bool,
+) -> bool {
+    let mut touched = sketch.strokes_near(top_left, bottom_right);
+    touched.retain(|key| {
+        let stroke = &sketch.strokes[*key];
+        stroke.visible && !stroke.erased && !stroke.locked && stroke.aabb(top_left, bottom_right)
+    });
+
+    match mode {
+        EraserMode::WholeStroke => {
+            touched.retain(|key| {
+                sketch.strokes[*key].vertices().any(|point| {
+                    hit(StrokePos {
+                        x: point.x,
+                        y: point.y,
+                    })
+                })
+            });
+
+            // erasing part of a group erases the whole group
+            let touched = sketch.with_groups(&touched);
+            for key in touched.iter() {
+                if !sketch.strokes[*key].erased() {
+                    sketch.strokes[*key].erase();
+                    undo_stack.push(Action::EraseStroke(*key));
+                }
+            }
+
+            !touched.is_empty()
+        }
+
+        EraserMode::PointSplit => {
+            touched.retain(|key| {
+                sketch.strokes[*key]
+                    .points()
+                    .iter()
+                    .any(|point| hit(point.into()))
+            });
+
+            for key in touched.iter() {
+                let pieces = sketch.strokes[*key].split_points(|point| !hit(point.into()));
+                let (
+                    color,
+                    brush_size,
+                    tags,
+                    group_id,
+                    clip,
+                    crisp,
+                    depth,
+                    style,
+                    tessellator_kind,
+                    stamp_spacing,
+                    brushes,
+                    style_override,
+                ) = {
+                    let stroke = &mut sketch.strokes[*key];
+                    stroke.erase();
+                    (
+                        stroke.color,
+                        stroke.brush_size,
+                        stroke.tags.clone(),
+                        stroke.group_id,
+                        stroke.clip,
+                        stroke.crisp,
+                        stroke.depth,
+                        stroke.style,
+                        stroke.tessellator,
+                        stroke.stamp_spacing,
+                        stroke.brushes.clone(),
+                        stroke.style_override,
+                    )
+                };
+
+                // the pieces stay where the stroke was among the others
+                let mut below = *key;
+                let into = pieces
+                    .into_iter()
+                    .map(|points| {
+                        let mut piece = Stroke::new(color, brush_size, true);
+                        piece.points = points;
+                        piece.tags = tags.clone();
+                        piece.group_id = group_id;
+                        piece.clip = clip;
+                        piece.crisp = crisp;
+                        piece.depth = depth;
+                        piece.style = style;
+                        piece.tessellator = tessellator_kind;
+                        piece.stamp_spacing = stamp_spacing;
+                        piece.brushes = brushes.clone();
+                        piece.style_override = style_override;
+                        piece.remesh(tessellator, options);
+                        piece.finish();
+                        below = sketch.add_stroke_above(below, piece);
+                        below
+                    })
+                    .collect();
+
+                undo_stack.push(Action::SplitStroke { from: *key, into });
+            }
+
+            !touched.is_empty()
+        }
+
+        EraserMode::Rewind => {
+            // they're in drawing order, so the last one is the one drawn most recently
+            let Some(key) = touched.into_iter().rev().find(|key| {
+                sketch.strokes[*key]
+                    .points()
+                    .last()
+                    .map(|point| hit(point.into()))
+                    .unwrap_or(false)
+            }) else {
+                return false;
+            };
+
+            // the stroke only gets smaller, so the index can keep the bounds it had
+            let stroke = &mut sketch.strokes[key];
+            let popped = stroke.pop_points_while(|point| hit(point.into()));
+            if stroke.points().is_empty() {
+                stroke.erase();
+            } else {
+                stroke.remesh(tessellator, options);
+            }
+
+            // keep rewinding the same stroke as one action
+            match undo_stack.last_mut() {
+                Some(Action::RewindStroke { key: last, points }) if *last == key => {
+                    points.extend(popped);
+                }
+                _ => undo_stack.push(Action::RewindStroke {
+                    key,
+                    points: popped,
+                }),
+            }
+
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        graphics::{Color, ColorExt},
+        stroke::StrokeElement,
+    };
+    use lyon::lyon_tessellation::{LineCap, LineJoin};
+
+    // a horizontal line from (0, 0) to (10, 0)
+    fn line() -> (Sketch<()>, slotmap::DefaultKey) {
+        let mut sketch = Sketch::empty();
+        let mut stroke = Stroke::with_points(
+            (0..=10)
+                .map(|x| StrokeElement {
+                    x: x as f32,
+                    y: 0.,
+                    pressure: 1.,
+                })
+                .collect(),
+            Color::WHITE,
+        );
+        stroke.brush_size = 0.1;
+        stroke.remesh(&mut StrokeTessellator::new(), &options());
+        let key = sketch.add_stroke(stroke);
+        (sketch, key)
+    }
+
+    fn options() -> StrokeOptions {
+        StrokeOptions::default()
+            .with_line_cap(LineCap::Round)
+            .with_line_join(LineJoin::Round)
+            .with_tolerance(0.001)
+            .with_variable_line_width(0)
+    }
+
+    // erase everything within 1.5 of (x, 0)
+    fn erase_at(
+        mode: EraserMode,
+        sketch: &mut Sketch<()>,
+        undo_stack: &mut UndoStack,
+        x: f32,
+    ) -> bool {
+        erase(
+            mode,
+            sketch,
+            undo_stack,
+            &mut StrokeTessellator::new(),
+            &options(),
+            StrokePos { x: x - 1.5, y: 1.5 },
+            StrokePos {
+                x: x + 1.5,
+                y: -1.5,
+            },
+            |pos| (pos.x - x).hypot(pos.y) <= 1.5,
+        )
+    }
+
+    fn xs(stroke: &Stroke<()>) -> Vec<f32> {
+        stroke.points().iter().map(|point| point.x).collect()
+    }
+
+    #[test]
+    fn whole_stroke() {
+        let (mut sketch, key) = line();
+        let mut undo_stack = UndoStack::new();
+
+        assert!(!erase_at(
+            EraserMode::WholeStroke,
+            &mut sketch,
+            &mut undo_stack,
+            20.
+        ));
+        assert!(!sketch.strokes[key].erased());
+
+        assert!(erase_at(
+            EraserMode::WholeStroke,
+            &mut sketch,
+            &mut undo_stack,
+            5.
+        ));
+        assert!(sketch.strokes[key].erased());
+        assert_eq!(sketch.strokes[key].points().len(), 11);
+        assert_eq!(undo_stack.last(), Some(Action::EraseStroke(key)));
+    }
+
+    #[test]
+    fn whole_group() {
+        let (mut sketch, key) = line();
+        let far = sketch
+            .strokes
+            .insert(Stroke::with_points(Vec::new(), Color::WHITE));
+        let other = sketch
+            .strokes
+            .insert(Stroke::with_points(Vec::new(), Color::WHITE));
+        sketch.group(&[key, far]);
+        let mut undo_stack = UndoStack::new();
+
+        assert!(erase_at(
+            EraserMode::WholeStroke,
+            &mut sketch,
+            &mut undo_stack,
+            5.
+        ));
+        assert!(sketch.strokes[key].erased());
+        assert!(sketch.strokes[far].erased());
+        assert!(!sketch.strokes[other].erased());
+    }
+
+    #[test]
+    fn point_split() {
+        let (mut sketch, key) = line();
+        let mut undo_stack = UndoStack::new();
+
+        assert!(erase_at(
+            EraserMode::PointSplit,
+            &mut sketch,
+            &mut undo_stack,
+            5.
+        ));
+        assert!(sketch.strokes[key].erased());
+
+        let Some(Action::SplitStroke { from, into }) = undo_stack.last() else {
+            panic!("expected a split, got {:?}", undo_stack.last());
+        };
+        assert_eq!(from, key);
+        assert_eq!(into.len(), 2);
+        assert_eq!(xs(&sketch.strokes[into[0]]), vec![0., 1., 2., 3.]);
+        assert_eq!(xs(&sketch.strokes[into[1]]), vec![7., 8., 9., 10.]);
+        assert!(into.iter().all(|piece| !sketch.strokes[*piece].erased()));
+        assert!(into
+            .iter()
+            .all(|piece| sketch.strokes[*piece].vertices().count() > 0));
+
+        // erasing the end of a piece only shortens it
+        assert!(erase_at(
+            EraserMode::PointSplit,
+            &mut sketch,
+            &mut undo_stack,
+            10.
+        ));
+        let Some(Action::SplitStroke {
+            into: shortened, ..
+        }) = undo_stack.last()
+        else {
+            panic!("expected a split, got {:?}", undo_stack.last());
+        };
+        assert_eq!(shortened.len(), 1);
+        assert_eq!(xs(&sketch.strokes[shortened[0]]), vec![7., 8.]);
+    }
+
+    #[test]
+    fn rewind() {
+        let (mut sketch, key) = line();
+        let mut undo_stack = UndoStack::new();
+
+        // only the end of the stroke can be rewound
+        assert!(!erase_at(
+            EraserMode::Rewind,
+            &mut sketch,
+            &mut undo_stack,
+            5.
+        ));
+        assert_eq!(sketch.strokes[key].points().len(), 11);
+
+        assert!(erase_at(
+            EraserMode::Rewind,
+            &mut sketch,
+            &mut undo_stack,
+            10.
+        ));
+        assert_eq!(
+            xs(&sketch.strokes[key]),
+            vec![0., 1., 2., 3., 4., 5., 6., 7., 8.]
+        );
+
+        assert!(erase_at(
+            EraserMode::Rewind,
+            &mut sketch,
+            &mut undo_stack,
+            8.
+        ));
+        assert_eq!(xs(&sketch.strokes[key]), vec![0., 1., 2., 3., 4., 5., 6.]);
+        assert!(!sketch.strokes[key].erased());
+
+        // both rewinds are one action, most recently removed point first
+        let Some(Action::RewindStroke {
+            key: rewound,
+            points,
+        }) = undo_stack.last()
+        else {
+            panic!("expected a rewind, got {:?}", undo_stack.last());
+        };
+        assert_eq!(rewound, key);
+        assert_eq!(
+            points.iter().map(|point| point.x).collect::<Vec<_>>(),
+            vec![10., 9., 8., 7.]
+        );
+
+        // rewinding the rest of the stroke gets rid of it
+        for x in [6., 4., 1.5] {
+            assert!(erase_at(
+                EraserMode::Rewind,
+                &mut sketch,
+                &mut undo_stack,
+                x
+            ));
+        }
+        assert!(sketch.strokes[key].points().is_empty());
+        assert!(sketch.strokes[key].erased());
+
+        // and there's nothing left to rewind
+        assert!(!erase_at(
+            EraserMode::Rewind,
+            &mut sketch,
+            &mut undo_stack,
+            0.
+        ));
+    }
+
+    // a vertical line ending just above the end of line()
+    fn down_to_end() -> Stroke<()> {
+        let mut stroke = Stroke::with_points(
+            vec![
+                StrokeElement {
+                    x: 10.,
+                    y: 10.,
+                    pressure: 1.,
+                },
+                StrokeElement {
+                    x: 10.,
+                    y: 0.5,
+                    pressure: 1.,
+                },
+            ],
+            Color::WHITE,
+        );
+        stroke.remesh(&mut StrokeTessellator::new(), &options());
+        stroke
+    }
+
+    #[test]
+    fn rewind_most_recent() {
+        let (mut sketch, first) = line();
+        let second = sketch.add_stroke(down_to_end());
+        let mut undo_stack = UndoStack::new();
+
+        assert!(erase_at(
+            EraserMode::Rewind,
+            &mut sketch,
+            &mut undo_stack,
+            10.
+        ));
+        assert_eq!(sketch.strokes[first].points().len(), 11);
+        assert_eq!(sketch.strokes[second].points().len(), 1);
+
+        // a newer stroke can get an older key when one is removed
+        let mut sketch = Sketch::empty();
+        let removed = sketch.add_stroke(Stroke::with_points(Vec::new(), Color::WHITE));
+        let (line, _) = line();
+        let first = sketch.add_stroke(line.strokes.into_iter().next().unwrap().1);
+        sketch.remove_stroke(removed);
+        let second = sketch.add_stroke(down_to_end());
+        assert!(second < first);
+
+        assert!(erase_at(
+            EraserMode::Rewind,
+            &mut sketch,
+            &mut undo_stack,
+            10.
+        ));
+        assert_eq!(sketch.strokes[first].points().len(), 11);
+        assert_eq!(sketch.strokes[second].points().len(), 1);
+    }
+
+    #[test]
+    fn locked_left_alone() {
+        let mut undo_stack = UndoStack::new();
+        for mode in [
+            EraserMode::WholeStroke,
+            EraserMode::PointSplit,
+            EraserMode::Rewind,
+        ] {
+            let (mut sketch, key) = line();
+            sketch.strokes[key].locked = true;
+            assert!(!erase_at(mode, &mut sketch, &mut undo_stack, 5.));
+            assert!(!erase_at(mode, &mut sketch, &mut undo_stack, 10.));
+            assert!(!sketch.strokes[key].erased());
+            assert_eq!(sketch.strokes.len(), 1);
+            assert_eq!(xs(&sketch.strokes[key]).len(), 11);
+        }
+        assert_eq!(undo_stack.last(), None);
+
+        // erasing the rest of its group doesn't take it along
+        let (mut sketch, key) = line();
+        let locked = sketch
+            .strokes
+            .insert(Stroke::with_points(Vec::new(), Color::WHITE));
+        sketch.group(&[key, locked]);
+        sketch.strokes[locked].locked = true;
+        assert!(erase_at(
+            EraserMode::WholeStroke,
+            &mut sketch,
+            &mut undo_stack,
+            5.
+        ));
+        assert!(sketch.strokes[key].erased());
+        assert!(!sketch.strokes[locked].erased());
+    }
+}
@@ -86,11 +86,15 @@ impl Display for PmbError {
 }
 
 impl Error for PmbError {
-    fn cause(&self) -> Option<&dyn Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self.kind {
             ErrorKind::IoError(err) => Some(err),
             ErrorKind::EncodeDecode(err) => Some(err.as_ref()),
-            _ => None,
+            ErrorKind::Tessellator(err) => Some(err),
+            ErrorKind::MissingHeader
+            | ErrorKind::VersionMismatch(_)
+            | ErrorKind::UnknownVersion(_)
+            | ErrorKind::IncompatibleVersion(_) => None,
         }
     }
 }
@@ -130,6 +134,12 @@ impl From<ron::error::SpannedError> for PmbError {
     }
 }
 
+impl From<ron::Error> for PmbError {
+    fn from(err: ron::Error) -> Self {
+        PmbError::new(ErrorKind::EncodeDecode(Box::new(err)))
+    }
+}
+
 impl From<lyon::lyon_tessellation::TessellationError> for PmbError {
     fn from(err: lyon::lyon_tessellation::TessellationError) -> Self {
         PmbError::new(ErrorKind::Tessellator(err))
@@ -161,3 +171,40 @@ impl Display for ErrorKind {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn source() {
+        let err = PmbError::from(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "no",
+        ));
+        let source = err.source().unwrap();
+        assert_eq!(
+            source
+                .downcast_ref::<std::io::Error>()
+                .map(std::io::Error::kind),
+            Some(std::io::ErrorKind::PermissionDenied)
+        );
+
+        let err = PmbError::from(ron::from_str::<u32>("nope").unwrap_err());
+        assert!(err
+            .source()
+            .unwrap()
+            .downcast_ref::<ron::error::SpannedError>()
+            .is_some());
+
+        assert!(PmbError::new(ErrorKind::MissingHeader).source().is_none());
+
+        // and it can be boxed up with everything else without losing the chain
+        let err: Box<dyn Error + Send + Sync> =
+            PmbError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "gone")).into();
+        assert!(err
+            .source()
+            .and_then(|source| source.downcast_ref::<std::io::Error>())
+            .is_some());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::graphics::PixelPos;
+use slotmap::DefaultKey;
 use std::collections::HashMap;
 use winit::event::{ElementState, MouseButton, Touch, VirtualKeyCode as Keycode};
 
@@ -29,6 +30,20 @@ pub enum Event {
     Exit,
 }
 
+/// what happened because of an event, from [`SketchWidget::apply`](crate::ui::widget::SketchWidget::apply)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    StrokeAdded(DefaultKey),
+    /// points were added to or taken off the stroke
+    StrokeChanged(DefaultKey),
+    StrokeErased(DefaultKey),
+    /// the stroke is gone like it was never drawn, not just erased
+    StrokeRemoved(DefaultKey),
+    ViewChanged,
+    Redraw,
+    Quit,
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Combination {
     keys: Vec<Keycode>,
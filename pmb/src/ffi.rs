@@ -0,0 +1,282 @@
+//! a C interface for embedding a sketch in an app that isn't written in rust
+//!
+//! everything goes through the opaque [`PmbSketch`] from [`pmb_sketch_new`], which has to be given
+//! back to [`pmb_sketch_free`]. there's no window and no gpu: touches go straight to a headless
+//! widget, and [`pmb_sketch_render`] fills a buffer on the cpu. build the library with
+//! `cargo rustc -p powdermilk-biscuits --release --features ffi --crate-type cdylib`
+
+use crate::{
+    config::Config,
+    document::Document,
+    event::Event,
+    graphics::{Color, ColorExt, PixelPos},
+    null::{NullCoords, NullStrokeBackend},
+    session::{LoggedPhase, LoggedTouch},
+    ui::widget::SketchWidget,
+    Device, Sketch,
+};
+
+pub const PMB_PHASE_STARTED: u32 = 0;
+pub const PMB_PHASE_MOVED: u32 = 1;
+pub const PMB_PHASE_ENDED: u32 = 2;
+pub const PMB_PHASE_CANCELLED: u32 = 3;
+
+pub struct PmbSketch {
+    config: Config,
+    document: Document<NullStrokeBackend, NullCoords>,
+    width: u32,
+    height: u32,
+}
+
+/// a finger or pen touching the sketch
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PmbTouch {
+    /// one of the `PMB_PHASE_` constants
+    pub phase: u32,
+    /// in pixels from the top left
+    pub x: f64,
+    pub y: f64,
+    /// between 0 and 1, or negative if the device doesn't know
+    pub force: f64,
+    /// tells fingers apart
+    pub id: u64,
+    pub pen: bool,
+}
+
+impl PmbTouch {
+    fn event(self) -> Option<Event> {
+        let phase = match self.phase {
+            PMB_PHASE_STARTED => LoggedPhase::Started,
+            PMB_PHASE_MOVED => LoggedPhase::Moved,
+            PMB_PHASE_ENDED => LoggedPhase::Ended,
+            PMB_PHASE_CANCELLED => LoggedPhase::Cancelled,
+            _ => return None,
+        };
+
+        let touch = LoggedTouch {
+            phase,
+            x: self.x,
+            y: self.y,
+            force: (self.force >= 0.).then_some(self.force),
+            id: self.id,
+            pen: self.pen.then_some([false; 3]),
+        }
+        .into();
+
+        Some(match (self.pen, phase) {
+            (true, LoggedPhase::Started) => Event::PenDown(touch),
+            (true, LoggedPhase::Moved) => Event::PenMove(touch),
+            (true, _) => Event::PenUp(touch),
+            (false, LoggedPhase::Started) => Event::Touch(touch),
+            (false, LoggedPhase::Moved) => Event::TouchMove(touch),
+            (false, _) => Event::Release(touch),
+        })
+    }
+}
+
+/// an empty sketch in a `width` by `height` pixel window
+#[no_mangle]
+pub extern "C" fn pmb_sketch_new(width: u32, height: u32) -> *mut PmbSketch {
+    let mut document = Document::new(SketchWidget::headless(width, height), Sketch::empty());
+    document.widget.force_update(&mut document.sketch);
+
+    Box::into_raw(Box::new(PmbSketch {
+        config: Config::new(),
+        document,
+        width,
+        height,
+    }))
+}
+
+/// # Safety
+///
+/// `sketch` has to be from [`pmb_sketch_new`] and not already freed, or null
+#[no_mangle]
+pub unsafe extern "C" fn pmb_sketch_free(sketch: *mut PmbSketch) {
+    if !sketch.is_null() {
+        drop(Box::from_raw(sketch));
+    }
+}
+
+/// returns false if the phase isn't one of the `PMB_PHASE_` constants or the sketch is null
+///
+/// # Safety
+///
+/// `sketch` has to be from [`pmb_sketch_new`] and not freed, or null
+#[no_mangle]
+pub unsafe extern "C" fn pmb_sketch_touch(sketch: *mut PmbSketch, touch: PmbTouch) -> bool {
+    let (Some(sketch), Some(event)) = (sketch.as_mut(), touch.event()) else {
+        return false;
+    };
+
+    let Document {
+        widget,
+        sketch: inner,
+    } = &mut sketch.document;
+    widget.next(&sketch.config, inner, event);
+    widget.prev_device = if touch.pen {
+        Device::Pen
+    } else {
+        Device::Touch
+    };
+
+    true
+}
+
+/// how many strokes there are that haven't been erased, or 0 if the sketch is null
+///
+/// # Safety
+///
+/// `sketch` has to be from [`pmb_sketch_new`] and not freed, or null
+#[no_mangle]
+pub unsafe extern "C" fn pmb_sketch_stroke_count(sketch: *const PmbSketch) -> usize {
+    sketch.as_ref().map_or(0, |sketch| {
+        sketch
+            .document
+            .sketch
+            .strokes
+            .values()
+            .filter(|stroke| !stroke.erased)
+            .count()
+    })
+}
+
+/// draw the sketch into `pixels` as rgba, a row at a time from the top left. returns false
+/// without touching `pixels` if it's shorter than width × height × 4 bytes
+///
+/// # Safety
+///
+/// `sketch` has to be from [`pmb_sketch_new`] and not freed, or null. `pixels` has to be valid
+/// for `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn pmb_sketch_render(
+    sketch: *const PmbSketch,
+    pixels: *mut u8,
+    len: usize,
+) -> bool {
+    let Some(sketch) = sketch.as_ref() else {
+        return false;
+    };
+    let size = sketch.width as usize * sketch.height as usize * 4;
+    if pixels.is_null() || len < size {
+        return false;
+    }
+
+    render(sketch, std::slice::from_raw_parts_mut(pixels, size));
+    true
+}
+
+/// every pixel whose middle is inside a triangle gets the stroke's color. no anti-aliasing, and
+/// deeper strokes on top like the gpu backends
+fn render(sketch: &PmbSketch, pixels: &mut [u8]) {
+    let PmbSketch { width, height, .. } = *sketch;
+    let inner = &sketch.document.sketch;
+    let rgba = |color: Color| {
+        let [r, g, b] = crate::graphics::resolve_edge(color, color, 1.).serialize_bytes();
+        [r, g, b, 255]
+    };
+
+    let bg = rgba(inner.bg_color);
+    pixels
+        .chunks_exact_mut(4)
+        .for_each(|pixel| pixel.copy_from_slice(&bg));
+
+    let mut strokes = inner.visible_strokes().collect::<Vec<_>>();
+    strokes.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+
+    for stroke in strokes {
+        let color = rgba(stroke.color());
+        for triangle in
+            stroke.pixel_triangles::<NullCoords>(width, height, inner.zoom, inner.origin)
+        {
+            fill(pixels, width, height, triangle, color);
+        }
+    }
+}
+
+fn fill(pixels: &mut [u8], width: u32, height: u32, [a, b, c]: [PixelPos; 3], color: [u8; 4]) {
+    let edge = |p: PixelPos, q: PixelPos, x: f32, y: f32| {
+        (q.x - p.x) * (y - p.y) - (q.y - p.y) * (x - p.x)
+    };
+    let clamp = |v: f32, max: u32| (v.max(0.) as u32).min(max);
+
+    let (left, right) = (a.x.min(b.x).min(c.x), a.x.max(b.x).max(c.x));
+    let (top, bottom) = (a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y));
+    for y in clamp(top.floor(), height)..clamp(bottom.ceil(), height) {
+        for x in clamp(left.floor(), width)..clamp(right.ceil(), width) {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let sides = [edge(a, b, px, py), edge(b, c, px, py), edge(c, a, px, py)];
+
+            // either winding
+            if sides.iter().all(|side| *side >= 0.) || sides.iter().all(|side| *side <= 0.) {
+                let i = (y as usize * width as usize + x as usize) * 4;
+                pixels[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PmbTouch, PMB_PHASE_ENDED, PMB_PHASE_MOVED, PMB_PHASE_STARTED};
+
+    // the same way a C program would see them, without knowing what's in a sketch
+    #[repr(C)]
+    struct PmbSketch {
+        _opaque: [u8; 0],
+    }
+
+    extern "C" {
+        fn pmb_sketch_new(width: u32, height: u32) -> *mut PmbSketch;
+        fn pmb_sketch_free(sketch: *mut PmbSketch);
+        fn pmb_sketch_touch(sketch: *mut PmbSketch, touch: PmbTouch) -> bool;
+        fn pmb_sketch_stroke_count(sketch: *const PmbSketch) -> usize;
+        fn pmb_sketch_render(sketch: *const PmbSketch, pixels: *mut u8, len: usize) -> bool;
+    }
+
+    fn pen(phase: u32, x: f64) -> PmbTouch {
+        PmbTouch {
+            phase,
+            x,
+            y: 100.,
+            force: 1.,
+            id: 0,
+            pen: true,
+        }
+    }
+
+    #[test]
+    fn draw_through_ffi() {
+        let (width, height) = (400, 200);
+        unsafe {
+            let sketch = pmb_sketch_new(width, height);
+            assert_eq!(pmb_sketch_stroke_count(sketch), 0);
+
+            assert!(pmb_sketch_touch(sketch, pen(PMB_PHASE_STARTED, 100.)));
+            for x in (100..=300).step_by(20) {
+                assert!(pmb_sketch_touch(sketch, pen(PMB_PHASE_MOVED, x as f64)));
+            }
+            assert!(pmb_sketch_touch(sketch, pen(PMB_PHASE_ENDED, 300.)));
+            assert!(!pmb_sketch_touch(sketch, pen(17, 300.)));
+            assert_eq!(pmb_sketch_stroke_count(sketch), 1);
+
+            let mut pixels = vec![0; (width * height * 4) as usize];
+            assert!(!pmb_sketch_render(sketch, pixels.as_mut_ptr(), 10));
+            assert!(pmb_sketch_render(sketch, pixels.as_mut_ptr(), pixels.len()));
+
+            let at = |x: usize, y: usize| {
+                let i = (y * width as usize + x) * 4;
+                [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+            };
+            let bg = at(0, 0);
+            assert_eq!(bg[3], 255);
+            assert_ne!(at(200, 100), bg);
+            assert_eq!(at(200, 10), bg);
+
+            pmb_sketch_free(sketch);
+            pmb_sketch_free(std::ptr::null_mut());
+            assert_eq!(pmb_sketch_stroke_count(std::ptr::null()), 0);
+        }
+    }
+}
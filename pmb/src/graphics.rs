@@ -1,6 +1,10 @@
+use crate::tess::CapStyle;
 use std::fmt::{Display, Formatter};
 use winit::dpi::PhysicalPosition;
 
+/// red, green, and blue, each between 0 and 1, in the same space the backends draw in. this is
+/// the only form colors take in memory. files and other formats store them either as these floats
+/// or as bytes, and go through [`ColorExt`] to get here and back
 pub type Color = [f32; 3];
 
 pub trait ColorExt {
@@ -16,48 +20,184 @@ pub trait ColorExt {
         [level, level, level]
     }
 
-    fn to_u8(&self) -> [u8; 3];
-    fn from_u8(color: [u8; 3]) -> Self;
+    /// each channel rounded to the nearest of 256 levels, how files before v7 stored colors
+    fn serialize_bytes(&self) -> [u8; 3];
+    fn from_bytes(color: [u8; 3]) -> Self;
+
+    /// each channel as is, how files since v7 store colors
+    fn serialize_floats(&self) -> [f32; 3];
+    /// channels outside 0 to 1 are clamped, and ones that aren't numbers are 0
+    fn from_floats(color: [f32; 3]) -> Self;
 }
 
 impl ColorExt for Color {
-    fn from_u8(color: [u8; 3]) -> Color {
-        [
-            color[0] as f32 / 255.,
-            color[1] as f32 / 255.,
-            color[2] as f32 / 255.,
-        ]
+    fn serialize_bytes(&self) -> [u8; 3] {
+        Color::from_floats(*self).map(|channel| (channel * 255.).round() as u8)
+    }
+
+    fn from_bytes(color: [u8; 3]) -> Color {
+        color.map(|channel| channel as f32 / 255.)
     }
 
-    fn to_u8(&self) -> [u8; 3] {
-        [
-            (self[0] * 255.) as u8,
-            (self[1] * 255.) as u8,
-            (self[2] * 255.) as u8,
-        ]
+    fn serialize_floats(&self) -> [f32; 3] {
+        *self
+    }
+
+    fn from_floats(color: [f32; 3]) -> Color {
+        color.map(|channel| {
+            if channel.is_nan() {
+                0.
+            } else {
+                channel.clamp(0., 1.)
+            }
+        })
     }
 }
 
-/// disjoint set of lines. for use with gl_LINES or PrimitiveTopology::LineList
-pub fn cursor_geometry(radius: f32, num_points: usize) -> Vec<f32> {
-    let mut points = Vec::with_capacity(num_points + 2);
+/// how a backend smooths the edges of strokes, if it gets to choose
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum AntiAliasing {
+    None,
 
-    let dtheta = std::f32::consts::TAU / (num_points as f32);
-    let mut theta: f32 = 0.;
+    /// post-processing, cheap but can blur fine strokes
+    #[default]
+    Smaa,
 
-    for _ in 0..=(num_points * 2) {
-        let (sin, cos) = theta.sin_cos();
-        points.push(cos * radius);
-        points.push(sin * radius);
+    /// multisampling, keeps fine strokes sharp
+    Msaa,
+}
 
-        let (sin, cos) = (theta + dtheta).sin_cos();
-        points.push(cos * radius);
-        points.push(sin * radius);
+impl AntiAliasing {
+    pub const MSAA_SAMPLES: u32 = 4;
 
-        theta += dtheta;
+    /// samples per pixel the stroke pipelines need to render with
+    pub fn sample_count(self) -> u32 {
+        match self {
+            AntiAliasing::Msaa => AntiAliasing::MSAA_SAMPLES,
+            AntiAliasing::None | AntiAliasing::Smaa => 1,
+        }
     }
 
-    points
+    /// whether switching from `self` to `other` means the pipelines have to be built again
+    pub fn needs_new_pipelines(self, other: AntiAliasing) -> bool {
+        self.sample_count() != other.sample_count()
+    }
+}
+
+/// what to draw with. each backend only does some of these, and goes with the closest it has
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum RendererKind {
+    /// the gpu if there is one, otherwise software
+    #[default]
+    Auto,
+    Wgpu,
+    Gl,
+    /// slow, but works without a gpu
+    Software,
+}
+
+/// one way a backend can try to get something to draw with
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum RendererAttempt {
+    /// whatever gpu api works
+    Gpu,
+    /// only opengl
+    Gl,
+    Software,
+}
+
+impl RendererKind {
+    /// what to try, in order, until one works
+    pub fn attempts(self) -> &'static [RendererAttempt] {
+        match self {
+            RendererKind::Auto => &[RendererAttempt::Gpu, RendererAttempt::Software],
+            RendererKind::Wgpu => &[RendererAttempt::Gpu],
+            RendererKind::Gl => &[RendererAttempt::Gl],
+            RendererKind::Software => &[RendererAttempt::Software],
+        }
+    }
+
+    /// the first attempt `start` manages, or why the last one didn't work
+    pub fn select<T, E: Display>(
+        self,
+        mut start: impl FnMut(RendererAttempt) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut attempts = self.attempts().iter().peekable();
+        loop {
+            let attempt = *attempts.next().expect("every kind has an attempt");
+            match start(attempt) {
+                Ok(renderer) => return Ok(renderer),
+                Err(err) if attempts.peek().is_some() => {
+                    tracing::warn!("couldn't start {attempt:?} renderer: {err}");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// encode one linear channel for display
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_to_linear(srgb: f32) -> f32 {
+    if srgb <= 0.04045 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// what a pixel `coverage` of the way covered by `fg` over `bg` should look like on an srgb
+/// display. both colors are linear, the result is encoded. this is what the backends' antialiasing
+/// should resolve edges to
+pub fn resolve_edge(fg: Color, bg: Color, coverage: f32) -> Color {
+    let coverage = coverage.clamp(0., 1.);
+    [0, 1, 2].map(|i| linear_to_srgb(fg[i] * coverage + bg[i] * (1. - coverage)))
+}
+
+/// disjoint set of lines in the shape of the end of a line with `cap`, so the cursor shows what a
+/// stroke will look like. round is a circle, square a square, and butt only a cross since it
+/// doesn't reach past the point. for use with gl_LINES or PrimitiveTopology::LineList
+pub fn cursor_geometry(cap: CapStyle, radius: f32, num_points: usize) -> Vec<f32> {
+    match cap {
+        CapStyle::Round => {
+            let mut points = Vec::with_capacity(num_points * 4);
+
+            let dtheta = std::f32::consts::TAU / (num_points as f32);
+            let mut theta: f32 = 0.;
+
+            for _ in 0..num_points {
+                let (sin, cos) = theta.sin_cos();
+                points.push(cos * radius);
+                points.push(sin * radius);
+
+                let (sin, cos) = (theta + dtheta).sin_cos();
+                points.push(cos * radius);
+                points.push(sin * radius);
+
+                theta += dtheta;
+            }
+
+            points
+        }
+
+        CapStyle::Square => {
+            let corners = [[1., 1.], [-1., 1.], [-1., -1.], [1., -1.], [1., 1.]];
+            corners
+                .windows(2)
+                .flat_map(|edge| [edge[0], edge[1]])
+                .flat_map(|[x, y]: [f32; 2]| [x * radius, y * radius])
+                .collect()
+        }
+
+        CapStyle::Butt => vec![-radius, 0., radius, 0., 0., -radius, 0., radius],
+    }
 }
 
 /// continuous set of points on a circle
@@ -83,7 +223,7 @@ pub fn circle_points(radius: f32, num_points: usize) -> Vec<f32> {
 
 macro_rules! coordinate_types {
     ($($Coord:ident),*) => {$(
-        #[derive(Default, Debug, Clone, Copy, pmb_macros::Disk)]
+        #[derive(Default, Debug, Clone, Copy, PartialEq, pmb_macros::Disk)]
         pub struct $Coord {
             pub x: f32,
             pub y: f32,
@@ -119,3 +259,97 @@ pub fn xform_pos_to_point(origin: StrokePoint, stroke: StrokePos) -> StrokePoint
     let y = stroke.y + origin.y;
     StrokePoint { x, y }
 }
+
+/// squish a stroke's depth into the 0 to 1 of a depth buffer, where strokes with a higher depth
+/// get a lower value so they pass a less-or-equal depth test on top of the ones below them
+pub fn depth_to_ndc(depth: f32) -> f32 {
+    0.5 - depth.atan() / std::f32::consts::PI
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn anti_aliasing_samples() {
+        use AntiAliasing::*;
+        assert_eq!(None.sample_count(), 1);
+        assert_eq!(Smaa.sample_count(), 1);
+        assert_eq!(Msaa.sample_count(), AntiAliasing::MSAA_SAMPLES);
+
+        // smaa is a separate pass, the pipelines are the same as without anti-aliasing
+        assert!(!None.needs_new_pipelines(Smaa));
+        assert!(!Smaa.needs_new_pipelines(None));
+        assert!(Smaa.needs_new_pipelines(Msaa));
+        assert!(Msaa.needs_new_pipelines(None));
+        assert!(!Msaa.needs_new_pipelines(Msaa));
+    }
+
+    #[test]
+    fn renderer_fallback() {
+        let no_gpu = |attempt| match attempt {
+            RendererAttempt::Software => Ok(attempt),
+            _ => Err("no gpu"),
+        };
+        assert_eq!(
+            RendererKind::Auto.select(no_gpu),
+            Ok(RendererAttempt::Software)
+        );
+        assert_eq!(RendererKind::Wgpu.select(no_gpu), Err("no gpu"));
+        assert_eq!(RendererKind::Gl.select(no_gpu), Err("no gpu"));
+
+        // the gpu is used when it's there
+        let mut tried = Vec::new();
+        let picked = RendererKind::Auto.select(|attempt| {
+            tried.push(attempt);
+            Ok::<_, &str>(attempt)
+        });
+        assert_eq!(picked, Ok(RendererAttempt::Gpu));
+        assert_eq!(tried, [RendererAttempt::Gpu]);
+    }
+
+    #[test]
+    fn cursor_follows_cap() {
+        let round = cursor_geometry(CapStyle::Round, 1., 50);
+        let square = cursor_geometry(CapStyle::Square, 1., 50);
+        let butt = cursor_geometry(CapStyle::Butt, 1., 50);
+        assert_ne!(round, square);
+        assert_ne!(square, butt);
+        assert_ne!(butt, round);
+
+        // pairs of points, all within the brush
+        for lines in [&round, &square, &butt] {
+            assert_eq!(lines.len() % 4, 0);
+            assert!(lines.iter().all(|coord| coord.abs() <= 1.0001));
+        }
+        assert_eq!(round.len(), 50 * 4);
+        assert!(square.chunks(2).any(|point| point == [1., 1.]));
+    }
+
+    #[test]
+    fn color_representations() {
+        for i in 0..=255 {
+            let bytes = [i, 255 - i, i / 2];
+            let color = Color::from_bytes(bytes);
+            assert_eq!(color.serialize_bytes(), bytes);
+            assert_eq!(Color::from_floats(color.serialize_floats()), color);
+        }
+
+        // floats only survive as bytes to the nearest 256th
+        let colors = [
+            Color::NICE_WHITE,
+            Color::NICE_GREY,
+            Color::PMB,
+            [0.5, 0.001, 0.999],
+        ];
+        for color in colors {
+            assert_eq!(Color::from_floats(color.serialize_floats()), color);
+            let back = Color::from_bytes(color.serialize_bytes());
+            assert!((0..3).all(|i| (back[i] - color[i]).abs() <= 0.5 / 255. + 1e-6));
+        }
+        assert_eq!(Color::WHITE.serialize_bytes(), [255, 255, 255]);
+
+        assert_eq!(Color::from_floats([-1., 2., f32::NAN]), [0., 1., 0.]);
+        assert_eq!([f32::INFINITY, 0.5, -0.].serialize_bytes(), [255, 128, 0]);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::graphics::{Color, ColorExt, StrokePos};
+
+/// lines closer together than this many pixels aren't drawn
+pub const MIN_PIXELS_BETWEEN_LINES: f32 = 4.;
+
+/// graph paper lines, drawn over the sketch along with the rest of the ui
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct GridConfig {
+    pub show: bool,
+    /// stroke units between minor lines
+    pub spacing: f32,
+    /// minor lines in each major square, so a major line every `spacing * subdivisions`
+    pub subdivisions: u32,
+    pub minor_color: Color,
+    pub major_color: Color,
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        GridConfig {
+            show: false,
+            spacing: 1.,
+            subdivisions: 5,
+            minor_color: Color::grey(0.15),
+            major_color: Color::grey(0.3),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridLine {
+    /// x of a vertical line or y of a horizontal one, in stroke space
+    pub at: f32,
+    pub vertical: bool,
+    pub major: bool,
+}
+
+impl GridConfig {
+    pub fn major_spacing(&self) -> f32 {
+        self.spacing * self.subdivisions.max(1) as f32
+    }
+
+    /// the lines that cross the rectangle between `top_left` and `bottom_right`, leaving out the
+    /// ones that would be too close together at `zoom`
+    pub fn lines(&self, top_left: StrokePos, bottom_right: StrokePos, zoom: f32) -> Vec<GridLine> {
+        if !(self.spacing.is_finite() && self.spacing > 0.) {
+            return Vec::new();
+        }
+
+        let major_spacing = self.major_spacing();
+        if major_spacing * zoom < MIN_PIXELS_BETWEEN_LINES {
+            return Vec::new();
+        }
+
+        // only the major lines if the minor ones would be a smear
+        let (spacing, every) = if self.spacing * zoom < MIN_PIXELS_BETWEEN_LINES {
+            (major_spacing, 1)
+        } else {
+            (self.spacing, self.subdivisions.max(1) as i64)
+        };
+
+        let along = |from: f32, to: f32, vertical: bool| {
+            let (from, to) = (from.min(to), from.max(to));
+            ((from / spacing).ceil() as i64..=(to / spacing).floor() as i64).map(move |i| {
+                GridLine {
+                    at: i as f32 * spacing,
+                    vertical,
+                    major: i.rem_euclid(every) == 0,
+                }
+            })
+        };
+
+        along(top_left.x, bottom_right.x, true)
+            .chain(along(bottom_right.y, top_left.y, false))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn major_every_subdivision() {
+        let grid = GridConfig {
+            show: true,
+            spacing: 0.5,
+            subdivisions: 4,
+            ..Default::default()
+        };
+        let lines = grid.lines(
+            StrokePos { x: -3.1, y: 1.2 },
+            StrokePos { x: 4.9, y: -0.2 },
+            50.,
+        );
+
+        let vertical = |major| {
+            lines
+                .iter()
+                .filter(|line| line.vertical && line.major == major)
+                .map(|line| line.at)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vertical(true), vec![-2., 0., 2., 4.]);
+        assert_eq!(
+            vertical(false),
+            vec![-3., -2.5, -1.5, -1., -0.5, 0.5, 1., 1.5, 2.5, 3., 3.5, 4.5]
+        );
+        assert!(vertical(true)
+            .windows(2)
+            .all(|pair| pair[1] - pair[0] == grid.major_spacing()));
+
+        let horizontal = lines
+            .iter()
+            .filter(|line| !line.vertical)
+            .map(|line| (line.at, line.major))
+            .collect::<Vec<_>>();
+        assert_eq!(horizontal, vec![(0., true), (0.5, false), (1., false)]);
+
+        // zoomed out too far for the minor lines, then for any lines
+        let far = grid.lines(
+            StrokePos { x: -3.1, y: 1.2 },
+            StrokePos { x: 4.9, y: -0.2 },
+            4.,
+        );
+        assert!(!far.is_empty() && far.iter().all(|line| line.major));
+        assert!(grid
+            .lines(
+                StrokePos { x: -3.1, y: 1.2 },
+                StrokePos { x: 4.9, y: -0.2 },
+                1.
+            )
+            .is_empty());
+    }
+}
@@ -34,14 +34,20 @@ messages!(
     MboxMessageMigrateWarning,
     MboxTitleError,
     MboxMessageOutOfMemory,
+    MboxMessageNoGraphics,
     MboxMessageCouldNotOpenConfigFile,
     MboxMessageCouldNotOpenFile,
     MboxMessageCouldNotSaveFile,
     MboxMessageAskToSaveBeforeOpening,
     MboxMessageAskToSaveBeforeClosing,
+    MboxMessageRecover,
+    MboxMessageRestoreAutosave,
     MboxTitleOpen,
     MboxTitleUnsavedChanges,
     MboxTitleSaveUnnamedFile,
+    MboxTitleSaveRecording,
+    MboxTitleRecover,
+    MboxTitleRestoreAutosave,
 
     // main UI
     WindowTitleNoFile,
@@ -50,17 +56,27 @@ messages!(
     MenuLabelEdit,
     MenuItemEditUndo,
     MenuItemEditRedo,
+    MenuItemEditClipToRuler,
+    MenuItemEditClipToLastStroke,
+    MenuItemEditClearClip,
+    MenuItemEditCrispStrokes,
+    MenuLabelEditDepth,
     MenuLabelFile,
     MenuItemFileNew,
     MenuItemFileOpen,
     MenuItemFileSave,
     MenuItemFileSaveUnnamed,
     MenuItemFileSettings,
+    MenuItemFileStartRecording,
+    MenuItemFileStopRecording,
     MenuItemFileQuitUnmodified,
     MenuItemFileQuitModified,
     RadioLabelToolPen,
     RadioLabelToolPan,
     RadioLabelToolEraser,
+    RadioLabelToolRuler,
+    RadioLabelToolEyedropper,
+    RadioLabelToolSelect,
     SliderLabelZoom,
     SliderLabelBrushSize,
     ColorPickerLabelStrokeColor,
@@ -72,15 +88,78 @@ messages!(
     ConfigLabelToolForGesture2,
     ConfigLabelToolForGesture3,
     ConfigLabelToolForGesture4,
+    ConfigLabelTwoFingerTapUndo,
+    ConfigLabelPalmRejectionMs,
+    ConfigOptionFingerUseTool,
+    ConfigOptionFingerIgnore,
     ConfigLabelDarkMode,
     ConfigLabelStylusMayBeInverted,
+    ConfigLabelLazyBrushRadius,
+    ConfigLabelPressureCurve,
+    ConfigLabelPressureMin,
+    ConfigLabelPressureMax,
+    ConfigLabelAutoStraighten,
+    ConfigLabelAutoShapes,
+    ConfigLabelShapeTolerance,
+    ConfigLabelPredictionMs,
+    ConfigLabelResizeDebounceMs,
+    ConfigLabelMaxFps,
+    ConfigLabelStrokeIdleMs,
+    ConfigLabelAutosaveSecs,
+    ConfigLabelRequireMovementToCommit,
+    ConfigLabelCommitMovementPx,
     ConfigLabelPrimaryMouseButton,
     ConfigLabelPenPanButton,
     ConfigOptionButtonLeftMouse,
     ConfigOptionButtonRightMouse,
+    ConfigLabelEraserMode,
+    ConfigOptionEraserModeWholeStroke,
+    ConfigOptionEraserModePointSplit,
+    ConfigOptionEraserModeRewind,
+    ConfigLabelSelectMode,
+    ConfigOptionSelectModeRectangle,
+    ConfigOptionSelectModeLasso,
+    ConfigLabelTessellator,
+    ConfigLabelMeshPixelTolerance,
+    ConfigOptionTessellatorLyon,
+    ConfigOptionTessellatorNative,
+    ConfigOptionTessellatorDeterministic,
+    ConfigLabelLineCap,
+    ConfigOptionLineCapRound,
+    ConfigOptionLineCapButt,
+    ConfigOptionLineCapSquare,
+    ConfigLabelLineJoin,
+    ConfigOptionLineJoinRound,
+    ConfigOptionLineJoinMiter,
+    ConfigOptionLineJoinBevel,
+    ConfigLabelDefaultStyle,
+    ConfigOptionStyleLines,
+    ConfigOptionStyleCircles,
+    ConfigOptionStyleCirclesPressure,
+    ConfigOptionStylePoints,
+    ConfigOptionStyleSpline,
+    ConfigLabelUseIndividualStyle,
+    ConfigLabelStampSpacing,
+    ConfigLabelAntiAliasing,
+    ConfigOptionAntiAliasingNone,
+    ConfigOptionAntiAliasingSmaa,
+    ConfigOptionAntiAliasingMsaa,
+    ConfigLabelRenderer,
+    ConfigOptionRendererAuto,
+    ConfigOptionRendererWgpu,
+    ConfigOptionRendererGl,
+    ConfigOptionRendererSoftware,
     ConfigOptionButtonMiddleMouse,
     ConfigLabelStartMaximized,
     ConfigLabelBackgroundColor,
+    ConfigLabelRulerCalibrated,
+    ConfigLabelRulerUnitsPerStrokeUnit,
+    ConfigLabelRulerUnitName,
+    ConfigLabelShowGrid,
+    ConfigLabelGridSpacing,
+    ConfigLabelGridSubdivisions,
+    ConfigLabelGridMinorColor,
+    ConfigLabelGridMajorColor,
 );
 
 #[macro_export]
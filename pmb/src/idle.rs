@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+/// notices when a stroke stops getting input without being ended
+///
+/// some tablets forget to say the pen was lifted, which would leave the stroke being drawn
+/// forever. if nothing has moved for `timeout` the stroke is finished as if it had been.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdleTimer {
+    last_input: Option<Instant>,
+
+    /// how long a stroke can go without input. None never finishes it early
+    pub timeout: Option<Duration>,
+}
+
+impl IdleTimer {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        IdleTimer {
+            last_input: None,
+            timeout,
+        }
+    }
+
+    /// the stroke got some input
+    pub fn push(&mut self, at: Instant) {
+        self.last_input = Some(at);
+    }
+
+    /// the stroke ended on its own
+    pub fn stop(&mut self) {
+        self.last_input = None;
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.last_input.is_some()
+    }
+
+    /// when the stroke will have been idle for too long
+    pub fn deadline(&self) -> Option<Instant> {
+        Some(self.last_input? + self.timeout?)
+    }
+
+    /// whether the stroke should be finished now. only says so once per stroke
+    pub fn take(&mut self, now: Instant) -> bool {
+        match self.deadline() {
+            Some(deadline) if deadline <= now => {
+                self.last_input = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn idle_after_timeout() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut idle = IdleTimer::new(Some(ms(100)));
+        assert!(!idle.take(start + ms(1000)));
+
+        // the pen moving every so often, with a pause that isn't quite long enough
+        for at in [0, 30, 60, 159, 200] {
+            assert!(!idle.take(start + ms(at)));
+            idle.push(start + ms(at));
+        }
+        assert_eq!(idle.deadline(), Some(start + ms(300)));
+        assert!(!idle.take(start + ms(299)));
+        assert!(idle.take(start + ms(300)));
+        assert!(!idle.is_waiting());
+        assert!(!idle.take(start + ms(400)));
+
+        // lifted normally
+        idle.push(start + ms(500));
+        idle.stop();
+        assert!(!idle.take(start + ms(1000)));
+
+        // turned off
+        let mut idle = IdleTimer::new(None);
+        idle.push(start);
+        assert_eq!(idle.deadline(), None);
+        assert!(!idle.take(start + ms(1_000_000)));
+    }
+}
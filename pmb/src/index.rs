@@ -0,0 +1,248 @@
+use crate::{graphics::StrokePos, layer::Layer, stroke::Stroke, StrokeBackend};
+use slotmap::{DefaultKey, SecondaryMap, SlotMap};
+use std::collections::HashMap;
+
+/// strokes that would be in more cells than this go in a list every query looks at instead, so
+/// one huge stroke doesn't fill the whole grid
+const MAX_CELLS: i64 = 64;
+
+/// the strokes of a sketch bucketed by the squares of a grid their bounding boxes touch, so
+/// finding the strokes in an area only looks at the ones near it. it's built from scratch the next
+/// time it's needed after being [invalidated](StrokeIndex::invalidate), which has to happen
+/// whenever strokes are added or removed, or change shape
+#[derive(Default, Debug)]
+pub struct StrokeIndex {
+    fresh: bool,
+    cell_size: f32,
+    cells: HashMap<(i64, i64), Vec<DefaultKey>>,
+    large: Vec<DefaultKey>,
+    /// which layer each stroke is on and where, to put what a query finds in drawing order
+    order: SecondaryMap<DefaultKey, (usize, usize)>,
+    /// the strokes that were on screen the last time the sketch
+    /// [looked](crate::Sketch::update_visible_strokes), in drawing order
+    pub(crate) visible: Vec<DefaultKey>,
+}
+
+impl StrokeIndex {
+    pub fn invalidate(&mut self) {
+        self.fresh = false;
+    }
+
+    /// whether the index still matches the strokes
+    pub fn is_fresh(&self) -> bool {
+        self.fresh
+    }
+
+    /// index the strokes on `layers`. strokes without a bounding box, like ones that haven't been
+    /// tessellated yet, can't overlap anything and are left out
+    pub fn rebuild<S: StrokeBackend>(
+        &mut self,
+        layers: &[Layer],
+        strokes: &SlotMap<DefaultKey, Stroke<S>>,
+    ) {
+        self.cells.clear();
+        self.large.clear();
+        self.order.clear();
+        self.visible.clear();
+
+        let bounded = layers
+            .iter()
+            .enumerate()
+            .flat_map(|(i, layer)| {
+                layer
+                    .strokes
+                    .iter()
+                    .enumerate()
+                    .map(move |(at, key)| (*key, (i, at)))
+            })
+            .filter_map(|(key, order)| Some((key, order, strokes.get(key)?)))
+            .filter(|(_, _, stroke)| bounded(stroke.top_left, stroke.bottom_right))
+            .collect::<Vec<_>>();
+
+        // about one stroke per cell
+        let total = bounded
+            .iter()
+            .map(|(_, _, stroke)| {
+                let width = stroke.bottom_right.x - stroke.top_left.x;
+                let height = stroke.top_left.y - stroke.bottom_right.y;
+                width.max(height)
+            })
+            .sum::<f32>();
+        self.cell_size = if total > 0. {
+            total / bounded.len() as f32
+        } else {
+            1.
+        };
+
+        for (key, order, stroke) in bounded {
+            self.insert(key, order, stroke.top_left, stroke.bottom_right);
+        }
+
+        self.fresh = true;
+    }
+
+    /// add the cells a stroke's bounding box covers now that it's grown, like while it's being
+    /// drawn, instead of building the whole index again. `order` is its layer and where it is on
+    /// it. the cells it was in before stay, since it still covers those too. it's being drawn, so
+    /// it's on screen
+    pub fn update(
+        &mut self,
+        key: DefaultKey,
+        order: (usize, usize),
+        top_left: StrokePos,
+        bottom_right: StrokePos,
+    ) {
+        if !self.fresh || !bounded(top_left, bottom_right) {
+            return;
+        }
+
+        self.insert(key, order, top_left, bottom_right);
+        if !self.visible.contains(&key) {
+            let at = self
+                .visible
+                .partition_point(|other| self.order.get(*other).map_or(false, |had| *had < order));
+            self.visible.insert(at, key);
+        }
+    }
+
+    fn insert(
+        &mut self,
+        key: DefaultKey,
+        order: (usize, usize),
+        top_left: StrokePos,
+        bottom_right: StrokePos,
+    ) {
+        self.order.insert(key, order);
+
+        let (left, top) = self.cell(top_left);
+        let (right, bottom) = self.cell(bottom_right);
+        let cells = span(left, right).saturating_mul(span(bottom, top));
+        if cells > MAX_CELLS {
+            if !self.large.contains(&key) {
+                self.large.push(key);
+            }
+            return;
+        }
+
+        for x in left..=right {
+            for y in bottom..=top {
+                let cell = self.cells.entry((x, y)).or_default();
+                if !cell.contains(&key) {
+                    cell.push(key);
+                }
+            }
+        }
+    }
+
+    /// the strokes whose bounding boxes might overlap the rectangle, in drawing order. they still
+    /// need to be checked against it
+    pub fn query(&self, top_left: StrokePos, bottom_right: StrokePos) -> Vec<DefaultKey> {
+        let (left, top) = self.cell(top_left);
+        let (right, bottom) = self.cell(bottom_right);
+        let contains = |&(x, y): &(i64, i64)| left <= x && x <= right && bottom <= y && y <= top;
+
+        let mut keys = self.large.clone();
+        let area = span(left, right).saturating_mul(span(bottom, top));
+        if area > self.cells.len() as i64 {
+            // zoomed out past most of the strokes, so it's faster to look through what's there
+            self.cells
+                .iter()
+                .filter(|(cell, _)| contains(cell))
+                .for_each(|(_, cell)| keys.extend(cell));
+        } else {
+            for x in left..=right {
+                for y in bottom..=top {
+                    if let Some(cell) = self.cells.get(&(x, y)) {
+                        keys.extend(cell);
+                    }
+                }
+            }
+        }
+
+        keys.sort_unstable_by_key(|key| self.order[*key]);
+        keys.dedup();
+        keys
+    }
+
+    /// the index of the layer `key` was on when the index was built
+    pub fn layer_of(&self, key: DefaultKey) -> Option<usize> {
+        self.order.get(key).map(|(layer, _)| *layer)
+    }
+
+    fn cell(&self, pos: StrokePos) -> (i64, i64) {
+        (
+            (pos.x / self.cell_size).floor() as i64,
+            (pos.y / self.cell_size).floor() as i64,
+        )
+    }
+}
+
+/// whether there's a bounding box, which strokes that haven't been tessellated yet don't have
+fn bounded(top_left: StrokePos, bottom_right: StrokePos) -> bool {
+    [top_left, bottom_right]
+        .iter()
+        .all(|pos| pos.x.is_finite() && pos.y.is_finite())
+}
+
+/// how many cells there are from `low` to `high`, which can be far apart if a cell is small
+fn span(low: i64, high: i64) -> i64 {
+    high.saturating_sub(low).saturating_add(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        graphics::{Color, ColorExt},
+        stroke::StrokeElement,
+        Sketch,
+    };
+    use lyon::lyon_tessellation::{StrokeOptions, StrokeTessellator};
+
+    #[test]
+    fn ten_thousand_strokes() {
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default();
+        let mut sketch = Sketch::<()>::empty();
+
+        // a 100 by 100 grid of short lines, 10 apart
+        for i in 0..10_000 {
+            let (x, y) = ((i % 100) as f32 * 10., (i / 100) as f32 * 10.);
+            let mut stroke = Stroke::with_points(
+                vec![
+                    StrokeElement { x, y, pressure: 1. },
+                    StrokeElement {
+                        x: x + 2.,
+                        y,
+                        pressure: 1.,
+                    },
+                ],
+                Color::BLACK,
+            );
+            stroke.remesh(&mut tessellator, &options);
+            sketch.add_stroke(stroke);
+        }
+        assert!(!sketch.index.is_fresh());
+
+        let top_left = StrokePos { x: 95., y: 125. };
+        let bottom_right = StrokePos { x: 125., y: 95. };
+        let near = sketch.strokes_near(top_left, bottom_right);
+        assert!(sketch.index.is_fresh());
+        assert!(near.len() < 100, "looked at {} strokes", near.len());
+
+        let mut overlapping = sketch
+            .strokes
+            .iter()
+            .filter(|(_, stroke)| stroke.aabb(top_left, bottom_right))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        assert!(!overlapping.is_empty());
+        overlapping.retain(|key| !near.contains(key));
+        assert!(overlapping.is_empty(), "missed {:?}", overlapping);
+
+        let key = sketch.add_stroke(Stroke::with_points(Vec::new(), Color::BLACK));
+        assert!(!sketch.index.is_fresh());
+        sketch.remove_stroke(key);
+        assert!(!sketch.index.is_fresh());
+    }
+}
@@ -0,0 +1,65 @@
+use slotmap::{DefaultKey, SlotMap};
+
+/// strokes that draw together, on top of the layers before it. the strokes themselves stay in
+/// [`Sketch::strokes`](crate::Sketch::strokes), and every stroke there is in exactly one layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    /// hidden layers aren't drawn, but their strokes are still saved
+    pub visible: bool,
+    /// in the order they're drawn
+    pub strokes: Vec<DefaultKey>,
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Layer::new("Layer 1")
+    }
+}
+
+impl Layer {
+    pub fn new(name: impl Into<String>) -> Self {
+        Layer {
+            name: name.into(),
+            visible: true,
+            strokes: Vec::new(),
+        }
+    }
+
+    /// the default layer with `strokes` on it
+    pub fn with_strokes(strokes: impl IntoIterator<Item = DefaultKey>) -> Self {
+        Layer {
+            strokes: strokes.into_iter().collect(),
+            ..Layer::default()
+        }
+    }
+}
+
+/// a layer in a file. the strokes are saved one layer after the other, so all it needs is how
+/// many of them are on it
+#[derive(Default, Debug, Clone, PartialEq, pmb_macros::Disk)]
+pub struct SavedLayer {
+    pub name: String,
+    pub visible: bool,
+    pub len: u64,
+}
+
+/// layers from a file. the strokes get read into a fresh map in the order they were saved, so
+/// they get the same keys as a fresh map of nothing
+pub fn from_saved(saved: Vec<SavedLayer>) -> Vec<Layer> {
+    let mut keys = SlotMap::<DefaultKey, ()>::new();
+    let layers = saved
+        .into_iter()
+        .map(|layer| Layer {
+            name: layer.name,
+            visible: layer.visible,
+            strokes: (0..layer.len).map(|_| keys.insert(())).collect(),
+        })
+        .collect::<Vec<_>>();
+
+    if layers.is_empty() {
+        vec![Layer::default()]
+    } else {
+        layers
+    }
+}
@@ -1,17 +1,51 @@
 #![allow(clippy::new_without_default, clippy::derive_partial_eq_without_eq)]
 
+pub mod autosave;
+pub mod brush;
+pub mod clip;
 pub mod config;
+#[cfg(any(test, feature = "test-backend"))]
+pub mod coords;
+pub mod document;
+pub mod eraser;
 pub mod error;
 pub mod event;
+#[cfg(any(test, feature = "ffi"))]
+pub mod ffi;
 pub mod graphics;
+pub mod grid;
 pub mod i18n;
+pub mod idle;
+pub mod index;
+pub mod layer;
 pub mod loop_;
+pub mod measure;
+pub mod mesh;
 pub mod migrate;
+#[cfg(any(test, feature = "test-backend"))]
+pub mod null;
+pub mod pace;
+pub mod pinch;
+pub mod predict;
+pub mod prelude;
+pub mod pressure;
+pub mod recovery;
+pub mod resize;
+#[cfg(any(test, feature = "test-backend"))]
+pub mod scratch;
+pub mod select;
+pub mod session;
+pub mod shape;
+pub mod simplify;
+pub mod spline;
+pub mod stabilizer;
 pub mod stroke;
+pub mod tap;
 pub mod tess;
 pub mod ui;
 
 pub extern crate bytemuck;
+#[cfg(feature = "desktop")]
 pub extern crate dirs;
 pub extern crate egui;
 pub extern crate gumdrop;
@@ -19,12 +53,20 @@ pub extern crate lyon;
 pub extern crate winit;
 
 use crate::{
+    clip::ClipRect,
+    error::{ErrorKind, PmbError},
     graphics::{Color, ColorExt, PixelPos, StrokePoint, StrokePos},
-    stroke::{Stroke, StrokeElement},
+    index::StrokeIndex,
+    layer::{from_saved, Layer, SavedLayer},
+    stroke::{GroupId, Stroke, StrokeElement, StrokeStyle},
+    tess::TessellatorKind,
+};
+use lyon::{
+    lyon_tessellation::{StrokeOptions, StrokeTessellator},
+    math::Transform,
 };
-use lyon::lyon_tessellation::{StrokeOptions, StrokeTessellator};
 use slotmap::{DefaultKey, SlotMap};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub const PMB_MAGIC: [u8; 3] = [b'P', b'M', b'B'];
 
@@ -37,6 +79,68 @@ pub const MAX_BRUSH: usize = 20;
 pub const MIN_BRUSH: usize = 1;
 pub const BRUSH_DELTA: usize = 1;
 
+/// how a backend gets between the places a point can be
+///
+/// - pixels are where the window says the cursor is, from its top left corner
+/// - ndc is whatever the backend's shaders want, worked out from pixels and the window size
+/// - stroke points are ndc scaled by the window size and zoom, so strokes stay the same size when
+///   the window changes size
+/// - stroke positions are stroke points moved by the origin, and are what gets saved
+///
+/// only the pixel ↔ ndc and ndc ↔ stroke conversions need to be written, and each has to undo the
+/// other. `coords::assert_round_trips` checks that, with the `test-backend` feature.
+///
+/// ```
+/// use powdermilk_biscuits::{
+///     graphics::{PixelPos, StrokePoint, StrokePos},
+///     CoordinateSystem,
+/// };
+///
+/// // y up, with the middle of the window at 0,0 and its edges at ±1
+/// #[derive(Debug, Default, Clone, Copy)]
+/// struct Centered;
+///
+/// impl CoordinateSystem for Centered {
+///     type Ndc = PixelPos;
+///
+///     fn pixel_to_ndc(width: u32, height: u32, pos: PixelPos) -> PixelPos {
+///         PixelPos {
+///             x: 2. * pos.x / width as f32 - 1.,
+///             y: 1. - 2. * pos.y / height as f32,
+///         }
+///     }
+///
+///     fn ndc_to_pixel(width: u32, height: u32, ndc: PixelPos) -> PixelPos {
+///         PixelPos {
+///             x: (ndc.x + 1.) * width as f32 / 2.,
+///             y: (1. - ndc.y) * height as f32 / 2.,
+///         }
+///     }
+///
+///     fn ndc_to_stroke(width: u32, height: u32, zoom: f32, ndc: PixelPos) -> StrokePoint {
+///         StrokePoint {
+///             x: ndc.x * width as f32 / zoom,
+///             y: ndc.y * height as f32 / zoom,
+///         }
+///     }
+///
+///     fn stroke_to_ndc(width: u32, height: u32, zoom: f32, point: StrokePoint) -> PixelPos {
+///         PixelPos {
+///             x: point.x * zoom / width as f32,
+///             y: point.y * zoom / height as f32,
+///         }
+///     }
+/// }
+///
+/// let (width, height, zoom) = (800, 600, 50.);
+/// let origin = StrokePoint { x: 2., y: -1. };
+///
+/// // the middle of the right edge is 1,0 in ndc, which is width / zoom stroke units to the right
+/// let edge = PixelPos { x: 800., y: 300. };
+/// let pos = Centered::pixel_to_pos(width, height, zoom, origin, edge);
+/// assert_eq!(pos, StrokePos { x: 16. - 2., y: 0. + 1. });
+/// assert_eq!(Centered::pos_to_pixel(width, height, zoom, origin, pos), edge);
+/// ```
 pub trait CoordinateSystem: std::fmt::Debug + Default + Clone + Copy {
     type Ndc: std::fmt::Display + Clone + Copy;
 
@@ -98,6 +202,13 @@ pub struct Args {
     #[options(help = "Config file location")]
     pub config: Option<PathBuf>,
 
+    #[options(
+        help = "Directory to keep the config file in, instead of the usual one",
+        no_short,
+        meta = "DIR"
+    )]
+    pub config_dir: Option<PathBuf>,
+
     #[options(free, help = "File to open")]
     pub file: Option<PathBuf>,
 }
@@ -108,6 +219,11 @@ pub enum Tool {
     Pen,
     Eraser,
     Pan,
+    Ruler,
+    /// use the color of the stroke that's tapped
+    Eyedropper,
+    /// pick out strokes with a rectangle or lasso
+    Select,
 }
 
 #[derive(Debug, Default, PartialEq, Clone, Copy)]
@@ -122,10 +238,22 @@ pub enum Device {
 pub struct Sketch<S: StrokeBackend> {
     #[custom_codec(to_vec, map_from_vec)]
     pub strokes: SlotMap<DefaultKey, Stroke<S>>,
+    /// bottom to top
+    #[custom_codec(to_saved_layers, from_saved)]
+    pub layers: Vec<Layer>,
     pub zoom: f32,
     pub origin: StrokePoint,
     pub bg_color: Color,
     pub fg_color: Color,
+    /// clip for strokes drawn from now on
+    #[skip]
+    pub clip: Option<ClipRect>,
+    /// the strokes picked out with the select tool
+    #[skip]
+    pub selection: Vec<DefaultKey>,
+    /// where the strokes are, for finding the ones in part of the sketch
+    #[skip]
+    pub index: StrokeIndex,
 }
 
 pub fn map_from_vec<S: StrokeBackend>(strokes: Vec<Stroke<S>>) -> SlotMap<DefaultKey, Stroke<S>> {
@@ -144,13 +272,19 @@ impl<S: StrokeBackend> Default for Sketch<S> {
 }
 
 impl<S: StrokeBackend> Sketch<S> {
+    /// a sketch with `strokes` on one layer
     pub fn new(strokes: Vec<Stroke<S>>) -> Self {
+        let strokes = map_from_vec(strokes);
         Self {
-            strokes: map_from_vec(strokes),
+            layers: vec![Layer::with_strokes(strokes.keys())],
+            strokes,
             zoom: crate::DEFAULT_ZOOM,
             origin: StrokePoint::default(),
             bg_color: Color::NICE_WHITE,
             fg_color: Color::NICE_GREY,
+            clip: None,
+            selection: Vec::new(),
+            index: StrokeIndex::default(),
         }
     }
 
@@ -158,11 +292,28 @@ impl<S: StrokeBackend> Sketch<S> {
         Self::new(Vec::new())
     }
 
+    /// what a new document starts with: the strokes and view of the sketch at `template`, or
+    /// nothing if there isn't one. older templates are upgraded
+    pub fn template(path: Option<&Path>) -> Result<Self, PmbError> {
+        let Some(path) = path else {
+            return Ok(Sketch::empty());
+        };
+
+        tracing::info!("new document from template {}", path.display());
+        match migrate::read(std::fs::File::open(path)?) {
+            Err(PmbError {
+                kind: ErrorKind::VersionMismatch(version),
+                ..
+            }) => migrate::from(version, path),
+            result => result,
+        }
+    }
+
     pub fn with_filename<C: CoordinateSystem>(
         widget: &mut ui::widget::SketchWidget<C>,
         path: impl AsRef<std::path::Path>,
     ) -> Self {
-        tracing::info!("create State from {}", path.as_ref().display());
+        tracing::info!("create Sketch from {}", path.as_ref().display());
 
         let mut this = Sketch::empty();
         ui::read_file(widget, Some(path), &mut this);
@@ -170,20 +321,410 @@ impl<S: StrokeBackend> Sketch<S> {
         this
     }
 
+    /// the strokes that get saved, one layer after the other
     fn to_vec(&self) -> Vec<Stroke<S>> {
-        self.strokes
-            .values()
+        self.layers
+            .iter()
+            .flat_map(|layer| self.saved_strokes(layer))
+            .map(Stroke::saved_copy)
+            .collect()
+    }
+
+    fn to_saved_layers(&self) -> Vec<SavedLayer> {
+        self.layers
+            .iter()
+            .map(|layer| SavedLayer {
+                name: layer.name.clone(),
+                visible: layer.visible,
+                len: self.saved_strokes(layer).count() as u64,
+            })
+            .collect()
+    }
+
+    /// the strokes on `layer` that get saved, in the order they're drawn
+    pub fn saved_strokes<'a>(&'a self, layer: &'a Layer) -> impl Iterator<Item = &'a Stroke<S>> {
+        layer
+            .strokes
+            .iter()
+            .filter_map(|key| self.strokes.get(*key))
             .filter(|stroke| !stroke.erased)
-            .map(|stroke| Stroke {
-                points: stroke.points.clone(),
-                color: stroke.color,
-                brush_size: stroke.brush_size,
-                ..Default::default()
+    }
+
+    /// strokes that haven't been erased and are tagged with `tag`, bottom layer first
+    pub fn strokes_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Stroke<S>> {
+        self.layers
+            .iter()
+            .flat_map(|layer| self.saved_strokes(layer))
+            .filter(move |stroke| stroke.has_tag(tag))
+    }
+
+    /// a copy of the parts of the sketch that get written to disk
+    pub fn saved_copy<T: StrokeBackend>(&self) -> Sketch<T> {
+        Sketch {
+            layers: from_saved(self.to_saved_layers()),
+            zoom: self.zoom,
+            origin: self.origin,
+            bg_color: self.bg_color,
+            fg_color: self.fg_color,
+            ..Sketch::new(
+                self.layers
+                    .iter()
+                    .flat_map(|layer| self.saved_strokes(layer))
+                    .map(Stroke::saved_copy)
+                    .collect(),
+            )
+        }
+    }
+
+    /// a copy of the sketch with only the strokes tagged `tag`, for exporting part of a sketch
+    pub fn only_tagged(&self, tag: &str) -> Self {
+        Sketch {
+            zoom: self.zoom,
+            origin: self.origin,
+            bg_color: self.bg_color,
+            fg_color: self.fg_color,
+            ..Sketch::new(self.strokes_with_tag(tag).map(Stroke::saved_copy).collect())
+        }
+    }
+
+    /// put `stroke` on top of the top layer
+    pub fn add_stroke(&mut self, stroke: Stroke<S>) -> DefaultKey {
+        self.index.invalidate();
+        let key = self.strokes.insert(stroke);
+        match self.layers.last_mut() {
+            Some(layer) => layer.strokes.push(key),
+            None => self.layers.push(Layer::with_strokes([key])),
+        }
+        key
+    }
+
+    /// put `stroke` just above `below`, on the same layer. on top of the top layer if `below`
+    /// isn't on one
+    pub fn add_stroke_above(&mut self, below: DefaultKey, stroke: Stroke<S>) -> DefaultKey {
+        let Some((layer, at)) = self.layers.iter().enumerate().find_map(|(i, layer)| {
+            layer
+                .strokes
+                .iter()
+                .position(|key| *key == below)
+                .map(|at| (i, at))
+        }) else {
+            return self.add_stroke(stroke);
+        };
+
+        self.index.invalidate();
+        let key = self.strokes.insert(stroke);
+        self.layers[layer].strokes.insert(at + 1, key);
+        key
+    }
+
+    /// take a stroke out of the sketch and its layer for good, unlike erasing it
+    pub fn remove_stroke(&mut self, key: DefaultKey) -> Option<Stroke<S>> {
+        self.index.invalidate();
+        for layer in self.layers.iter_mut() {
+            layer.strokes.retain(|other| *other != key);
+        }
+        self.strokes.remove(key)
+    }
+
+    /// a copy of the sketch with only the strokes in `keys`, zoomed to fit them in a window `width`
+    /// by `height` pixels big, for exporting a selection. they stay on the layers they were on, in
+    /// the order they're drawn
+    pub fn only_strokes<C: CoordinateSystem>(
+        &self,
+        keys: &[DefaultKey],
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let strokes = layer
+                    .strokes
+                    .iter()
+                    .filter(|key| keys.contains(key))
+                    .filter_map(|key| self.strokes.get(*key))
+                    .filter(|stroke| !stroke.erased)
+                    .map(Stroke::saved_copy)
+                    .collect::<Vec<_>>();
+                let saved = SavedLayer {
+                    name: layer.name.clone(),
+                    visible: layer.visible,
+                    len: strokes.len() as u64,
+                };
+                (saved, strokes)
+            })
+            .collect::<Vec<_>>();
+        let (layers, strokes): (Vec<_>, Vec<_>) = layers.into_iter().unzip();
+        let strokes = strokes.into_iter().flatten().collect::<Vec<_>>();
+
+        // with room for how thick the strokes are
+        let (min, max) = strokes
+            .iter()
+            .flat_map(|stroke| {
+                let pad = stroke.brush_size / 2.;
+                stroke.points().iter().map(move |point| (point, pad))
+            })
+            .fold(
+                (
+                    StrokePoint {
+                        x: f32::INFINITY,
+                        y: f32::INFINITY,
+                    },
+                    StrokePoint {
+                        x: f32::NEG_INFINITY,
+                        y: f32::NEG_INFINITY,
+                    },
+                ),
+                |(min, max), (point, pad)| {
+                    (
+                        StrokePoint {
+                            x: min.x.min(point.x - pad),
+                            y: min.y.min(point.y - pad),
+                        },
+                        StrokePoint {
+                            x: max.x.max(point.x + pad),
+                            y: max.y.max(point.y + pad),
+                        },
+                    )
+                },
+            );
+
+        let mut only = Sketch {
+            layers: from_saved(layers),
+            zoom: self.zoom,
+            origin: self.origin,
+            bg_color: self.bg_color,
+            fg_color: self.fg_color,
+            ..Sketch::new(strokes)
+        };
+        if !(min.x <= max.x && min.y <= max.y) {
+            return only;
+        }
+
+        // how many pixels from the middle of the window to the corner of the strokes at zoom 1
+        let middle = C::stroke_to_pixel(width, height, 1., StrokePoint { x: 0., y: 0. });
+        let corner = C::stroke_to_pixel(
+            width,
+            height,
+            1.,
+            StrokePoint {
+                x: (max.x - min.x) / 2.,
+                y: (max.y - min.y) / 2.,
+            },
+        );
+        let fit = |from: f32, to: f32, pixels: u32| pixels as f32 / 2. / (to - from).abs();
+        only.zoom = fit(middle.x, corner.x, width)
+            .min(fit(middle.y, corner.y, height))
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        // the middle of the screen is where the origin is negated
+        only.origin = StrokePoint {
+            x: -(min.x + max.x) / 2.,
+            y: -(min.y + max.y) / 2.,
+        };
+        only
+    }
+
+    /// select the strokes entirely inside the rectangle with corners `a` and `b`
+    pub fn select_rect(&mut self, a: StrokePos, b: StrokePos) -> Vec<DefaultKey> {
+        let (left, right) = (a.x.min(b.x), a.x.max(b.x));
+        let (bottom, top) = (a.y.min(b.y), a.y.max(b.y));
+        self.select_where(|point| {
+            (left..=right).contains(&point.x) && (bottom..=top).contains(&point.y)
+        })
+    }
+
+    /// select the strokes entirely inside `polygon`, which goes back from its last point to its
+    /// first
+    pub fn select_lasso(&mut self, polygon: &[StrokePos]) -> Vec<DefaultKey> {
+        self.select_where(|point| select::in_polygon(polygon, point))
+    }
+
+    /// replace the selection with the strokes with every point `inside`. erased and locked strokes
+    /// can't be selected
+    fn select_where(&mut self, inside: impl Fn(StrokePos) -> bool) -> Vec<DefaultKey> {
+        self.selection = self
+            .strokes
+            .iter()
+            .filter(|(_, stroke)| {
+                !stroke.erased
+                    && !stroke.locked
+                    && !stroke.points().is_empty()
+                    && stroke.points().iter().all(|point| inside(point.into()))
+            })
+            .map(|(key, _)| key)
+            .collect();
+        self.selection.clone()
+    }
+
+    /// put the strokes in a new group, taking them out of any group they were in before. returns
+    /// None if there weren't any strokes to group
+    pub fn group(&mut self, keys: &[DefaultKey]) -> Option<GroupId> {
+        let keys = keys
+            .iter()
+            .copied()
+            .filter(|key| self.strokes.contains_key(*key))
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return None;
+        }
+
+        let id = self
+            .strokes
+            .values()
+            .filter_map(|stroke| stroke.group_id)
+            .max()
+            .map(|id| id + 1)
+            .unwrap_or_default();
+
+        for key in keys {
+            self.strokes[key].group_id = Some(id);
+        }
+
+        Some(id)
+    }
+
+    /// take every stroke out of the group, returning the strokes that were in it
+    pub fn ungroup(&mut self, id: GroupId) -> Vec<DefaultKey> {
+        self.strokes
+            .iter_mut()
+            .filter(|(_, stroke)| stroke.group_id == Some(id))
+            .map(|(key, stroke)| {
+                stroke.group_id = None;
+                key
             })
             .collect()
     }
 
-    fn screen_rect<C: CoordinateSystem>(&self, width: u32, height: u32) -> (StrokePos, StrokePos) {
+    /// the strokes along with every other stroke in their groups, so picking one stroke of a
+    /// group picks the whole group. locked strokes can't be picked, so they're left out
+    pub fn with_groups(&self, keys: &[DefaultKey]) -> Vec<DefaultKey> {
+        let groups = keys
+            .iter()
+            .filter_map(|key| self.strokes.get(*key)?.group_id)
+            .collect::<Vec<_>>();
+
+        self.strokes
+            .iter()
+            .filter(|(key, stroke)| {
+                !stroke.locked
+                    && (keys.contains(key)
+                        || stroke
+                            .group_id
+                            .map(|id| groups.contains(&id))
+                            .unwrap_or(false))
+            })
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// lock or unlock the strokes, returning the ones that weren't already
+    pub fn set_locked(&mut self, keys: &[DefaultKey], locked: bool) -> Vec<DefaultKey> {
+        keys.iter()
+            .copied()
+            .filter(|key| {
+                self.strokes
+                    .get_mut(*key)
+                    .filter(|stroke| stroke.locked != locked)
+                    .map(|stroke| stroke.locked = locked)
+                    .is_some()
+            })
+            .collect()
+    }
+
+    /// recolor the strokes that aren't locked, returning the ones that changed
+    pub fn set_stroke_color(&mut self, keys: &[DefaultKey], color: Color) -> Vec<DefaultKey> {
+        keys.iter()
+            .copied()
+            .filter(|key| {
+                let Some(stroke) = self.strokes.get_mut(*key) else {
+                    return false;
+                };
+                if stroke.locked || stroke.color == color {
+                    return false;
+                }
+
+                stroke.color = color;
+                if let Some(backend) = stroke.backend_mut() {
+                    backend.make_dirty();
+                }
+                true
+            })
+            .collect()
+    }
+
+    /// give the strokes that aren't locked a new brush size, returning the ones that changed
+    pub fn set_stroke_brush_size(
+        &mut self,
+        keys: &[DefaultKey],
+        brush_size: f32,
+        tessellator: &mut StrokeTessellator,
+        options: &StrokeOptions,
+    ) -> Vec<DefaultKey> {
+        self.index.invalidate();
+        keys.iter()
+            .copied()
+            .filter(|key| {
+                let Some(stroke) = self.strokes.get_mut(*key) else {
+                    return false;
+                };
+                if stroke.locked || stroke.brush_size == brush_size {
+                    return false;
+                }
+
+                stroke.brush_size = brush_size;
+                stroke.remesh(tessellator, options);
+                true
+            })
+            .collect()
+    }
+
+    /// move the strokes and the rest of their groups by (dx, dy)
+    pub fn translate_strokes(
+        &mut self,
+        keys: &[DefaultKey],
+        dx: f32,
+        dy: f32,
+        tessellator: &mut StrokeTessellator,
+        options: &StrokeOptions,
+    ) {
+        self.index.invalidate();
+        for key in self.with_groups(keys) {
+            self.strokes[key].translate(dx, dy, tessellator, options);
+        }
+    }
+
+    /// move every stroke through `transform`, erased or not. this changes the strokes themselves,
+    /// not the view
+    pub fn transform_all(
+        &mut self,
+        transform: &Transform,
+        tessellator: &mut StrokeTessellator,
+        options: &StrokeOptions,
+    ) {
+        self.index.invalidate();
+        for stroke in self.strokes.values_mut() {
+            stroke.transform(transform, tessellator, options);
+        }
+    }
+
+    /// move the strokes to where the view puts them and reset the view, so the strokes look the
+    /// same on screen but their coordinates match what's drawn
+    pub fn bake_view(&mut self, tessellator: &mut StrokeTessellator, options: &StrokeOptions) {
+        let scale = self.zoom / crate::DEFAULT_ZOOM;
+        let transform =
+            Transform::translation(self.origin.x, self.origin.y).then_scale(scale, scale);
+        self.transform_all(&transform, tessellator, options);
+
+        self.zoom = crate::DEFAULT_ZOOM;
+        self.origin = StrokePoint::default();
+    }
+
+    pub(crate) fn screen_rect<C: CoordinateSystem>(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> (StrokePos, StrokePos) {
         let top_left = C::pixel_to_pos(width, height, self.zoom, self.origin, PixelPos::default());
 
         let bottom_right = C::pixel_to_pos(
@@ -200,29 +741,173 @@ impl<S: StrokeBackend> Sketch<S> {
         (top_left, bottom_right)
     }
 
+    /// find which strokes are on screen. only the ones that were or might be now are looked at,
+    /// unless the strokes changed since the last time
     pub fn update_visible_strokes<C: CoordinateSystem>(&mut self, width: u32, height: u32) {
         let (top_left, bottom_right) = self.screen_rect::<C>(width, height);
-        for stroke in self.strokes.values_mut() {
-            stroke.update_visible(top_left, bottom_right);
+        if self.index.is_fresh() {
+            for key in self.index.visible.iter() {
+                if let Some(stroke) = self.strokes.get_mut(*key) {
+                    stroke.visible = false;
+                }
+            }
+        } else {
+            self.index.rebuild(&self.layers, &self.strokes);
+            for stroke in self.strokes.values_mut() {
+                stroke.visible = false;
+            }
         }
+
+        let strokes = &mut self.strokes;
+        self.index.visible = self
+            .index
+            .query(top_left, bottom_right)
+            .into_iter()
+            .filter(|key| {
+                let stroke = &mut strokes[*key];
+                stroke.update_visible(top_left, bottom_right);
+                stroke.visible
+            })
+            .collect();
+    }
+
+    /// keep the index up to date with a stroke that got bigger, like the one being drawn, without
+    /// building it again
+    pub fn stroke_grew(&mut self, key: DefaultKey) {
+        let Some(stroke) = self.strokes.get(key) else {
+            return;
+        };
+
+        // the stroke being drawn is on top, so look from there down
+        let order = self.layers.iter().enumerate().rev().find_map(|(i, layer)| {
+            layer
+                .strokes
+                .iter()
+                .rposition(|other| *other == key)
+                .map(|at| (i, at))
+        });
+
+        match order {
+            Some(order) => self
+                .index
+                .update(key, order, stroke.top_left, stroke.bottom_right),
+            None => self.index.invalidate(),
+        }
+    }
+
+    /// the strokes that might overlap the rectangle, in drawing order, from the index
+    pub fn strokes_near(
+        &mut self,
+        top_left: StrokePos,
+        bottom_right: StrokePos,
+    ) -> Vec<DefaultKey> {
+        if !self.index.is_fresh() {
+            self.index.rebuild(&self.layers, &self.strokes);
+        }
+        self.index.query(top_left, bottom_right)
     }
 
     fn update_stroke_primitive(&mut self) {
         for stroke in self.strokes.values_mut() {
-            stroke.draw_tesselated = stroke.brush_size * self.zoom > 1.0;
+            stroke.update_primitive(self.zoom);
+        }
+    }
+
+    pub fn use_tessellator(&mut self, kind: TessellatorKind) {
+        for stroke in self.strokes.values_mut() {
+            stroke.tessellator = kind;
+        }
+    }
+
+    /// draw every stroke with `brushes` from now on
+    pub fn use_brushes(&mut self, brushes: &brush::Brushes) {
+        for stroke in self.strokes.values_mut() {
+            stroke.brushes = brushes.clone();
+        }
+    }
+
+    pub fn use_stamp_spacing(&mut self, spacing: f32) {
+        for stroke in self.strokes.values_mut() {
+            stroke.stamp_spacing = spacing;
+        }
+    }
+
+    pub fn use_style_override(&mut self, style: Option<StrokeStyle>) {
+        for stroke in self.strokes.values_mut() {
+            stroke.style_override = style;
         }
     }
 
+    /// a hash of everything that gets saved, to tell whether two sketches would be the same on disk
+    pub fn content_hash(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        // encoding only fails for types that refuse to be encoded, and sketches don't
+        let bytes = bincode::encode_to_vec(self, bincode::config::standard()).unwrap_or_default();
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        hasher.finish()
+    }
+
     pub fn clear_strokes(&mut self) {
+        self.index.invalidate();
         self.strokes.clear();
+        for layer in self.layers.iter_mut() {
+            layer.strokes.clear();
+        }
     }
 
+    /// the strokes to draw, in the order to draw them. hidden layers are skipped
     pub fn visible_strokes(&self) -> impl Iterator<Item = &Stroke<S>> {
-        self.strokes
-            .values()
+        // the ones found on screen last time if nothing changed since, otherwise all of them
+        let fresh = self.index.is_fresh();
+        let indexed = fresh.then(|| {
+            self.index.visible.iter().filter(|key| {
+                self.index
+                    .layer_of(**key)
+                    .and_then(|layer| self.layers.get(layer))
+                    .map_or(false, |layer| layer.visible)
+            })
+        });
+        let all = (!fresh).then(|| {
+            self.layers
+                .iter()
+                .filter(|layer| layer.visible)
+                .flat_map(|layer| layer.strokes.iter())
+        });
+
+        indexed
+            .into_iter()
+            .flatten()
+            .chain(all.into_iter().flatten())
+            .filter_map(|key| self.strokes.get(*key))
             .filter(|stroke| stroke.visible && !stroke.erased)
     }
 
+    /// every pair of consecutive points in the visible strokes, in pixels, with the width of the
+    /// line between them in pixels. for backends that draw the strokes themselves instead of
+    /// using the meshes
+    pub fn iter_screen_segments<C: CoordinateSystem>(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> impl Iterator<Item = (PixelPos, PixelPos, f32)> + '_ {
+        let (zoom, origin) = (self.zoom, self.origin);
+        let to_pixel =
+            move |point: &StrokeElement| C::pos_to_pixel(width, height, zoom, origin, point.into());
+
+        self.visible_strokes().flat_map(move |stroke| {
+            let size = stroke.brush_size() * zoom;
+            stroke.points().windows(2).map(move |pair| {
+                (
+                    to_pixel(&pair[0]),
+                    to_pixel(&pair[1]),
+                    (pair[0].pressure + pair[1].pressure) / 2. * size,
+                )
+            })
+        })
+    }
+
     pub fn update_zoom<C: CoordinateSystem>(&mut self, width: u32, height: u32, next_zoom: f32) {
         self.zoom = next_zoom.clamp(crate::MIN_ZOOM, crate::MAX_ZOOM);
         self.update_visible_strokes::<C>(width, height);
@@ -251,16 +936,35 @@ impl<S: StrokeBackend> Sketch<S> {
         options: &StrokeOptions,
     ) {
         tracing::info!("forcing update");
-        self.strokes
-            .values_mut()
-            .flat_map(|stroke| {
-                stroke.rebuild_entire_mesh(tessellator, options);
-                stroke.backend_mut()
-            })
-            .for_each(|backend| backend.make_dirty());
+        self.index.invalidate();
+        for stroke in self.strokes.values_mut() {
+            stroke.remesh(tessellator, options);
+        }
         self.update_visible_strokes::<C>(width, height);
         self.update_stroke_primitive();
     }
+
+    /// tessellate the strokes that [need it](Stroke::needs_mesh) and leave the rest alone, without
+    /// touching the view. returns how many were tessellated
+    pub fn ensure_tessellated(
+        &mut self,
+        tessellator: &mut StrokeTessellator,
+        options: &StrokeOptions,
+    ) -> usize {
+        let mut tessellated = 0;
+        for stroke in self
+            .strokes
+            .values_mut()
+            .filter(|stroke| !stroke.erased && stroke.needs_mesh())
+        {
+            stroke.remesh(tessellator, options);
+            tessellated += 1;
+        }
+        if tessellated > 0 {
+            self.index.invalidate();
+        }
+        tessellated
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -382,3 +1086,584 @@ where
 
     strokes
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::null::NullCoords as Coords;
+
+    #[test]
+    fn strokes_with_tag() {
+        let tagged = |tags: &[&str]| {
+            let mut stroke = Stroke::with_points(Vec::new(), Color::WHITE);
+            tags.iter().for_each(|tag| {
+                stroke.add_tag(*tag);
+            });
+            stroke
+        };
+
+        let mut sketch = Sketch::<()>::new(vec![
+            tagged(&["ink"]),
+            tagged(&["pencil"]),
+            tagged(&["ink", "annotation"]),
+            tagged(&[]),
+        ]);
+
+        assert_eq!(sketch.strokes_with_tag("ink").count(), 2);
+        assert_eq!(sketch.strokes_with_tag("pencil").count(), 1);
+        assert_eq!(sketch.strokes_with_tag("annotation").count(), 1);
+        assert_eq!(sketch.strokes_with_tag("nothing").count(), 0);
+
+        // erased strokes don't count
+        let (_, pencil) = sketch
+            .strokes
+            .iter_mut()
+            .find(|(_, stroke)| stroke.has_tag("pencil"))
+            .unwrap();
+        pencil.erase();
+        assert_eq!(sketch.strokes_with_tag("pencil").count(), 0);
+
+        let ink = sketch.only_tagged("ink");
+        assert_eq!(ink.strokes.len(), 2);
+        assert!(ink.strokes.values().all(|stroke| stroke.has_tag("ink")));
+        assert_eq!(ink.zoom, sketch.zoom);
+    }
+
+    #[test]
+    fn only_some_strokes() {
+        let point = |x: f32, y: f32| {
+            Stroke::with_points(vec![StrokeElement { x, y, pressure: 1. }], Color::WHITE)
+        };
+
+        let mut sketch = Sketch::<()>::empty();
+        let keys = (0..5)
+            .map(|i| sketch.add_stroke(point(i as f32 * 100., i as f32 * 200.)))
+            .collect::<Vec<_>>();
+        sketch.strokes[keys[4]].erase();
+
+        let part = sketch.only_strokes::<Coords>(&[keys[1], keys[3], keys[4]], 800, 600);
+        let mut xs = part
+            .strokes
+            .values()
+            .map(|stroke| stroke.points()[0].x)
+            .collect::<Vec<_>>();
+        xs.sort_by(f32::total_cmp);
+        assert_eq!(xs, vec![100., 300.]);
+
+        // looking at the middle of the two, zoomed so they just fit top to bottom
+        assert_eq!(part.origin, StrokePoint { x: -200., y: -400. });
+        let pixels = part
+            .strokes
+            .values()
+            .map(|stroke| {
+                Coords::pos_to_pixel(800, 600, part.zoom, part.origin, stroke.points()[0].into())
+            })
+            .collect::<Vec<_>>();
+        assert!(pixels
+            .iter()
+            .all(|pixel| (0. ..=800.).contains(&pixel.x) && (0. ..=600.).contains(&pixel.y)));
+        assert!((pixels[0].y - pixels[1].y).abs() > 550.);
+
+        let none = sketch.only_strokes::<Coords>(&[], 800, 600);
+        assert_eq!((none.origin, none.zoom), (sketch.origin, sketch.zoom));
+    }
+
+    #[test]
+    fn groups_move_together() {
+        let point = |x: f32| {
+            Stroke::with_points(
+                vec![StrokeElement {
+                    x,
+                    y: 0.,
+                    pressure: 1.,
+                }],
+                Color::WHITE,
+            )
+        };
+
+        let mut sketch = Sketch::<()>::empty();
+        let a = sketch.add_stroke(point(0.));
+        let b = sketch.add_stroke(point(1.));
+        let c = sketch.add_stroke(point(2.));
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default();
+        let x = |sketch: &Sketch<()>, key: DefaultKey| sketch.strokes[key].points()[0].x;
+
+        assert_eq!(sketch.group(&[]), None);
+        let group = sketch.group(&[a, b]).unwrap();
+        assert_eq!(sketch.strokes[a].group_id, Some(group));
+        assert_eq!(sketch.strokes[c].group_id, None);
+        assert_ne!(sketch.group(&[c]), Some(group));
+        sketch.ungroup(sketch.strokes[c].group_id.unwrap());
+
+        // moving one member of a group moves all of them
+        sketch.translate_strokes(&[b], 10., 0., &mut tessellator, &options);
+        assert_eq!(x(&sketch, a), 10.);
+        assert_eq!(x(&sketch, b), 11.);
+        assert_eq!(x(&sketch, c), 2.);
+
+        let mut ungrouped = sketch.ungroup(group);
+        ungrouped.sort();
+        let mut expected = vec![a, b];
+        expected.sort();
+        assert_eq!(ungrouped, expected);
+        assert!(sketch
+            .strokes
+            .values()
+            .all(|stroke| stroke.group_id.is_none()));
+
+        // and once they're ungrouped they move by themselves
+        sketch.translate_strokes(&[b], 10., 0., &mut tessellator, &options);
+        assert_eq!(x(&sketch, a), 10.);
+        assert_eq!(x(&sketch, b), 21.);
+    }
+
+    #[test]
+    fn locked_not_picked() {
+        let point = |x: f32| {
+            Stroke::with_points(
+                vec![StrokeElement {
+                    x,
+                    y: 0.,
+                    pressure: 1.,
+                }],
+                Color::WHITE,
+            )
+        };
+
+        let mut sketch = Sketch::<()>::empty();
+        let a = sketch.add_stroke(point(0.));
+        let b = sketch.add_stroke(point(1.));
+        let c = sketch.add_stroke(point(2.));
+        sketch.group(&[a, b]);
+
+        assert_eq!(sketch.set_locked(&[b, c], true), vec![b, c]);
+        assert_eq!(sketch.set_locked(&[a, b], true), vec![a]);
+        assert_eq!(sketch.set_locked(&[a], false), vec![a]);
+
+        // picking a locked stroke picks nothing, and picking the rest of its group leaves it out
+        assert!(sketch.with_groups(&[c]).is_empty());
+        assert_eq!(sketch.with_groups(&[a]), vec![a]);
+
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default();
+        sketch.translate_strokes(&[a, c], 10., 0., &mut tessellator, &options);
+        let xs = [a, b, c].map(|key| sketch.strokes[key].points()[0].x);
+        assert_eq!(xs, [10., 1., 2.]);
+    }
+
+    #[test]
+    fn layers_draw_in_order() {
+        let point = |x: f32| {
+            Stroke::with_points(
+                vec![StrokeElement {
+                    x,
+                    y: 0.,
+                    pressure: 1.,
+                }],
+                Color::WHITE,
+            )
+        };
+        let drawn = |sketch: &Sketch<()>| {
+            sketch
+                .visible_strokes()
+                .map(|stroke| stroke.points()[0].x)
+                .collect::<Vec<_>>()
+        };
+
+        let mut sketch = Sketch::<()>::empty();
+        let top = sketch.add_stroke(point(0.));
+        sketch.layers.insert(0, Layer::new("below"));
+        let below = sketch.strokes.insert(point(1.));
+        sketch.layers[0].strokes.push(below);
+
+        // the layer underneath draws first even though its stroke came later
+        assert_eq!(drawn(&sketch), [1., 0.]);
+        sketch.add_stroke_above(top, point(2.));
+        sketch.add_stroke_above(below, point(3.));
+        assert_eq!(drawn(&sketch), [1., 3., 0., 2.]);
+
+        sketch.layers[0].visible = false;
+        assert_eq!(drawn(&sketch), [0., 2.]);
+
+        assert!(sketch.remove_stroke(top).is_some());
+        assert_eq!(drawn(&sketch), [2.]);
+        assert_eq!(sketch.layers[1].strokes.len(), 1);
+    }
+
+    #[test]
+    fn restyle_strokes() {
+        let mut sketch = Sketch::<()>::empty();
+        let mut stroke = || {
+            sketch.add_stroke(Stroke::with_points(
+                vec![StrokeElement {
+                    x: 0.,
+                    y: 0.,
+                    pressure: 1.,
+                }],
+                Color::WHITE,
+            ))
+        };
+        let (a, b, c) = (stroke(), stroke(), stroke());
+        sketch.set_locked(&[c], true);
+
+        let red = [1., 0., 0.];
+        assert_eq!(sketch.set_stroke_color(&[a, b, c], red), vec![a, b]);
+        assert_eq!(sketch.set_stroke_color(&[a], red), vec![]);
+        assert_eq!(sketch.strokes[b].color, red);
+        assert_eq!(sketch.strokes[c].color, Color::WHITE);
+
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default();
+        assert_eq!(
+            sketch.set_stroke_brush_size(&[b, c], 2., &mut tessellator, &options),
+            vec![b]
+        );
+        assert_eq!(sketch.strokes[b].brush_size(), 2.);
+        assert_ne!(sketch.strokes[a].brush_size(), 2.);
+    }
+
+    #[test]
+    fn screen_segments() {
+        let (width, height) = (200, 100);
+        let points = [(0., 0., 1.), (1., 2., 0.5), (-3., 1., 0.)]
+            .map(|(x, y, pressure)| StrokeElement { x, y, pressure })
+            .to_vec();
+        let mut stroke = Stroke::with_points(points.clone(), Color::WHITE);
+        stroke.brush_size = 0.1;
+
+        let mut hidden = Stroke::with_points(points.clone(), Color::WHITE);
+        hidden.erase();
+
+        let mut sketch = Sketch::<()>::new(vec![stroke, hidden]);
+        sketch.zoom = 20.;
+        sketch.origin = StrokePoint { x: 1., y: -2. };
+
+        let pixel = |point: &StrokeElement| {
+            Coords::pos_to_pixel(width, height, sketch.zoom, sketch.origin, point.into())
+        };
+
+        let segments = sketch
+            .iter_screen_segments::<Coords>(width, height)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            segments,
+            vec![
+                (pixel(&points[0]), pixel(&points[1]), 0.75 * 0.1 * 20.),
+                (pixel(&points[1]), pixel(&points[2]), 0.25 * 0.1 * 20.),
+            ]
+        );
+    }
+
+    #[test]
+    fn transform_all() {
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default();
+        let points = [(1., 0., 1.), (2., 1., 0.5), (0., 3., 0.25)]
+            .map(|(x, y, pressure)| StrokeElement { x, y, pressure })
+            .to_vec();
+        let mut stroke = Stroke::with_points(points.clone(), Color::WHITE);
+        stroke.brush_size = 0.1;
+        let mut sketch = Sketch::<()>::new(vec![stroke]);
+        let key = sketch.strokes.keys().next().unwrap();
+
+        sketch.transform_all(&Transform::identity(), &mut tessellator, &options);
+        assert_eq!(sketch.strokes[key].points(), points);
+        assert_eq!(sketch.strokes[key].brush_size(), 0.1);
+
+        // a quarter turn counter-clockwise takes (x, y) to (-y, x)
+        let quarter = Transform::rotation(lyon::math::Angle::frac_pi_2());
+        sketch.transform_all(&quarter, &mut tessellator, &options);
+        let stroke = &sketch.strokes[key];
+        for (before, after) in points.iter().zip(stroke.points()) {
+            assert!((after.x + before.y).abs() < 1e-6, "{} {}", before, after);
+            assert!((after.y - before.x).abs() < 1e-6, "{} {}", before, after);
+            assert_eq!(after.pressure, before.pressure);
+        }
+        assert!((stroke.brush_size() - 0.1).abs() < 1e-6);
+
+        // the bounding box moved along with the points
+        assert!(stroke.top_left.x < -2.9 && stroke.top_left.y > 1.9);
+        assert!(stroke.bottom_right.x > -0.1 && stroke.bottom_right.y < 0.1);
+
+        // and scaling scales the brush
+        sketch.transform_all(&Transform::scale(2., 2.), &mut tessellator, &options);
+        assert!((sketch.strokes[key].brush_size() - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bake_view() {
+        let (width, height) = (300, 200);
+        let points = [
+            (1., 0., 1.),
+            (2., 1., 0.5),
+            (0., 3., 0.25),
+            (-4., -1., 0.75),
+        ]
+        .map(|(x, y, pressure)| StrokeElement { x, y, pressure })
+        .to_vec();
+        let mut stroke = Stroke::with_points(points, Color::WHITE);
+        stroke.brush_size = 0.1;
+        let mut sketch = Sketch::<()>::new(vec![stroke]);
+        sketch.zoom = 130.;
+        sketch.origin = StrokePoint { x: -0.5, y: 1.25 };
+
+        let before = sketch
+            .iter_screen_segments::<Coords>(width, height)
+            .collect::<Vec<_>>();
+        sketch.bake_view(&mut StrokeTessellator::new(), &StrokeOptions::default());
+        assert_eq!(sketch.zoom, DEFAULT_ZOOM);
+        assert_eq!(sketch.origin, StrokePoint::default());
+        let after = sketch
+            .iter_screen_segments::<Coords>(width, height)
+            .collect::<Vec<_>>();
+
+        let close = |a: PixelPos, b: PixelPos| (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3;
+        assert_eq!(before.len(), after.len());
+        for ((a1, a2, a_width), (b1, b2, b_width)) in before.into_iter().zip(after) {
+            assert!(close(a1, b1), "{} {}", a1, b1);
+            assert!(close(a2, b2), "{} {}", a2, b2);
+            assert!((a_width - b_width).abs() < 1e-4);
+        }
+    }
+
+    fn in_triangle([a, b, c]: &[PixelPos; 3], x: f32, y: f32) -> bool {
+        let side = |p: PixelPos, q: PixelPos| (q.x - p.x) * (y - p.y) - (q.y - p.y) * (x - p.x);
+        let (ab, bc, ca) = (side(*a, *b), side(*b, *c), side(*c, *a));
+        (ab >= 0. && bc >= 0. && ca >= 0.) || (ab <= 0. && bc <= 0. && ca <= 0.)
+    }
+
+    #[test]
+    fn crisp_edges() {
+        use lyon::lyon_tessellation::LineCap;
+
+        // how many pixels an edge only partly covers, sampling each pixel on an 8x8 grid
+        fn blurry_pixels(triangles: &[[PixelPos; 3]]) -> usize {
+            let mut blurry = 0;
+            for py in 250..350 {
+                for px in 300..500 {
+                    let covered = (0..64)
+                        .filter(|sample| {
+                            let x = px as f32 + (sample % 8) as f32 / 8. + 1. / 16.;
+                            let y = py as f32 + (sample / 8) as f32 / 8. + 1. / 16.;
+                            triangles.iter().any(|triangle| in_triangle(triangle, x, y))
+                        })
+                        .count();
+                    if covered != 0 && covered != 64 {
+                        blurry += 1;
+                    }
+                }
+            }
+            blurry
+        }
+
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default()
+            .with_line_cap(LineCap::Butt)
+            .with_variable_line_width(0);
+        let (width, height, zoom) = (800, 600, DEFAULT_ZOOM);
+        // nowhere near lined up with the pixels
+        let origin = StrokePoint {
+            x: 0.0123,
+            y: -0.0377,
+        };
+
+        let mut blurry = |crisp| {
+            let mut stroke = Stroke::<()>::with_points(
+                (0..5)
+                    .map(|i| StrokeElement {
+                        x: i as f32 * 0.5 - 1.,
+                        y: 0.1,
+                        pressure: 1.,
+                    })
+                    .collect(),
+                Color::WHITE,
+            );
+            stroke.brush_size = 0.13;
+            stroke.crisp = crisp;
+            stroke.rebuild_entire_mesh(&mut tessellator, &options);
+            blurry_pixels(
+                &stroke
+                    .pixel_triangles::<Coords>(width, height, zoom, origin)
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let smooth = blurry(false);
+        assert!(smooth > 0);
+        assert_eq!(blurry(true), 0);
+    }
+
+    #[test]
+    fn higher_depth_on_top() {
+        let (width, height, zoom, origin) = (800, 600, DEFAULT_ZOOM, StrokePoint::default());
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default().with_variable_line_width(0);
+
+        let mut line = |from: (f32, f32), to: (f32, f32), color, depth| {
+            let mut stroke = Stroke::<()>::with_points(
+                vec![
+                    StrokeElement {
+                        x: from.0,
+                        y: from.1,
+                        pressure: 1.,
+                    },
+                    StrokeElement {
+                        x: to.0,
+                        y: to.1,
+                        pressure: 1.,
+                    },
+                ],
+                color,
+            );
+            stroke.brush_size = 0.5;
+            stroke.depth = depth;
+            stroke.rebuild_entire_mesh(&mut tessellator, &options);
+            stroke
+        };
+
+        // what a less-or-equal depth test leaves in the pixel where the strokes cross
+        let crossing = |strokes: &[&Stroke<()>]| {
+            let (x, y) = (width as f32 / 2. + 0.5, height as f32 / 2. + 0.5);
+            let mut depth_buffer = 1.;
+            let mut color = None;
+            for stroke in strokes {
+                let depth = graphics::depth_to_ndc(stroke.depth);
+                if stroke
+                    .pixel_triangles::<Coords>(width, height, zoom, origin)
+                    .any(|triangle| in_triangle(&triangle, x, y))
+                    && depth <= depth_buffer
+                {
+                    depth_buffer = depth;
+                    color = Some(stroke.color);
+                }
+            }
+            color
+        };
+
+        let red = [1., 0., 0.];
+        let blue = [0., 0., 1.];
+        let across = line((-2., 0.), (2., 0.), red, 1.);
+        let down = line((0., -2.), (0., 2.), blue, -1.);
+        assert_eq!(crossing(&[&across, &down]), Some(red));
+        assert_eq!(crossing(&[&down, &across]), Some(red));
+
+        // at the same depth it's whichever is drawn last, like before
+        let down = line((0., -2.), (0., 2.), blue, 1.);
+        assert_eq!(crossing(&[&across, &down]), Some(blue));
+        assert_eq!(crossing(&[&down, &across]), Some(red));
+
+        // and any depth fits in the depth buffer
+        for depth in [-1e9, -3., 0., 0.25, 3., 1e9] {
+            assert!((0. ..=1.).contains(&graphics::depth_to_ndc(depth)));
+        }
+        assert!(graphics::depth_to_ndc(2.) < graphics::depth_to_ndc(1.));
+    }
+
+    #[test]
+    fn styles_pick_primitives() {
+        use crate::stroke::LinePrimitive;
+
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default().with_variable_line_width(0);
+        let points = (0..20)
+            .map(|i| StrokeElement {
+                x: i as f32 / 4.,
+                y: (i as f32 / 4.).sin(),
+                pressure: 0.5,
+            })
+            .collect::<Vec<_>>();
+
+        let mut styled = |style, zoom| {
+            let mut stroke = Stroke::<()>::with_points(points.clone(), Color::WHITE);
+            stroke.brush_size = 0.1;
+            stroke.style = style;
+            stroke.remesh(&mut tessellator, &options);
+            stroke.update_primitive(zoom);
+            stroke
+        };
+
+        // how far the farthest vertex is from the closest point
+        let reach = |stroke: &Stroke<()>| {
+            stroke.vertices().fold(0f32, |reach, vertex| {
+                reach.max(
+                    points
+                        .iter()
+                        .map(|point| (vertex.x - point.x).hypot(vertex.y - point.y))
+                        .fold(f32::INFINITY, f32::min),
+                )
+            })
+        };
+
+        let lines = styled(StrokeStyle::Lines, 50.);
+        assert_eq!(lines.line_primitive(), Some(LinePrimitive::Strip));
+        assert!(lines.draw_tesselated);
+        assert_eq!(*lines.line_points(), points);
+
+        // the same kind of line, just smoother and going through more points
+        let spline = styled(StrokeStyle::Spline, 50.);
+        assert_eq!(spline.line_primitive(), Some(LinePrimitive::Strip));
+        assert!(spline.draw_tesselated);
+        let smooth = spline.line_points();
+        assert!(smooth.len() > points.len());
+        assert_eq!(smooth.first(), points.first());
+        assert!((smooth.last().unwrap().x - points.last().unwrap().x).abs() < 1e-5);
+
+        let dots = styled(StrokeStyle::Points, 50.);
+        assert_eq!(dots.line_primitive(), Some(LinePrimitive::Points));
+        assert!(!dots.draw_tesselated);
+
+        // circles are only a mesh, and pressure makes them smaller
+        let circles = styled(StrokeStyle::Circles, 50.);
+        assert_eq!(circles.line_primitive(), None);
+        assert!(circles.draw_tesselated);
+        assert!((reach(&circles) - 0.1).abs() < 1e-4);
+        let pressure = styled(StrokeStyle::CirclesPressure, 50.);
+        assert_eq!(pressure.line_primitive(), None);
+        assert!((reach(&pressure) - 0.05).abs() < 1e-4);
+
+        // unless they're too small to see
+        let tiny = styled(StrokeStyle::Circles, 1.);
+        assert!(!tiny.draw_tesselated);
+        assert_eq!(tiny.line_primitive(), Some(LinePrimitive::Points));
+    }
+
+    #[test]
+    fn catch_up_tessellation() {
+        let line = |x: f32| {
+            Stroke::<()>::with_points(
+                vec![
+                    StrokeElement {
+                        x,
+                        y: 0.,
+                        pressure: 1.,
+                    },
+                    StrokeElement {
+                        x,
+                        y: 1.,
+                        pressure: 1.,
+                    },
+                ],
+                Color::WHITE,
+            )
+        };
+        let mut sketch = Sketch::new(vec![line(0.), line(1.), line(2.), line(3.)]);
+        let keys = sketch.strokes.keys().collect::<Vec<_>>();
+        let (mut tessellator, options) = (StrokeTessellator::new(), StrokeOptions::default());
+
+        sketch.strokes[keys[0]].remesh(&mut tessellator, &options);
+        sketch.strokes[keys[3]].erase();
+        let clean = sketch.strokes[keys[0]].meshes[0].vertices().as_ptr();
+        let (zoom, origin) = (sketch.zoom, sketch.origin);
+
+        assert_eq!(sketch.ensure_tessellated(&mut tessellator, &options), 2);
+        assert_eq!(sketch.strokes[keys[0]].meshes[0].vertices().as_ptr(), clean);
+        assert!(!sketch.strokes[keys[1]].needs_mesh());
+        assert!(sketch.strokes[keys[2]].vertices().count() > 0);
+        assert!(sketch.strokes[keys[3]].needs_mesh());
+        assert_eq!((sketch.zoom, sketch.origin), (zoom, origin));
+
+        // caught up now
+        assert_eq!(sketch.ensure_tessellated(&mut tessellator, &options), 0);
+    }
+}
@@ -1,12 +1,20 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use winit::{
-    event_loop::{ControlFlow, DeviceEventFilter, EventLoopBuilder},
+    event_loop::{ControlFlow, DeviceEventFilter, EventLoopBuilder, EventLoopProxy},
     window::Window,
 };
 
 use crate::{
-    config::Config,
+    config::{Config, MonitorRect},
+    document::{Document, Documents},
     event::Event,
     gumdrop::Options,
+    pace::FramePacer,
+    recovery::Recovery,
+    resize::ResizeDebouncer,
     s,
     ui::widget::SketchWidget,
     winit::{
@@ -30,6 +38,8 @@ pub enum PerEvent {
 
 pub enum RenderResult {
     Redraw,
+    /// draw again after this long, or never if it's too long to wait for
+    RedrawAfter(Duration),
     Nothing,
 }
 
@@ -37,10 +47,19 @@ pub enum RenderResult {
 pub enum LoopEvent {
     Quit,
     Redraw,
+    /// open another document next to the focused one
+    NewDocument,
+    /// focus the next open document
+    NextDocument,
 }
 
 pub trait LoopContext<S: StrokeBackend, C: CoordinateSystem> {
-    fn setup(ev: &EventLoop<LoopEvent>, window: &Window, sketch: &mut Sketch<S>) -> Self;
+    fn setup(
+        ev: &EventLoop<LoopEvent>,
+        window: &Window,
+        sketch: &mut Sketch<S>,
+        config: &Config,
+    ) -> Self;
 
     fn per_event(
         &mut self,
@@ -102,21 +121,47 @@ where
     }
 
     let config_path = if let Some(config_path) = args.config {
-        config_path
-    } else if cfg!(feature = "pmb-release") {
+        Ok(config_path)
+    } else if let Some(config_dir) = args.config_dir {
+        Config::config_path_in(config_dir)
+    } else if cfg!(feature = "pmb-release")
+        || std::env::var_os(crate::config::CONFIG_DIR_VAR).is_some()
+    {
+        Config::config_path()
+    } else {
+        Ok(std::path::PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../config.ron"
+        )))
+    };
+
+    let config_path = {
         use crate::error::PmbErrorExt;
-        match Config::config_path().problem(s!(MboxMessageCouldNotOpenConfigFile)) {
+        match config_path.problem(s!(MboxMessageCouldNotOpenConfigFile)) {
             Ok(path) => path,
             Err(e) => {
                 e.display();
                 return;
             }
         }
-    } else {
-        std::path::PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../config.ron"))
     };
 
     let mut config = Config::from_disk(&config_path);
+    let recovery = Recovery::next_to(&config_path);
+    recovery.install_hook();
+
+    let ev = EventLoopBuilder::<LoopEvent>::with_user_event().build();
+    let monitors = ev
+        .available_monitors()
+        .map(|monitor| MonitorRect {
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+        })
+        .collect::<Vec<_>>();
+    config.forget_offscreen_window(&monitors);
+
     let mut builder = WindowBuilder::new()
         .with_maximized(config.window_start_maximized)
         .with_title(format!(
@@ -133,34 +178,73 @@ where
         builder = builder.with_inner_size(PhysicalSize { width, height });
     }
 
-    let ev = EventLoopBuilder::<LoopEvent>::with_user_event().build();
     let widget_proxy = ev.create_proxy();
     let proxy = ev.create_proxy();
     let window = builder.build(&ev).unwrap();
     ev.set_device_event_filter(DeviceEventFilter::Always);
 
-    let mut widget = {
-        let PhysicalSize { width, height } = window.inner_size();
-        SketchWidget::<C>::new(widget_proxy, width, height)
-    };
-    let mut sketch: Sketch<S> = if let Some(filename) = args.file {
-        Sketch::with_filename(&mut widget, filename)
-    } else {
-        Sketch::default()
-    };
-
     let mut size = window.inner_size();
+    let mut first = open_document(&config, widget_proxy, size, args.file);
+
+    let mut resizes = ResizeDebouncer::default();
+    let mut frames = FramePacer::default();
     let mut cursor_visible = true;
+    let mut ime_allowed = false;
 
     if let Ok(pos) = window.outer_position() {
         config.move_window(pos.x, pos.y);
     }
     config.resize_window(size.width, size.height);
 
-    let mut ctx = L::setup(&ev, &window, &mut sketch);
+    let mut ctx = L::setup(&ev, &window, &mut first.sketch, &config);
+    let mut documents = Documents::new(first);
+
+    if !recovery.left_over().is_empty() {
+        let wanted = crate::ui::prompt_recover();
+        for recovered in recovery.take() {
+            match recovered {
+                Ok(sketch) if wanted => {
+                    let mut document = open_document(&config, proxy.clone(), size, None);
+                    document.sketch = sketch;
+                    document.widget.force_update(&mut document.sketch);
+                    document.widget.modified = true;
+                    documents.push(document);
+                }
+                Err(err) if wanted => {
+                    use crate::error::PmbErrorExt;
+                    err.display();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // how each document had changed when its recovery snapshot was taken. only the focused one
+    // changes, so the others keep the snapshot from when they were last focused
+    let mut snapshots = std::collections::HashMap::new();
 
     ev.run(move |event, _, flow| {
-        flow.set_wait();
+        let (focused, count) = (documents.focused_index(), documents.count());
+        let Document { widget, sketch } = documents.focused_mut();
+
+        if widget.finish_idle_stroke(sketch, Instant::now()) {
+            frames.request();
+        }
+
+        // come back when the window has stopped changing size, to finish a stroke nobody
+        // finished, to draw a frame that had to wait, or to autosave once the stroke is done
+        let ready = widget.state == crate::ui::widget::SketchWidgetState::Ready;
+        match resizes
+            .deadline()
+            .into_iter()
+            .chain(widget.idle.deadline())
+            .chain(frames.deadline())
+            .chain(widget.autosave.deadline().filter(|_| ready))
+            .min()
+        {
+            Some(deadline) => flow.set_wait_until(deadline),
+            None => flow.set_wait(),
+        }
 
         match &event {
             WinitEvent::WindowEvent { event, .. } => {
@@ -169,47 +253,52 @@ where
             _ => {}
         }
 
-        let per_event = ctx.per_event(&event, &window, &mut sketch, &mut widget, &mut config);
+        let per_event = ctx.per_event(&event, &window, sketch, widget, &mut config);
 
         match per_event {
             PerEvent::ConsumedByEgui(redraw) => {
                 if redraw {
-                    window.request_redraw();
+                    frames.request();
                 }
 
                 return;
             }
 
-            PerEvent::Redraw => {
-                window.request_redraw();
-                proxy.send_event(LoopEvent::Redraw).unwrap();
-            }
+            PerEvent::Redraw => frames.request(),
 
             _ => {}
         }
 
         fn maybe_exit<S: StrokeBackend, C: CoordinateSystem>(
             flow: &mut ControlFlow,
-            sketch: &Sketch<S>,
-            widget: &mut SketchWidget<C>,
+            documents: &mut Documents<S, C>,
             config: &Config,
             config_path: &std::path::Path,
         ) {
-            if widget.modified {
-                if crate::ui::ask_to_save_then_save(
+            // every modified document gets asked about, and saying cancel to any of them stays open
+            for i in documents.modified().collect::<Vec<_>>() {
+                documents.focus(i);
+                let Document { widget, sketch } = documents.focused_mut();
+                if !crate::ui::ask_to_save_then_save(
                     widget,
                     sketch,
                     s!(&MboxMessageAskToSaveBeforeClosing),
                 )
                 .unwrap_or(false)
                 {
-                    flow.set_exit();
-                    config.save(config_path);
+                    return;
+                }
+            }
+
+            // everything was either saved or thrown away on purpose
+            for Document { widget, .. } in documents.iter_mut() {
+                if let Some(path) = widget.autosave.forget() {
+                    crate::autosave::remove(&path);
                 }
-            } else {
-                flow.set_exit();
-                config.save(config_path);
             }
+
+            flow.set_exit();
+            config.save(config_path);
         }
 
         match event {
@@ -223,25 +312,7 @@ where
             WinitEvent::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => maybe_exit(flow, &sketch, &mut widget, &config, &config_path),
-
-            #[cfg(not(feature = "pmb-release"))]
-            WinitEvent::WindowEvent {
-                event:
-                    WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(winit::event::VirtualKeyCode::Escape),
-                                ..
-                            },
-                        ..
-                    },
-                ..
-            } => {
-                flow.set_exit();
-                config.save(&config_path);
-            }
+            } => maybe_exit(flow, &mut documents, &config, &config_path),
 
             WinitEvent::WindowEvent {
                 event:
@@ -256,8 +327,8 @@ where
                     },
                 ..
             } => {
-                widget.handle_key(&mut config, &mut sketch, key, state);
-                window.request_redraw();
+                widget.handle_key(&mut config, sketch, key, state);
+                frames.request();
             }
 
             WinitEvent::WindowEvent {
@@ -266,14 +337,14 @@ where
             } => {
                 match delta {
                     MouseScrollDelta::LineDelta(_, delta) => {
-                        widget.next(&config, &mut sketch, Event::ScrollZoom(delta));
+                        widget.next(&config, sketch, Event::ScrollZoom(delta));
                     }
                     MouseScrollDelta::PixelDelta(delta) => {
-                        widget.next(&config, &mut sketch, Event::ScrollZoom(delta.y as f32));
+                        widget.next(&config, sketch, Event::ScrollZoom(delta.y as f32));
                     }
                 }
 
-                window.request_redraw();
+                frames.request();
             }
 
             WinitEvent::WindowEvent {
@@ -282,33 +353,33 @@ where
             } => {
                 match (button, state) {
                     (primary, ElementState::Pressed) if primary == config.primary_button => {
-                        widget.next(&config, &mut sketch, Event::MouseDown(button));
+                        widget.next(&config, sketch, Event::MouseDown(button));
                     }
                     (primary, ElementState::Released) if primary == config.primary_button => {
-                        widget.next(&config, &mut sketch, Event::MouseUp(button));
+                        widget.next(&config, sketch, Event::MouseUp(button));
                     }
                     (pan, ElementState::Pressed) if pan == config.pen_pan_button => {
-                        widget.next(&config, &mut sketch, Event::StartPan);
+                        widget.next(&config, sketch, Event::StartPan);
                     }
                     (pan, ElementState::Released) if pan == config.pen_pan_button => {
-                        widget.next(&config, &mut sketch, Event::EndPan);
+                        widget.next(&config, sketch, Event::EndPan);
                     }
                     _ => {}
                 }
 
                 widget.prev_device = crate::Device::Mouse;
-                window.request_redraw();
+                frames.request();
             }
 
             WinitEvent::WindowEvent {
                 event: WindowEvent::CursorMoved { position, .. },
                 ..
             } => {
-                widget.next(&config, &mut sketch, Event::MouseMove(position.into()));
+                widget.next(&config, sketch, Event::MouseMove(position.into()));
                 widget.prev_device = crate::Device::Mouse;
 
                 if config.use_mouse_for_pen || widget.state.redraw() {
-                    window.request_redraw();
+                    frames.request();
                 }
             }
 
@@ -324,16 +395,16 @@ where
                 ..
             } => {
                 match phase {
-                    TouchPhase::Started => widget.next(&config, &mut sketch, Event::PenDown(touch)),
-                    TouchPhase::Moved => widget.next(&config, &mut sketch, Event::PenMove(touch)),
+                    TouchPhase::Started => widget.next(&config, sketch, Event::PenDown(touch)),
+                    TouchPhase::Moved => widget.next(&config, sketch, Event::PenMove(touch)),
                     TouchPhase::Ended | TouchPhase::Cancelled => {
-                        widget.next(&config, &mut sketch, Event::PenUp(touch))
+                        widget.next(&config, sketch, Event::PenUp(touch))
                     }
                 }
 
                 widget.prev_device = crate::Device::Pen;
 
-                window.request_redraw();
+                frames.request();
             }
 
             WinitEvent::WindowEvent {
@@ -349,7 +420,7 @@ where
             } => {
                 widget.next(
                     &config,
-                    &mut sketch,
+                    sketch,
                     match phase {
                         TouchPhase::Started => Event::Touch(touch),
                         TouchPhase::Moved => Event::TouchMove(touch),
@@ -359,7 +430,7 @@ where
 
                 widget.prev_device = crate::Device::Touch;
 
-                window.request_redraw();
+                frames.request();
             }
 
             WinitEvent::WindowEvent {
@@ -378,27 +449,59 @@ where
                     },
                 ..
             } => {
-                size = new_size;
-                widget.resize(new_size.width, new_size.height, &mut sketch);
-                config.resize_window(new_size.width, new_size.height);
-                ctx.resize(new_size);
-                window.request_redraw();
+                resizes.quiet = Duration::from_secs_f32(config.resize_debounce_ms.max(0.) / 1000.);
+                resizes.push(new_size, Instant::now());
+                frames.request();
             }
 
             WinitEvent::MainEventsCleared => {
-                match (widget.path.as_ref(), widget.modified) {
+                // wait for strokes to finish so the snapshot has all of them
+                let changes = widget.undo_stack.changes();
+                if !widget.modified {
+                    recovery.clear(focused);
+                    snapshots.remove(&focused);
+                } else if widget.state == crate::ui::widget::SketchWidgetState::Ready
+                    && snapshots.get(&focused) != Some(&changes)
+                {
+                    recovery.update(focused, sketch);
+                    snapshots.insert(focused, changes);
+                }
+
+                widget.autosave.interval = config.autosave_interval();
+                widget.autosave.update(
+                    widget.modified,
+                    widget.undo_stack.changes(),
+                    Instant::now(),
+                );
+                if let Some(path) = widget.autosave.discard() {
+                    crate::autosave::remove(&path);
+                } else if widget.state == crate::ui::widget::SketchWidgetState::Ready {
+                    if let Some(path) = widget.autosave.take(Instant::now(), widget.path.as_deref())
+                    {
+                        match crate::autosave::write(&path, sketch) {
+                            Ok(()) => tracing::info!("autosaved to {}", path.display()),
+                            Err(err) => tracing::error!("couldn't autosave: {}", err),
+                        }
+                    }
+                }
+
+                let title = match (widget.path.as_ref(), widget.modified) {
                     (Some(path), true) => {
-                        let title =
-                            format!("{} ({})", path.display(), s!(&WindowTitleModifiedSign));
-                        window.set_title(title.as_str());
+                        format!("{} ({})", path.display(), s!(&WindowTitleModifiedSign))
                     }
-                    (Some(path), false) => window.set_title(&path.display().to_string()),
-                    (None, true) => window.set_title(&format!(
+                    (Some(path), false) => path.display().to_string(),
+                    (None, true) => format!(
                         "{} ({})",
                         s!(&WindowTitleNoFile),
                         s!(&WindowTitleModifiedSign)
-                    )),
-                    (None, false) => window.set_title(s!(&WindowTitleNoFile)),
+                    ),
+                    (None, false) => s!(&WindowTitleNoFile).to_string(),
+                };
+
+                if count > 1 {
+                    window.set_title(&format!("{} [{}/{}]", title, focused + 1, count));
+                } else {
+                    window.set_title(&title);
                 }
 
                 if ctx.egui_ctx().wants_pointer_input() {
@@ -416,35 +519,95 @@ where
                     }
                 }
 
-                window.request_redraw();
-            }
+                // winit only sends input method events once they're allowed, so let them through
+                // while a text box has focus
+                let next_ime = ctx.egui_ctx().wants_keyboard_input();
+                if ime_allowed != next_ime {
+                    window.set_ime_allowed(next_ime);
+                    ime_allowed = next_ime;
+                }
 
-            WinitEvent::UserEvent(LoopEvent::Redraw) => {
-                window.request_redraw();
-                flow.set_poll();
+                frames.interval = crate::pace::interval(
+                    config.max_fps,
+                    window
+                        .current_monitor()
+                        .and_then(|monitor| monitor.refresh_rate_millihertz()),
+                );
+                frames.request();
+                if frames.take(Instant::now()) {
+                    window.request_redraw();
+                }
             }
 
+            WinitEvent::UserEvent(LoopEvent::Redraw) => frames.request(),
+
             WinitEvent::UserEvent(LoopEvent::Quit) => {
-                maybe_exit(flow, &sketch, &mut widget, &config, &config_path)
+                maybe_exit(flow, &mut documents, &config, &config_path)
             }
 
-            WinitEvent::RedrawRequested(_) => match ctx.render(
-                &window,
-                &mut sketch,
-                &mut widget,
-                &mut config,
-                size,
-                cursor_visible,
-            ) {
-                RenderResult::Redraw => {
-                    window.request_redraw();
-                    proxy.send_event(LoopEvent::Redraw).unwrap();
+            WinitEvent::UserEvent(LoopEvent::NewDocument) => {
+                widget.input.clear();
+                documents.push(open_document(&config, proxy.clone(), size, None));
+                frames.request();
+            }
+
+            WinitEvent::UserEvent(LoopEvent::NextDocument) => {
+                // the keys that switched away shouldn't still be held when switching back
+                widget.input.clear();
+                documents.focus_next();
+                frames.request();
+            }
+
+            WinitEvent::RedrawRequested(_) => {
+                if let Some(new_size) = resizes.take(Instant::now()) {
+                    size = new_size;
+                    for Document { widget, sketch } in documents.iter_mut() {
+                        widget.resize(new_size.width, new_size.height, sketch);
+                    }
+                    config.resize_window(new_size.width, new_size.height);
+                    ctx.resize(new_size);
                 }
 
-                RenderResult::Nothing => {}
-            },
+                let Document { widget, sketch } = documents.focused_mut();
+
+                match ctx.render(&window, sketch, widget, &mut config, size, cursor_visible) {
+                    RenderResult::Redraw => frames.request(),
+
+                    RenderResult::RedrawAfter(after) => {
+                        if let Some(at) = Instant::now().checked_add(after) {
+                            frames.request_at(at);
+                        }
+                    }
+
+                    RenderResult::Nothing => {}
+                }
+            }
 
             _ => {}
         }
     });
 }
+
+/// a widget set up from the config, with the file or the configured template
+fn open_document<S: StrokeBackend, C: CoordinateSystem>(
+    config: &Config,
+    proxy: EventLoopProxy<LoopEvent>,
+    size: PhysicalSize<u32>,
+    file: Option<PathBuf>,
+) -> Document<S, C> {
+    let mut widget = SketchWidget::<C>::new(proxy, size.width, size.height);
+    widget.tessellator_kind = config.tessellator;
+    widget.line_cap = config.line_cap;
+    widget.line_join = config.line_join;
+    widget.style_override = config.style_override();
+    widget.stamp_spacing = config.stamp_spacing;
+    let sketch = if let Some(filename) = file {
+        Sketch::with_filename(&mut widget, filename)
+    } else {
+        let mut sketch = crate::ui::template_or_empty(config.template.as_deref());
+        widget.force_update(&mut sketch);
+        sketch
+    };
+
+    Document::new(widget, sketch)
+}
@@ -0,0 +1,110 @@
+use crate::graphics::StrokePos;
+
+/// a line between two points in stroke space, measured with the ruler tool
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Measurement {
+    pub start: StrokePos,
+    pub end: StrokePos,
+}
+
+impl Measurement {
+    pub fn new(start: StrokePos) -> Self {
+        Measurement { start, end: start }
+    }
+
+    pub fn dx(&self) -> f32 {
+        self.end.x - self.start.x
+    }
+
+    pub fn dy(&self) -> f32 {
+        self.end.y - self.start.y
+    }
+
+    /// distance in stroke units
+    pub fn distance(&self) -> f32 {
+        self.dx().hypot(self.dy())
+    }
+
+    /// distance in real-world units, given how many of them fit in one stroke unit
+    pub fn calibrated_distance(&self, units_per_stroke_unit: f32) -> f32 {
+        stroke_to_units(self.distance(), units_per_stroke_unit)
+    }
+
+    /// angle in degrees counter-clockwise from the positive x axis, in the range [0, 360)
+    pub fn angle(&self) -> f32 {
+        if self.distance() == 0. {
+            return 0.;
+        }
+
+        self.dy().atan2(self.dx()).to_degrees().rem_euclid(360.)
+    }
+
+    pub fn readout(&self, units_per_stroke_unit: Option<f32>, unit_name: &str) -> String {
+        match units_per_stroke_unit {
+            Some(factor) => format!(
+                "{:.02} ({:.02} {}) {:.01}°",
+                self.distance(),
+                self.calibrated_distance(factor),
+                unit_name,
+                self.angle(),
+            ),
+            None => format!("{:.02} {:.01}°", self.distance(), self.angle()),
+        }
+    }
+}
+
+pub fn stroke_to_units(distance: f32, units_per_stroke_unit: f32) -> f32 {
+    distance * units_per_stroke_unit
+}
+
+pub fn units_to_stroke(distance: f32, units_per_stroke_unit: f32) -> f32 {
+    distance / units_per_stroke_unit
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    fn measure(x1: f32, y1: f32, x2: f32, y2: f32) -> Measurement {
+        Measurement {
+            start: StrokePos { x: x1, y: y1 },
+            end: StrokePos { x: x2, y: y2 },
+        }
+    }
+
+    #[test]
+    fn distance() {
+        assert!(close(measure(0., 0., 3., 4.).distance(), 5.));
+        assert!(close(measure(-1., -1., 2., 3.).distance(), 5.));
+        assert!(close(measure(1., 1., 1., 1.).distance(), 0.));
+        assert!(close(
+            Measurement::new(StrokePos { x: 7., y: 2. }).distance(),
+            0.
+        ));
+    }
+
+    #[test]
+    fn angle() {
+        assert!(close(measure(0., 0., 1., 0.).angle(), 0.));
+        assert!(close(measure(0., 0., 1., 1.).angle(), 45.));
+        assert!(close(measure(0., 0., 0., 1.).angle(), 90.));
+        assert!(close(measure(0., 0., -1., 0.).angle(), 180.));
+        assert!(close(measure(0., 0., 0., -1.).angle(), 270.));
+        assert!(close(measure(0., 0., 1., -1.).angle(), 315.));
+        assert!(close(measure(2., 2., 2., 2.).angle(), 0.));
+    }
+
+    #[test]
+    fn units() {
+        let m = measure(0., 0., 3., 4.);
+        assert!(close(m.calibrated_distance(2.5), 12.5));
+        assert!(close(m.calibrated_distance(1.), m.distance()));
+        assert!(close(units_to_stroke(stroke_to_units(5., 0.3), 0.3), 5.));
+        assert_eq!(m.readout(None, "cm"), "5.00 53.1°");
+        assert_eq!(m.readout(Some(2.), "cm"), "5.00 (10.00 cm) 53.1°");
+    }
+}
@@ -0,0 +1,226 @@
+use crate::stroke::{MeshBuffer, WideMeshBuffer};
+use lyon::{
+    lyon_tessellation::{GeometryBuilderError, TessellationError, VertexBuffers},
+    math::Point,
+};
+use std::collections::HashMap;
+
+/// most vertices u16 indices can reach
+pub const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+/// merge vertices with exactly the same position, rewriting the indices to point at the merged
+/// vertex. the triangles stay the same, there are just fewer vertices to send to the GPU
+pub fn dedup(vertices: &[Point], indices: &[u16]) -> (Vec<Point>, Vec<u16>) {
+    let mut seen = HashMap::with_capacity(vertices.len());
+    let mut new_vertices = Vec::with_capacity(vertices.len());
+
+    // where each old vertex ended up
+    let remap = vertices
+        .iter()
+        .map(|vertex| {
+            // -0.0 and 0.0 are the same place
+            let key = ((vertex.x + 0.).to_bits(), (vertex.y + 0.).to_bits());
+            *seen.entry(key).or_insert_with(|| {
+                new_vertices.push(*vertex);
+                (new_vertices.len() - 1) as u16
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let new_indices = indices.iter().map(|index| remap[*index as usize]).collect();
+
+    (new_vertices, new_indices)
+}
+
+/// dedup a buffer in place
+pub fn dedup_buffer(buffer: &mut MeshBuffer) {
+    let before = buffer.vertices.len();
+    let (vertices, indices) = dedup(&buffer.vertices, &buffer.indices);
+    buffer.vertices = vertices;
+    buffer.indices = indices;
+
+    tracing::trace!(
+        "dedup {} -> {} vertices ({} bytes saved)",
+        before,
+        buffer.vertices.len(),
+        (before - buffer.vertices.len()) * std::mem::size_of::<Point>(),
+    );
+}
+
+/// the same mesh with u16 indices, if it isn't too big for them
+pub fn narrow(wide: WideMeshBuffer) -> Result<MeshBuffer, TessellationError> {
+    if wide.vertices.len() > MAX_VERTICES {
+        return Err(TessellationError::GeometryBuilder(
+            GeometryBuilderError::TooManyVertices,
+        ));
+    }
+
+    Ok(VertexBuffers {
+        vertices: wide.vertices,
+        indices: wide.indices.into_iter().map(|index| index as u16).collect(),
+    })
+}
+
+/// split a mesh into meshes of at most `max_vertices` vertices each, keeping every triangle in
+/// order. vertices shared between triangles in different meshes are copied into both
+pub fn chunks(wide: &WideMeshBuffer, max_vertices: usize) -> Vec<MeshBuffer> {
+    let max_vertices = max_vertices.clamp(3, MAX_VERTICES);
+    let mut chunks = Vec::new();
+    let mut chunk = MeshBuffer::new();
+    // where each wide vertex went in this chunk
+    let mut remap = HashMap::<u32, u16>::new();
+
+    for triangle in wide.indices.chunks_exact(3) {
+        let new = triangle
+            .iter()
+            .filter(|index| !remap.contains_key(index))
+            .count();
+        if chunk.vertices.len() + new > max_vertices {
+            chunks.push(std::mem::replace(&mut chunk, MeshBuffer::new()));
+            remap.clear();
+        }
+
+        for index in triangle {
+            let vertex = *remap.entry(*index).or_insert_with(|| {
+                chunk.vertices.push(wide.vertices[*index as usize]);
+                (chunk.vertices.len() - 1) as u16
+            });
+            chunk.indices.push(vertex);
+        }
+    }
+
+    if !chunk.indices.is_empty() {
+        chunks.push(chunk);
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stroke::StrokeElement;
+    use lyon::{
+        lyon_tessellation::{LineCap, LineJoin, StrokeOptions, StrokeTessellator},
+        math::point,
+    };
+
+    // every triangle as its three corners, in a canonical order
+    fn triangles(vertices: &[Point], indices: &[u16]) -> Vec<[(u32, u32); 3]> {
+        let mut triangles = indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let mut corners = [0, 1, 2].map(|i| {
+                    let vertex = vertices[triangle[i] as usize];
+                    ((vertex.x + 0.).to_bits(), (vertex.y + 0.).to_bits())
+                });
+                corners.sort();
+                corners
+            })
+            .collect::<Vec<_>>();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn same_triangles_fewer_vertices() {
+        // two triangles sharing an edge, with the shared corners duplicated
+        let vertices = [
+            point(0., 0.),
+            point(1., 0.),
+            point(0., 1.),
+            point(1., 0.),
+            point(0., 1.),
+            point(1., 1.),
+        ];
+        let indices = [0, 1, 2, 3, 4, 5];
+
+        let (new_vertices, new_indices) = dedup(&vertices, &indices);
+        assert_eq!(new_vertices.len(), 4);
+        assert_eq!(new_indices.len(), indices.len());
+        assert_eq!(
+            triangles(&new_vertices, &new_indices),
+            triangles(&vertices, &indices)
+        );
+
+        // already unique vertices are left alone
+        let (again_vertices, again_indices) = dedup(&new_vertices, &new_indices);
+        assert_eq!(again_vertices, new_vertices);
+        assert_eq!(again_indices, new_indices);
+    }
+
+    #[test]
+    fn tessellated_stroke() {
+        // scribbling back and forth over the same spot
+        let points = (0..200)
+            .map(|i| StrokeElement {
+                x: (i % 2) as f32,
+                y: (i % 3) as f32 / 2.,
+                pressure: 1.,
+            })
+            .collect::<Vec<_>>();
+        let options = StrokeOptions::default()
+            .with_line_cap(LineCap::Round)
+            .with_line_join(LineJoin::Round)
+            .with_tolerance(0.001)
+            .with_variable_line_width(0);
+
+        let mut buffer = crate::tess::tessellate_raw(
+            &mut StrokeTessellator::new(),
+            &options,
+            0.1,
+            &points,
+            false,
+        )
+        .unwrap();
+        let original = buffer.clone();
+        dedup_buffer(&mut buffer);
+
+        // lyon emits the same vertices every time the stroke goes back over itself
+        assert!(buffer.vertices.len() * 4 < original.vertices.len());
+        assert_eq!(buffer.indices.len(), original.indices.len());
+        assert_eq!(
+            triangles(&buffer.vertices, &buffer.indices),
+            triangles(&original.vertices, &original.indices)
+        );
+    }
+
+    #[test]
+    fn chunks_keep_every_triangle() {
+        // a fan of 100 triangles around the origin
+        let mut wide = WideMeshBuffer::new();
+        wide.vertices.push(point(0., 0.));
+        for i in 0..=100 {
+            let angle = i as f32 / 10.;
+            wide.vertices.push(point(angle.cos(), angle.sin()));
+            if i > 0 {
+                wide.indices.extend([0, i, i + 1]);
+            }
+        }
+
+        let chunks = chunks(&wide, 20);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.vertices.len() <= 20));
+
+        // the same corners of the same triangles in the same order
+        let corners = chunks
+            .iter()
+            .flat_map(|chunk| {
+                chunk
+                    .indices
+                    .iter()
+                    .map(|index| chunk.vertices[*index as usize])
+            })
+            .collect::<Vec<_>>();
+        let wide_corners = wide
+            .indices
+            .iter()
+            .map(|index| wide.vertices[*index as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(corners, wide_corners);
+
+        assert!(narrow(wide.clone()).is_ok());
+        wide.vertices.resize(MAX_VERTICES + 1, point(0., 0.));
+        assert!(narrow(wide).is_err());
+    }
+}
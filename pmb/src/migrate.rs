@@ -16,18 +16,20 @@
 //! - Add the new version to [Version::upgrade_type] and edit the compatibility between the old and
 //!   new [Sketch]es
 //! - Add an About impl in pmb_util for the old version
-//! - Add the new version to [from], replacing the old types from the new version to the previous
+//! - Add the new version to `from_reader`, replacing the old types from the new version to the previous
 //!   version and fight for your life
 
 use crate::{
     error::{ErrorKind, PmbError},
+    layer::Layer,
     Sketch, StrokeBackend,
 };
 use bincode::config::standard;
 use std::{
+    ffi::OsString,
     fmt::{Display, Formatter, Result as FmtResult},
     io::{Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 pub fn read<S: StrokeBackend>(mut reader: impl Read) -> Result<Sketch<S>, PmbError> {
@@ -59,18 +61,63 @@ pub fn write<S: StrokeBackend>(
     path: impl AsRef<std::path::Path>,
     state: &Sketch<S>,
 ) -> Result<(), PmbError> {
-    tracing::debug!("truncating {} and deflating", path.as_ref().display());
+    write_atomic(path, |file| encode(file, state))
+}
 
-    let mut file = std::fs::File::create(&path)?;
-    file.write_all(&crate::PMB_MAGIC)?;
-    file.write_all(&u64::to_le_bytes(Version::CURRENT.0))?;
+/// everything that goes in a file, header and all
+pub fn encode<S: StrokeBackend>(mut writer: impl Write, state: &Sketch<S>) -> Result<(), PmbError> {
+    tracing::debug!("deflating");
+    writer.write_all(&crate::PMB_MAGIC)?;
+    writer.write_all(&u64::to_le_bytes(Version::CURRENT.0))?;
 
-    let mut deflate_writer = flate2::write::DeflateEncoder::new(file, flate2::Compression::fast());
+    let mut deflate_writer =
+        flate2::write::DeflateEncoder::new(writer, flate2::Compression::fast());
     bincode::encode_into_std_write(state, &mut deflate_writer, standard())?;
+    deflate_writer.finish()?;
 
     Ok(())
 }
 
+/// Write to a temporary file next to `path`, only replacing `path` once everything has been
+/// written successfully. If anything goes wrong the temporary file is removed and whatever was at
+/// `path` is left untouched.
+pub fn write_atomic(
+    path: impl AsRef<Path>,
+    write: impl FnOnce(&mut std::fs::File) -> Result<(), PmbError>,
+) -> Result<(), PmbError> {
+    let path = path.as_ref();
+    let tmp_path = tmp_path(path);
+    tracing::debug!(
+        "writing {} before replacing {}",
+        tmp_path.display(),
+        path.display()
+    );
+
+    let result = std::fs::File::create(&tmp_path)
+        .map_err(PmbError::from)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()?;
+            Ok(())
+        })
+        .and_then(|()| Ok(std::fs::rename(&tmp_path, path)?));
+
+    if result.is_err() && tmp_path.exists() {
+        if let Err(err) = std::fs::remove_file(&tmp_path) {
+            tracing::error!("couldn't remove {}: {}", tmp_path.display(), err);
+        }
+    }
+
+    result
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
 #[derive(Debug, PartialEq)]
 pub enum UpgradeType {
     Smooth,
@@ -89,7 +136,7 @@ impl Display for Version {
 }
 
 impl Version {
-    pub const CURRENT: Self = Version(9);
+    pub const CURRENT: Self = Version(18);
 
     pub fn upgrade_type(from: Self) -> UpgradeType {
         use UpgradeType::*;
@@ -99,7 +146,7 @@ impl Version {
         }
 
         match from {
-            Version(5..=8) => Smooth,
+            Version(5..=17) => Smooth,
             Version(1..=4) => Rocky,
             _ => Incompatible,
         }
@@ -116,7 +163,6 @@ impl Version {
     }
 }
 
-#[allow(clippy::needless_return)]
 pub fn from<S>(version: Version, path: impl AsRef<Path>) -> Result<Sketch<S>, PmbError>
 where
     S: StrokeBackend,
@@ -132,16 +178,508 @@ where
         return Err(PmbError::new(ErrorKind::IncompatibleVersion(version)));
     }
 
+    let file = std::fs::File::open(&path)?;
+    let mut sketch = from_reader(version, file)?;
+
+    // files from before layers have everything on one
+    sketch.layers = vec![Layer::with_strokes(sketch.strokes.keys())];
+    Ok(sketch)
+}
+
+#[allow(clippy::needless_return)]
+fn from_reader<S>(version: Version, file: impl Read) -> Result<Sketch<S>, PmbError>
+where
+    S: StrokeBackend,
+{
     use crate::{
-        graphics::{Color, ColorExt, StrokePoint},
+        graphics::{Color, ColorExt, StrokePoint, StrokePos},
         stroke::*,
     };
 
-    let file = std::fs::File::open(&path)?;
-
     match version {
         version if version == Version::CURRENT => unreachable!(),
 
+        Version(17) => {
+            let v17: v17::SketchV17 = v17::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v17.strokes
+                        .into_iter()
+                        .map(|v17| Stroke {
+                            points: {
+                                v17.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v17.color,
+                            brush_size: v17.brush_size,
+                            tags: v17.tags,
+                            group_id: v17.group_id,
+                            clip: v17.clip.map(|clip| crate::clip::ClipRect {
+                                top_left: StrokePos {
+                                    x: clip.top_left.x,
+                                    y: clip.top_left.y,
+                                },
+                                bottom_right: StrokePos {
+                                    x: clip.bottom_right.x,
+                                    y: clip.bottom_right.y,
+                                },
+                            }),
+                            crisp: v17.crisp,
+                            depth: v17.depth,
+                            style: match v17.style {
+                                v17::StrokeStyleV17::Lines => StrokeStyle::Lines,
+                                v17::StrokeStyleV17::Circles => StrokeStyle::Circles,
+                                v17::StrokeStyleV17::CirclesPressure => {
+                                    StrokeStyle::CirclesPressure
+                                }
+                                v17::StrokeStyleV17::Points => StrokeStyle::Points,
+                                v17::StrokeStyleV17::Spline => StrokeStyle::Spline,
+                            },
+                            locked: v17.locked,
+                            closed: v17.closed,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v17.zoom,
+                origin: StrokePoint {
+                    x: v17.origin.x,
+                    y: v17.origin.y,
+                },
+                bg_color: v17.bg_color,
+                fg_color: v17.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(16) => {
+            let v16: v16::SketchV16 = v16::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v16.strokes
+                        .into_iter()
+                        .map(|v16| Stroke {
+                            points: {
+                                v16.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v16.color,
+                            brush_size: v16.brush_size,
+                            tags: v16.tags,
+                            group_id: v16.group_id,
+                            clip: v16.clip.map(|clip| crate::clip::ClipRect {
+                                top_left: StrokePos {
+                                    x: clip.top_left.x,
+                                    y: clip.top_left.y,
+                                },
+                                bottom_right: StrokePos {
+                                    x: clip.bottom_right.x,
+                                    y: clip.bottom_right.y,
+                                },
+                            }),
+                            crisp: v16.crisp,
+                            depth: v16.depth,
+                            style: match v16.style {
+                                v16::StrokeStyleV16::Lines => StrokeStyle::Lines,
+                                v16::StrokeStyleV16::Circles => StrokeStyle::Circles,
+                                v16::StrokeStyleV16::CirclesPressure => {
+                                    StrokeStyle::CirclesPressure
+                                }
+                                v16::StrokeStyleV16::Points => StrokeStyle::Points,
+                                v16::StrokeStyleV16::Spline => StrokeStyle::Spline,
+                            },
+                            locked: v16.locked,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v16.zoom,
+                origin: StrokePoint {
+                    x: v16.origin.x,
+                    y: v16.origin.y,
+                },
+                bg_color: v16.bg_color,
+                fg_color: v16.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(15) => {
+            let v15: v15::SketchV15 = v15::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v15.strokes
+                        .into_iter()
+                        .map(|v15| Stroke {
+                            points: {
+                                v15.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v15.color,
+                            brush_size: v15.brush_size,
+                            tags: v15.tags,
+                            group_id: v15.group_id,
+                            clip: v15.clip.map(|clip| crate::clip::ClipRect {
+                                top_left: StrokePos {
+                                    x: clip.top_left.x,
+                                    y: clip.top_left.y,
+                                },
+                                bottom_right: StrokePos {
+                                    x: clip.bottom_right.x,
+                                    y: clip.bottom_right.y,
+                                },
+                            }),
+                            crisp: v15.crisp,
+                            depth: v15.depth,
+                            style: match v15.style {
+                                v15::StrokeStyleV15::Lines => StrokeStyle::Lines,
+                                v15::StrokeStyleV15::Circles => StrokeStyle::Circles,
+                                v15::StrokeStyleV15::CirclesPressure => {
+                                    StrokeStyle::CirclesPressure
+                                }
+                                v15::StrokeStyleV15::Points => StrokeStyle::Points,
+                                v15::StrokeStyleV15::Spline => StrokeStyle::Spline,
+                            },
+                            locked: false,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v15.zoom,
+                origin: StrokePoint {
+                    x: v15.origin.x,
+                    y: v15.origin.y,
+                },
+                bg_color: v15.bg_color,
+                fg_color: v15.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(14) => {
+            let v14: v14::SketchV14 = v14::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v14.strokes
+                        .into_iter()
+                        .map(|v14| Stroke {
+                            points: {
+                                v14.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v14.color,
+                            brush_size: v14.brush_size,
+                            tags: v14.tags,
+                            group_id: v14.group_id,
+                            clip: v14.clip.map(|clip| crate::clip::ClipRect {
+                                top_left: StrokePos {
+                                    x: clip.top_left.x,
+                                    y: clip.top_left.y,
+                                },
+                                bottom_right: StrokePos {
+                                    x: clip.bottom_right.x,
+                                    y: clip.bottom_right.y,
+                                },
+                            }),
+                            crisp: v14.crisp,
+                            depth: v14.depth,
+                            style: StrokeStyle::Lines,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v14.zoom,
+                origin: StrokePoint {
+                    x: v14.origin.x,
+                    y: v14.origin.y,
+                },
+                bg_color: v14.bg_color,
+                fg_color: v14.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(13) => {
+            let v13: v13::SketchV13 = v13::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v13.strokes
+                        .into_iter()
+                        .map(|v13| Stroke {
+                            points: {
+                                v13.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v13.color,
+                            brush_size: v13.brush_size,
+                            tags: v13.tags,
+                            group_id: v13.group_id,
+                            clip: v13.clip.map(|clip| crate::clip::ClipRect {
+                                top_left: StrokePos {
+                                    x: clip.top_left.x,
+                                    y: clip.top_left.y,
+                                },
+                                bottom_right: StrokePos {
+                                    x: clip.bottom_right.x,
+                                    y: clip.bottom_right.y,
+                                },
+                            }),
+                            crisp: v13.crisp,
+                            depth: 0.,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v13.zoom,
+                origin: StrokePoint {
+                    x: v13.origin.x,
+                    y: v13.origin.y,
+                },
+                bg_color: v13.bg_color,
+                fg_color: v13.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(12) => {
+            let v12: v12::SketchV12 = v12::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v12.strokes
+                        .into_iter()
+                        .map(|v12| Stroke {
+                            points: {
+                                v12.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v12.color,
+                            brush_size: v12.brush_size,
+                            tags: v12.tags,
+                            group_id: v12.group_id,
+                            clip: v12.clip.map(|clip| crate::clip::ClipRect {
+                                top_left: StrokePos {
+                                    x: clip.top_left.x,
+                                    y: clip.top_left.y,
+                                },
+                                bottom_right: StrokePos {
+                                    x: clip.bottom_right.x,
+                                    y: clip.bottom_right.y,
+                                },
+                            }),
+                            crisp: false,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v12.zoom,
+                origin: StrokePoint {
+                    x: v12.origin.x,
+                    y: v12.origin.y,
+                },
+                bg_color: v12.bg_color,
+                fg_color: v12.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(11) => {
+            let v11: v11::SketchV11 = v11::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v11.strokes
+                        .into_iter()
+                        .map(|v11| Stroke {
+                            points: {
+                                v11.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v11.color,
+                            brush_size: v11.brush_size,
+                            tags: v11.tags,
+                            group_id: v11.group_id,
+                            clip: None,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v11.zoom,
+                origin: StrokePoint {
+                    x: v11.origin.x,
+                    y: v11.origin.y,
+                },
+                bg_color: v11.bg_color,
+                fg_color: v11.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(10) => {
+            let v10: v10::SketchV10 = v10::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v10.strokes
+                        .into_iter()
+                        .map(|v10| Stroke {
+                            points: {
+                                v10.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v10.color,
+                            brush_size: v10.brush_size,
+                            tags: v10.tags,
+                            group_id: None,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v10.zoom,
+                origin: StrokePoint {
+                    x: v10.origin.x,
+                    y: v10.origin.y,
+                },
+                bg_color: v10.bg_color,
+                fg_color: v10.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(9) => {
+            let v9: v9::SketchV9 = v9::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v9.strokes
+                        .into_iter()
+                        .map(|v9| Stroke {
+                            points: {
+                                v9.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: v9.color,
+                            brush_size: v9.brush_size,
+                            tags: Vec::new(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v9.zoom,
+                origin: StrokePoint {
+                    x: v9.origin.x,
+                    y: v9.origin.y,
+                },
+                bg_color: v9.bg_color,
+                fg_color: v9.fg_color,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
         Version(8) => {
             let v8: v8::SketchV8 = v8::read(file)?;
 
@@ -174,6 +712,10 @@ where
                 },
                 bg_color: v8.bg_color,
                 fg_color: Color::WHITE,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
             };
 
             return Ok(state);
@@ -211,6 +753,10 @@ where
                 },
                 bg_color: v7.bg_color,
                 fg_color: Color::WHITE,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
             };
 
             return Ok(state);
@@ -235,7 +781,7 @@ where
                                     })
                                     .collect()
                             },
-                            color: Color::from_u8(v6.color),
+                            color: Color::from_bytes(v6.color),
                             brush_size: v6.brush_size,
                             ..Default::default()
                         })
@@ -248,196 +794,904 @@ where
                 },
                 bg_color: Color::BLACK,
                 fg_color: Color::WHITE,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
             };
 
-            return Ok(state);
-        }
+            return Ok(state);
+        }
+
+        Version(5) => {
+            let v5: v5::SketchV5 = v5::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v5.strokes
+                        .into_iter()
+                        .filter(|v5| !v5.erased)
+                        .map(|v5| Stroke {
+                            points: {
+                                v5.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: Color::from_bytes(v5.color),
+                            brush_size: v5.brush_size,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v5.zoom,
+                origin: StrokePoint {
+                    x: v5.origin.x,
+                    y: v5.origin.y,
+                },
+                bg_color: Color::BLACK,
+                fg_color: Color::WHITE,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(4) => {
+            let v4: v4::StateV4 = v4::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v4.strokes
+                        .into_iter()
+                        .filter(|v4| !v4.erased)
+                        .map(|v4| Stroke {
+                            points: {
+                                v4.points
+                                    .iter()
+                                    .map(|point| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure: point.pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: Color::from_bytes(v4.color),
+                            brush_size: v4.brush_size,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v4.zoom,
+                origin: StrokePoint {
+                    x: v4.origin.x,
+                    y: v4.origin.y,
+                },
+                bg_color: Color::BLACK,
+                fg_color: Color::WHITE,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(3) => {
+            let v3: v3::StateV3 = v3::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v3.strokes
+                        .into_iter()
+                        .filter(|v3| !v3.erased)
+                        .map(|v3| Stroke {
+                            points: {
+                                v3.points
+                                    .iter()
+                                    .zip(v3.pressure.iter())
+                                    .map(|(point, &pressure)| StrokeElement {
+                                        x: point.x,
+                                        y: point.y,
+                                        pressure,
+                                    })
+                                    .collect()
+                            },
+                            color: Color::from_bytes(v3.color),
+                            brush_size: v3.brush_size,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v3.zoom,
+                origin: StrokePoint {
+                    x: v3.origin.x,
+                    y: v3.origin.y,
+                },
+                bg_color: Color::BLACK,
+                fg_color: Color::WHITE,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(2) => {
+            let v2: v2::StateV2 = v2::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v2.strokes
+                        .into_iter()
+                        .filter(|v3| !v3.erased)
+                        .map(|v2| Stroke {
+                            points: v2
+                                .points
+                                .iter()
+                                .map(|v2| StrokeElement {
+                                    x: v2.x,
+                                    y: v2.y,
+                                    pressure: v2.pressure,
+                                })
+                                .collect(),
+                            color: Color::from_bytes(v2.color),
+                            brush_size: v2.brush_size,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v2.zoom,
+                origin: StrokePoint {
+                    x: v2.origin.x,
+                    y: v2.origin.y,
+                },
+                bg_color: Color::BLACK,
+                fg_color: Color::WHITE,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        Version(1) => {
+            let v1: v1::StateV1 = v1::read(file)?;
+
+            let state = Sketch {
+                strokes: crate::map_from_vec(
+                    v1.strokes
+                        .into_iter()
+                        .filter(|v2| !v2.erased)
+                        .map(|v1| Stroke {
+                            points: v1
+                                .points
+                                .iter()
+                                .map(|v1| StrokeElement {
+                                    x: v1.x,
+                                    y: v1.y,
+                                    pressure: v1.pressure,
+                                })
+                                .collect(),
+                            color: Color::from_bytes(v1.color),
+                            brush_size: v1.brush_size,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                zoom: v1.zoom,
+                origin: StrokePoint {
+                    x: v1.origin.x,
+                    y: v1.origin.y,
+                },
+                bg_color: Color::BLACK,
+                fg_color: Color::WHITE,
+                clip: None,
+                selection: Vec::new(),
+                layers: Vec::new(),
+                index: Default::default(),
+            };
+
+            return Ok(state);
+        }
+
+        _ => Err(PmbError::new(ErrorKind::UnknownVersion(version))),
+    }
+}
+
+pub mod v17 {
+    use super::*;
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV17 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePosV17 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV17 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct ClipRectV17 {
+        pub top_left: StrokePosV17,
+        pub bottom_right: StrokePosV17,
+    }
+
+    #[derive(bincode::Decode)]
+    pub enum StrokeStyleV17 {
+        Lines,
+        Circles,
+        CirclesPressure,
+        Points,
+        Spline,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeV17 {
+        pub points: Vec<StrokeElementV17>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+        pub tags: Vec<String>,
+        pub group_id: Option<u32>,
+        pub clip: Option<ClipRectV17>,
+        pub crisp: bool,
+        pub depth: f32,
+        pub style: StrokeStyleV17,
+        pub locked: bool,
+        pub closed: bool,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct SketchV17 {
+        pub zoom: f32,
+        pub origin: StrokePointV17,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV17>,
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<SketchV17, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
+
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
+        }
+
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
+
+        tracing::debug!("got version {}", version);
+        if version != Version(17) {
+            unreachable!(
+                "called v17::read when you should have called v{}::read",
+                version
+            );
+        }
+
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
+    }
+}
+
+pub mod v16 {
+    use super::*;
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV16 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePosV16 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV16 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct ClipRectV16 {
+        pub top_left: StrokePosV16,
+        pub bottom_right: StrokePosV16,
+    }
+
+    #[derive(bincode::Decode)]
+    pub enum StrokeStyleV16 {
+        Lines,
+        Circles,
+        CirclesPressure,
+        Points,
+        Spline,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeV16 {
+        pub points: Vec<StrokeElementV16>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+        pub tags: Vec<String>,
+        pub group_id: Option<u32>,
+        pub clip: Option<ClipRectV16>,
+        pub crisp: bool,
+        pub depth: f32,
+        pub style: StrokeStyleV16,
+        pub locked: bool,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct SketchV16 {
+        pub zoom: f32,
+        pub origin: StrokePointV16,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV16>,
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<SketchV16, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
+
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
+        }
+
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
+
+        tracing::debug!("got version {}", version);
+        if version != Version(16) {
+            unreachable!(
+                "called v16::read when you should have called v{}::read",
+                version
+            );
+        }
+
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
+    }
+}
+
+pub mod v15 {
+    use super::*;
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV15 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePosV15 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV15 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct ClipRectV15 {
+        pub top_left: StrokePosV15,
+        pub bottom_right: StrokePosV15,
+    }
+
+    #[derive(bincode::Decode)]
+    pub enum StrokeStyleV15 {
+        Lines,
+        Circles,
+        CirclesPressure,
+        Points,
+        Spline,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeV15 {
+        pub points: Vec<StrokeElementV15>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+        pub tags: Vec<String>,
+        pub group_id: Option<u32>,
+        pub clip: Option<ClipRectV15>,
+        pub crisp: bool,
+        pub depth: f32,
+        pub style: StrokeStyleV15,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct SketchV15 {
+        pub zoom: f32,
+        pub origin: StrokePointV15,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV15>,
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<SketchV15, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
+
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
+        }
+
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
+
+        tracing::debug!("got version {}", version);
+        if version != Version(15) {
+            unreachable!(
+                "called v15::read when you should have called v{}::read",
+                version
+            );
+        }
+
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
+    }
+}
+
+pub mod v14 {
+    use super::*;
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV14 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePosV14 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV14 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct ClipRectV14 {
+        pub top_left: StrokePosV14,
+        pub bottom_right: StrokePosV14,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeV14 {
+        pub points: Vec<StrokeElementV14>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+        pub tags: Vec<String>,
+        pub group_id: Option<u32>,
+        pub clip: Option<ClipRectV14>,
+        pub crisp: bool,
+        pub depth: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct SketchV14 {
+        pub zoom: f32,
+        pub origin: StrokePointV14,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV14>,
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<SketchV14, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
+
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
+        }
+
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
+
+        tracing::debug!("got version {}", version);
+        if version != Version(14) {
+            unreachable!(
+                "called v14::read when you should have called v{}::read",
+                version
+            );
+        }
+
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
+    }
+}
+
+pub mod v13 {
+    use super::*;
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV13 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePosV13 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV13 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct ClipRectV13 {
+        pub top_left: StrokePosV13,
+        pub bottom_right: StrokePosV13,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeV13 {
+        pub points: Vec<StrokeElementV13>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+        pub tags: Vec<String>,
+        pub group_id: Option<u32>,
+        pub clip: Option<ClipRectV13>,
+        pub crisp: bool,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct SketchV13 {
+        pub zoom: f32,
+        pub origin: StrokePointV13,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV13>,
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<SketchV13, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
+
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
+        }
+
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
+
+        tracing::debug!("got version {}", version);
+        if version != Version(13) {
+            unreachable!(
+                "called v13::read when you should have called v{}::read",
+                version
+            );
+        }
+
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
+    }
+}
+
+pub mod v12 {
+    use super::*;
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV12 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePosV12 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV12 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct ClipRectV12 {
+        pub top_left: StrokePosV12,
+        pub bottom_right: StrokePosV12,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeV12 {
+        pub points: Vec<StrokeElementV12>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+        pub tags: Vec<String>,
+        pub group_id: Option<u32>,
+        pub clip: Option<ClipRectV12>,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct SketchV12 {
+        pub zoom: f32,
+        pub origin: StrokePointV12,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV12>,
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<SketchV12, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
+
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
+        }
+
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
+
+        tracing::debug!("got version {}", version);
+        if version != Version(12) {
+            unreachable!(
+                "called v12::read when you should have called v{}::read",
+                version
+            );
+        }
+
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
+    }
+}
+
+pub mod v11 {
+    use super::*;
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV11 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV11 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeV11 {
+        pub points: Vec<StrokeElementV11>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+        pub tags: Vec<String>,
+        pub group_id: Option<u32>,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct SketchV11 {
+        pub zoom: f32,
+        pub origin: StrokePointV11,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV11>,
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<SketchV11, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
+
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
+        }
+
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
+
+        tracing::debug!("got version {}", version);
+        if version != Version(11) {
+            unreachable!(
+                "called v11::read when you should have called v{}::read",
+                version
+            );
+        }
+
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
+    }
+}
+
+pub mod v10 {
+    use super::*;
+
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV10 {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV10 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
+
+    #[derive(bincode::Decode)]
+    pub struct StrokeV10 {
+        pub points: Vec<StrokeElementV10>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+        pub tags: Vec<String>,
+    }
 
-        Version(5) => {
-            let v5: v5::SketchV5 = v5::read(file)?;
+    #[derive(bincode::Decode)]
+    pub struct SketchV10 {
+        pub zoom: f32,
+        pub origin: StrokePointV10,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV10>,
+    }
 
-            let state = Sketch {
-                strokes: crate::map_from_vec(
-                    v5.strokes
-                        .into_iter()
-                        .filter(|v5| !v5.erased)
-                        .map(|v5| Stroke {
-                            points: {
-                                v5.points
-                                    .iter()
-                                    .map(|point| StrokeElement {
-                                        x: point.x,
-                                        y: point.y,
-                                        pressure: point.pressure,
-                                    })
-                                    .collect()
-                            },
-                            color: Color::from_u8(v5.color),
-                            brush_size: v5.brush_size,
-                            ..Default::default()
-                        })
-                        .collect(),
-                ),
-                zoom: v5.zoom,
-                origin: StrokePoint {
-                    x: v5.origin.x,
-                    y: v5.origin.y,
-                },
-                bg_color: Color::BLACK,
-                fg_color: Color::WHITE,
-            };
+    pub fn read(mut reader: impl Read) -> Result<SketchV10, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
 
-            return Ok(state);
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
         }
 
-        Version(4) => {
-            let v4: v4::StateV4 = v4::read(file)?;
-
-            let state = Sketch {
-                strokes: crate::map_from_vec(
-                    v4.strokes
-                        .into_iter()
-                        .filter(|v4| !v4.erased)
-                        .map(|v4| Stroke {
-                            points: {
-                                v4.points
-                                    .iter()
-                                    .map(|point| StrokeElement {
-                                        x: point.x,
-                                        y: point.y,
-                                        pressure: point.pressure,
-                                    })
-                                    .collect()
-                            },
-                            color: Color::from_u8(v4.color),
-                            brush_size: v4.brush_size,
-                            ..Default::default()
-                        })
-                        .collect(),
-                ),
-                zoom: v4.zoom,
-                origin: StrokePoint {
-                    x: v4.origin.x,
-                    y: v4.origin.y,
-                },
-                bg_color: Color::BLACK,
-                fg_color: Color::WHITE,
-            };
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
 
-            return Ok(state);
+        tracing::debug!("got version {}", version);
+        if version != Version(10) {
+            unreachable!(
+                "called v10::read when you should have called v{}::read",
+                version
+            );
         }
 
-        Version(3) => {
-            let v3: v3::StateV3 = v3::read(file)?;
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
+    }
+}
 
-            let state = Sketch {
-                strokes: crate::map_from_vec(
-                    v3.strokes
-                        .into_iter()
-                        .filter(|v3| !v3.erased)
-                        .map(|v3| Stroke {
-                            points: {
-                                v3.points
-                                    .iter()
-                                    .zip(v3.pressure.iter())
-                                    .map(|(point, &pressure)| StrokeElement {
-                                        x: point.x,
-                                        y: point.y,
-                                        pressure,
-                                    })
-                                    .collect()
-                            },
-                            color: Color::from_u8(v3.color),
-                            brush_size: v3.brush_size,
-                            ..Default::default()
-                        })
-                        .collect(),
-                ),
-                zoom: v3.zoom,
-                origin: StrokePoint {
-                    x: v3.origin.x,
-                    y: v3.origin.y,
-                },
-                bg_color: Color::BLACK,
-                fg_color: Color::WHITE,
-            };
+pub mod v9 {
+    use super::*;
 
-            return Ok(state);
-        }
+    #[derive(bincode::Decode)]
+    pub struct StrokePointV9 {
+        pub x: f32,
+        pub y: f32,
+    }
 
-        Version(2) => {
-            let v2: v2::StateV2 = v2::read(file)?;
+    #[derive(bincode::Decode)]
+    pub struct StrokeElementV9 {
+        pub x: f32,
+        pub y: f32,
+        pub pressure: f32,
+    }
 
-            let state = Sketch {
-                strokes: crate::map_from_vec(
-                    v2.strokes
-                        .into_iter()
-                        .filter(|v3| !v3.erased)
-                        .map(|v2| Stroke {
-                            points: v2
-                                .points
-                                .iter()
-                                .map(|v2| StrokeElement {
-                                    x: v2.x,
-                                    y: v2.y,
-                                    pressure: v2.pressure,
-                                })
-                                .collect(),
-                            color: Color::from_u8(v2.color),
-                            brush_size: v2.brush_size,
-                            ..Default::default()
-                        })
-                        .collect(),
-                ),
-                zoom: v2.zoom,
-                origin: StrokePoint {
-                    x: v2.origin.x,
-                    y: v2.origin.y,
-                },
-                bg_color: Color::BLACK,
-                fg_color: Color::WHITE,
-            };
+    #[derive(bincode::Decode)]
+    pub struct StrokeV9 {
+        pub points: Vec<StrokeElementV9>,
+        pub color: [f32; 3],
+        pub brush_size: f32,
+    }
 
-            return Ok(state);
-        }
+    #[derive(bincode::Decode)]
+    pub struct SketchV9 {
+        pub zoom: f32,
+        pub origin: StrokePointV9,
+        pub bg_color: [f32; 3],
+        pub fg_color: [f32; 3],
+        pub strokes: Vec<StrokeV9>,
+    }
 
-        Version(1) => {
-            let v1: v1::StateV1 = v1::read(file)?;
+    pub fn read(mut reader: impl Read) -> Result<SketchV9, PmbError> {
+        let mut magic = [0; 3];
+        reader.read_exact(&mut magic)?;
 
-            let state = Sketch {
-                strokes: crate::map_from_vec(
-                    v1.strokes
-                        .into_iter()
-                        .filter(|v2| !v2.erased)
-                        .map(|v1| Stroke {
-                            points: v1
-                                .points
-                                .iter()
-                                .map(|v1| StrokeElement {
-                                    x: v1.x,
-                                    y: v1.y,
-                                    pressure: v1.pressure,
-                                })
-                                .collect(),
-                            color: Color::from_u8(v1.color),
-                            brush_size: v1.brush_size,
-                            ..Default::default()
-                        })
-                        .collect(),
-                ),
-                zoom: v1.zoom,
-                origin: StrokePoint {
-                    x: v1.origin.x,
-                    y: v1.origin.y,
-                },
-                bg_color: Color::BLACK,
-                fg_color: Color::WHITE,
-            };
+        if magic != crate::PMB_MAGIC {
+            return Err(PmbError::new(ErrorKind::MissingHeader));
+        }
 
-            return Ok(state);
+        let mut version_bytes = [0; std::mem::size_of::<u64>()];
+        reader.read_exact(&mut version_bytes)?;
+        let version = Version(u64::from_le_bytes(version_bytes));
+
+        tracing::debug!("got version {}", version);
+        if version != Version(9) {
+            unreachable!(
+                "called v9::read when you should have called v{}::read",
+                version
+            );
         }
 
-        _ => Err(PmbError::new(ErrorKind::UnknownVersion(version))),
+        tracing::debug!("inflating");
+        let mut deflate_reader = flate2::read::DeflateDecoder::new(reader);
+        Ok(bincode::decode_from_std_read(
+            &mut deflate_reader,
+            standard(),
+        )?)
     }
 }
 
@@ -914,3 +2168,276 @@ pub mod v1 {
         )?)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stroke::{Stroke, StrokeElement};
+
+    #[test]
+    fn write_round_trip() {
+        let path = std::env::temp_dir().join("pmb-write-round-trip.pmb");
+
+        let sketch = Sketch::<()>::new(vec![Stroke::with_points(
+            vec![
+                StrokeElement {
+                    x: 0.,
+                    y: 1.,
+                    pressure: 0.5,
+                },
+                StrokeElement {
+                    x: 2.,
+                    y: 3.,
+                    pressure: 1.,
+                },
+            ],
+            [0.25, 0.5, 0.75],
+        )]);
+
+        write(&path, &sketch).unwrap();
+        let read: Sketch<()> = read(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.strokes.len(), 1);
+        let stroke = read.strokes.values().next().unwrap();
+        assert_eq!(stroke.points().len(), 2);
+        assert_eq!(stroke.points()[1].x, 2.);
+        assert_eq!(stroke.color(), [0.25, 0.5, 0.75]);
+    }
+
+    #[test]
+    fn write_is_quiet() {
+        // the test harness captures stdout, so the write happens again in a child process that
+        // doesn't, between two markers
+        if let Some(path) = std::env::var_os("PMB_QUIET_WRITE") {
+            print!("<<");
+            write(path, &Sketch::<()>::default()).unwrap();
+            print!(">>");
+            return;
+        }
+
+        let dir = crate::scratch::dir("quiet-write");
+        let path = dir.join("quiet.pmb");
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "migrate::test::write_is_quiet", "--nocapture"])
+            .env("PMB_QUIET_WRITE", &path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(path.exists());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("<<>>"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn encode_in_memory() {
+        let mut sketch = Sketch::<()>::new(vec![Stroke::with_points(
+            vec![StrokeElement {
+                x: 4.,
+                y: 5.,
+                pressure: 0.75,
+            }],
+            [0.25, 0.5, 0.75],
+        )]);
+        sketch.zoom = 12.;
+
+        let mut bytes = Vec::new();
+        encode(&mut bytes, &sketch).unwrap();
+        assert_eq!(bytes[..3], crate::PMB_MAGIC);
+        assert_eq!(bytes[3..11], Version::CURRENT.0.to_le_bytes());
+
+        let read = read::<()>(bytes.as_slice()).unwrap();
+        assert_eq!(read.zoom, 12.);
+        assert_eq!(read.strokes.len(), 1);
+        let stroke = read.strokes.values().next().unwrap();
+        assert_eq!(stroke.points()[0].x, 4.);
+        assert_eq!(stroke.points()[0].pressure, 0.75);
+
+        let mut newer = bytes.clone();
+        newer[3..11].copy_from_slice(&(Version::CURRENT.0 + 1).to_le_bytes());
+        assert!(matches!(
+            super::read::<()>(newer.as_slice()),
+            Err(PmbError {
+                kind: ErrorKind::VersionMismatch(version),
+                ..
+            }) if version == Version(Version::CURRENT.0 + 1)
+        ));
+
+        bytes[0] = b'X';
+        assert!(matches!(
+            super::read::<()>(bytes.as_slice()),
+            Err(PmbError {
+                kind: ErrorKind::MissingHeader,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn tags_round_trip() {
+        let path = std::env::temp_dir().join("pmb-tags-round-trip.pmb");
+
+        let mut inked = Stroke::with_points(
+            vec![StrokeElement {
+                x: 0.,
+                y: 1.,
+                pressure: 0.5,
+            }],
+            [0.25, 0.5, 0.75],
+        );
+        inked.add_tag("ink");
+        inked.add_tag("annotation");
+        let untagged = Stroke::with_points(Vec::new(), [1., 1., 1.]);
+        let sketch = Sketch::<()>::new(vec![inked, untagged]);
+
+        write(&path, &sketch).unwrap();
+        let read: Sketch<()> = read(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut tags = read
+            .strokes
+            .values()
+            .map(|stroke| stroke.tags.clone())
+            .collect::<Vec<_>>();
+        tags.sort();
+        assert_eq!(
+            tags,
+            vec![
+                vec![],
+                vec![String::from("ink"), String::from("annotation")]
+            ]
+        );
+    }
+
+    #[test]
+    fn groups_round_trip() {
+        let path = std::env::temp_dir().join("pmb-groups-round-trip.pmb");
+
+        let mut sketch = Sketch::<()>::new(vec![
+            Stroke::with_points(Vec::new(), [1., 1., 1.]),
+            Stroke::with_points(Vec::new(), [1., 1., 1.]),
+            Stroke::with_points(Vec::new(), [1., 1., 1.]),
+        ]);
+        let keys = sketch.strokes.keys().take(2).collect::<Vec<_>>();
+        let group = sketch.group(&keys).unwrap();
+
+        write(&path, &sketch).unwrap();
+        let read: Sketch<()> = read(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut groups = read
+            .strokes
+            .values()
+            .map(|stroke| stroke.group_id)
+            .collect::<Vec<_>>();
+        groups.sort();
+        assert_eq!(groups, vec![None, Some(group), Some(group)]);
+    }
+
+    #[test]
+    fn v9_has_no_tags() {
+        let path = std::env::temp_dir().join("pmb-v9-has-no-tags.pmb");
+
+        // a v9 file is a v10 file without the tags
+        let mut bytes = Vec::new();
+        bytes.extend(crate::PMB_MAGIC);
+        bytes.extend(u64::to_le_bytes(9));
+        let mut deflate_writer =
+            flate2::write::DeflateEncoder::new(&mut bytes, flate2::Compression::fast());
+        let strokes = vec![(vec![(4f32, 5f32, 0.5f32)], [0.25f32; 3], 0.1f32)];
+        bincode::encode_into_std_write(
+            (1.5f32, (2f32, 3f32), [0f32; 3], [1f32; 3], strokes),
+            &mut deflate_writer,
+            standard(),
+        )
+        .unwrap();
+        deflate_writer.finish().unwrap();
+        std::fs::write(&path, bytes).unwrap();
+
+        let sketch = from::<()>(Version(9), &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(sketch.zoom, 1.5);
+        assert_eq!(sketch.fg_color, [1.; 3]);
+        assert_eq!(sketch.strokes.len(), 1);
+        let stroke = sketch.strokes.values().next().unwrap();
+        assert_eq!(stroke.points()[0].y, 5.);
+        assert_eq!(stroke.brush_size(), 0.1);
+        assert!(stroke.tags.is_empty());
+        assert_eq!(
+            sketch.layers,
+            vec![Layer::with_strokes(sketch.strokes.keys())]
+        );
+    }
+
+    #[test]
+    fn layers_round_trip() {
+        let point = |x| {
+            Stroke::with_points(
+                vec![StrokeElement {
+                    x,
+                    y: 0.,
+                    pressure: 1.,
+                }],
+                [1., 1., 1.],
+            )
+        };
+
+        let mut sketch = Sketch::<()>::new(vec![point(0.), point(1.)]);
+        let erased = sketch.strokes.keys().next().unwrap();
+        sketch.strokes[erased].erase();
+        sketch.layers.push(Layer {
+            visible: false,
+            ..Layer::new("hidden")
+        });
+        sketch.add_stroke(point(2.));
+        sketch.add_stroke(point(3.));
+        sketch.layers.push(Layer::new("empty"));
+
+        let mut bytes = Vec::new();
+        encode(&mut bytes, &sketch).unwrap();
+        let read = read::<()>(bytes.as_slice()).unwrap();
+
+        // the erased stroke is gone, and everything else is on the same layer as before
+        let xs = |layer: &Layer| {
+            layer
+                .strokes
+                .iter()
+                .map(|key| read.strokes[*key].points()[0].x)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(read.strokes.len(), 3);
+        assert_eq!(read.layers.len(), 3);
+        assert_eq!(xs(&read.layers[0]), vec![1.]);
+        assert_eq!(xs(&read.layers[1]), vec![2., 3.]);
+        assert!(read.layers[2].strokes.is_empty());
+        assert_eq!(
+            read.layers
+                .iter()
+                .map(|layer| layer.visible)
+                .collect::<Vec<_>>(),
+            vec![true, false, true]
+        );
+        assert_eq!(read.layers[1].name, "hidden");
+    }
+
+    #[test]
+    fn failed_write_keeps_original() {
+        let path = std::env::temp_dir().join("pmb-failed-write-keeps-original.pmb");
+        std::fs::write(&path, b"original contents").unwrap();
+
+        let result = write_atomic(&path, |file| {
+            file.write_all(b"half of the new")?;
+            Err(PmbError::new(ErrorKind::MissingHeader))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"original contents");
+        assert!(!tmp_path(&path).exists());
+
+        write(&path, &Sketch::<()>::default()).unwrap();
+        assert!(read::<()>(std::fs::File::open(&path).unwrap()).is_ok());
+        assert!(!tmp_path(&path).exists());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,62 @@
+//! a backend that doesn't draw anything, for testing without a window or a GPU
+//!
+//! enable the `test-backend` feature to use it from outside the crate. pair it with
+//! [`SketchWidget::headless`](crate::ui::widget::SketchWidget::headless) to drive the whole
+//! input → stroke → tessellation pipeline with made up events.
+
+use crate::{
+    graphics::{PixelPos, StrokePoint},
+    CoordinateSystem, StrokeBackend,
+};
+
+/// y up with the origin in the middle of the window, like the GL backend
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullCoords;
+
+impl CoordinateSystem for NullCoords {
+    type Ndc = PixelPos;
+
+    fn pixel_to_ndc(width: u32, height: u32, pos: PixelPos) -> PixelPos {
+        PixelPos {
+            x: 2. * pos.x / width as f32 - 1.,
+            y: -(2. * pos.y / height as f32 - 1.),
+        }
+    }
+
+    fn ndc_to_pixel(width: u32, height: u32, pos: PixelPos) -> PixelPos {
+        PixelPos {
+            x: (pos.x + 1.) * width as f32 / 2.,
+            y: (-pos.y + 1.) * height as f32 / 2.,
+        }
+    }
+
+    fn ndc_to_stroke(width: u32, height: u32, zoom: f32, ndc: PixelPos) -> StrokePoint {
+        StrokePoint {
+            x: ndc.x * width as f32 / zoom,
+            y: ndc.y * height as f32 / zoom,
+        }
+    }
+
+    fn stroke_to_ndc(width: u32, height: u32, zoom: f32, point: StrokePoint) -> PixelPos {
+        PixelPos {
+            x: point.x * zoom / width as f32,
+            y: point.y * zoom / height as f32,
+        }
+    }
+}
+
+/// remembers whether the stroke needs to be uploaded again, and nothing else
+#[derive(Debug, Default)]
+pub struct NullStrokeBackend {
+    pub dirty: bool,
+}
+
+impl StrokeBackend for NullStrokeBackend {
+    fn make_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
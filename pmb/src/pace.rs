@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+/// the refresh rate to assume when the monitor doesn't say
+pub const FALLBACK_FPS: f32 = 60.;
+
+/// holds on to redraw requests until the next frame is due
+///
+/// pretty much every event asks for a redraw, and egui asks for another one after most frames.
+/// drawing for each of them would draw many times a frame or spin the loop as fast as it can go,
+/// so requests only mark a frame as wanted, and it's drawn once at most every `interval`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FramePacer {
+    wanted: bool,
+    /// a frame that's wanted later on, like when egui has an animation going
+    later: Option<Instant>,
+    last_frame: Option<Instant>,
+
+    /// how long to wait between frames. zero draws as soon as anything asks
+    pub interval: Duration,
+}
+
+impl FramePacer {
+    pub fn new(interval: Duration) -> Self {
+        FramePacer {
+            wanted: false,
+            later: None,
+            last_frame: None,
+            interval,
+        }
+    }
+
+    /// something changed and the window needs to be drawn again
+    pub fn request(&mut self) {
+        self.wanted = true;
+    }
+
+    /// draw again at `at` even if nothing else asks to by then
+    pub fn request_at(&mut self, at: Instant) {
+        self.later = Some(self.later.map_or(at, |later| later.min(at)));
+    }
+
+    pub fn is_wanted(&self) -> bool {
+        self.wanted
+    }
+
+    /// when the wanted frame can be drawn, or None if nothing wants one
+    pub fn deadline(&self) -> Option<Instant> {
+        if !self.wanted {
+            return self.later.map(|later| match self.last_frame {
+                Some(last) => later.max(last + self.interval),
+                None => later,
+            });
+        }
+
+        // nothing has been drawn yet, so it's due as soon as possible
+        Some(match self.last_frame {
+            Some(last) => last + self.interval,
+            None => Instant::now(),
+        })
+    }
+
+    /// whether to draw a frame now. only says so once for any number of requests
+    pub fn take(&mut self, now: Instant) -> bool {
+        if matches!(self.later, Some(later) if later <= now) {
+            self.later = None;
+            self.wanted = true;
+        }
+
+        let due = match self.last_frame {
+            Some(last) => last + self.interval <= now,
+            None => true,
+        };
+
+        if self.wanted && due {
+            self.wanted = false;
+            self.last_frame = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// time between frames for at most `max_fps` frames a second. zero or less uses the monitor's
+/// refresh rate, given in millihertz
+pub fn interval(max_fps: f32, refresh_millihertz: Option<u32>) -> Duration {
+    let fps = if max_fps > 0. {
+        max_fps
+    } else {
+        refresh_millihertz
+            .filter(|&mhz| mhz > 0)
+            .map_or(FALLBACK_FPS, |mhz| mhz as f32 / 1000.)
+    };
+
+    Duration::from_secs_f32(1. / fps)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requests_coalesced() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut pacer = FramePacer::new(ms(16));
+        assert!(!pacer.take(start));
+        assert_eq!(pacer.deadline(), None);
+
+        // the first frame doesn't wait
+        pacer.request();
+        assert!(pacer.take(start));
+        assert!(!pacer.take(start));
+
+        // lots of events inside one frame
+        let mut frames = 0;
+        for i in 0..10 {
+            pacer.request();
+            if pacer.take(start + ms(i)) {
+                frames += 1;
+            }
+        }
+        assert_eq!(frames, 0);
+        assert!(pacer.is_wanted());
+        assert_eq!(pacer.deadline(), Some(start + ms(16)));
+
+        assert!(pacer.take(start + ms(16)));
+        assert!(!pacer.take(start + ms(17)));
+        assert!(!pacer.take(start + ms(100)));
+        assert_eq!(pacer.deadline(), None);
+
+        // no interval draws every request
+        let mut pacer = FramePacer::new(Duration::ZERO);
+        for _ in 0..3 {
+            pacer.request();
+            pacer.request();
+            assert!(pacer.take(start));
+            assert!(!pacer.take(start));
+        }
+    }
+
+    #[test]
+    fn requested_later() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut pacer = FramePacer::new(ms(16));
+        pacer.request();
+        assert!(pacer.take(start));
+
+        pacer.request_at(start + ms(500));
+        pacer.request_at(start + ms(200));
+        assert!(!pacer.is_wanted());
+        assert_eq!(pacer.deadline(), Some(start + ms(200)));
+        assert!(!pacer.take(start + ms(100)));
+        assert!(pacer.take(start + ms(200)));
+        assert_eq!(pacer.deadline(), None);
+
+        // not sooner than the frame rate allows
+        pacer.request_at(start + ms(201));
+        assert_eq!(pacer.deadline(), Some(start + ms(216)));
+        assert!(!pacer.take(start + ms(210)));
+        assert!(pacer.take(start + ms(216)));
+    }
+
+    #[test]
+    fn frame_rate() {
+        assert_eq!(interval(50., Some(144_000)), Duration::from_millis(20));
+        assert_eq!(interval(0., Some(100_000)), Duration::from_millis(10));
+        assert_eq!(interval(-1., Some(0)), interval(FALLBACK_FPS, None));
+        assert_eq!(interval(0., None), interval(FALLBACK_FPS, None));
+    }
+}
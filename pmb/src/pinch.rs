@@ -0,0 +1,121 @@
+use crate::graphics::PixelPos;
+
+/// how the fingers on the screen moved together since the last time one of them did
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PinchMove {
+    /// where the middle of the fingers was
+    pub from: PixelPos,
+    /// where it is now
+    pub to: PixelPos,
+    /// how much further apart the fingers are, 1 for only one finger
+    pub scale: f32,
+}
+
+/// follows each finger on the screen to move and zoom the view with them
+///
+/// the view follows the middle of the fingers, and zooms by how much further apart they get. it's
+/// measured from where the fingers were the last time any of them touched, moved, or lifted, so a
+/// finger joining in or leaving only changes where the next move is measured from instead of
+/// jumping the view to the new middle.
+#[derive(Debug, Default, Clone)]
+pub struct Pinch {
+    down: Vec<(u64, PixelPos)>,
+    /// the middle of the fingers and how far they are from it on average
+    last: Option<(PixelPos, f32)>,
+}
+
+impl Pinch {
+    pub fn touch(&mut self, id: u64, at: PixelPos) {
+        self.down.retain(|(down, _)| *down != id);
+        self.down.push((id, at));
+        self.last = self.measure();
+    }
+
+    pub fn release(&mut self, id: u64) {
+        self.down.retain(|(down, _)| *down != id);
+        self.last = self.measure();
+    }
+
+    pub fn fingers(&self) -> usize {
+        self.down.len()
+    }
+
+    /// move a finger, returning how that moved the fingers together. nothing for a finger that
+    /// never touched
+    pub fn move_to(&mut self, id: u64, at: PixelPos) -> Option<PinchMove> {
+        let (_, pos) = self.down.iter_mut().find(|(down, _)| *down == id)?;
+        *pos = at;
+
+        let (from, from_spread) = self.last?;
+        let (to, to_spread) = self.measure()?;
+        self.last = Some((to, to_spread));
+
+        let scale = if self.down.len() > 1 && from_spread > 0. {
+            to_spread / from_spread
+        } else {
+            1.
+        };
+
+        Some(PinchMove { from, to, scale })
+    }
+
+    fn measure(&self) -> Option<(PixelPos, f32)> {
+        if self.down.is_empty() {
+            return None;
+        }
+
+        let count = self.down.len() as f32;
+        let middle = PixelPos {
+            x: self.down.iter().map(|(_, pos)| pos.x).sum::<f32>() / count,
+            y: self.down.iter().map(|(_, pos)| pos.y).sum::<f32>() / count,
+        };
+        let spread = self
+            .down
+            .iter()
+            .map(|(_, pos)| (pos.x - middle.x).hypot(pos.y - middle.y))
+            .sum::<f32>()
+            / count;
+
+        Some((middle, spread))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pinches() {
+        let at = |x, y| PixelPos { x, y };
+        let mut pinch = Pinch::default();
+        assert_eq!(pinch.move_to(1, at(0., 0.)), None);
+
+        // one finger drags
+        pinch.touch(1, at(100., 100.));
+        assert_eq!(
+            pinch.move_to(1, at(110., 100.)),
+            Some(PinchMove {
+                from: at(100., 100.),
+                to: at(110., 100.),
+                scale: 1.
+            })
+        );
+
+        // the second finger landing doesn't move anything
+        pinch.touch(2, at(310., 100.));
+        assert_eq!(pinch.fingers(), 2);
+        let apart = pinch.move_to(2, at(410., 100.)).unwrap();
+        assert_eq!((apart.from, apart.to), (at(210., 100.), at(260., 100.)));
+        assert!((apart.scale - 1.5).abs() < 1e-4);
+
+        // and neither does it lifting
+        pinch.release(2);
+        let alone = pinch.move_to(1, at(120., 100.)).unwrap();
+        assert_eq!((alone.from, alone.to), (at(110., 100.), at(120., 100.)));
+        assert_eq!(alone.scale, 1.);
+
+        pinch.release(1);
+        assert_eq!(pinch.fingers(), 0);
+        assert_eq!(pinch.move_to(1, at(0., 0.)), None);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::graphics::StrokePos;
+use std::time::{Duration, Instant};
+
+/// guesses where the pen is headed from how fast it was moving between the last two samples
+///
+/// drawing the stroke out to the guess makes it look like it's keeping up with the pen, even
+/// though the real point is still a frame or two away.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Predictor {
+    last: Option<(StrokePos, Instant)>,
+    velocity: Option<StrokePos>,
+}
+
+impl Predictor {
+    pub fn new() -> Self {
+        Predictor::default()
+    }
+
+    /// forget about the previous stroke
+    pub fn reset(&mut self) {
+        *self = Predictor::default();
+    }
+
+    pub fn push(&mut self, pos: StrokePos, at: Instant) {
+        if let Some((last, last_at)) = self.last {
+            let dt = at.saturating_duration_since(last_at).as_secs_f32();
+            if dt > 0. {
+                self.velocity = Some(StrokePos {
+                    x: (pos.x - last.x) / dt,
+                    y: (pos.y - last.y) / dt,
+                });
+            }
+        }
+
+        self.last = Some((pos, at));
+    }
+
+    /// velocity in stroke units per second
+    pub fn velocity(&self) -> Option<StrokePos> {
+        self.velocity
+    }
+
+    /// where the pen will be `ahead` after the last sample, if it keeps going the same way
+    pub fn predict(&self, ahead: Duration) -> Option<StrokePos> {
+        let (last, _) = self.last?;
+        let velocity = self.velocity?;
+        let ahead = ahead.as_secs_f32();
+
+        Some(StrokePos {
+            x: last.x + velocity.x * ahead,
+            y: last.y + velocity.y * ahead,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        graphics::{Color, ColorExt},
+        stroke::{Stroke, StrokeElement},
+        Stylus,
+    };
+    use lyon::lyon_tessellation::{StrokeOptions, StrokeTessellator};
+
+    fn close(a: StrokePos, x: f32, y: f32) -> bool {
+        (a.x - x).abs() < 1e-3 && (a.y - y).abs() < 1e-3
+    }
+
+    #[test]
+    fn extrapolate() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut predictor = Predictor::new();
+        assert!(predictor.predict(ms(10)).is_none());
+
+        predictor.push(StrokePos { x: 0., y: 0. }, start);
+        assert!(predictor.predict(ms(10)).is_none());
+
+        // 1 unit right and 2 down every 10ms
+        predictor.push(StrokePos { x: 1., y: -2. }, start + ms(10));
+        assert!(close(predictor.velocity().unwrap(), 100., -200.));
+        assert!(close(predictor.predict(ms(10)).unwrap(), 2., -4.));
+        assert!(close(predictor.predict(ms(5)).unwrap(), 1.5, -3.));
+        assert!(close(predictor.predict(ms(0)).unwrap(), 1., -2.));
+
+        // only the most recent movement counts
+        predictor.push(StrokePos { x: 1., y: -2. }, start + ms(20));
+        assert!(close(predictor.predict(ms(10)).unwrap(), 1., -2.));
+
+        // two samples at the same time don't say anything about velocity
+        predictor.push(StrokePos { x: 5., y: 5. }, start + ms(20));
+        assert!(close(predictor.predict(ms(10)).unwrap(), 5., 5.));
+
+        predictor.reset();
+        assert!(predictor.predict(ms(10)).is_none());
+    }
+
+    #[test]
+    fn prediction_replaced_by_real_point() {
+        let mut tessellator = StrokeTessellator::new();
+        let options = StrokeOptions::default();
+        let mut stroke = Stroke::<()>::new(Color::WHITE, 0.1, true);
+
+        let mut stylus = Stylus::default();
+        for x in [0., 1.] {
+            stylus.pos = StrokePos { x, y: 0. };
+            stylus.pressure = 1.;
+            stroke.add_point(&stylus, &mut tessellator, &options, None);
+        }
+
+        stroke.predict(
+            StrokeElement {
+                x: 2.,
+                y: 0.,
+                pressure: 1.,
+            },
+            &mut tessellator,
+            &options,
+        );
+        assert!(stroke.prediction.is_some());
+        assert_eq!(stroke.drawn_meshes().count(), stroke.meshes.len() + 1);
+        assert_eq!(stroke.points().len(), 2);
+
+        stylus.pos = StrokePos { x: 1.8, y: 0.1 };
+        stroke.add_point(&stylus, &mut tessellator, &options, None);
+        assert!(stroke.prediction.is_none());
+        assert_eq!(stroke.drawn_meshes().count(), stroke.meshes.len());
+        assert_eq!(stroke.points().len(), 3);
+        assert_eq!(stroke.points()[2].x, 1.8);
+
+        stroke.predict(
+            StrokeElement {
+                x: 2.6,
+                y: 0.2,
+                pressure: 1.,
+            },
+            &mut tessellator,
+            &options,
+        );
+        stroke.finish();
+        assert!(stroke.prediction.is_none());
+        assert!(stroke.points().iter().all(|point| point.x <= 1.8));
+    }
+}
@@ -0,0 +1,73 @@
+//! what a backend needs, from somewhere that won't move
+//!
+//! the modules these come from get rearranged now and then. a backend that does
+//! `use powdermilk_biscuits::prelude::*` keeps building when they do.
+
+pub use crate::{
+    clip::ScissorRect,
+    config::Config,
+    event::Event,
+    graphics::{AntiAliasing, Color, ColorExt, PixelPos, RendererKind, StrokePoint, StrokePos},
+    loop_::{loop_, LoopContext, LoopEvent, PerEvent, RenderResult},
+    stroke::{LinePrimitive, Stroke, StrokeElement},
+    tess::CapStyle,
+    ui::widget::SketchWidget,
+    CoordinateSystem, Device, Sketch, StrokeBackend, Tool,
+};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::null::{NullCoords, NullStrokeBackend};
+
+    // nothing from outside the prelude, the way a backend would write it. this one only has to
+    // build, since there's no window to run the loop in
+    #[allow(dead_code)]
+    fn run<S, C, L>()
+    where
+        S: StrokeBackend + 'static,
+        C: CoordinateSystem + 'static,
+        L: LoopContext<S, C> + 'static,
+    {
+        loop_::<S, C, L>()
+    }
+
+    fn draw<S: StrokeBackend, C: CoordinateSystem>(
+        widget: &mut SketchWidget<C>,
+        sketch: &mut Sketch<S>,
+        config: &Config,
+    ) -> Vec<(StrokePos, Color, LinePrimitive)> {
+        widget.next(config, sketch, Event::IncreaseBrush(1));
+        sketch
+            .strokes
+            .values()
+            .flat_map(|stroke: &Stroke<S>| {
+                let color = stroke.color();
+                stroke
+                    .points
+                    .iter()
+                    .map(move |point: &StrokeElement| (point.into(), color, LinePrimitive::Strip))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn enough_for_a_backend() {
+        let _: Option<(LoopEvent, PerEvent, RenderResult, ScissorRect)> = None;
+        let _: Option<(AntiAliasing, RendererKind, CapStyle)> = None;
+        let _: (Tool, Device) = (Tool::Pen, Device::Pen);
+
+        let mut widget = SketchWidget::<NullCoords>::headless(100, 100);
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        widget.force_update(&mut sketch);
+        let points = draw(&mut widget, &mut sketch, &Config::new());
+        assert!(points.is_empty());
+        assert_eq!(widget.brush_size, crate::DEFAULT_BRUSH + 1);
+
+        let pixel = PixelPos { x: 10., y: 20. };
+        let point: StrokePoint = NullCoords::pixel_to_stroke(100, 100, 50., pixel);
+        let back = NullCoords::stroke_to_pixel(100, 100, 50., point);
+        assert!((back.x - pixel.x).hypot(back.y - pixel.y) < 1e-3);
+        assert_eq!(Color::WHITE, [1., 1., 1.]);
+    }
+}
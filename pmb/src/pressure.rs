@@ -0,0 +1,65 @@
+/// the curve that changes nothing
+pub const IDENTITY: [f32; 4] = [0., 0., 1., 1.];
+
+/// how many times the curve is halved looking for the input. it's done in f64 so the flat ends of
+/// steep curves don't round off before getting to 0 or 1
+const STEPS: usize = 40;
+
+/// how hard a pen was pressed, after putting the `force` it reported through `curve` and keeping
+/// it between `min` and `max`
+///
+/// the curve is a cubic bezier from (0, 0) to (1, 1), and `curve` is its two control points as
+/// `[x1, y1, x2, y2]` like CSS's `cubic-bezier`. control points above the diagonal make light
+/// presses heavier, below it lighter
+pub fn remap(curve: [f32; 4], min: f32, max: f32, force: f32) -> f32 {
+    let [x1, y1, x2, y2] = curve.map(|control| control.clamp(0., 1.) as f64);
+    let force = force.clamp(0., 1.) as f64;
+
+    // the x controls are in 0..1, so x only goes up along the curve
+    let (mut low, mut high) = (0., 1.);
+    for _ in 0..STEPS {
+        let t = (low + high) / 2.;
+        if bezier(x1, x2, t) < force {
+            low = t;
+        } else {
+            high = t;
+        }
+    }
+
+    (bezier(y1, y2, (low + high) / 2.) as f32).max(min).min(max)
+}
+
+/// one coordinate of a cubic bezier that starts at 0 and ends at 1
+fn bezier(p1: f64, p2: f64, t: f64) -> f64 {
+    let u = 1. - t;
+    3. * u * u * t * p1 + 3. * u * t * t * p2 + t * t * t
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn curves() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+
+        for force in [0., 0.25, 0.5, 0.75, 1.] {
+            assert!(close(remap(IDENTITY, 0., 1., force), force));
+        }
+
+        let soft = [0., 1., 0., 1.];
+        assert!(close(remap(soft, 0., 1., 0.), 0.));
+        assert!(close(remap(soft, 0., 1., 1.), 1.));
+        assert!(remap(soft, 0., 1., 0.5) > 0.9);
+
+        let hard = [1., 0., 1., 0.];
+        assert!(close(remap(hard, 0., 1., 0.), 0.));
+        assert!(close(remap(hard, 0., 1., 1.), 1.));
+        assert!(remap(hard, 0., 1., 0.5) < 0.1);
+
+        assert!(close(remap(IDENTITY, 0.2, 0.8, 0.), 0.2));
+        assert!(close(remap(IDENTITY, 0.2, 0.8, 1.), 0.8));
+        assert!(close(remap(IDENTITY, 0.2, 0.8, 0.5), 0.5));
+        assert!(close(remap(IDENTITY, 0., 1., 2.), 1.));
+    }
+}
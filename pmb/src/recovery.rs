@@ -0,0 +1,228 @@
+//! copies of the open sketches to write out if the app panics
+//!
+//! the sketches belong to the event loop, so the panic hook can't reach them. instead the loop
+//! gives a [`Recovery`] a copy of each modified document every time it settles after a change, and
+//! the hook encodes those and writes them somewhere. copying is cheap next to encoding, so the
+//! encoding waits until there's a panic. the files are named after the process so two instances
+//! crashing don't overwrite each other. next launch, if there are recovery files left over the
+//! user gets asked whether to open them.
+
+use crate::{error::PmbError, Sketch, StrokeBackend};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// what the recovery files' names start with
+pub const RECOVERY_FILE_PREFIX: &str = "recovery-";
+
+/// the extension of the recovery files
+pub const RECOVERY_FILE_EXTENSION: &str = "pmb";
+
+#[derive(Clone)]
+pub struct Recovery {
+    /// the modified documents, by their index in the open documents
+    snapshots: Arc<Mutex<BTreeMap<usize, Sketch<()>>>>,
+    dir: PathBuf,
+    /// where they go if `dir` can't be written
+    fallback: PathBuf,
+}
+
+impl Recovery {
+    /// recovery files in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Recovery {
+            snapshots: Arc::new(Mutex::new(BTreeMap::new())),
+            dir: dir.into(),
+            fallback: std::env::temp_dir(),
+        }
+    }
+
+    /// the recovery files next to the config file
+    pub fn next_to(config_path: &Path) -> Self {
+        Recovery::new(config_path.parent().unwrap_or_else(|| Path::new(".")))
+    }
+
+    pub fn with_fallback(self, fallback: impl Into<PathBuf>) -> Self {
+        Recovery {
+            fallback: fallback.into(),
+            ..self
+        }
+    }
+
+    /// remember document `document` as it is now
+    pub fn update<S: StrokeBackend>(&self, document: usize, sketch: &Sketch<S>) {
+        if let Ok(mut snapshots) = self.snapshots.lock() {
+            snapshots.insert(document, sketch.saved_copy());
+        }
+    }
+
+    /// nothing's changed in document `document` since it was last saved, so there's nothing to lose
+    pub fn clear(&self, document: usize) {
+        if let Ok(mut snapshots) = self.snapshots.lock() {
+            snapshots.remove(&document);
+        }
+    }
+
+    /// encode and write every snapshot to its recovery file, or the fallback directory if that
+    /// doesn't work. returns where they went
+    pub fn emergency_write(&self) -> Result<Vec<PathBuf>, PmbError> {
+        // the panic might have happened while the lock was held, so don't wait for it
+        let Ok(snapshots) = self.snapshots.try_lock() else {
+            return Ok(Vec::new());
+        };
+
+        let mut written = Vec::new();
+        for (document, sketch) in snapshots.iter() {
+            let mut bytes = Vec::new();
+            crate::migrate::encode(&mut bytes, sketch)?;
+
+            let name = file_name(std::process::id(), *document);
+            let path = self.dir.join(&name);
+            match std::fs::write(&path, &bytes) {
+                Ok(()) => written.push(path),
+                Err(err) => {
+                    tracing::error!("couldn't write {}: {}", path.display(), err);
+                    let fallback = self.fallback.join(&name);
+                    std::fs::write(&fallback, &bytes)?;
+                    written.push(fallback);
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    /// write the snapshots when anything panics, before the usual panic message. nothing in here
+    /// unwraps, since panicking in a panic hook aborts
+    pub fn install_hook(&self) {
+        let recovery = self.clone();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            match recovery.emergency_write() {
+                Ok(paths) => {
+                    for path in paths {
+                        eprintln!("saved your work to {}", path.display());
+                    }
+                }
+                Err(err) => eprintln!("couldn't save your work: {err}"),
+            }
+            previous(info);
+        }));
+    }
+
+    /// the recovery files left by crashes before, from any process
+    pub fn left_over(&self) -> Vec<PathBuf> {
+        let mut left_over = [&self.dir, &self.fallback]
+            .into_iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| is_recovery_file(path))
+            .collect::<Vec<_>>();
+        left_over.sort();
+        left_over.dedup();
+        left_over
+    }
+
+    /// the recovered sketches. the files they were in are removed either way
+    pub fn take<S: StrokeBackend>(&self) -> Vec<Result<Sketch<S>, PmbError>> {
+        self.left_over()
+            .into_iter()
+            .map(|path| {
+                tracing::info!("recovering {}", path.display());
+                let sketch = std::fs::File::open(&path)
+                    .map_err(PmbError::from)
+                    .and_then(crate::migrate::read);
+                if let Err(err) = std::fs::remove_file(&path) {
+                    tracing::error!("couldn't remove {}: {}", path.display(), err);
+                }
+                sketch
+            })
+            .collect()
+    }
+}
+
+/// the recovery file for document `document` of process `process`
+fn file_name(process: u32, document: usize) -> String {
+    format!(
+        "{}{}-{}.{}",
+        RECOVERY_FILE_PREFIX, process, document, RECOVERY_FILE_EXTENSION
+    )
+}
+
+fn is_recovery_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    name.starts_with(RECOVERY_FILE_PREFIX)
+        && path.extension().and_then(|ext| ext.to_str()) == Some(RECOVERY_FILE_EXTENSION)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        graphics::{Color, ColorExt},
+        stroke::{Stroke, StrokeElement},
+    };
+
+    fn dot(x: f32) -> Sketch<()> {
+        Sketch::new(vec![Stroke::with_points(
+            vec![StrokeElement {
+                x,
+                y: 2.,
+                pressure: 0.5,
+            }],
+            Color::WHITE,
+        )])
+    }
+
+    #[test]
+    fn write_to_fallback() {
+        let dir = crate::scratch::dir("recovery");
+        let recovery = Recovery::new(dir.join("nowhere")).with_fallback(&dir);
+
+        // nothing to save yet
+        assert!(recovery.emergency_write().unwrap().is_empty());
+
+        // every modified document is saved, not just the focused one
+        let (first, second) = (dot(1.), dot(2.));
+        recovery.update(0, &first);
+        recovery.update(1, &second);
+        let written = recovery.emergency_write().unwrap();
+        assert_eq!(
+            written,
+            vec![
+                dir.join(file_name(std::process::id(), 0)),
+                dir.join(file_name(std::process::id(), 1)),
+            ]
+        );
+        assert_eq!(recovery.left_over(), written);
+
+        // another process's files are recovered too
+        std::fs::write(dir.join(file_name(0, 0)), "").unwrap();
+        std::fs::write(dir.join("not-recovery.pmb"), "").unwrap();
+        assert_eq!(recovery.left_over().len(), 3);
+
+        let recovered = recovery.take::<()>();
+        assert_eq!(recovered.len(), 3);
+        assert!(recovered[0].is_err());
+        assert_eq!(
+            recovered[1].as_ref().unwrap().content_hash(),
+            first.content_hash()
+        );
+        assert_eq!(
+            recovered[2].as_ref().unwrap().content_hash(),
+            second.content_hash()
+        );
+        assert!(recovery.left_over().is_empty());
+
+        recovery.clear(0);
+        recovery.clear(1);
+        assert!(recovery.emergency_write().unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+use winit::dpi::PhysicalSize;
+
+/// holds on to window resizes until they stop coming in
+///
+/// dragging the corner of the window sends a resize for almost every pixel it moves, and each
+/// one recreates the render targets. only the last size matters, so it's applied once the window
+/// has been the same size for a little while.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ResizeDebouncer {
+    pending: Option<(PhysicalSize<u32>, Instant)>,
+
+    /// how long the size has to stay the same before it's applied. zero applies it on the next
+    /// frame
+    pub quiet: Duration,
+}
+
+impl ResizeDebouncer {
+    pub fn new(quiet: Duration) -> Self {
+        ResizeDebouncer {
+            pending: None,
+            quiet,
+        }
+    }
+
+    pub fn push(&mut self, size: PhysicalSize<u32>, at: Instant) {
+        self.pending = Some((size, at));
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// when the pending size will be ready to apply
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, at)| at + self.quiet)
+    }
+
+    /// the size to resize to, if there is one and it's been long enough since the last resize
+    pub fn take(&mut self, now: Instant) -> Option<PhysicalSize<u32>> {
+        match self.deadline() {
+            Some(deadline) if deadline <= now => self.pending.take().map(|(size, _)| size),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn burst_applied_once() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut resizes = ResizeDebouncer::new(ms(50));
+        assert_eq!(resizes.take(start), None);
+
+        // dragging the corner of the window
+        let mut applied = Vec::new();
+        for i in 0..20 {
+            let at = start + ms(i * 5);
+            resizes.push(PhysicalSize::new(800 + i as u32, 600), at);
+            applied.extend(resizes.take(at));
+        }
+        assert!(applied.is_empty());
+        assert!(resizes.is_pending());
+        assert_eq!(resizes.deadline(), Some(start + ms(19 * 5 + 50)));
+
+        // not quite long enough
+        assert_eq!(resizes.take(start + ms(19 * 5 + 49)), None);
+
+        applied.extend(resizes.take(start + ms(200)));
+        applied.extend(resizes.take(start + ms(300)));
+        assert_eq!(applied, vec![PhysicalSize::new(819, 600)]);
+        assert!(!resizes.is_pending());
+
+        // no waiting means the next frame
+        let mut resizes = ResizeDebouncer::new(Duration::ZERO);
+        resizes.push(PhysicalSize::new(1, 1), start);
+        resizes.push(PhysicalSize::new(2, 2), start);
+        assert_eq!(resizes.take(start), Some(PhysicalSize::new(2, 2)));
+        assert_eq!(resizes.take(start), None);
+    }
+}
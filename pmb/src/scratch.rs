@@ -0,0 +1,14 @@
+//! directories for tests that write files
+//!
+//! enable the `test-backend` feature to use them from outside the crate.
+
+use std::path::PathBuf;
+
+/// an empty directory in the temp directory for the test `name`, only used by this process.
+/// remove it when the test is done
+pub fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pmb-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
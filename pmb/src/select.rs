@@ -0,0 +1,71 @@
+use crate::graphics::StrokePos;
+
+/// the shape the select tool drags out
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum SelectMode {
+    /// from where the pen went down to where it comes up
+    #[default]
+    Rectangle,
+
+    /// everything the pen goes around
+    Lasso,
+}
+
+/// whether `point` is inside `polygon` by the even-odd rule, so a lasso that crosses itself leaves
+/// the parts it went around twice out. the edge from the last point back to the first is implied,
+/// and anything with fewer than three points has nothing inside
+pub fn in_polygon(polygon: &[StrokePos], point: StrokePos) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+
+    let mut inside = false;
+    let mut prev = polygon[polygon.len() - 1];
+    for &next in polygon {
+        // count the edges a ray going right from the point crosses
+        if (next.y > point.y) != (prev.y > point.y)
+            && point.x < prev.x + (point.y - prev.y) / (next.y - prev.y) * (next.x - prev.x)
+        {
+            inside = !inside;
+        }
+        prev = next;
+    }
+
+    inside
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(x: f32, y: f32) -> StrokePos {
+        StrokePos { x, y }
+    }
+
+    #[test]
+    fn polygons() {
+        let square = [at(0., 0.), at(2., 0.), at(2., 2.), at(0., 2.)];
+        assert!(in_polygon(&square, at(1., 1.)));
+        assert!(!in_polygon(&square, at(3., 1.)));
+        assert!(!in_polygon(&square, at(-1., 1.)));
+        assert!(!in_polygon(&square, at(1., 3.)));
+
+        // a U shape doesn't have the gap between its arms
+        let u = [
+            at(0., 0.),
+            at(3., 0.),
+            at(3., 3.),
+            at(2., 3.),
+            at(2., 1.),
+            at(1., 1.),
+            at(1., 3.),
+            at(0., 3.),
+        ];
+        assert!(in_polygon(&u, at(0.5, 2.)));
+        assert!(in_polygon(&u, at(2.5, 2.)));
+        assert!(!in_polygon(&u, at(1.5, 2.)));
+
+        assert!(!in_polygon(&square[..2], at(1., 0.)));
+        assert!(!in_polygon(&[], at(0., 0.)));
+    }
+}
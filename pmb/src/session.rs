@@ -0,0 +1,200 @@
+//! recordings of what happened while drawing, to share how something was drawn or to reproduce a
+//! bug
+//!
+//! events don't have any serde impls of their own since winit's touches don't, so they're copied
+//! into [`LoggedEvent`] while recording.
+
+use crate::{error::PmbError, event::Event, graphics::PixelPos};
+use std::path::Path;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{DeviceId, Force, MouseButton, PenInfo, Touch, TouchPhase},
+};
+
+/// every event the widget got between starting and stopping a recording. replaying it into an
+/// empty sketch with the same window size draws the same strokes
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SessionLog {
+    pub width: u32,
+    pub height: u32,
+    pub events: Vec<TimedEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TimedEvent {
+    /// since the recording started
+    pub ms: u64,
+    pub event: LoggedEvent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum LoggedEvent {
+    Touch(LoggedTouch),
+    TouchMove(LoggedTouch),
+    Release(LoggedTouch),
+    PenDown(LoggedTouch),
+    PenMove(LoggedTouch),
+    PenUp(LoggedTouch),
+    MouseDown(MouseButton),
+    MouseMove([f32; 2]),
+    MouseUp(MouseButton),
+    StartPan,
+    EndPan,
+    StartZoom,
+    EndZoom,
+    IncreaseBrush(usize),
+    DecreaseBrush(usize),
+    ScrollZoom(f32),
+    Exit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LoggedTouch {
+    pub phase: LoggedPhase,
+    pub x: f64,
+    pub y: f64,
+    /// between 0 and 1, if the device reports it
+    pub force: Option<f64>,
+    pub id: u64,
+    /// barrel, inverted, and eraser
+    pub pen: Option<[bool; 3]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum LoggedPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+impl SessionLog {
+    pub fn new(width: u32, height: u32) -> Self {
+        SessionLog {
+            width,
+            height,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<SessionLog, PmbError> {
+        Ok(ron::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), PmbError> {
+        let contents = ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::new()
+                .new_line(String::from("\n"))
+                .indentor(String::from("  ")),
+        )?;
+        crate::migrate::write_atomic(path, |file| {
+            use std::io::Write;
+            file.write_all(contents.as_bytes())?;
+            Ok(())
+        })
+    }
+
+    /// the events in the order they happened, without their timestamps
+    pub fn events(&self) -> impl Iterator<Item = Event> + '_ {
+        self.events.iter().map(|timed| timed.event.into())
+    }
+}
+
+impl From<Event> for LoggedEvent {
+    fn from(event: Event) -> Self {
+        use Event as E;
+        use LoggedEvent as L;
+        match event {
+            E::Touch(touch) => L::Touch(touch.into()),
+            E::TouchMove(touch) => L::TouchMove(touch.into()),
+            E::Release(touch) => L::Release(touch.into()),
+            E::PenDown(touch) => L::PenDown(touch.into()),
+            E::PenMove(touch) => L::PenMove(touch.into()),
+            E::PenUp(touch) => L::PenUp(touch.into()),
+            E::MouseDown(button) => L::MouseDown(button),
+            E::MouseMove(pos) => L::MouseMove([pos.x, pos.y]),
+            E::MouseUp(button) => L::MouseUp(button),
+            E::StartPan => L::StartPan,
+            E::EndPan => L::EndPan,
+            E::StartZoom => L::StartZoom,
+            E::EndZoom => L::EndZoom,
+            E::IncreaseBrush(by) => L::IncreaseBrush(by),
+            E::DecreaseBrush(by) => L::DecreaseBrush(by),
+            E::ScrollZoom(by) => L::ScrollZoom(by),
+            E::Exit => L::Exit,
+        }
+    }
+}
+
+impl From<LoggedEvent> for Event {
+    fn from(event: LoggedEvent) -> Self {
+        use Event as E;
+        use LoggedEvent as L;
+        match event {
+            L::Touch(touch) => E::Touch(touch.into()),
+            L::TouchMove(touch) => E::TouchMove(touch.into()),
+            L::Release(touch) => E::Release(touch.into()),
+            L::PenDown(touch) => E::PenDown(touch.into()),
+            L::PenMove(touch) => E::PenMove(touch.into()),
+            L::PenUp(touch) => E::PenUp(touch.into()),
+            L::MouseDown(button) => E::MouseDown(button),
+            L::MouseMove([x, y]) => E::MouseMove(PixelPos { x, y }),
+            L::MouseUp(button) => E::MouseUp(button),
+            L::StartPan => E::StartPan,
+            L::EndPan => E::EndPan,
+            L::StartZoom => E::StartZoom,
+            L::EndZoom => E::EndZoom,
+            L::IncreaseBrush(by) => E::IncreaseBrush(by),
+            L::DecreaseBrush(by) => E::DecreaseBrush(by),
+            L::ScrollZoom(by) => E::ScrollZoom(by),
+            L::Exit => E::Exit,
+        }
+    }
+}
+
+impl From<Touch> for LoggedTouch {
+    fn from(touch: Touch) -> Self {
+        LoggedTouch {
+            phase: match touch.phase {
+                TouchPhase::Started => LoggedPhase::Started,
+                TouchPhase::Moved => LoggedPhase::Moved,
+                TouchPhase::Ended => LoggedPhase::Ended,
+                TouchPhase::Cancelled => LoggedPhase::Cancelled,
+            },
+            x: touch.location.x,
+            y: touch.location.y,
+            force: touch.force.map(|force| force.normalized()),
+            id: touch.id,
+            pen: touch
+                .pen_info
+                .map(|info| [info.barrel, info.inverted, info.eraser]),
+        }
+    }
+}
+
+impl From<LoggedTouch> for Touch {
+    fn from(touch: LoggedTouch) -> Self {
+        Touch {
+            // the device it came from is long gone
+            device_id: unsafe { DeviceId::dummy() },
+            phase: match touch.phase {
+                LoggedPhase::Started => TouchPhase::Started,
+                LoggedPhase::Moved => TouchPhase::Moved,
+                LoggedPhase::Ended => TouchPhase::Ended,
+                LoggedPhase::Cancelled => TouchPhase::Cancelled,
+            },
+            location: PhysicalPosition {
+                x: touch.x,
+                y: touch.y,
+            },
+            force: touch.force.map(Force::Normalized),
+            id: touch.id,
+            pen_info: touch.pen.map(|[barrel, inverted, eraser]| PenInfo {
+                barrel,
+                inverted,
+                eraser,
+            }),
+        }
+    }
+}
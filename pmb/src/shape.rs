@@ -0,0 +1,386 @@
+use crate::{simplify::distance_to_segment, stroke::StrokeElement};
+
+/// how many points go around a recognized circle
+pub const CIRCLE_POINTS: usize = 64;
+
+/// tidies up strokes that were meant to be simple shapes when they're finished
+///
+/// the tolerance is relative to the size of the shape, so it works the same at any zoom. a
+/// stroke that strays further than `tolerance` times its length from the straight line between
+/// its ends is left alone, and so on for circles and rectangles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recognizer {
+    /// replace nearly straight strokes with a line between their ends
+    pub lines: bool,
+    /// replace closed strokes that are nearly circles or rectangles with the real thing
+    pub shapes: bool,
+    pub tolerance: f32,
+}
+
+impl Default for Recognizer {
+    fn default() -> Self {
+        Recognizer {
+            lines: false,
+            shapes: false,
+            tolerance: 0.05,
+        }
+    }
+}
+
+impl Recognizer {
+    /// the points of the shape the stroke looks like, or None if it doesn't look like one
+    pub fn recognize(&self, points: &[StrokeElement]) -> Option<Vec<StrokeElement>> {
+        if points.len() <= 2 {
+            return None;
+        }
+
+        if self.lines {
+            if let Some(line) = line(points, self.tolerance) {
+                return Some(line);
+            }
+        }
+
+        if self.shapes {
+            circle(points, self.tolerance).or_else(|| rectangle(points, self.tolerance))
+        } else {
+            None
+        }
+    }
+}
+
+fn mean_pressure(points: &[StrokeElement]) -> f32 {
+    points.iter().map(|point| point.pressure).sum::<f32>() / points.len() as f32
+}
+
+fn distance(a: &StrokeElement, b: &StrokeElement) -> f32 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+/// the two ends, if every point is within `tolerance` times the distance between them of the line
+/// between them
+pub fn line(points: &[StrokeElement], tolerance: f32) -> Option<Vec<StrokeElement>> {
+    let (first, last) = (points.first()?, points.last()?);
+    let chord = distance(first, last);
+    if chord <= 0. {
+        return None;
+    }
+
+    let straight = points
+        .iter()
+        .all(|point| distance_to_segment(point, first, last) <= tolerance * chord);
+
+    straight.then(|| {
+        let pressure = mean_pressure(points);
+        vec![
+            StrokeElement { pressure, ..*first },
+            StrokeElement { pressure, ..*last },
+        ]
+    })
+}
+
+/// whether the ends of the stroke meet, give or take a quarter of `size`
+fn closed(points: &[StrokeElement], size: f32) -> bool {
+    match (points.first(), points.last()) {
+        (Some(first), Some(last)) => size > 0. && distance(first, last) <= size / 4.,
+        _ => false,
+    }
+}
+
+/// the root mean square of `residuals`
+fn rms(residuals: impl Iterator<Item = f32>) -> f32 {
+    let (sum, n) = residuals.fold((0., 0), |(sum, n), residual| {
+        (sum + residual * residual, n + 1)
+    });
+    (sum / n.max(1) as f32).sqrt()
+}
+
+/// the center and radius of the circle that best fits the points, by least squares. None if
+/// they're all in a line
+pub fn fit_circle(points: &[StrokeElement]) -> Option<(f32, f32, f32)> {
+    // around the middle so the squares stay small
+    let n = points.len() as f32;
+    let mean_x = points.iter().map(|point| point.x).sum::<f32>() / n;
+    let mean_y = points.iter().map(|point| point.y).sum::<f32>() / n;
+
+    // the circle x² + y² + dx + ey + f = 0 closest to going through every point
+    let (mut xx, mut xy, mut yy, mut x, mut y) = (0., 0., 0., 0., 0.);
+    let (mut xz, mut yz, mut z) = (0., 0., 0.);
+    for point in points {
+        let (px, py) = (point.x - mean_x, point.y - mean_y);
+        let pz = -(px * px + py * py);
+        xx += px * px;
+        xy += px * py;
+        yy += py * py;
+        x += px;
+        y += py;
+        xz += px * pz;
+        yz += py * pz;
+        z += pz;
+    }
+
+    let det3 = |m: [[f32; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let normal = [[xx, xy, x], [xy, yy, y], [x, y, n]];
+    let det = det3(normal);
+    if det.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let rhs = [xz, yz, z];
+    let solve = |column: usize| {
+        let mut m = normal;
+        (0..3).for_each(|row| m[row][column] = rhs[row]);
+        det3(m) / det
+    };
+    let (d, e, f) = (solve(0), solve(1), solve(2));
+
+    let radius_squared = (d * d + e * e) / 4. - f;
+    (radius_squared > 0.).then(|| (mean_x - d / 2., mean_y - e / 2., radius_squared.sqrt()))
+}
+
+/// the best fitting circle, if the points are closed and their root mean square distance from it
+/// is within `tolerance` times its diameter. starts where the stroke did and goes around the same
+/// way
+pub fn circle(points: &[StrokeElement], tolerance: f32) -> Option<Vec<StrokeElement>> {
+    let (center_x, center_y, radius) = fit_circle(points)?;
+    let center = StrokeElement {
+        x: center_x,
+        y: center_y,
+        pressure: 0.,
+    };
+
+    let residual = rms(points.iter().map(|point| distance(point, &center) - radius));
+    if !closed(points, radius * 2.) || residual > tolerance * radius * 2. {
+        return None;
+    }
+
+    // positive area is counterclockwise
+    let area = points
+        .windows(2)
+        .map(|pair| pair[0].x * pair[1].y - pair[1].x * pair[0].y)
+        .sum::<f32>();
+    let direction = if area < 0. { -1. } else { 1. };
+    let start = (points[0].y - center_y).atan2(points[0].x - center_x);
+    let pressure = mean_pressure(points);
+
+    Some(
+        (0..=CIRCLE_POINTS)
+            .map(|i| {
+                let angle =
+                    start + direction * std::f32::consts::TAU * i as f32 / CIRCLE_POINTS as f32;
+                StrokeElement {
+                    x: center_x + radius * angle.cos(),
+                    y: center_y + radius * angle.sin(),
+                    pressure,
+                }
+            })
+            .collect(),
+    )
+}
+
+/// the left, right, bottom, and top of the axis-aligned rectangle that best fits the points. each
+/// point belongs to whichever side of the bounding box it's closest to, and each side goes through
+/// the middle of its points
+pub fn fit_rectangle(points: &[StrokeElement]) -> [f32; 4] {
+    let bounds = points.iter().fold(
+        [
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+        ],
+        |[left, right, bottom, top], point| {
+            [
+                left.min(point.x),
+                right.max(point.x),
+                bottom.min(point.y),
+                top.max(point.y),
+            ]
+        },
+    );
+
+    let mut sums = [(0., 0); 4];
+    for point in points {
+        let [left, right, bottom, top] = bounds;
+        let gaps = [
+            point.x - left,
+            right - point.x,
+            point.y - bottom,
+            top - point.y,
+        ];
+        let side = (0..4)
+            .min_by(|&a, &b| gaps[a].total_cmp(&gaps[b]))
+            .unwrap_or(0);
+        let along = if side < 2 { point.x } else { point.y };
+        sums[side] = (sums[side].0 + along, sums[side].1 + 1);
+    }
+
+    let mut sides = bounds;
+    for (side, (sum, n)) in sums.into_iter().enumerate() {
+        if n > 0 {
+            sides[side] = sum / n as f32;
+        }
+    }
+    sides
+}
+
+/// the best fitting axis-aligned rectangle, if the points are closed, their root mean square
+/// distance from its sides is within `tolerance` times its diagonal, and they get near every
+/// corner
+pub fn rectangle(points: &[StrokeElement], tolerance: f32) -> Option<Vec<StrokeElement>> {
+    let [left, right, bottom, top] = fit_rectangle(points);
+    let diagonal = (right - left).hypot(top - bottom);
+    let slack = tolerance * diagonal;
+    if left >= right || bottom >= top || !closed(points, diagonal) {
+        return None;
+    }
+
+    let pressure = mean_pressure(points);
+    let corners = [(left, bottom), (right, bottom), (right, top), (left, top)]
+        .map(|(x, y)| StrokeElement { x, y, pressure });
+
+    let residual = rms(points.iter().map(|point| {
+        (0..4)
+            .map(|i| distance_to_segment(point, &corners[i], &corners[(i + 1) % 4]))
+            .fold(f32::INFINITY, f32::min)
+    }));
+    if residual > slack {
+        return None;
+    }
+
+    // a rounded-off corner is still a corner, but a circle doesn't have any
+    let cornered = corners.iter().all(|corner| {
+        points
+            .iter()
+            .any(|point| distance(point, corner) <= slack * 2.)
+    });
+    if !cornered {
+        return None;
+    }
+
+    // start at the corner nearest where the stroke did
+    let start = (0..4)
+        .min_by(|&a, &b| {
+            distance(&corners[a], &points[0]).total_cmp(&distance(&corners[b], &points[0]))
+        })
+        .unwrap_or(0);
+    Some((0..=4).map(|i| corners[(start + i) % 4]).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn points(xy: impl IntoIterator<Item = (f32, f32)>) -> Vec<StrokeElement> {
+        xy.into_iter()
+            .map(|(x, y)| StrokeElement { x, y, pressure: 1. })
+            .collect()
+    }
+
+    fn both() -> Recognizer {
+        Recognizer {
+            lines: true,
+            shapes: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn wobbly_line() {
+        let wobbly = points((0..=20).map(|i| (i as f32, (i as f32 * 1.7).sin() * 0.3)));
+        let line = both().recognize(&wobbly).unwrap();
+        assert_eq!(line, [wobbly[0], wobbly[20]]);
+
+        let off = Recognizer {
+            lines: false,
+            ..both()
+        };
+        assert!(off.recognize(&wobbly).is_none());
+    }
+
+    #[test]
+    fn curve_left_alone() {
+        let arc = points((0..=20).map(|i| {
+            let angle = i as f32 / 20. * std::f32::consts::PI;
+            (angle.cos() * 10., angle.sin() * 10.)
+        }));
+        assert!(both().recognize(&arc).is_none());
+    }
+
+    #[test]
+    fn fit_circle_drawn_unevenly() {
+        // bunched up on one side, where the pen went slowly
+        let slow = points((0..=60).map(|i| {
+            let t = i as f32 / 60.;
+            let angle = t * t * std::f32::consts::TAU;
+            (3. + angle.cos() * 8., -2. + angle.sin() * 8.)
+        }));
+        let (x, y, radius) = fit_circle(&slow).unwrap();
+        assert!((x - 3.).abs() < 1e-3 && (y + 2.).abs() < 1e-3);
+        assert!((radius - 8.).abs() < 1e-3);
+
+        let flat = points((0..10).map(|i| (i as f32, 0.)));
+        assert!(fit_circle(&flat).is_none());
+    }
+
+    #[test]
+    fn shapes() {
+        let wobbly_circle = points((0..=40).map(|i| {
+            let angle = i as f32 / 40. * std::f32::consts::TAU;
+            let radius = 10. + (i as f32 * 2.3).sin() * 0.4;
+            (5. + angle.cos() * radius, angle.sin() * radius)
+        }));
+        let circle = both().recognize(&wobbly_circle).unwrap();
+        assert_eq!(circle.len(), CIRCLE_POINTS + 1);
+        for point in &circle {
+            assert!(((point.x - 5.).hypot(point.y) - 10.).abs() < 0.3);
+        }
+
+        let square = points([
+            (0.1, 0.),
+            (5., 0.2),
+            (10., -0.1),
+            (10.2, 5.),
+            (9.9, 10.),
+            (5., 10.1),
+            (0., 9.8),
+            (-0.1, 5.),
+            (0.2, 0.3),
+        ]);
+        let rectangle = both().recognize(&square).unwrap();
+        assert_eq!(rectangle.len(), 5);
+        assert_eq!(rectangle[0], rectangle[4]);
+        let corners = [(0., 0.), (10., 0.), (10., 10.), (0., 10.)];
+        for (point, (x, y)) in rectangle.iter().zip(corners) {
+            assert!((point.x - x).abs() < 0.3 && (point.y - y).abs() < 0.3);
+        }
+
+        // a circle isn't a rectangle, even though it fits in one
+        assert!(super::rectangle(&wobbly_circle, 0.05).is_none());
+        let lines_only = Recognizer {
+            shapes: false,
+            ..both()
+        };
+        assert!(lines_only.recognize(&square).is_none());
+    }
+
+    #[test]
+    fn scribble_left_alone() {
+        // back and forth all over the place, and back where it started
+        let mut scribble = points((0..40).map(|i| {
+            let t = i as f32;
+            (
+                (t * 2.1).sin() * 10. + (t * 0.7).cos() * 3.,
+                (t * 1.3).cos() * 10.,
+            )
+        }));
+        scribble.push(scribble[0]);
+
+        assert!(circle(&scribble, 0.05).is_none());
+        assert!(super::rectangle(&scribble, 0.05).is_none());
+        assert!(both().recognize(&scribble).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{stroke::StrokeElement, tess::curve::Point};
+
+/// which points are needed to keep the stroke within `tolerance` of where it was, using
+/// ramer-douglas-peucker. the ends are always kept
+pub fn rdp_keep<P: Point>(points: &[P], tolerance: f32) -> Vec<bool> {
+    keep_by(points.len(), |i, first, last| {
+        distance_to_segment(&points[i], &points[first], &points[last]) / tolerance
+    })
+}
+
+/// like [`rdp_keep`], but also keeping points whose pressure is more than `pressure_tolerance`
+/// off from what it would be if it went straight from one kept point to the next
+pub fn rdp_keep_pressure(
+    points: &[StrokeElement],
+    tolerance: f32,
+    pressure_tolerance: f32,
+) -> Vec<bool> {
+    keep_by(points.len(), |i, first, last| {
+        let (point, a, b) = (&points[i], &points[first], &points[last]);
+        let pressure = a.pressure + (b.pressure - a.pressure) * along_segment(point, a, b);
+        (distance_to_segment(point, a, b) / tolerance)
+            .max((point.pressure - pressure).abs() / pressure_tolerance)
+    })
+}
+
+/// rdp, with `off(i, first, last)` saying how far point `i` is from the span between `first` and
+/// `last`. anything more than 1 is too far
+fn keep_by(len: usize, off: impl Fn(usize, usize, usize) -> f32) -> Vec<bool> {
+    let mut keep = vec![false; len];
+    if len <= 2 {
+        keep.iter_mut().for_each(|keep| *keep = true);
+        return keep;
+    }
+
+    keep[0] = true;
+    keep[len - 1] = true;
+
+    let mut spans = vec![(0, len - 1)];
+    while let Some((first, last)) = spans.pop() {
+        let farthest = (first + 1..last).map(|i| (i, off(i, first, last))).fold(
+            None,
+            |farthest: Option<(usize, f32)>, (i, distance)| match farthest {
+                Some((_, most)) if most >= distance => farthest,
+                _ => Some((i, distance)),
+            },
+        );
+
+        if let Some((i, distance)) = farthest {
+            // no tolerance is 0 / 0 for points right on the line, which has to be kept too
+            if distance > 1. || distance.is_nan() {
+                keep[i] = true;
+                spans.push((first, i));
+                spans.push((i, last));
+            }
+        }
+    }
+
+    keep
+}
+
+/// the points `rdp_keep` says to keep
+pub fn rdp<P: Point>(points: &[P], tolerance: f32) -> Vec<P> {
+    kept(points, rdp_keep(points, tolerance))
+}
+
+/// the points `rdp_keep_pressure` says to keep
+pub fn rdp_pressure(
+    points: &[StrokeElement],
+    tolerance: f32,
+    pressure_tolerance: f32,
+) -> Vec<StrokeElement> {
+    kept(
+        points,
+        rdp_keep_pressure(points, tolerance, pressure_tolerance),
+    )
+}
+
+fn kept<P: Point>(points: &[P], keep: Vec<bool>) -> Vec<P> {
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(point, _)| *point)
+        .collect()
+}
+
+/// how far along the segment from `a` to `b` the closest part of it to `point` is, from 0 to 1
+fn along_segment<P: Point>(point: &P, a: &P, b: &P) -> f32 {
+    let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+    let length_squared = dx * dx + dy * dy;
+    if length_squared > 0. {
+        (((point.x() - a.x()) * dx + (point.y() - a.y()) * dy) / length_squared).clamp(0., 1.)
+    } else {
+        0.
+    }
+}
+
+/// how far `point` is from the closest part of the segment from `a` to `b`
+pub fn distance_to_segment<P: Point>(point: &P, a: &P, b: &P) -> f32 {
+    let t = along_segment(point, a, b);
+    let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+    (a.x() + dx * t - point.x()).hypot(a.y() + dy * t - point.y())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn points(xy: &[(f32, f32)]) -> Vec<StrokeElement> {
+        xy.iter()
+            .map(|&(x, y)| StrokeElement { x, y, pressure: 1. })
+            .collect()
+    }
+
+    #[test]
+    fn straight_line() {
+        let line = points(&[(0., 0.), (1., 0.), (2., 0.001), (3., 0.), (4., 0.)]);
+        assert_eq!(rdp(&line, 0.01), points(&[(0., 0.), (4., 0.)]));
+
+        // nothing is removed with no tolerance
+        assert_eq!(rdp(&line, 0.), line);
+        assert_eq!(rdp(&line[..2], 10.), line[..2]);
+        assert!(rdp::<StrokeElement>(&[], 1.).is_empty());
+    }
+
+    #[test]
+    fn corners_kept() {
+        let zigzag = points(&[
+            (0., 0.),
+            (0.5, 0.5),
+            (1., 1.),
+            (1.5, 0.5),
+            (2., 0.),
+            (2.5, 0.5),
+            (3., 1.),
+        ]);
+        assert_eq!(
+            rdp_keep(&zigzag, 0.1),
+            vec![true, false, true, false, true, false, true]
+        );
+    }
+
+    #[test]
+    fn jittery_line() {
+        // a line drawn fast with a shaky hand
+        let jitter = |i: usize| ((i * 7919) % 13) as f32 / 13. * 0.02 - 0.01;
+        let line = (0..1000)
+            .map(|i| StrokeElement {
+                x: i as f32 * 0.01,
+                y: jitter(i),
+                pressure: 0.5 + jitter(i + 3),
+            })
+            .collect::<Vec<_>>();
+        let ends = vec![line[0], line[999]];
+        assert_eq!(rdp(&line, 0.05), ends);
+        assert_eq!(rdp_pressure(&line, 0.05, 0.05), ends);
+
+        // same for lyon's points
+        let lyon = line
+            .iter()
+            .map(|point| lyon::math::point(point.x, point.y))
+            .collect::<Vec<_>>();
+        assert_eq!(rdp(&lyon, 0.05), vec![lyon[0], lyon[999]]);
+
+        // pressing harder in the middle keeps the point there
+        let mut pressed = line.clone();
+        pressed[500].pressure = 1.;
+        let kept = rdp_pressure(&pressed, 0.05, 0.1);
+        // with the ones either side so it doesn't spread out
+        assert!(kept.contains(&pressed[500]));
+        assert!(kept.len() < 10, "{}", kept.len());
+        assert_eq!(rdp(&pressed, 0.05), ends);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::stroke::StrokeElement;
+
+/// how many points of the spline to draw for every point of the stroke
+pub const SAMPLES_PER_POINT: usize = 4;
+
+/// `n` points evenly spaced along the uniform cubic b-spline with `points` as its control
+/// points. the ends are repeated so the spline starts and ends where the stroke does, and the
+/// pressure is smoothed along with the position
+pub fn sample(points: &[StrokeElement], n: usize) -> Vec<StrokeElement> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Vec::new();
+    };
+
+    let control = [*first, *first]
+        .into_iter()
+        .chain(points.iter().copied())
+        .chain([*last, *last])
+        .collect::<Vec<_>>();
+    let spans = control.len() - 3;
+
+    (0..n)
+        .map(|i| {
+            let u = if n == 1 {
+                0.
+            } else {
+                spans as f32 * i as f32 / (n - 1) as f32
+            };
+            let span = (u as usize).min(spans - 1);
+            evaluate(&control[span..span + 4], u - span as f32)
+        })
+        .collect()
+}
+
+/// the spline of a stroke with [`SAMPLES_PER_POINT`] samples on every span, kept up to date as
+/// points are added to the end. a point only moves the last couple spans, so everything before
+/// them stays put and adding a point costs the same no matter how long the stroke is
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spline {
+    samples: Vec<StrokeElement>,
+    /// how many points it's built from, and the last of them
+    len: usize,
+    last: Option<StrokeElement>,
+}
+
+impl Spline {
+    pub fn new(points: &[StrokeElement]) -> Self {
+        let mut spline = Spline::default();
+        spline.update(points);
+        spline
+    }
+
+    /// catch up with points added to the end since last time. anything else, like points taken
+    /// off or a different stroke entirely, builds it again from scratch
+    pub fn update(&mut self, points: &[StrokeElement]) {
+        if !self.extends_to(points) {
+            *self = Spline::default();
+        }
+        if points.is_empty() || self.is_for(points) {
+            return;
+        }
+
+        // span j goes through control points j to j+3, and the last two are the end repeated,
+        // so the spans that can reach past the old last point need doing again
+        let settled = self.len.saturating_sub(1);
+        self.samples.truncate(settled * SAMPLES_PER_POINT);
+
+        let spans = points.len() + 1;
+        for span in settled..spans {
+            let control = span_control(points, span);
+            self.samples.extend(
+                (0..SAMPLES_PER_POINT)
+                    .map(|i| evaluate(&control, i as f32 / SAMPLES_PER_POINT as f32)),
+            );
+        }
+        self.samples
+            .push(evaluate(&span_control(points, spans - 1), 1.));
+
+        self.len = points.len();
+        self.last = points.last().copied();
+    }
+
+    /// whether it's up to date with `points`
+    pub fn is_for(&self, points: &[StrokeElement]) -> bool {
+        self.len == points.len() && self.last.as_ref() == points.last()
+    }
+
+    /// how many points it's built from
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn samples(&self) -> &[StrokeElement] {
+        &self.samples
+    }
+
+    fn extends_to(&self, points: &[StrokeElement]) -> bool {
+        self.len == 0
+            || (self.len <= points.len() && self.last.as_ref() == points.get(self.len - 1))
+    }
+}
+
+/// the points of the spline through `points` that [`Spline`] would have
+pub fn samples(points: &[StrokeElement]) -> Vec<StrokeElement> {
+    Spline::new(points).samples
+}
+
+/// the four control points of `span`, with the ends repeated like in [`sample`]
+fn span_control(points: &[StrokeElement], span: usize) -> [StrokeElement; 4] {
+    [0, 1, 2, 3].map(|i| points[(span + i).saturating_sub(2).min(points.len() - 1)])
+}
+
+/// a point on one span of the spline, `t` between 0 and 1
+fn evaluate(control: &[StrokeElement], t: f32) -> StrokeElement {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let weights = [
+        (1. - t).powi(3) / 6.,
+        (3. * t3 - 6. * t2 + 4.) / 6.,
+        (-3. * t3 + 3. * t2 + 3. * t + 1.) / 6.,
+        t3 / 6.,
+    ];
+
+    control.iter().zip(weights).fold(
+        StrokeElement {
+            x: 0.,
+            y: 0.,
+            pressure: 0.,
+        },
+        |sum, (point, weight)| StrokeElement {
+            x: sum.x + point.x * weight,
+            y: sum.y + point.y * weight,
+            pressure: sum.pressure + point.pressure * weight,
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        graphics::{Color, ColorExt},
+        stroke::Stroke,
+    };
+
+    fn stroke(xy: &[(f32, f32)]) -> Stroke<()> {
+        Stroke::with_points(
+            xy.iter()
+                .enumerate()
+                .map(|(i, &(x, y))| StrokeElement {
+                    x,
+                    y,
+                    pressure: i as f32 / xy.len() as f32,
+                })
+                .collect(),
+            Color::WHITE,
+        )
+    }
+
+    #[test]
+    fn sampled_spline() {
+        // too short to smooth
+        let short = stroke(&[(0., 0.), (1., 1.)]);
+        assert_eq!(short.sampled_spline(50), short.points());
+        assert!(stroke(&[]).sampled_spline(50).is_empty());
+
+        let zigzag = stroke(&[(0., 0.), (1., 1.), (2., 0.), (3., 1.), (4., 0.)]);
+        let smooth = zigzag.sampled_spline(33);
+        assert_eq!(smooth.len(), 33);
+        assert_eq!(smooth.first(), zigzag.points().first());
+        let (last, end) = (smooth.last().unwrap(), zigzag.points().last().unwrap());
+        assert!((last.x - end.x).abs() < 1e-5 && (last.y - end.y).abs() < 1e-5);
+
+        // the corners get rounded off, and the pressure goes up smoothly along with the points
+        assert!(smooth.iter().all(|point| point.y > -1e-5 && point.y < 0.9));
+        assert!(smooth.windows(2).all(|pair| pair[0].x < pair[1].x));
+        assert!(smooth
+            .windows(2)
+            .all(|pair| pair[0].pressure <= pair[1].pressure + 1e-6));
+    }
+
+    #[test]
+    fn incremental_spline() {
+        let points = (0..200)
+            .map(|i| StrokeElement {
+                x: i as f32 * 0.1,
+                y: (i as f32 * 0.7).sin(),
+                pressure: (i % 7) as f32 / 7.,
+            })
+            .collect::<Vec<_>>();
+
+        let mut spline = Spline::default();
+        assert!(spline.is_empty());
+        for len in 1..=points.len() {
+            spline.update(&points[..len]);
+            assert!(spline.is_for(&points[..len]));
+            assert_eq!(spline.len(), len);
+        }
+        assert_eq!(spline, Spline::new(&points));
+        assert_eq!(
+            spline.samples().len(),
+            (points.len() + 1) * SAMPLES_PER_POINT + 1
+        );
+        assert_eq!(spline.samples().first(), points.first());
+
+        // taking points off starts over
+        spline.update(&points[..50]);
+        assert_eq!(spline, Spline::new(&points[..50]));
+        assert_eq!(spline.len(), 50);
+        let mut moved = points[..50].to_vec();
+        moved[49].x += 1.;
+        spline.update(&moved);
+        assert_eq!(spline, Spline::new(&moved));
+    }
+}
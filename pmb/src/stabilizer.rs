@@ -0,0 +1,100 @@
+use crate::graphics::PixelPos;
+
+/// a brush point on a leash, trailing behind the cursor
+///
+/// the brush only moves once the cursor gets further away from it than the leash radius, at which
+/// point it's dragged along the line towards the cursor until it's exactly one radius away. this
+/// throws away jitter from a shaky hand or mouse and makes slow, deliberate lines very smooth.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LazyBrush {
+    pub radius: f32,
+    brush: Option<PixelPos>,
+}
+
+impl LazyBrush {
+    pub fn new(radius: f32) -> Self {
+        LazyBrush {
+            radius,
+            brush: None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.radius > 0.
+    }
+
+    pub fn brush(&self) -> Option<PixelPos> {
+        self.brush
+    }
+
+    /// put the brush directly under the cursor, for example at the start of a stroke
+    pub fn reset(&mut self, cursor: PixelPos) {
+        self.brush = Some(cursor);
+    }
+
+    /// pull the brush towards the cursor, returning the new brush position if it moved
+    pub fn update(&mut self, cursor: PixelPos) -> Option<PixelPos> {
+        let Some(brush) = self.brush else {
+            self.brush = Some(cursor);
+            return self.brush;
+        };
+
+        let dx = cursor.x - brush.x;
+        let dy = cursor.y - brush.y;
+        let distance = dx.hypot(dy);
+
+        if distance <= self.radius {
+            return None;
+        }
+
+        let pull = (distance - self.radius) / distance;
+        let next = PixelPos {
+            x: brush.x + dx * pull,
+            y: brush.y + dy * pull,
+        };
+
+        self.brush = Some(next);
+        self.brush
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pos(x: f32, y: f32) -> PixelPos {
+        PixelPos { x, y }
+    }
+
+    #[test]
+    fn jitter_inside_leash() {
+        let mut lazy = LazyBrush::new(10.);
+        lazy.reset(pos(100., 100.));
+
+        for (x, y) in [
+            (103., 98.),
+            (95., 101.),
+            (100., 109.),
+            (92., 94.),
+            (107., 107.),
+        ] {
+            assert!(lazy.update(pos(x, y)).is_none());
+            let brush = lazy.brush().unwrap();
+            assert_eq!((brush.x, brush.y), (100., 100.));
+        }
+    }
+
+    #[test]
+    fn drag_outside_leash() {
+        let mut lazy = LazyBrush::new(10.);
+        lazy.reset(pos(0., 0.));
+
+        let brush = lazy.update(pos(30., 40.)).unwrap();
+        assert!((brush.x - 24.).abs() < 1e-4);
+        assert!((brush.y - 32.).abs() < 1e-4);
+        assert!(((30. - brush.x).hypot(40. - brush.y) - 10.).abs() < 1e-4);
+
+        // moving back towards the brush doesn't pull it
+        assert!(lazy.update(pos(26., 34.)).is_none());
+    }
+}
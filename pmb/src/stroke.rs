@@ -1,15 +1,22 @@
 use crate::{
-    graphics::{Color, ColorExt, StrokePos},
-    StrokeBackend,
+    brush::{BrushConfig, Brushes},
+    clip::ClipRect,
+    graphics::{Color, ColorExt, PixelPos, StrokePoint, StrokePos},
+    spline::Spline,
+    tess::TessellatorKind,
+    CoordinateSystem, StrokeBackend,
 };
 use lyon::{
     lyon_tessellation::{
         GeometryBuilderError, StrokeOptions, StrokeTessellator, TessellationError, VertexBuffers,
     },
-    math::Point,
+    math::{point, Point, Transform},
 };
+use std::borrow::Cow;
 
-#[derive(Default, Debug, Clone, Copy, pmb_macros::Disk, bytemuck::Zeroable, bytemuck::Pod)]
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, pmb_macros::Disk, bytemuck::Zeroable, bytemuck::Pod,
+)]
 #[repr(C)]
 pub struct StrokeElement {
     pub x: f32,
@@ -35,8 +42,45 @@ impl std::fmt::Display for StrokeElement {
     }
 }
 
+/// strokes with the same group id move, transform, and get deleted together
+pub type GroupId = u32;
+
+/// how a stroke gets drawn
+#[derive(
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Debug,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub enum StrokeStyle {
+    #[default]
+    Lines,
+    Circles,
+    CirclesPressure,
+    Points,
+    Spline,
+}
+
+/// how the backends draw the points of a stroke
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum LinePrimitive {
+    Strip,
+    Points,
+}
+
 pub type MeshBuffer = VertexBuffers<Point, u16>;
 
+/// a mesh that can have more vertices than u16 indices can reach, before it's split up with
+/// [`chunks`](crate::mesh::chunks)
+pub type WideMeshBuffer = VertexBuffers<Point, u32>;
+
 pub struct Mesh {
     pub buffer: MeshBuffer,
     from: usize,
@@ -66,6 +110,18 @@ where
     pub points: Vec<StrokeElement>,
     pub color: Color,
     pub brush_size: f32,
+    pub tags: Vec<String>,
+    pub group_id: Option<GroupId>,
+    pub clip: Option<ClipRect>,
+    /// snapped to whole pixels when it's drawn, for diagrams
+    pub crisp: bool,
+    /// strokes with a higher depth are drawn on top, no matter what order they're drawn in
+    pub depth: f32,
+    pub style: StrokeStyle,
+    /// can't be erased, selected, or moved until it's unlocked
+    pub locked: bool,
+    /// the last point joins back up with the first, for shapes
+    pub closed: bool,
 
     #[skip] pub erased: bool,
     #[skip] pub visible: bool,
@@ -75,6 +131,15 @@ where
     #[skip] pub meshes: Vec<Mesh>,
     #[skip] pub backend: Option<S>,
     #[skip] pub done: bool,
+    #[skip] pub prediction: Option<Mesh>,
+    #[skip] pub tessellator: TessellatorKind,
+    /// brush sizes between the circles of a circles style, or 0 for one on every point
+    #[skip] pub stamp_spacing: f32,
+    /// the samples drawn for a spline style, built up as points are added
+    #[skip] pub spline: Spline,
+    #[skip] pub brushes: Brushes,
+    /// drawn in this style instead of its own, without saving it that way
+    #[skip] pub style_override: Option<StrokeStyle>,
 }
 
 impl<S> Default for Stroke<S>
@@ -86,6 +151,14 @@ where
             points: Default::default(),
             color: Color::WHITE,
             brush_size: 0.01,
+            tags: Vec::new(),
+            group_id: None,
+            clip: None,
+            crisp: false,
+            depth: 0.,
+            style: StrokeStyle::default(),
+            locked: false,
+            closed: false,
             erased: false,
             visible: true,
             bottom_right: StrokePos::default(),
@@ -94,6 +167,12 @@ where
             meshes: Vec::new(),
             backend: None,
             done: false,
+            prediction: None,
+            tessellator: TessellatorKind::default(),
+            stamp_spacing: 0.,
+            spline: Spline::default(),
+            brushes: Brushes::default(),
+            style_override: None,
         }
     }
 }
@@ -133,10 +212,98 @@ where
         }
     }
 
+    /// a copy of the parts of the stroke that get written to disk
+    pub fn saved_copy<T: StrokeBackend>(&self) -> Stroke<T> {
+        Stroke {
+            points: self.points.clone(),
+            color: self.color,
+            brush_size: self.brush_size,
+            tags: self.tags.clone(),
+            group_id: self.group_id,
+            clip: self.clip,
+            crisp: self.crisp,
+            depth: self.depth,
+            style: self.style,
+            locked: self.locked,
+            closed: self.closed,
+            ..Default::default()
+        }
+    }
+
+    /// the style the stroke is drawn in, which isn't its own while there's an override
+    pub fn drawn_style(&self) -> StrokeStyle {
+        self.style_override.unwrap_or(self.style)
+    }
+
     pub fn points(&self) -> &[StrokeElement] {
         &self.points
     }
 
+    /// `n` points evenly spaced along the smoothed stroke, or just the points if there are too few
+    /// to smooth
+    pub fn sampled_spline(&self, n: usize) -> Vec<StrokeElement> {
+        if self.points.len() < Stroke::<()>::DEGREE {
+            self.points.clone()
+        } else {
+            crate::spline::sample(&self.points, n)
+        }
+    }
+
+    /// the points the backends draw with [`line_primitive`](Self::line_primitive), back to the
+    /// first one at the end if the stroke is closed
+    pub fn line_points(&self) -> Cow<'_, [StrokeElement]> {
+        let points: Cow<'_, [StrokeElement]> = match self.drawn_style() {
+            StrokeStyle::Spline if self.points.len() >= Stroke::<()>::DEGREE => {
+                if self.spline.is_for(&self.points) {
+                    Cow::Borrowed(self.spline.samples())
+                } else {
+                    Cow::Owned(crate::spline::samples(&self.points))
+                }
+            }
+            _ => Cow::Borrowed(&self.points),
+        };
+
+        if self.closed && self.line_primitive() == Some(LinePrimitive::Strip) {
+            Cow::Owned(crate::tess::looped(&points, true).into_owned())
+        } else {
+            points
+        }
+    }
+
+    /// how many of the [`line_points`](Self::line_points) from when the stroke had its first
+    /// `len` points are still the same, if points have only been added to the end since then.
+    /// adding a point moves the last couple of spans of a spline, and the point closing a loop
+    pub fn settled_line_points(&self, len: usize) -> usize {
+        let len = len.min(self.points.len());
+        match self.drawn_style() {
+            StrokeStyle::Spline if len >= Stroke::<()>::DEGREE => {
+                (len - 1) * crate::spline::SAMPLES_PER_POINT
+            }
+            // it was drawn straight through the points until there were enough to smooth
+            StrokeStyle::Spline => 0,
+            _ => len,
+        }
+    }
+
+    /// how the points are drawn under the mesh, if they're drawn at all
+    pub fn line_primitive(&self) -> Option<LinePrimitive> {
+        match self.drawn_style() {
+            StrokeStyle::Lines | StrokeStyle::Spline => Some(LinePrimitive::Strip),
+            StrokeStyle::Points => Some(LinePrimitive::Points),
+            // too small to see the circles, so at least show where they are
+            StrokeStyle::Circles | StrokeStyle::CirclesPressure if !self.draw_tesselated => {
+                Some(LinePrimitive::Points)
+            }
+            StrokeStyle::Circles | StrokeStyle::CirclesPressure => None,
+        }
+    }
+
+    /// draw the mesh only if it's more than a pixel wide at `zoom`. points are never meshed
+    pub fn update_primitive(&mut self, zoom: f32) {
+        self.draw_tesselated =
+            self.drawn_style() != StrokeStyle::Points && self.brush_size * zoom > 1.0;
+    }
+
     fn points_mut(&mut self) -> &mut Vec<StrokeElement> {
         &mut self.points
     }
@@ -149,6 +316,28 @@ where
         self.brush_size
     }
 
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// returns whether the tag was added, i.e. the stroke didn't have it already
+    pub fn add_tag(&mut self, tag: impl Into<String>) -> bool {
+        let tag = tag.into();
+        if self.has_tag(&tag) {
+            return false;
+        }
+
+        self.tags.push(tag);
+        true
+    }
+
+    /// returns whether the stroke had the tag
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|t| t != tag);
+        before != self.tags.len()
+    }
+
     pub fn erased(&self) -> bool {
         self.erased
     }
@@ -235,12 +424,19 @@ where
         let x = stylus.pos.x;
         let y = stylus.pos.y;
 
+        // the real point is here, we don't need to guess anymore
+        self.prediction = None;
+
         self.points_mut().push(StrokeElement {
             x,
             y,
             pressure: stylus.pressure,
         });
 
+        if self.drawn_style() == StrokeStyle::Spline {
+            self.spline.update(&self.points);
+        }
+
         if self.points.len() >= 2 {
             self.rebuild_partial_mesh(tesselator, options, max_points);
         }
@@ -255,10 +451,200 @@ where
         }
     }
 
+    /// take points off the end of the stroke for as long as `should_pop` says so, returning them
+    /// in the order they were taken off
+    pub fn pop_points_while(
+        &mut self,
+        should_pop: impl Fn(&StrokeElement) -> bool,
+    ) -> Vec<StrokeElement> {
+        let mut popped = Vec::new();
+        while let Some(point) = self.points.last() {
+            if !should_pop(point) {
+                break;
+            }
+
+            popped.extend(self.points.pop());
+        }
+
+        popped
+    }
+
+    /// the runs of consecutive points that `keep` says to keep
+    pub fn split_points(&self, keep: impl Fn(&StrokeElement) -> bool) -> Vec<Vec<StrokeElement>> {
+        self.points
+            .split(|point| !keep(point))
+            .filter(|run| !run.is_empty())
+            .map(|run| run.to_vec())
+            .collect()
+    }
+
+    /// whether some of the points aren't in a mesh yet, like after the stroke is read from a file.
+    /// the stroke being drawn is always caught up by [`add_point`](Self::add_point)
+    pub fn needs_mesh(&self) -> bool {
+        match self.meshes.last() {
+            Some(mesh) => mesh.to < self.points.len(),
+            None => !self.points.is_empty(),
+        }
+    }
+
+    /// throw out the meshes and tessellate the whole stroke again
+    pub fn remesh(&mut self, tessellator: &mut StrokeTessellator, options: &StrokeOptions) {
+        self.meshes.clear();
+        if !self.points.is_empty() {
+            self.rebuild_entire_mesh(tessellator, options);
+        }
+        if self.drawn_style() == StrokeStyle::Spline {
+            self.spline = Spline::new(&self.points);
+        }
+
+        if let Some(backend) = self.backend_mut() {
+            backend.make_dirty();
+        }
+    }
+
+    /// move every point of the stroke by (dx, dy)
+    pub fn translate(
+        &mut self,
+        dx: f32,
+        dy: f32,
+        tessellator: &mut StrokeTessellator,
+        options: &StrokeOptions,
+    ) {
+        for point in self.points.iter_mut() {
+            point.x += dx;
+            point.y += dy;
+        }
+
+        if let Some(clip) = self.clip.as_mut() {
+            for corner in [&mut clip.top_left, &mut clip.bottom_right] {
+                corner.x += dx;
+                corner.y += dy;
+            }
+        }
+
+        self.remesh(tessellator, options);
+    }
+
+    /// move every point of the stroke through `transform`. the brush gets bigger or smaller along
+    /// with the stroke, but the pressure at each point stays the same
+    pub fn transform(
+        &mut self,
+        transform: &Transform,
+        tessellator: &mut StrokeTessellator,
+        options: &StrokeOptions,
+    ) {
+        let apply = |x, y| {
+            let moved = transform.transform_point(point(x, y));
+            StrokePos {
+                x: moved.x,
+                y: moved.y,
+            }
+        };
+
+        for element in self.points.iter_mut() {
+            let moved = apply(element.x, element.y);
+            element.x = moved.x;
+            element.y = moved.y;
+        }
+
+        // only exact for transforms that keep the clip lined up with the axes
+        if let Some(clip) = self.clip.as_mut() {
+            *clip = ClipRect::new(
+                apply(clip.top_left.x, clip.top_left.y),
+                apply(clip.bottom_right.x, clip.bottom_right.y),
+            );
+        }
+
+        self.brush_size *= transform.determinant().abs().sqrt();
+        self.remesh(tessellator, options);
+    }
+
+    /// draw the stroke out to where we think the next point will be, until that point arrives
+    pub fn predict(
+        &mut self,
+        point: StrokeElement,
+        tessellator: &mut StrokeTessellator,
+        options: &StrokeOptions,
+    ) {
+        self.prediction = None;
+
+        if let Some(last) = self.points.last().copied() {
+            match self.brushes.engine(self.drawn_style()).build_mesh(
+                &[last, point],
+                &mut BrushConfig {
+                    tessellator,
+                    stroke_options: options,
+                    kind: self.tessellator,
+                    brush_size: self.brush_size,
+                    stamp_spacing: self.stamp_spacing,
+                    closed: false,
+                },
+            ) {
+                Ok(buffer) => {
+                    self.prediction = Some(Mesh {
+                        buffer,
+                        from: self.points.len(),
+                        to: self.points.len(),
+                    });
+                }
+
+                Err(err) => {
+                    tracing::warn!("couldn't tessellate prediction: {}", err);
+                }
+            }
+        }
+
+        if let Some(backend) = self.backend_mut() {
+            backend.make_dirty();
+        }
+    }
+
+    /// the meshes that should be drawn, including the predicted end of the stroke
+    pub fn drawn_meshes(&self) -> impl Iterator<Item = &Mesh> {
+        self.meshes.iter().chain(self.prediction.iter())
+    }
+
     pub fn vertices(&self) -> impl Iterator<Item = &Point> {
         self.meshes.iter().flat_map(|mesh| mesh.vertices().iter())
     }
 
+    /// every triangle of the mesh in pixels. if the stroke is crisp the corners are snapped to whole
+    /// pixels, the same as the renderers do in their vertex shaders
+    pub fn pixel_triangles<C: CoordinateSystem>(
+        &self,
+        width: u32,
+        height: u32,
+        zoom: f32,
+        origin: StrokePoint,
+    ) -> impl Iterator<Item = [PixelPos; 3]> + '_ {
+        self.meshes.iter().flat_map(move |mesh| {
+            mesh.indices().chunks_exact(3).map(move |triangle| {
+                [0, 1, 2].map(|i| {
+                    let vertex = mesh.vertices()[triangle[i] as usize];
+                    let pixel = C::pos_to_pixel(
+                        width,
+                        height,
+                        zoom,
+                        origin,
+                        StrokePos {
+                            x: vertex.x,
+                            y: vertex.y,
+                        },
+                    );
+
+                    if self.crisp {
+                        PixelPos {
+                            x: pixel.x.round(),
+                            y: pixel.y.round(),
+                        }
+                    } else {
+                        pixel
+                    }
+                })
+            })
+        })
+    }
+
     pub(crate) fn num_indices(&self) -> usize {
         self.meshes
             .iter()
@@ -271,7 +657,17 @@ where
         stroke_options: &StrokeOptions,
     ) {
         tracing::info!("rebuild entire mesh ({} points)", self.points.len());
-        match crate::tess::tessellate(tessellator, stroke_options, self.brush_size, self.points()) {
+        match self.brushes.engine(self.drawn_style()).build_mesh(
+            self.points(),
+            &mut BrushConfig {
+                tessellator,
+                stroke_options,
+                kind: self.tessellator,
+                brush_size: self.brush_size,
+                stamp_spacing: self.stamp_spacing,
+                closed: self.closed,
+            },
+        ) {
             Ok(buffer) => self.meshes.push(Mesh {
                 buffer,
                 from: 0,
@@ -280,60 +676,33 @@ where
 
             Err(err) if is_tmv(&err) => {
                 tracing::warn!("have to split stroke (entire mesh)");
-                self.meshes.clear();
-
-                // start with two segments
-                let mut num_segments = 2;
-                'with_more_segments: loop {
-                    // split the points into num_breaks segments
-                    let per_segment = self.points.len() / num_segments;
-                    tracing::info!(
-                        "trying {} segments, {} points per segment",
-                        num_segments,
-                        per_segment,
-                    );
-
-                    let mut meshes = Vec::new();
-                    for (i, (from, to, subset)) in (0..num_segments)
-                        .map(|offset| {
-                            let from = per_segment * offset;
-                            let to = per_segment * (offset + 1);
-                            (from, to, &self.points[from..to])
-                        })
-                        .enumerate()
-                    {
-                        // try tessellating the segment
-                        match crate::tess::tessellate(
-                            tessellator,
-                            stroke_options,
-                            self.brush_size,
-                            subset,
-                        ) {
-                            // if it works, hooray
-                            Ok(buffer) => {
-                                tracing::debug!("got segment {}/{}", i, num_segments);
-                                meshes.push(Mesh { buffer, from, to });
-                            }
-
-                            // if it's too many, try again with more segments
-                            Err(err) if is_tmv(&err) => {
-                                tracing::debug!("it didn't work ({}/{})", i, num_segments);
-                                meshes.clear();
-                                num_segments += 1;
-                                continue 'with_more_segments;
-                            }
-
-                            Err(err) => {
-                                tracing::error!("{}", err);
-                                return;
-                            }
-                        }
+                match self.brushes.engine(self.drawn_style()).build_chunks(
+                    self.points(),
+                    &mut BrushConfig {
+                        tessellator,
+                        stroke_options,
+                        kind: self.tessellator,
+                        brush_size: self.brush_size,
+                        stamp_spacing: self.stamp_spacing,
+                        closed: self.closed,
+                    },
+                ) {
+                    Ok(chunks) => {
+                        tracing::info!("split into {} meshes", chunks.len());
+                        let to = self.points.len();
+                        self.meshes = chunks
+                            .into_iter()
+                            .map(|buffer| Mesh {
+                                buffer,
+                                from: 0,
+                                to,
+                            })
+                            .collect();
                     }
 
-                    // all the segments were tessellable (sp.?)
-                    tracing::info!("tessellated with {} segments", num_segments);
-                    self.meshes = meshes;
-                    break;
+                    Err(err) => {
+                        tracing::error!("couldn't split mesh: {}", err);
+                    }
                 }
             }
 
@@ -352,19 +721,26 @@ where
         max_points: Option<usize>,
     ) {
         let mut to_add = None;
+        let style = self.drawn_style();
 
         let split =
             |tessellator: &mut StrokeTessellator, to_add: &mut Option<Mesh>, subset: &Mesh| {
-                match crate::tess::tessellate(
-                    tessellator,
-                    options,
-                    self.brush_size,
-                    &self.points[subset.to..],
+                match self.brushes.engine(style).build_mesh(
+                    &self.points[subset.to.saturating_sub(1)..],
+                    &mut BrushConfig {
+                        tessellator,
+                        stroke_options: options,
+                        kind: self.tessellator,
+                        brush_size: self.brush_size,
+                        stamp_spacing: self.stamp_spacing,
+                        closed: false,
+                    },
                 ) {
+                    // starting from the last point of the one before, so there's no gap
                     Ok(buffer) => {
                         *to_add = Some(Mesh {
                             buffer,
-                            from: subset.to,
+                            from: subset.to.saturating_sub(1),
                             to: self.points.len(),
                         });
                     }
@@ -390,11 +766,16 @@ where
                     );
                     split(tessellator, &mut to_add, subset);
                 } else {
-                    match crate::tess::tessellate(
-                        tessellator,
-                        options,
-                        self.brush_size,
+                    match self.brushes.engine(style).build_mesh(
                         &self.points[subset.from..],
+                        &mut BrushConfig {
+                            tessellator,
+                            stroke_options: options,
+                            kind: self.tessellator,
+                            brush_size: self.brush_size,
+                            stamp_spacing: self.stamp_spacing,
+                            closed: false,
+                        },
                     ) {
                         Ok(buffer) => {
                             subset.buffer = buffer;
@@ -432,6 +813,12 @@ where
 
     pub fn finish(&mut self) {
         self.done = true;
+
+        if self.prediction.take().is_some() {
+            if let Some(backend) = self.backend_mut() {
+                backend.make_dirty();
+            }
+        }
     }
 }
 
@@ -441,3 +828,139 @@ fn is_tmv(err: &TessellationError) -> bool {
         TessellationError::GeometryBuilder(GeometryBuilderError::TooManyVertices)
     )
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn huge_stroke_split() {
+        // a long zig-zag the native tessellator subdivides as much as it can
+        let points = (0..5000)
+            .map(|i| StrokeElement {
+                x: i as f32,
+                y: (i % 2) as f32,
+                pressure: 1.,
+            })
+            .collect::<Vec<_>>();
+        let mut stroke = Stroke::<()>::with_points(points, Color::WHITE);
+        stroke.brush_size = 0.01;
+        stroke.tessellator = TessellatorKind::Native;
+        stroke.rebuild_entire_mesh(&mut StrokeTessellator::new(), &StrokeOptions::default());
+
+        assert!(stroke.meshes.len() > 1);
+        assert!(stroke.vertices().count() > u16::MAX as usize);
+        assert!(stroke.meshes.iter().all(|mesh| mesh
+            .indices()
+            .iter()
+            .all(|index| (*index as usize) < mesh.vertices().len())));
+
+        assert!(stroke
+            .meshes
+            .iter()
+            .all(|mesh| mesh.vertices().len() <= crate::mesh::MAX_VERTICES));
+
+        // the same triangles as one big mesh would have
+        let corners = stroke
+            .meshes
+            .iter()
+            .flat_map(|mesh| {
+                mesh.indices()
+                    .iter()
+                    .map(|index| mesh.vertices()[*index as usize])
+            })
+            .collect::<Vec<_>>();
+        let wide = crate::tess::native::tessellate_wide(
+            StrokeOptions::default().tolerance,
+            stroke.brush_size,
+            (&StrokeOptions::default()).into(),
+            stroke.points(),
+        )
+        .unwrap();
+        let wide_corners = wide
+            .indices
+            .iter()
+            .map(|index| wide.vertices[*index as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(corners, wide_corners);
+    }
+
+    #[test]
+    fn line_points_settle() {
+        let points = (0..40)
+            .map(|i| StrokeElement {
+                x: i as f32,
+                y: (i as f32).sin(),
+                pressure: 1.,
+            })
+            .collect::<Vec<_>>();
+
+        for (style, closed) in [
+            (StrokeStyle::Lines, false),
+            (StrokeStyle::Lines, true),
+            (StrokeStyle::Spline, false),
+            (StrokeStyle::Spline, true),
+            (StrokeStyle::Points, false),
+        ] {
+            let stroke = |len| {
+                let mut stroke = Stroke::<()>::with_points(points[..len].to_vec(), Color::WHITE);
+                stroke.style = style;
+                stroke.closed = closed;
+                stroke
+            };
+
+            for len in 1..points.len() {
+                let before = stroke(len).line_points().into_owned();
+                for more in [1, 5] {
+                    let after = stroke((len + more).min(points.len()));
+                    let settled = after.settled_line_points(len);
+                    assert!(settled <= before.len(), "{style:?} {len}");
+                    assert_eq!(
+                        before[..settled],
+                        after.line_points()[..settled],
+                        "{style:?} {closed} {len}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn closed_stroke_joins_up() {
+        // three sides of a square, open at the bottom
+        let points = [(0., 0.), (0., 10.), (10., 10.), (10., 0.)]
+            .map(|(x, y)| StrokeElement { x, y, pressure: 1. })
+            .to_vec();
+        let covers = |stroke: &Stroke<()>, x: f32, y: f32| {
+            stroke.meshes.iter().any(|mesh| {
+                mesh.indices().chunks_exact(3).any(|triangle| {
+                    let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices()[triangle[i] as usize]);
+                    let side =
+                        |p: Point, q: Point| (q.x - p.x) * (y - p.y) - (q.y - p.y) * (x - p.x);
+                    let sides = [side(a, b), side(b, c), side(c, a)];
+                    sides.iter().all(|side| *side >= 0.) || sides.iter().all(|side| *side <= 0.)
+                })
+            })
+        };
+
+        for kind in [
+            TessellatorKind::Lyon,
+            TessellatorKind::Native,
+            TessellatorKind::Deterministic,
+        ] {
+            let mut stroke = Stroke::<()>::with_points(points.clone(), Color::WHITE);
+            stroke.brush_size = 1.;
+            stroke.tessellator = kind;
+            stroke.remesh(&mut StrokeTessellator::new(), &StrokeOptions::default());
+            assert!(covers(&stroke, 0.1, 9.9), "{kind:?}");
+            assert!(!covers(&stroke, 5.3, 0.1), "{kind:?}");
+            assert_eq!(stroke.line_points().len(), 4);
+
+            stroke.closed = true;
+            stroke.remesh(&mut StrokeTessellator::new(), &StrokeOptions::default());
+            assert!(covers(&stroke, 5.3, 0.1), "{kind:?}");
+            assert_eq!(stroke.line_points().len(), 5);
+            assert_eq!(stroke.line_points()[4], points[0]);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::graphics::PixelPos;
+
+/// how far a finger can wander and still be tapping, in pixels
+pub const TAP_SLOP: f32 = 12.;
+
+/// notices fingers touching the screen and letting go without going anywhere
+///
+/// a tap is everything from the first finger touching to the last one lifting. it's only a tap if
+/// none of them moved further than `slop` from where they touched.
+#[derive(Debug, Default, Clone)]
+pub struct TapDetector {
+    down: Vec<(u64, PixelPos)>,
+    fingers: u8,
+    moved: bool,
+
+    /// how far a finger can move in pixels before it's not a tap
+    pub slop: f32,
+}
+
+impl TapDetector {
+    pub fn new(slop: f32) -> Self {
+        TapDetector {
+            down: Vec::new(),
+            fingers: 0,
+            moved: false,
+            slop,
+        }
+    }
+
+    pub fn touch(&mut self, id: u64, at: PixelPos) {
+        if self.down.is_empty() {
+            self.fingers = 0;
+            self.moved = false;
+        }
+
+        self.down.retain(|(down, _)| *down != id);
+        self.down.push((id, at));
+        self.fingers = self
+            .fingers
+            .max(self.down.len().min(u8::MAX as usize) as u8);
+    }
+
+    pub fn move_to(&mut self, id: u64, at: PixelPos) {
+        if let Some((_, start)) = self.down.iter().find(|(down, _)| *down == id) {
+            if (at.x - start.x).hypot(at.y - start.y) > self.slop {
+                self.moved = true;
+            }
+        }
+    }
+
+    /// how many fingers tapped, if this was the last one lifted and none of them moved
+    pub fn release(&mut self, id: u64, at: PixelPos) -> Option<u8> {
+        self.move_to(id, at);
+        self.down.retain(|(down, _)| *down != id);
+        if !self.down.is_empty() {
+            return None;
+        }
+
+        let fingers = std::mem::take(&mut self.fingers);
+        (!self.moved && fingers > 0).then_some(fingers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn taps() {
+        let at = |x, y| PixelPos { x, y };
+        let mut taps = TapDetector::new(TAP_SLOP);
+
+        // two fingers, a little wobble
+        taps.touch(1, at(100., 100.));
+        taps.touch(2, at(200., 100.));
+        taps.move_to(1, at(105., 103.));
+        assert_eq!(taps.release(1, at(104., 104.)), None);
+        assert_eq!(taps.release(2, at(200., 101.)), Some(2));
+
+        // one of them dragged
+        taps.touch(1, at(100., 100.));
+        taps.touch(2, at(200., 100.));
+        taps.move_to(2, at(260., 100.));
+        taps.move_to(2, at(200., 100.));
+        assert_eq!(taps.release(2, at(200., 100.)), None);
+        assert_eq!(taps.release(1, at(100., 100.)), None);
+
+        // fingers coming and going still count the most that were down
+        taps.touch(1, at(0., 0.));
+        taps.touch(2, at(50., 0.));
+        assert_eq!(taps.release(2, at(50., 0.)), None);
+        taps.touch(3, at(60., 0.));
+        taps.touch(4, at(70., 0.));
+        assert_eq!(taps.release(1, at(0., 0.)), None);
+        assert_eq!(taps.release(3, at(60., 0.)), None);
+        assert_eq!(taps.release(4, at(70., 0.)), Some(3));
+
+        // a finger nobody saw touch down
+        assert_eq!(taps.release(9, at(0., 0.)), None);
+    }
+}
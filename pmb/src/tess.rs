@@ -53,15 +53,16 @@
 //   have to draw faster than a human can to have that happen. (TODO determine how many points
 //   it usually would take to cause a split to occur)
 
-use crate::stroke::{MeshBuffer, StrokeElement};
+use crate::stroke::{MeshBuffer, StrokeElement, WideMeshBuffer};
 use lyon::{
     lyon_algorithms::path::Path,
     lyon_tessellation::{
-        GeometryBuilderError, LineCap, LineJoin, StrokeOptions, StrokeTessellator,
-        TessellationError, VertexBuffers,
+        geometry_builder::Positions, BuffersBuilder, GeometryBuilderError, LineCap, LineJoin,
+        StrokeOptions, StrokeTessellator, TessellationError, VertexBuffers,
     },
 };
 use std::{
+    borrow::Cow,
     collections::VecDeque,
     sync::{
         mpsc::{self, Sender},
@@ -70,33 +71,224 @@ use std::{
     thread::JoinHandle,
 };
 
+pub mod curve;
+pub mod fit;
+pub mod native;
+
+/// how far a mesh can stray from the stroke it's for, in pixels, unless the config says otherwise
+pub const PIXEL_TOLERANCE: f32 = 0.025;
+
+/// zooms between the same two powers of two share a level, and meshes are only rebuilt when the
+/// level changes, so zooming a little doesn't tessellate everything again
+pub fn zoom_level(zoom: f32) -> i32 {
+    zoom.log2().floor() as i32
+}
+
+/// the tolerance in stroke units that keeps meshes within `pixel_tolerance` of their strokes at
+/// every zoom in `level`
+pub fn level_tolerance(level: i32, pixel_tolerance: f32) -> f32 {
+    // a stroke unit is zoom / 2 pixels, and the most zoom in the level is 2^(level + 1)
+    pixel_tolerance / 2f32.powi(level)
+}
+
+/// what turns the points of a stroke into triangles
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum TessellatorKind {
+    #[default]
+    Lyon,
+    Native,
+    /// native, but always the same mesh for the same points. for golden images and hashing meshes
+    Deterministic,
+}
+
+/// what the ends of lines look like
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum CapStyle {
+    #[default]
+    Round,
+    Butt,
+    Square,
+}
+
+impl From<CapStyle> for LineCap {
+    fn from(cap: CapStyle) -> Self {
+        match cap {
+            CapStyle::Round => LineCap::Round,
+            CapStyle::Butt => LineCap::Butt,
+            CapStyle::Square => LineCap::Square,
+        }
+    }
+}
+
+impl From<LineCap> for CapStyle {
+    fn from(cap: LineCap) -> Self {
+        match cap {
+            LineCap::Round => CapStyle::Round,
+            LineCap::Butt => CapStyle::Butt,
+            LineCap::Square => CapStyle::Square,
+        }
+    }
+}
+
+/// what the corners of lines look like
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum JoinStyle {
+    #[default]
+    Round,
+    Miter,
+    Bevel,
+}
+
+impl From<JoinStyle> for LineJoin {
+    fn from(join: JoinStyle) -> Self {
+        match join {
+            JoinStyle::Round => LineJoin::Round,
+            JoinStyle::Miter => LineJoin::Miter,
+            JoinStyle::Bevel => LineJoin::Bevel,
+        }
+    }
+}
+
+impl From<LineJoin> for JoinStyle {
+    fn from(join: LineJoin) -> Self {
+        match join {
+            LineJoin::Round => JoinStyle::Round,
+            LineJoin::Miter | LineJoin::MiterClip => JoinStyle::Miter,
+            LineJoin::Bevel => JoinStyle::Bevel,
+        }
+    }
+}
+
+/// the points with the first one again at the end, if they're closed and there are enough of them
+/// to go around
+pub fn looped(points: &[StrokeElement], closed: bool) -> Cow<'_, [StrokeElement]> {
+    match points.first() {
+        Some(first) if closed && points.len() > 2 => {
+            Cow::Owned(points.iter().chain(Some(first)).copied().collect())
+        }
+        _ => Cow::Borrowed(points),
+    }
+}
+
+/// a line through the points, joining the last one back up with the first if it's `closed`
 pub fn tessellate(
+    kind: TessellatorKind,
     tessellator: &mut StrokeTessellator,
     stroke_options: &StrokeOptions,
     brush_size: f32,
     points: &[StrokeElement],
+    closed: bool,
 ) -> Result<MeshBuffer, TessellationError> {
+    match kind {
+        TessellatorKind::Lyon => {
+            let mut mesh = tessellate_raw(tessellator, stroke_options, brush_size, points, closed)?;
+            crate::mesh::dedup_buffer(&mut mesh);
+            Ok(mesh)
+        }
+
+        TessellatorKind::Native => native::tessellate(
+            stroke_options.tolerance,
+            brush_size,
+            stroke_options.into(),
+            &looped(points, closed),
+        ),
+
+        TessellatorKind::Deterministic => {
+            native::deterministic(brush_size, stroke_options.into(), &looped(points, closed))
+        }
+    }
+}
+
+/// split a mesh too big for u16 indices into ones that aren't, cleaned up the way `kind` cleans up
+/// its meshes
+pub fn chunks(kind: TessellatorKind, wide: &WideMeshBuffer) -> Vec<MeshBuffer> {
+    let mut chunks = crate::mesh::chunks(wide, crate::mesh::MAX_VERTICES);
+    for chunk in chunks.iter_mut() {
+        match kind {
+            TessellatorKind::Lyon => crate::mesh::dedup_buffer(chunk),
+            TessellatorKind::Native => {}
+            TessellatorKind::Deterministic => native::snap(chunk),
+        }
+    }
+
+    chunks
+}
+
+/// [`tessellate`] without the limit on vertices
+pub fn tessellate_wide(
+    kind: TessellatorKind,
+    tessellator: &mut StrokeTessellator,
+    stroke_options: &StrokeOptions,
+    brush_size: f32,
+    points: &[StrokeElement],
+    closed: bool,
+) -> Result<WideMeshBuffer, TessellationError> {
+    match kind {
+        TessellatorKind::Lyon => {
+            let mut mesh = VertexBuffers::new();
+            let mut builder = BuffersBuilder::new(&mut mesh, Positions);
+            tessellator.tessellate_path(
+                &path(brush_size, points, closed),
+                stroke_options,
+                &mut builder,
+            )?;
+            Ok(mesh)
+        }
+
+        TessellatorKind::Native => native::tessellate_wide(
+            stroke_options.tolerance,
+            brush_size,
+            stroke_options.into(),
+            &looped(points, closed),
+        ),
+
+        TessellatorKind::Deterministic => native::tessellate_wide(
+            native::DETERMINISTIC_TOLERANCE,
+            brush_size,
+            stroke_options.into(),
+            &looped(points, closed),
+        ),
+    }
+}
+
+/// tessellate without merging lyon's duplicate vertices
+pub fn tessellate_raw(
+    tessellator: &mut StrokeTessellator,
+    stroke_options: &StrokeOptions,
+    brush_size: f32,
+    points: &[StrokeElement],
+    closed: bool,
+) -> Result<MeshBuffer, TessellationError> {
+    let mut new_mesh = VertexBuffers::new();
+    let mut builder = lyon::lyon_tessellation::geometry_builder::simple_builder(&mut new_mesh);
+
+    tessellator.tessellate_path(
+        &path(brush_size, points, closed),
+        stroke_options,
+        &mut builder,
+    )?;
+    Ok(new_mesh)
+}
+
+/// the points as a path, with the width of the brush at each point as its attribute
+fn path(brush_size: f32, points: &[StrokeElement], closed: bool) -> Path {
     use lyon::geom::point as point2d;
     let mut path = Path::builder_with_attributes(1);
+    // no points is an empty path, not one that ends before it begins
     if let Some(first) = points.first() {
         path.begin(
             point2d(first.x, first.y),
             &[first.pressure * brush_size * 2.],
         );
+        points.iter().skip(1).for_each(|point| {
+            path.line_to(
+                point2d(point.x, point.y),
+                &[point.pressure * brush_size * 2.],
+            );
+        });
+        path.end(closed);
     }
-    points.iter().skip(1).for_each(|point| {
-        path.line_to(
-            point2d(point.x, point.y),
-            &[point.pressure * brush_size * 2.],
-        );
-    });
-    path.end(false);
-    let path = path.build();
-    let mut new_mesh = VertexBuffers::new();
-    let mut builder = lyon::lyon_tessellation::geometry_builder::simple_builder(&mut new_mesh);
-
-    tessellator.tessellate_path(&path, stroke_options, &mut builder)?;
-    Ok(new_mesh)
+    path.build()
 }
 
 pub enum TessResult {
@@ -160,7 +352,14 @@ pub fn tessellator() -> Tessellator {
                 TessMess::AddPoint(point) => {
                     points.push(point);
 
-                    match tessellate(&mut tessellator, &stroke_options, brush_size, &points) {
+                    match tessellate(
+                        TessellatorKind::Lyon,
+                        &mut tessellator,
+                        &stroke_options,
+                        brush_size,
+                        &points,
+                        false,
+                    ) {
                         Ok(new_mesh) => {
                             result.write().unwrap().add(TessResult::Mesh(new_mesh));
                         }
@@ -0,0 +1,757 @@
+//! curves over anything with a position
+//!
+//! the curves only need to know where their points are, so they work on stroke points as well as
+//! lyon's. points that carry more than a position, like the pressure of a [`StrokeElement`], blend
+//! it along with the position.
+
+use crate::stroke::StrokeElement;
+
+/// most pieces a curve gets flattened into
+const MAX_SEGMENTS: usize = 256;
+
+/// offsets are as close to the real offset curve as their width over this
+const OFFSET_TOLERANCE_DIVISOR: f32 = 64.;
+
+/// most times a curve gets cut in half when flattening adaptively, so at most 2^16 pieces
+const MAX_DEPTH: u32 = 16;
+
+pub trait Point: Copy {
+    fn x(&self) -> f32;
+    fn y(&self) -> f32;
+
+    /// a point at (x, y) that's otherwise like this one
+    fn at(&self, x: f32, y: f32) -> Self;
+
+    /// `t` of the way to `other`
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self.at(
+            self.x() + (other.x() - self.x()) * t,
+            self.y() + (other.y() - self.y()) * t,
+        )
+    }
+}
+
+impl Point for StrokeElement {
+    fn x(&self) -> f32 {
+        self.x
+    }
+
+    fn y(&self) -> f32 {
+        self.y
+    }
+
+    fn at(&self, x: f32, y: f32) -> Self {
+        StrokeElement { x, y, ..*self }
+    }
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        StrokeElement {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+            pressure: self.pressure + (other.pressure - self.pressure) * t,
+        }
+    }
+}
+
+impl Point for lyon::math::Point {
+    fn x(&self) -> f32 {
+        self.x
+    }
+
+    fn y(&self) -> f32 {
+        self.y
+    }
+
+    fn at(&self, x: f32, y: f32) -> Self {
+        lyon::math::point(x, y)
+    }
+}
+
+/// a cubic bezier from `a` to `d`, pulled towards `b` and `c`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cubic<P: Point> {
+    pub a: P,
+    pub b: P,
+    pub c: P,
+    pub d: P,
+}
+
+impl<P: Point> Cubic<P> {
+    pub fn new(a: P, b: P, c: P, d: P) -> Self {
+        Cubic { a, b, c, d }
+    }
+
+    /// the point `t` of the way along, `t` between 0 and 1
+    pub fn eval(&self, t: f32) -> P {
+        let ab = self.a.lerp(&self.b, t);
+        let bc = self.b.lerp(&self.c, t);
+        let cd = self.c.lerp(&self.d, t);
+        ab.lerp(&bc, t).lerp(&bc.lerp(&cd, t), t)
+    }
+
+    /// points along the curve, starting at `a` and ending at `d`, with the lines between them
+    /// never more than `tolerance` from the curve
+    pub fn flatten(&self, tolerance: f32) -> Vec<P> {
+        let segments = self.segments(tolerance);
+        (0..=segments)
+            .map(|i| self.eval(i as f32 / segments as f32))
+            .collect()
+    }
+
+    /// the curve before and after `t`, which put together are the same curve
+    pub fn split(&self, t: f32) -> (Self, Self) {
+        let ab = self.a.lerp(&self.b, t);
+        let bc = self.b.lerp(&self.c, t);
+        let cd = self.c.lerp(&self.d, t);
+        let abc = ab.lerp(&bc, t);
+        let bcd = bc.lerp(&cd, t);
+        let middle = abc.lerp(&bcd, t);
+        (
+            Cubic::new(self.a, ab, abc, middle),
+            Cubic::new(middle, bcd, cd, self.d),
+        )
+    }
+
+    /// like [`flatten`](Cubic::flatten), but only cutting up the parts of the curve that bend, so
+    /// straighter parts get fewer points
+    pub fn flatten_tolerance(&self, tolerance: f32) -> Vec<P> {
+        let mut points = vec![self.a];
+        self.subdivide(tolerance, 0, &mut points);
+        points
+    }
+
+    fn subdivide(&self, tolerance: f32, depth: u32, points: &mut Vec<P>) {
+        let off = off_chord(&self.a, &self.d, &self.b).max(off_chord(&self.a, &self.d, &self.c));
+        // a curve that's not a number can't be cut up into anything better
+        if off.is_nan() || off <= tolerance || depth == MAX_DEPTH {
+            points.push(self.d);
+            return;
+        }
+
+        let (before, after) = self.split(0.5);
+        before.subdivide(tolerance, depth + 1, points);
+        after.subdivide(tolerance, depth + 1, points);
+    }
+
+    /// the top left and bottom right corners of the smallest box the curve fits in, as points
+    /// like `a` otherwise. only as big as the curve, not its control points
+    pub fn bounds(&self) -> (P, P) {
+        // the curve turns around where its derivative, a quadratic, is zero
+        let turns = |a: f32, b: f32, c: f32, d: f32| {
+            let (qa, qb, qc) = (-a + 3. * b - 3. * c + d, 2. * (a - 2. * b + c), b - a);
+            let mut roots = [None, None];
+            if qa.abs() <= f32::EPSILON * (qb.abs() + qc.abs()).max(1.) {
+                if qb != 0. {
+                    roots[0] = Some(-qc / qb);
+                }
+            } else {
+                let discriminant = qb * qb - 4. * qa * qc;
+                if discriminant >= 0. {
+                    let root = discriminant.sqrt();
+                    roots = [
+                        Some((-qb + root) / (2. * qa)),
+                        Some((-qb - root) / (2. * qa)),
+                    ];
+                }
+            }
+            roots
+        };
+
+        let ts = turns(self.a.x(), self.b.x(), self.c.x(), self.d.x())
+            .into_iter()
+            .chain(turns(self.a.y(), self.b.y(), self.c.y(), self.d.y()))
+            .flatten();
+        bounds(self.a, self.d, ts.map(|t| (t, self.eval(t))))
+    }
+
+    /// the way the curve is heading at `t`, not normalized
+    pub fn derivative(&self, t: f32) -> (f32, f32) {
+        let s = 1. - t;
+        let d = |a: f32, b: f32, c: f32, d: f32| {
+            3. * (s * s * (b - a) + 2. * s * t * (c - b) + t * t * (d - c))
+        };
+        (
+            d(self.a.x(), self.b.x(), self.c.x(), self.d.x()),
+            d(self.a.y(), self.b.y(), self.c.y(), self.d.y()),
+        )
+    }
+
+    /// how fast the direction at `t` is changing
+    pub fn derivative2(&self, t: f32) -> (f32, f32) {
+        let d = |a: f32, b: f32, c: f32, d: f32| {
+            6. * (1. - t) * (c - 2. * b + a) + 6. * t * (d - 2. * c + b)
+        };
+        (
+            d(self.a.x(), self.b.x(), self.c.x(), self.d.x()),
+            d(self.a.y(), self.b.y(), self.c.y(), self.d.y()),
+        )
+    }
+
+    pub fn bezier_n(&self) -> BezierN<P> {
+        BezierN {
+            points: vec![self.a, self.b, self.c, self.d],
+        }
+    }
+
+    /// points `distance` to the left of the curve, or the right if it's negative. see
+    /// [`offset_by`](Cubic::offset_by)
+    pub fn offset(&self, distance: f32) -> Vec<P> {
+        self.offset_by(|_| distance)
+    }
+
+    /// points `width(t)` to the left of the curve at each `t`, or the right where it's negative,
+    /// like one side of a stroke whose width follows the pressure. where the curve bends tighter
+    /// than the width the offset would loop back on itself, so the loop is cut off where it crosses
+    pub fn offset_by(&self, width: impl Fn(f32) -> f32) -> Vec<P> {
+        let widest = [0., 0.25, 0.5, 0.75, 1.]
+            .map(|t| width(t).abs())
+            .into_iter()
+            .fold(0., f32::max);
+        let segments = self.segments(widest / OFFSET_TOLERANCE_DIVISOR);
+
+        let mut points: Vec<P> = Vec::with_capacity(segments + 1);
+        let mut heading = (self.d.x() - self.a.x(), self.d.y() - self.a.y());
+        for i in 0..=segments {
+            let t = i as f32 / segments as f32;
+            let on = self.eval(t);
+
+            // the ends of a curve with a control point on top of them have no direction there
+            let (dx, dy) = self.derivative(t);
+            if dx.hypot(dy) > f32::EPSILON * 16. {
+                heading = (dx, dy);
+            }
+            let length = heading.0.hypot(heading.1);
+            if length == 0. {
+                points.push(on);
+                continue;
+            }
+
+            let w = width(t) / length;
+            let next = on.at(on.x() - heading.1 * w, on.y() + heading.0 * w);
+
+            // the newest piece crossing an older one is a loop
+            if let Some(last) = points.last().copied() {
+                let crossing = points
+                    .windows(2)
+                    .enumerate()
+                    .take(points.len().saturating_sub(2))
+                    .find_map(|(j, pair)| {
+                        crossing(&pair[0], &pair[1], &last, &next).map(|along| (j, along))
+                    });
+                if let Some((j, along)) = crossing {
+                    let cut = points[j].lerp(&points[j + 1], along);
+                    points.truncate(j + 1);
+                    points.push(cut);
+                }
+            }
+            points.push(next);
+        }
+
+        points
+    }
+
+    /// how many even pieces the curve needs to be within `tolerance`, from how far the control
+    /// points bend away from a straight line
+    fn segments(&self, tolerance: f32) -> usize {
+        let bend =
+            |p: &P, q: &P, r: &P| (p.x() - 2. * q.x() + r.x()).hypot(p.y() - 2. * q.y() + r.y());
+        let bend = bend(&self.a, &self.b, &self.c).max(bend(&self.b, &self.c, &self.d));
+
+        if !(tolerance.is_finite() && tolerance > 0. && bend.is_finite()) {
+            return 1;
+        }
+
+        ((0.75 * bend / tolerance).sqrt().ceil() as usize).clamp(1, MAX_SEGMENTS)
+    }
+}
+
+/// a quadratic bezier from `a` to `c`, pulled towards `b`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quadratic<P: Point> {
+    pub a: P,
+    pub b: P,
+    pub c: P,
+}
+
+impl<P: Point> Quadratic<P> {
+    pub fn new(a: P, b: P, c: P) -> Self {
+        Quadratic { a, b, c }
+    }
+
+    /// the point `t` of the way along, `t` between 0 and 1
+    pub fn eval(&self, t: f32) -> P {
+        self.a.lerp(&self.b, t).lerp(&self.b.lerp(&self.c, t), t)
+    }
+
+    /// the curve before and after `t`, which put together are the same curve
+    pub fn split(&self, t: f32) -> (Self, Self) {
+        let ab = self.a.lerp(&self.b, t);
+        let bc = self.b.lerp(&self.c, t);
+        let middle = ab.lerp(&bc, t);
+        (
+            Quadratic::new(self.a, ab, middle),
+            Quadratic::new(middle, bc, self.c),
+        )
+    }
+
+    /// the same curve as a cubic, so it can go anywhere a cubic can
+    pub fn elevate(&self) -> Cubic<P> {
+        Cubic::new(
+            self.a,
+            self.a.lerp(&self.b, 2. / 3.),
+            self.c.lerp(&self.b, 2. / 3.),
+            self.c,
+        )
+    }
+
+    /// the top left and bottom right corners of the smallest box the curve fits in, as points
+    /// like `a` otherwise
+    pub fn bounds(&self) -> (P, P) {
+        // the derivative is a line, zero where the curve turns around
+        let turn = |a: f32, b: f32, c: f32| {
+            let bend = a - 2. * b + c;
+            (bend != 0.).then(|| (a - b) / bend)
+        };
+
+        let ts = [
+            turn(self.a.x(), self.b.x(), self.c.x()),
+            turn(self.a.y(), self.b.y(), self.c.y()),
+        ];
+        let ts = ts.into_iter().flatten();
+        bounds(self.a, self.c, ts.map(|t| (t, self.eval(t))))
+    }
+
+    /// points along the curve, starting at `a` and ending at `c`, cut up more where it bends more,
+    /// with the lines between them never more than `tolerance` from the curve
+    pub fn flatten_tolerance(&self, tolerance: f32) -> Vec<P> {
+        self.elevate().flatten_tolerance(tolerance)
+    }
+
+    pub fn offset(&self, distance: f32) -> Vec<P> {
+        self.elevate().offset(distance)
+    }
+
+    pub fn bezier_n(&self) -> BezierN<P> {
+        BezierN {
+            points: vec![self.a, self.b, self.c],
+        }
+    }
+
+    pub fn offset_by(&self, width: impl Fn(f32) -> f32) -> Vec<P> {
+        self.elevate().offset_by(width)
+    }
+}
+
+/// a bezier of any degree, one less than how many points it has. slower than [`Cubic`] and
+/// [`Quadratic`], which are the same thing with 4 and 3 points
+#[derive(Debug, Clone, PartialEq)]
+pub struct BezierN<P: Point> {
+    points: Vec<P>,
+}
+
+impl<P: Point> BezierN<P> {
+    /// None without any points
+    pub fn new(points: Vec<P>) -> Option<Self> {
+        (!points.is_empty()).then_some(BezierN { points })
+    }
+
+    pub fn points(&self) -> &[P] {
+        &self.points
+    }
+
+    pub fn degree(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    /// the point `t` of the way along, `t` between 0 and 1
+    pub fn eval(&self, t: f32) -> P {
+        de_casteljau(self.points.clone(), |a, b| a.lerp(b, t))
+    }
+
+    /// the way the curve is heading at `t`, not normalized. this is a bezier one degree lower, of
+    /// the differences between the points
+    pub fn derivative(&self, t: f32) -> (f32, f32) {
+        let n = self.degree() as f32;
+        let differences = self
+            .points
+            .windows(2)
+            .map(|pair| {
+                (
+                    n * (pair[1].x() - pair[0].x()),
+                    n * (pair[1].y() - pair[0].y()),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if differences.is_empty() {
+            return (0., 0.);
+        }
+
+        de_casteljau(differences, |a, b| {
+            (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+        })
+    }
+}
+
+/// blend neighbors together until there's only one left
+fn de_casteljau<T: Copy>(mut points: Vec<T>, lerp: impl Fn(&T, &T) -> T) -> T {
+    while points.len() > 1 {
+        for i in 0..points.len() - 1 {
+            points[i] = lerp(&points[i], &points[i + 1]);
+        }
+        points.pop();
+    }
+    points[0]
+}
+
+/// how far along the line from `a` to `b` it crosses the line from `c` to `d`, if they cross
+fn crossing<P: Point>(a: &P, b: &P, c: &P, d: &P) -> Option<f32> {
+    let (rx, ry) = (b.x() - a.x(), b.y() - a.y());
+    let (sx, sy) = (d.x() - c.x(), d.y() - c.y());
+    let across = rx * sy - ry * sx;
+    if across == 0. {
+        return None;
+    }
+
+    let (qx, qy) = (c.x() - a.x(), c.y() - a.y());
+    let t = (qx * sy - qy * sx) / across;
+    let u = (qx * ry - qy * rx) / across;
+    ((0. ..=1.).contains(&t) && (0. ..=1.).contains(&u)).then_some(t)
+}
+
+/// the corners of the box around the ends of a curve and the points it turns around at. only the
+/// turns inside the curve count, anything else is a turn the curve would make if it kept going
+fn bounds<P: Point>(start: P, end: P, turns: impl Iterator<Item = (f32, P)>) -> (P, P) {
+    let (mut min, mut max) = (start, start);
+    let mut grow = |p: P| {
+        min = min.at(min.x().min(p.x()), min.y().min(p.y()));
+        max = max.at(max.x().max(p.x()), max.y().max(p.y()));
+    };
+
+    grow(end);
+    turns
+        .filter(|(t, _)| (0. ..=1.).contains(t))
+        .for_each(|(_, p)| grow(p));
+    (min, max)
+}
+
+/// how far the control point `p` is from the line between `a` and `b`. the curve stays inside its
+/// control points, so it can't be any further from the line than they are
+fn off_chord<P: Point>(a: &P, b: &P, p: &P) -> f32 {
+    let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+    let length = dx.hypot(dy);
+    if length == 0. {
+        return (p.x() - a.x()).hypot(p.y() - a.y());
+    }
+
+    ((p.x() - a.x()) * dy - (p.y() - a.y()) * dx).abs() / length
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flatten_stroke_points() {
+        let at = |x, y, pressure| StrokeElement { x, y, pressure };
+        let cubic = Cubic::new(
+            at(0., 0., 0.),
+            at(1., 2., 0.2),
+            at(3., 2., 0.6),
+            at(4., 0., 1.),
+        );
+
+        let tolerance = 0.01;
+        let points = cubic.flatten(tolerance);
+        assert!(points.len() > 4);
+        assert_eq!(points.first(), Some(&cubic.a));
+        assert_eq!(points.last(), Some(&cubic.d));
+
+        // an arch over the middle, inside the control points, with the pressure going up the
+        // whole way
+        assert!(points.windows(2).all(|pair| pair[0].x < pair[1].x));
+        assert!(points
+            .windows(2)
+            .all(|pair| pair[0].pressure < pair[1].pressure));
+        assert!(points.iter().all(|point| point.y >= 0. && point.y <= 2.));
+        let middle = cubic.eval(0.5);
+        assert!((middle.x - 2.).abs() < 1e-6 && (middle.y - 1.5).abs() < 1e-6);
+        assert!((middle.pressure - 0.425).abs() < 1e-6);
+
+        // every line between the points stays close to the curve
+        for pair in points.windows(2) {
+            let halfway = pair[0].lerp(&pair[1], 0.5);
+            let closest = (0..=1000)
+                .map(|i| cubic.eval(i as f32 / 1000.))
+                .map(|on| (on.x - halfway.x).hypot(on.y - halfway.y))
+                .fold(f32::INFINITY, f32::min);
+            assert!(closest <= tolerance, "{closest}");
+        }
+
+        // a straight line doesn't need to be split up
+        let line = Cubic::new(
+            at(0., 0., 1.),
+            at(1., 1., 1.),
+            at(2., 2., 1.),
+            at(3., 3., 1.),
+        );
+        assert_eq!(line.flatten(tolerance), vec![line.a, line.d]);
+    }
+
+    /// how far the furthest line between `points` gets from `curve`
+    fn furthest<P: Point>(points: &[P], curve: impl Fn(f32) -> P) -> f32 {
+        let on = (0..=20000)
+            .map(|i| curve(i as f32 / 20000.))
+            .collect::<Vec<_>>();
+        points
+            .windows(2)
+            .flat_map(|pair| (1..4).map(move |i| pair[0].lerp(&pair[1], i as f32 / 4.)))
+            .map(|along| {
+                on.iter()
+                    .map(|on| (on.x() - along.x()).hypot(on.y() - along.y()))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .fold(0., f32::max)
+    }
+
+    #[test]
+    fn adaptive_flattening() {
+        let at = |x, y| lyon::math::point(x, y);
+        let tolerance = 0.01;
+
+        // nearly straight most of the way, with a hook at the end
+        let hook = Cubic::new(at(0., 0.), at(20., 0.), at(20., 0.), at(20., 1.));
+        let (left, right) = hook.split(0.3);
+        assert_eq!(left.a, hook.a);
+        assert_eq!(right.d, hook.d);
+        assert_eq!(left.d, right.a);
+        let (split, whole) = (left.eval(0.5), hook.eval(0.15));
+        assert!((split.x - whole.x).abs() < 1e-5 && (split.y - whole.y).abs() < 1e-5);
+
+        let adaptive = hook.flatten_tolerance(tolerance);
+        assert_eq!(adaptive.first(), Some(&hook.a));
+        assert_eq!(adaptive.last(), Some(&hook.d));
+        assert!(adaptive.len() < hook.flatten(tolerance).len());
+        let off = furthest(&adaptive, |t| hook.eval(t));
+        assert!(off <= tolerance, "{off}");
+        // the straight part gets fewer points than the hook
+        let straight = adaptive.iter().filter(|point| point.x < 10.).count();
+        assert!(straight < adaptive.len() - straight);
+
+        let arch = Quadratic::new(at(0., 0.), at(1., 2.), at(2., 0.));
+        let points = arch.flatten_tolerance(tolerance);
+        assert!(points.len() > 3);
+        assert_eq!(points.first(), Some(&arch.a));
+        assert_eq!(points.last(), Some(&arch.c));
+        assert!(furthest(&points, |t| arch.eval(t)) <= tolerance);
+
+        // straight lines and points stay as they are
+        let line = Quadratic::new(at(0., 0.), at(1., 1.), at(2., 2.));
+        assert_eq!(line.flatten_tolerance(tolerance), vec![line.a, line.c]);
+        let dot = Cubic::new(at(1., 1.), at(1., 1.), at(1., 1.), at(1., 1.));
+        assert_eq!(dot.flatten_tolerance(tolerance), vec![dot.a, dot.d]);
+
+        // the depth cap stops curves that can never be flat enough
+        let wild = Cubic::new(at(0., 0.), at(0., 1e30), at(1., -1e30), at(1., 0.));
+        assert!(wild.flatten_tolerance(0.).len() <= (1 << MAX_DEPTH) + 1);
+        let broken = Cubic::new(at(0., 0.), at(f32::NAN, 0.), at(1., 0.), at(1., 0.));
+        assert_eq!(broken.flatten_tolerance(tolerance).len(), 2);
+    }
+
+    #[test]
+    fn split_reproduces_curve() {
+        let at = |x, y, pressure| StrokeElement { x, y, pressure };
+        let cubic = Cubic::new(
+            at(0., 0., 0.),
+            at(1., 3., 0.5),
+            at(4., -2., 0.25),
+            at(5., 1., 1.),
+        );
+        let quadratic = Quadratic::new(at(0., 0., 0.), at(2., 4., 0.5), at(5., 1., 1.));
+        let close = |p: StrokeElement, q: StrokeElement| {
+            assert!(
+                (p.x - q.x).abs() < 1e-5
+                    && (p.y - q.y).abs() < 1e-5
+                    && (p.pressure - q.pressure).abs() < 1e-5,
+                "{p:?} isn't {q:?}"
+            );
+        };
+
+        for split in [0., 0.1, 0.5, 0.73, 1.] {
+            let (before, after) = cubic.split(split);
+            let (first, second) = quadratic.split(split);
+            close(before.d, cubic.eval(split));
+            close(first.c, quadratic.eval(split));
+
+            for i in 0..=10 {
+                let t = i as f32 / 10.;
+                close(before.eval(t), cubic.eval(split * t));
+                close(after.eval(t), cubic.eval(split + (1. - split) * t));
+                close(first.eval(t), quadratic.eval(split * t));
+                close(second.eval(t), quadratic.eval(split + (1. - split) * t));
+            }
+        }
+    }
+
+    #[test]
+    fn tight_bounds() {
+        let at = |x, y| lyon::math::point(x, y);
+        // the box the curve really fills, from lots of points along it
+        let sampled = |curve: &dyn Fn(f32) -> lyon::math::Point| {
+            (0..=10000).map(|i| curve(i as f32 / 10000.)).fold(
+                (at(f32::MAX, f32::MAX), at(f32::MIN, f32::MIN)),
+                |(min, max), p| (min.min(p), max.max(p)),
+            )
+        };
+        let close = |(min, max): (lyon::math::Point, lyon::math::Point),
+                     (smin, smax): (lyon::math::Point, lyon::math::Point)| {
+            assert!(
+                (min - smin).length() < 1e-3 && (max - smax).length() < 1e-3,
+                "{min:?} {max:?} isn't {smin:?} {smax:?}"
+            );
+        };
+
+        let cubics = [
+            // an arch, well inside its control points
+            Cubic::new(at(0., 0.), at(1., 4.), at(3., 4.), at(4., 0.)),
+            // an s that turns around in x and y
+            Cubic::new(at(0., 0.), at(5., 3.), at(-2., 3.), at(3., 0.)),
+            // a straight line, and one that doubles back
+            Cubic::new(at(0., 0.), at(1., 1.), at(2., 2.), at(3., 3.)),
+            Cubic::new(at(0., 0.), at(3., 0.), at(3., 0.), at(1., 0.)),
+            // a parabola in x, with the cubic part gone
+            Cubic::new(at(0., 0.), at(2., 1.), at(4., 2.), at(0., 3.)),
+        ];
+        for cubic in cubics {
+            close(cubic.bounds(), sampled(&|t| cubic.eval(t)));
+        }
+        let (min, max) = cubics[0].bounds();
+        assert!(max.y < 4.);
+        assert_eq!((min.x, max.x), (0., 4.));
+
+        let quadratics = [
+            Quadratic::new(at(0., 0.), at(1., 2.), at(2., 0.)),
+            Quadratic::new(at(0., 0.), at(-3., 1.), at(2., 2.)),
+            Quadratic::new(at(0., 0.), at(1., 1.), at(2., 2.)),
+        ];
+        for quadratic in quadratics {
+            close(quadratic.bounds(), sampled(&|t| quadratic.eval(t)));
+        }
+        assert_eq!(quadratics[0].bounds().1.y, 1.);
+    }
+
+    #[test]
+    fn elevated_quadratic_same_curve() {
+        let at = |x, y, pressure| StrokeElement { x, y, pressure };
+        let quadratic = Quadratic::new(at(0., 0., 0.2), at(3., 5., 1.), at(6., -1., 0.4));
+        let cubic = quadratic.elevate();
+        assert_eq!((cubic.a, cubic.d), (quadratic.a, quadratic.c));
+
+        for i in 0..=16 {
+            let t = i as f32 / 16.;
+            let (p, q) = (quadratic.eval(t), cubic.eval(t));
+            assert!(
+                (p.x - q.x).abs() < 1e-5
+                    && (p.y - q.y).abs() < 1e-5
+                    && (p.pressure - q.pressure).abs() < 1e-5,
+                "{p:?} isn't {q:?} at {t}"
+            );
+        }
+    }
+
+    #[test]
+    fn offsets() {
+        let at = |x, y| lyon::math::point(x, y);
+
+        // a straight line moves over without needing any more points
+        let line = Cubic::new(at(0., 0.), at(1., 0.), at(2., 0.), at(3., 0.));
+        assert_eq!(line.offset(1.), vec![at(0., 1.), at(3., 1.)]);
+        assert_eq!(line.offset(-1.), vec![at(0., -1.), at(3., -1.)]);
+
+        // a quarter circle-ish arc stays the same distance out from its middle
+        let k = 0.552_284_8;
+        let arc = Cubic::new(at(1., 0.), at(1., k), at(k, 1.), at(0., 1.));
+        for point in arc.offset(-0.5) {
+            assert!((point.to_vector().length() - 1.5).abs() < 0.01, "{point:?}");
+        }
+        for point in arc.offset(0.5) {
+            assert!((point.to_vector().length() - 0.5).abs() < 0.01, "{point:?}");
+        }
+
+        // growing wider along the way, like pressing harder
+        let wider = line.offset_by(|t| 1. + t);
+        assert_eq!(wider.first(), Some(&at(0., 1.)));
+        assert_eq!(wider.last(), Some(&at(3., 2.)));
+
+        // a hairpin much tighter than the offset would loop on the inside. the loop is cut off, so
+        // no two pieces of the offset cross
+        let hairpin = Cubic::new(at(0., 0.), at(4., 0.), at(4., 1.), at(0., 1.));
+        let inside = hairpin.offset(0.8);
+        assert!(inside.len() > 2);
+        for (i, first) in inside.windows(2).enumerate() {
+            for second in inside.windows(2).skip(i + 2) {
+                assert_eq!(crossing(&first[0], &first[1], &second[0], &second[1]), None);
+            }
+        }
+        let close = |a: lyon::math::Point, b: lyon::math::Point| (a - b).length() < 1e-5;
+        assert!(close(inside[0], at(0., 0.8)));
+        assert!(close(inside[inside.len() - 1], at(0., 0.2)));
+    }
+
+    #[test]
+    fn derivatives() {
+        let at = |x, y| lyon::math::point(x, y);
+        let curve = Cubic::new(at(0., 0.), at(1., 3.), at(5., -2.), at(4., 1.));
+
+        let h = 1e-3;
+        for t in [0., 0.1, 0.25, 0.5, 0.8, 1.] {
+            let (before, after) = (curve.derivative(t - h), curve.derivative(t + h));
+            let slope = (
+                (after.0 - before.0) / (2. * h),
+                (after.1 - before.1) / (2. * h),
+            );
+            let exact = curve.derivative2(t);
+            assert!((exact.0 - slope.0).hypot(exact.1 - slope.1) < 1e-2, "{t}");
+
+            let (before, after) = (curve.eval(t - h), curve.eval(t + h));
+            let slope = (after - before) / (2. * h);
+            let exact = curve.derivative(t);
+            assert!((exact.0 - slope.x).hypot(exact.1 - slope.y) < 1e-2, "{t}");
+        }
+    }
+
+    #[test]
+    fn any_degree() {
+        let at = |x, y, pressure| StrokeElement { x, y, pressure };
+        let cubic = Cubic::new(
+            at(0., 0., 0.1),
+            at(1., 3., 0.9),
+            at(5., -2., 0.3),
+            at(4., 1., 0.6),
+        );
+        let quadratic = Quadratic::new(at(0., 0., 0.1), at(2., 5., 1.), at(4., 1., 0.5));
+        let (n3, n4) = (quadratic.bezier_n(), cubic.bezier_n());
+        assert_eq!((n3.degree(), n4.degree()), (2, 3));
+
+        for t in (0..=20).map(|i| i as f32 / 20.) {
+            assert_eq!(n4.eval(t), cubic.eval(t));
+            assert_eq!(n3.eval(t), quadratic.eval(t));
+
+            let (exact, general) = (cubic.derivative(t), n4.derivative(t));
+            assert!((exact.0 - general.0).hypot(exact.1 - general.1) < 1e-4);
+            let (exact, general) = (quadratic.elevate().derivative(t), n3.derivative(t));
+            assert!((exact.0 - general.0).hypot(exact.1 - general.1) < 1e-4);
+        }
+
+        // a line, and a point that goes nowhere
+        let line = BezierN::new(vec![at(0., 0., 0.), at(2., 4., 1.)]).unwrap();
+        assert_eq!(line.eval(0.25), at(0.5, 1., 0.25));
+        assert_eq!(line.derivative(0.5), (2., 4.));
+        let still = BezierN::new(vec![at(1., 1., 1.)]).unwrap();
+        assert_eq!(
+            (still.eval(0.5), still.derivative(0.5)),
+            (at(1., 1., 1.), (0., 0.))
+        );
+        assert_eq!(BezierN::<StrokeElement>::new(vec![]), None);
+    }
+}
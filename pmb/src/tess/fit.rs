@@ -0,0 +1,318 @@
+//! fitting cubic beziers to points
+//!
+//! schneider's algorithm from graphics gems: guess how far along the curve each point is from the
+//! distances between them, fit one cubic to all of them by least squares, and nudge the guesses
+//! with newton's method a few times. if it still isn't good enough, split at the worst point and
+//! fit each side on its own.
+
+use super::curve::{Cubic, Point};
+
+/// most times the guesses get nudged before giving up and splitting
+const MAX_ITERATIONS: usize = 8;
+
+type Vec2 = (f32, f32);
+
+fn xy<P: Point>(p: &P) -> Vec2 {
+    (p.x(), p.y())
+}
+
+fn sub(a: Vec2, b: Vec2) -> Vec2 {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn add(a: Vec2, b: Vec2) -> Vec2 {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn scale(a: Vec2, s: f32) -> Vec2 {
+    (a.0 * s, a.1 * s)
+}
+
+fn dot(a: Vec2, b: Vec2) -> f32 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn length(a: Vec2) -> f32 {
+    a.0.hypot(a.1)
+}
+
+fn unit(a: Vec2) -> Vec2 {
+    let length = length(a);
+    if length > 0. {
+        scale(a, 1. / length)
+    } else {
+        a
+    }
+}
+
+/// cubics that go through the first and last of `points` and pass within `error` of the rest.
+/// the ends of neighboring cubics meet, and the control points carry whatever else the points do,
+/// like pressure, blended from one end to the other
+pub fn fit_cubic<P: Point>(points: &[P], error: f32) -> Vec<Cubic<P>> {
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| a.x() == b.x() && a.y() == b.y());
+
+    let mut cubics = Vec::new();
+    if points.len() < 2 {
+        return cubics;
+    }
+
+    let start = end_direction(&points);
+    points.reverse();
+    let end = end_direction(&points);
+    points.reverse();
+    fit(&points, start, end, error.max(0.), &mut cubics);
+    cubics
+}
+
+/// which way the curve leaves the first point. with only the first two points to go on it's
+/// pointed at the second, which is a little off on a bend, so a parabola through the first three
+/// is used when there are enough
+fn end_direction<P: Point>(points: &[P]) -> Vec2 {
+    let towards = |i: usize| sub(xy(&points[i]), xy(&points[0]));
+    let chord = unit(towards(1));
+    if points.len() < 3 {
+        return chord;
+    }
+
+    let (t1, t2) = (
+        length(towards(1)),
+        length(towards(1)) + length(sub(xy(&points[2]), xy(&points[1]))),
+    );
+    let parabola = unit(sub(
+        scale(towards(1), t2 / (t1 * (t2 - t1))),
+        scale(towards(2), t1 / (t2 * (t2 - t1))),
+    ));
+
+    // points doubling back can point it anywhere
+    if dot(parabola, chord) > 0. {
+        parabola
+    } else {
+        chord
+    }
+}
+
+/// `start` and `end` point from the ends of `points` into the curve
+fn fit<P: Point>(points: &[P], start: Vec2, end: Vec2, error: f32, cubics: &mut Vec<Cubic<P>>) {
+    if points.len() == 2 {
+        let third = length(sub(xy(&points[1]), xy(&points[0]))) / 3.;
+        cubics.push(cubic(points, start, end, third, third));
+        return;
+    }
+
+    let mut ts = chord_lengths(points);
+    let mut curve = least_squares(points, &ts, start, end);
+    let (mut worst, mut split) = max_error(points, &curve, &ts);
+    if worst <= error {
+        cubics.push(curve);
+        return;
+    }
+
+    // points drawn slowing down and speeding up are a long way from where their distances say, so
+    // even a fit that's far off can be close once they're moved
+    for _ in 0..MAX_ITERATIONS {
+        let nudged = nudge(points, &curve, &ts);
+        let better = least_squares(points, &nudged, start, end);
+        let (off, at) = max_error(points, &better, &nudged);
+        if off <= error {
+            cubics.push(better);
+            return;
+        }
+
+        // not getting anywhere
+        if off.is_nan() || off >= worst {
+            break;
+        }
+
+        (ts, curve, worst, split) = (nudged, better, off, at);
+    }
+
+    // the curve goes through the worst point, smoothly
+    let through = unit(sub(xy(&points[split - 1]), xy(&points[split + 1])));
+    let through = if through == (0., 0.) {
+        unit(sub(xy(&points[split - 1]), xy(&points[split])))
+    } else {
+        through
+    };
+    fit(&points[..=split], start, through, error, cubics);
+    fit(&points[split..], scale(through, -1.), end, error, cubics);
+}
+
+/// from the first to the last point, with the inner control points `towards_start` and
+/// `towards_end` along the directions from each end
+fn cubic<P: Point>(
+    points: &[P],
+    start: Vec2,
+    end: Vec2,
+    towards_start: f32,
+    towards_end: f32,
+) -> Cubic<P> {
+    let (a, d) = (points[0], points[points.len() - 1]);
+    let b = add(xy(&a), scale(start, towards_start));
+    let c = add(xy(&d), scale(end, towards_end));
+    Cubic::new(
+        a,
+        a.lerp(&d, 1. / 3.).at(b.0, b.1),
+        a.lerp(&d, 2. / 3.).at(c.0, c.1),
+        d,
+    )
+}
+
+/// how far along the curve each point probably is, by how far it is along the lines between them
+fn chord_lengths<P: Point>(points: &[P]) -> Vec<f32> {
+    let mut ts = vec![0.];
+    for pair in points.windows(2) {
+        let last = ts[ts.len() - 1];
+        ts.push(last + length(sub(xy(&pair[1]), xy(&pair[0]))));
+    }
+
+    let total = ts[ts.len() - 1];
+    ts.iter_mut().for_each(|t| *t /= total);
+    ts
+}
+
+/// how far the inner control points go along `start` and `end` to get as close as possible to
+/// the points at `ts`
+fn least_squares<P: Point>(points: &[P], ts: &[f32], start: Vec2, end: Vec2) -> Cubic<P> {
+    let (first, last) = (xy(&points[0]), xy(&points[points.len() - 1]));
+
+    let mut c = [[0.; 2]; 2];
+    let mut x = [0.; 2];
+    for (point, &t) in points.iter().zip(ts) {
+        let s = 1. - t;
+        let (b0, b1, b2, b3) = (s * s * s, 3. * s * s * t, 3. * s * t * t, t * t * t);
+        let (a0, a1) = (scale(start, b1), scale(end, b2));
+
+        c[0][0] += dot(a0, a0);
+        c[0][1] += dot(a0, a1);
+        c[1][1] += dot(a1, a1);
+
+        let off = sub(xy(point), add(scale(first, b0 + b1), scale(last, b2 + b3)));
+        x[0] += dot(a0, off);
+        x[1] += dot(a1, off);
+    }
+    c[1][0] = c[0][1];
+
+    let det = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let (towards_start, towards_end) = if det.abs() > f32::EPSILON * c[0][0] * c[1][1] {
+        (
+            (x[0] * c[1][1] - x[1] * c[0][1]) / det,
+            (c[0][0] * x[1] - c[1][0] * x[0]) / det,
+        )
+    } else {
+        (0., 0.)
+    };
+
+    // control points on the wrong side or right on top of the ends don't make a curve worth
+    // having. a third of the way each works well enough
+    let chord = length(sub(last, first));
+    let tiny = 1e-6 * chord;
+    if towards_start < tiny || towards_end < tiny || !(towards_start + towards_end).is_finite() {
+        cubic(points, start, end, chord / 3., chord / 3.)
+    } else {
+        cubic(points, start, end, towards_start, towards_end)
+    }
+}
+
+/// the furthest any point is from where the curve puts it, and which point that is. never the
+/// ends, which the curve always goes through
+fn max_error<P: Point>(points: &[P], curve: &Cubic<P>, ts: &[f32]) -> (f32, usize) {
+    let mut worst = (0., points.len() / 2);
+    for i in 1..points.len() - 1 {
+        let off = length(sub(xy(&curve.eval(ts[i])), xy(&points[i])));
+        if off > worst.0 || off.is_nan() {
+            worst = (off, i);
+        }
+    }
+    worst
+}
+
+/// better guesses at how far along the curve each point is, with a step of newton's method on
+/// the distance between them
+fn nudge<P: Point>(points: &[P], curve: &Cubic<P>, ts: &[f32]) -> Vec<f32> {
+    points
+        .iter()
+        .zip(ts)
+        .map(|(point, &t)| {
+            let on = sub(xy(&curve.eval(t)), xy(point));
+            let slope = curve.derivative(t);
+            let bend = curve.derivative2(t);
+
+            let numerator = dot(on, slope);
+            let denominator = dot(slope, slope) + dot(on, bend);
+            if denominator == 0. {
+                t
+            } else {
+                (t - numerator / denominator).clamp(0., 1.)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stroke::StrokeElement;
+
+    /// how far `point` is from the closest of lots of points along the cubics
+    fn off_curve(cubics: &[Cubic<StrokeElement>], point: &StrokeElement) -> f32 {
+        cubics
+            .iter()
+            .flat_map(|cubic| (0..=2000).map(|i| cubic.eval(i as f32 / 2000.)))
+            .map(|on| (on.x - point.x).hypot(on.y - point.y))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn recovers_one_cubic() {
+        let at = |x, y, pressure| StrokeElement { x, y, pressure };
+        let known = Cubic::new(
+            at(0., 0., 0.2),
+            at(1., 3., 0.4),
+            at(4., 3., 0.6),
+            at(5., 0., 0.8),
+        );
+        // bunched up in the middle, like a pen slowing down around the bend
+        let points = (0..=40)
+            .map(|i| i as f32 / 40.)
+            .map(|t| known.eval(t + 0.1 * (std::f32::consts::TAU * t).sin()))
+            .collect::<Vec<_>>();
+
+        let error = 0.01;
+        let cubics = fit_cubic(&points, error);
+        assert_eq!(cubics.len(), 1);
+        let fitted = cubics[0];
+        assert_eq!((fitted.a, fitted.d), (known.a, known.d));
+        for point in &points {
+            assert!(off_curve(&cubics, point) <= error * 1.01);
+        }
+        // the pressure goes from one end to the other
+        assert!(fitted.b.pressure > fitted.a.pressure && fitted.c.pressure < fitted.d.pressure);
+    }
+
+    #[test]
+    fn splits_at_corners() {
+        let at = |x, y| StrokeElement { x, y, pressure: 1. };
+        // an L, which no one cubic can follow
+        let points = (0..=20)
+            .map(|i| at(0., 2. - i as f32 / 10.))
+            .chain((1..=20).map(|i| at(i as f32 / 10., 0.)))
+            .collect::<Vec<_>>();
+
+        let error = 0.01;
+        let cubics = fit_cubic(&points, error);
+        assert!(cubics.len() >= 2);
+        assert_eq!(cubics.first().unwrap().a, points[0]);
+        assert_eq!(cubics.last().unwrap().d, points[points.len() - 1]);
+        assert!(cubics.windows(2).all(|pair| pair[0].d == pair[1].a));
+        for point in &points {
+            assert!(off_curve(&cubics, point) <= error * 1.01);
+        }
+
+        // a straight line is one cubic, and not enough points to fit is none
+        assert_eq!(fit_cubic(&points[..20], error).len(), 1);
+        assert_eq!(fit_cubic(&points[..2], error).len(), 1);
+        assert!(fit_cubic(&points[..1], error).is_empty());
+        assert!(fit_cubic(&[points[0], points[0]], error).is_empty());
+    }
+}
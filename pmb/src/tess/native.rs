@@ -0,0 +1,805 @@
+//! a stroke tessellator that doesn't go through lyon
+//!
+//! the points of the stroke are joined with a catmull-rom spline, which goes through every point,
+//! and the spline is swept with a circle as wide as the pressure at each point to make a ribbon of
+//! triangles. the ends of the ribbon get caps and its sharp corners get joins, like lyon's. only
+//! lyon's mesh types are used, so the output can go anywhere a lyon mesh can.
+
+use crate::{
+    mesh::narrow,
+    stroke::{MeshBuffer, StrokeElement, WideMeshBuffer},
+    tess::{CapStyle, JoinStyle},
+};
+use lyon::{
+    lyon_tessellation::{GeometryBuilderError, StrokeOptions, TessellationError, VertexBuffers},
+    math::{point, vector, Point, Vector},
+};
+use std::f32::consts::PI;
+
+/// most segments a catmull-rom curve between two points gets split into
+const MAX_SUBDIVISIONS: usize = 16;
+
+/// directions shorter than this are too short to normalize without blowing up
+const MIN_LENGTH: f32 = 1e-6;
+
+/// the tolerance [`deterministic`] uses, whatever the stroke options say
+pub const DETERMINISTIC_TOLERANCE: f32 = 0.001;
+
+/// vertices from [`deterministic`] land on multiples of this. a power of two, so snapping to it
+/// doesn't round twice
+pub const DETERMINISTIC_GRID: f32 = 1. / 65536.;
+
+/// what the ends and corners of a line look like
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStyle {
+    pub cap: CapStyle,
+    pub join: JoinStyle,
+    /// how many times the width of the line a miter can stick out before it's beveled instead
+    pub miter_limit: f32,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle {
+            cap: CapStyle::Round,
+            join: JoinStyle::Round,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT,
+        }
+    }
+}
+
+impl From<&StrokeOptions> for LineStyle {
+    fn from(options: &StrokeOptions) -> Self {
+        LineStyle {
+            cap: options.start_cap.into(),
+            join: options.line_join.into(),
+            miter_limit: options.miter_limit,
+        }
+    }
+}
+
+pub fn tessellate(
+    tolerance: f32,
+    brush_size: f32,
+    style: LineStyle,
+    points: &[StrokeElement],
+) -> Result<MeshBuffer, TessellationError> {
+    narrow(tessellate_wide(tolerance, brush_size, style, points)?)
+}
+
+/// [`tessellate`] without the limit of u16 indices
+pub fn tessellate_wide(
+    tolerance: f32,
+    brush_size: f32,
+    style: LineStyle,
+    points: &[StrokeElement],
+) -> Result<WideMeshBuffer, TessellationError> {
+    let mut mesh = VertexBuffers::new();
+
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| a.x == b.x && a.y == b.y);
+
+    let samples = sample(tolerance, brush_size, &points);
+    let Some(first) = samples.first() else {
+        return Ok(mesh);
+    };
+
+    // a dot is both ends at once
+    let cap = style.cap;
+    if samples.len() == 1 {
+        let radius = first.pressure * brush_size;
+        match cap {
+            CapStyle::Round => {
+                add_fan(&mut mesh, tolerance, first, radius, vector(1., 0.), 2. * PI)?
+            }
+            CapStyle::Butt | CapStyle::Square => {
+                add_cap(&mut mesh, cap, tolerance, first, radius, vector(1., 0.))?;
+                add_cap(&mut mesh, cap, tolerance, first, radius, vector(-1., 0.))?;
+            }
+        }
+        return Ok(mesh);
+    }
+
+    // a rib of one vertex on either side of every sample, joined up with the rib before it
+    let to_point = |sample: &StrokeElement| point(sample.x, sample.y);
+    let mut tangent = vector(1., 0.);
+    let mut last_rib = None;
+    for (i, sample) in samples.iter().enumerate() {
+        let at = to_point(sample);
+        let before = to_point(&samples[i.saturating_sub(1)]);
+        let after = to_point(&samples[(i + 1).min(samples.len() - 1)]);
+        tangent = unit_or(after - before, tangent);
+        let incoming = unit_or(at - before, tangent);
+        let outgoing = unit_or(after - at, tangent);
+        let reach = (at - before).length().min((after - at).length());
+
+        let radius = sample.pressure * brush_size;
+        let (arrive, leave) = add_rib(
+            &mut mesh,
+            style,
+            tolerance,
+            (at, radius, reach),
+            incoming,
+            outgoing,
+        )?;
+        if let Some((left, right)) = last_rib {
+            mesh.indices
+                .extend([left, right, arrive.0, right, arrive.1, arrive.0]);
+        }
+        last_rib = Some(leave);
+
+        if i == 0 {
+            add_cap(&mut mesh, cap, tolerance, sample, radius, left_of(outgoing))?;
+        }
+        if i == samples.len() - 1 {
+            add_cap(
+                &mut mesh,
+                cap,
+                tolerance,
+                sample,
+                radius,
+                -left_of(incoming),
+            )?;
+        }
+    }
+
+    Ok(mesh)
+}
+
+/// [`tessellate`] with a fixed tolerance and every vertex snapped onto a grid, so the same points
+/// give the same vertex and index buffers on every platform, no matter how its sin and cos round
+pub fn deterministic(
+    brush_size: f32,
+    style: LineStyle,
+    points: &[StrokeElement],
+) -> Result<MeshBuffer, TessellationError> {
+    let mut mesh = tessellate(DETERMINISTIC_TOLERANCE, brush_size, style, points)?;
+    snap(&mut mesh);
+    Ok(mesh)
+}
+
+/// round every vertex onto [`DETERMINISTIC_GRID`] and merge the ones that end up in the same place.
+/// the vertices keep their order otherwise
+pub fn snap(mesh: &mut MeshBuffer) {
+    let round = |x: f32| (x / DETERMINISTIC_GRID).round() * DETERMINISTIC_GRID + 0.;
+    for vertex in mesh.vertices.iter_mut() {
+        *vertex = point(round(vertex.x), round(vertex.y));
+    }
+    crate::mesh::dedup_buffer(mesh);
+}
+
+/// a circle every `spacing` along the stroke, as big as the brush or shrunk by the pressure. a
+/// spacing of 0 puts one on every point instead
+pub fn circles(
+    tolerance: f32,
+    brush_size: f32,
+    spacing: f32,
+    points: &[StrokeElement],
+    pressure: bool,
+) -> Result<MeshBuffer, TessellationError> {
+    narrow(circles_wide(
+        tolerance, brush_size, spacing, points, pressure,
+    )?)
+}
+
+/// [`circles`] without the limit of u16 indices
+pub fn circles_wide(
+    tolerance: f32,
+    brush_size: f32,
+    spacing: f32,
+    points: &[StrokeElement],
+    pressure: bool,
+) -> Result<WideMeshBuffer, TessellationError> {
+    let stamps = if spacing.is_finite() && spacing > 0. {
+        stamps(points, spacing)
+    } else {
+        points.to_vec()
+    };
+
+    let mut mesh = VertexBuffers::new();
+    for point in stamps.iter() {
+        let radius = if pressure {
+            point.pressure * brush_size
+        } else {
+            brush_size
+        };
+        add_fan(&mut mesh, tolerance, point, radius, vector(1., 0.), 2. * PI)?;
+    }
+
+    Ok(mesh)
+}
+
+/// points every `spacing` along the straight lines between `points`, starting at the first one,
+/// with the pressure in between interpolated
+pub fn stamps(points: &[StrokeElement], spacing: f32) -> Vec<StrokeElement> {
+    let Some(first) = points.first() else {
+        return Vec::new();
+    };
+
+    let mut stamps = vec![*first];
+    // how far along the current segment the next stamp goes
+    let mut next = spacing;
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let length = (b.x - a.x).hypot(b.y - a.y);
+        while next <= length {
+            let t = next / length;
+            stamps.push(StrokeElement {
+                x: a.x + (b.x - a.x) * t,
+                y: a.y + (b.y - a.y) * t,
+                pressure: a.pressure + (b.pressure - a.pressure) * t,
+            });
+            next += spacing;
+        }
+        next -= length;
+    }
+
+    stamps
+}
+
+/// points along the catmull-rom spline through `points`, close enough together that the ribbon
+/// looks smooth
+fn sample(tolerance: f32, brush_size: f32, points: &[StrokeElement]) -> Vec<StrokeElement> {
+    let Some(last) = points.last() else {
+        return Vec::new();
+    };
+
+    let step = brush_size.max(tolerance);
+    let mut samples = Vec::with_capacity(points.len());
+    for i in 0..points.len() - 1 {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(points.len() - 1)];
+
+        let length = (p2.x - p1.x).hypot(p2.y - p1.y);
+        let subdivisions = ((length / step).ceil() as usize).clamp(1, MAX_SUBDIVISIONS);
+        for j in 0..subdivisions {
+            let t = j as f32 / subdivisions as f32;
+            samples.push(StrokeElement {
+                x: catmull_rom(p0.x, p1.x, p2.x, p3.x, t),
+                y: catmull_rom(p0.y, p1.y, p2.y, p3.y, t),
+                pressure: p1.pressure + (p2.pressure - p1.pressure) * t,
+            });
+        }
+    }
+
+    samples.push(*last);
+    samples
+}
+
+/// `v` scaled to length 1, or `fallback` if it's too short to have a direction. points where the
+/// pen didn't move would otherwise make NaN vertices
+fn unit_or(v: Vector, fallback: Vector) -> Vector {
+    let length = v.length();
+    if length.is_finite() && length > MIN_LENGTH {
+        v / length
+    } else {
+        fallback
+    }
+}
+
+fn catmull_rom(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * ((2. * p1)
+        + (p2 - p0) * t
+        + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+        + (3. * p1 - p0 - 3. * p2 + p3) * t3)
+}
+
+/// the indices of the left and right vertices of a rib
+type Rib = (u32, u32);
+
+fn left_of(v: Vector) -> Vector {
+    vector(-v.y, v.x)
+}
+
+/// a rib through `at`, `radius` out on either side, where the line comes in heading `incoming` and
+/// leaves heading `outgoing`, which are both normalized. `reach` is how far it is to the closest
+/// sample on either side.
+///
+/// gives the (left, right) vertices the line arrives at and the ones it leaves from. they're the
+/// same unless it turns too sharply to stay within `tolerance` with one rib, when the inside of the
+/// turn gets one vertex where the sides cross and the outside gets a join
+fn add_rib(
+    mesh: &mut WideMeshBuffer,
+    style: LineStyle,
+    tolerance: f32,
+    (at, radius, reach): (Point, f32, f32),
+    incoming: Vector,
+    outgoing: Vector,
+) -> Result<(Rib, Rib), TessellationError> {
+    let (into, out_of) = (left_of(incoming), left_of(outgoing));
+    let bisector = unit_or(into + out_of, incoming);
+    // how much longer than the radius the sides are at the bisector, 1 / cos of half the turn
+    let half_cos = ((1. + incoming.dot(outgoing)) / 2.).max(0.).sqrt();
+    let miter = 1. / half_cos;
+
+    if radius * (miter - 1.) <= tolerance {
+        let left = push(mesh, at + bisector * radius * miter)?;
+        let right = push(mesh, at - bisector * radius * miter)?;
+        return Ok(((left, right), (left, right)));
+    }
+
+    // which of the sides is on the outside of the turn, and the inside doesn't go past the samples
+    // on either side, where it would fold back over the ribbon
+    let side = if incoming.cross(outgoing) > 0. {
+        -1.
+    } else {
+        1.
+    };
+    let inner_length = (radius * miter).min(radius.hypot(reach));
+    let inner = push(mesh, at - bisector * side * inner_length)?;
+    let pair = |outer, inner| {
+        if side > 0. {
+            (outer, inner)
+        } else {
+            (inner, outer)
+        }
+    };
+    let wedge = |from, to| {
+        if side > 0. {
+            [inner, to, from]
+        } else {
+            [inner, from, to]
+        }
+    };
+
+    match style.join {
+        JoinStyle::Miter if miter <= style.miter_limit => {
+            let outer = push(mesh, at + bisector * side * radius * miter)?;
+            Ok((pair(outer, inner), pair(outer, inner)))
+        }
+
+        JoinStyle::Round => {
+            let from = push(mesh, at + into * side * radius)?;
+            let start = into * side;
+            let sweep = into.cross(out_of).atan2(into.dot(out_of));
+            let segments = arc_segments(tolerance, radius, sweep.abs()).clamp(1, 64);
+            let start_angle = start.y.atan2(start.x);
+
+            let mut last = from;
+            for i in 1..=segments {
+                let angle = start_angle + sweep * i as f32 / segments as f32;
+                let next = push(mesh, at + vector(angle.cos(), angle.sin()) * radius)?;
+                mesh.indices.extend(wedge(last, next));
+                last = next;
+            }
+            Ok((pair(from, inner), pair(last, inner)))
+        }
+
+        // and miters that would stick out too far
+        JoinStyle::Miter | JoinStyle::Bevel => {
+            let from = push(mesh, at + into * side * radius)?;
+            let to = push(mesh, at + out_of * side * radius)?;
+            mesh.indices.extend(wedge(from, to));
+            Ok((pair(from, inner), pair(to, inner)))
+        }
+    }
+}
+
+/// how many pieces an arc sweeping `sweep` radians needs to stay within `tolerance`
+fn arc_segments(tolerance: f32, radius: f32, sweep: f32) -> usize {
+    // the largest angle where the chord stays within tolerance of the arc
+    let max_angle = 2. * (1. - (tolerance / radius).min(1.)).acos();
+    (sweep / max_angle.max(f32::EPSILON)).ceil() as usize
+}
+
+/// the end of a line `radius` wide on either side of `center`, from `start` around to the other
+/// side counter-clockwise. butt caps stop right there, square ones stick out as far as the line is
+/// wide, and round ones are a half circle
+fn add_cap(
+    mesh: &mut WideMeshBuffer,
+    cap: CapStyle,
+    tolerance: f32,
+    center: &StrokeElement,
+    radius: f32,
+    start: Vector,
+) -> Result<(), TessellationError> {
+    if radius <= 0. {
+        return Ok(());
+    }
+
+    match cap {
+        CapStyle::Butt => Ok(()),
+        CapStyle::Round => add_fan(mesh, tolerance, center, radius, start, PI),
+        CapStyle::Square => {
+            let center = point(center.x, center.y);
+            let out = vector(-start.y, start.x) * radius;
+            let side = start * radius;
+            let first = push(mesh, center + side)?;
+            push(mesh, center + side + out)?;
+            push(mesh, center - side + out)?;
+            push(mesh, center - side)?;
+            mesh.indices
+                .extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+            Ok(())
+        }
+    }
+}
+
+/// a fan of triangles around `center`, sweeping `sweep` radians counter-clockwise from `start`
+fn add_fan(
+    mesh: &mut WideMeshBuffer,
+    tolerance: f32,
+    center: &StrokeElement,
+    radius: f32,
+    start: Vector,
+    sweep: f32,
+) -> Result<(), TessellationError> {
+    if radius <= 0. {
+        return Ok(());
+    }
+
+    let segments = arc_segments(tolerance, radius, sweep).clamp(2, 64);
+
+    let center_point = point(center.x, center.y);
+    let middle = push(mesh, center_point)?;
+    let start_angle = start.y.atan2(start.x);
+    for i in 0..=segments {
+        let angle = start_angle + sweep * i as f32 / segments as f32;
+        let index = push(
+            mesh,
+            center_point + vector(angle.cos(), angle.sin()) * radius,
+        )?;
+        if i > 0 {
+            mesh.indices.extend([middle, index - 1, index]);
+        }
+    }
+
+    Ok(())
+}
+
+fn push(mesh: &mut WideMeshBuffer, vertex: Point) -> Result<u32, TessellationError> {
+    let index = u32::try_from(mesh.vertices.len())
+        .map_err(|_| TessellationError::GeometryBuilder(GeometryBuilderError::TooManyVertices))?;
+    mesh.vertices.push(vertex);
+    Ok(index)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lyon::lyon_tessellation::{LineCap, LineJoin, StrokeOptions, StrokeTessellator};
+
+    const BRUSH_SIZE: f32 = 0.1;
+
+    fn wave() -> Vec<StrokeElement> {
+        (0..100)
+            .map(|i| {
+                let t = i as f32 / 10.;
+                StrokeElement {
+                    x: t,
+                    y: t.sin(),
+                    pressure: 0.5 + t.cos() / 4.,
+                }
+            })
+            .collect()
+    }
+
+    fn bounds(mesh: &MeshBuffer) -> (Point, Point) {
+        mesh.vertices.iter().fold(
+            (
+                point(f32::INFINITY, f32::INFINITY),
+                point(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), vertex| (min.min(*vertex), max.max(*vertex)),
+        )
+    }
+
+    // distance from the vertex to the closest part of the line through the points
+    fn distance_to_line(vertex: Point, points: &[StrokeElement]) -> f32 {
+        points
+            .windows(2)
+            .map(|pair| {
+                let a = point(pair[0].x, pair[0].y);
+                let b = point(pair[1].x, pair[1].y);
+                let ab = b - a;
+                let t = ((vertex - a).dot(ab) / ab.square_length()).clamp(0., 1.);
+                (a + ab * t - vertex).length()
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn valid_mesh() {
+        let points = wave();
+        let mesh = tessellate(0.001, BRUSH_SIZE, LineStyle::default(), &points).unwrap();
+
+        assert!(!mesh.vertices.is_empty());
+        assert!(mesh.indices.len() >= 3);
+        assert_eq!(mesh.indices.len() % 3, 0);
+        assert!(mesh
+            .indices
+            .iter()
+            .all(|index| (*index as usize) < mesh.vertices.len()));
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.x.is_finite() && vertex.y.is_finite()));
+
+        // a single point is a dot, and no points is nothing
+        let dot = tessellate(0.001, BRUSH_SIZE, LineStyle::default(), &points[..1]).unwrap();
+        assert!(dot.indices.len() >= 3);
+        assert!(tessellate(0.001, BRUSH_SIZE, LineStyle::default(), &[])
+            .unwrap()
+            .indices
+            .is_empty());
+    }
+
+    #[test]
+    fn close_to_lyon() {
+        let points = wave();
+        let options = StrokeOptions::default()
+            .with_line_cap(LineCap::Round)
+            .with_line_join(LineJoin::Round)
+            .with_tolerance(0.001)
+            .with_variable_line_width(0);
+
+        let lyon = crate::tess::tessellate_raw(
+            &mut StrokeTessellator::new(),
+            &options,
+            BRUSH_SIZE,
+            &points,
+            false,
+        )
+        .unwrap();
+        let native = tessellate(0.001, BRUSH_SIZE, LineStyle::default(), &points).unwrap();
+
+        let (lyon_min, lyon_max) = bounds(&lyon);
+        let (native_min, native_max) = bounds(&native);
+        let slop = BRUSH_SIZE / 10.;
+        assert!((lyon_min - native_min).length() < slop);
+        assert!((lyon_max - native_max).length() < slop);
+
+        // nothing sticks out further than the widest part of the brush
+        let widest = points.iter().map(|p| p.pressure).fold(0., f32::max) * BRUSH_SIZE;
+        assert!(native
+            .vertices
+            .iter()
+            .all(|vertex| distance_to_line(*vertex, &points) <= widest + slop));
+    }
+
+    #[test]
+    fn caps_like_lyon() {
+        let at = |x: f32, y: f32| StrokeElement { x, y, pressure: 1. };
+        let line = [at(0., 0.), at(1., 0.)];
+
+        for cap in [CapStyle::Round, CapStyle::Butt, CapStyle::Square] {
+            let options = StrokeOptions::default()
+                .with_line_cap(cap.into())
+                .with_tolerance(0.001)
+                .with_variable_line_width(0);
+            let lyon = crate::tess::tessellate_raw(
+                &mut StrokeTessellator::new(),
+                &options,
+                BRUSH_SIZE,
+                &line,
+                false,
+            )
+            .unwrap();
+            let style = LineStyle {
+                cap,
+                ..LineStyle::default()
+            };
+            let native = tessellate(0.001, BRUSH_SIZE, style, &line).unwrap();
+
+            let (lyon_min, lyon_max) = bounds(&lyon);
+            let (native_min, native_max) = bounds(&native);
+            assert!((lyon_min - native_min).length() < 1e-4, "{cap:?}");
+            assert!((lyon_max - native_max).length() < 1e-4, "{cap:?}");
+        }
+
+        // square ones go all the way out to the corners, round ones don't
+        let corner = point(1. + BRUSH_SIZE, BRUSH_SIZE);
+        let capped = |cap| LineStyle {
+            cap,
+            ..LineStyle::default()
+        };
+        let square = tessellate(0.001, BRUSH_SIZE, capped(CapStyle::Square), &line).unwrap();
+        assert!(square.vertices.contains(&corner));
+        let round = tessellate(0.001, BRUSH_SIZE, LineStyle::default(), &line).unwrap();
+        assert!(round.vertices.iter().all(|v| (*v - corner).length() > 0.02));
+
+        // a dot with no ends is nothing
+        let dot = |cap| tessellate(0.001, BRUSH_SIZE, capped(cap), &line[..1]).unwrap();
+        assert!(dot(CapStyle::Butt).indices.is_empty());
+        assert_eq!(dot(CapStyle::Square).indices.len(), 12);
+        let (min, max) = bounds(&dot(CapStyle::Square));
+        assert_eq!((min, max), (point(-0.1, -0.1), point(0.1, 0.1)));
+    }
+
+    #[test]
+    fn ribbon_starts_after_cap() {
+        let line = [(0., 0.), (1., 0.)].map(|(x, y)| StrokeElement { x, y, pressure: 1. });
+        let second = sample(0.001, BRUSH_SIZE, &line)[1];
+
+        for cap in [CapStyle::Round, CapStyle::Butt, CapStyle::Square] {
+            let style = LineStyle {
+                cap,
+                ..LineStyle::default()
+            };
+            let mesh = tessellate(0.001, BRUSH_SIZE, style, &line).unwrap();
+
+            // the start cap's triangles come first, then the first piece of the ribbon
+            let mut start_cap = VertexBuffers::new();
+            add_cap(
+                &mut start_cap,
+                cap,
+                0.001,
+                &line[0],
+                BRUSH_SIZE,
+                vector(0., 1.),
+            )
+            .unwrap();
+            let quad = &mesh.indices[start_cap.indices.len()..][..6];
+
+            // which goes from the first sample to the second, not back into the cap
+            for index in quad {
+                let vertex = mesh.vertices[*index as usize];
+                assert!(vertex.x > -1e-6 && vertex.x < second.x + 1e-6, "{cap:?}");
+                assert!((vertex.y.abs() - BRUSH_SIZE).abs() < 1e-6, "{cap:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn joins_at_corners() {
+        // an L with a brush much wider than the samples are apart, so it turns a lot between them
+        let points = [(0., 0.), (2., 0.), (2., -2.)]
+            .map(|(x, y)| StrokeElement { x, y, pressure: 1. })
+            .to_vec();
+        let brush_size = 0.5;
+        let joined = |join| {
+            let style = LineStyle {
+                join,
+                ..LineStyle::default()
+            };
+            tessellate(0.001, brush_size, style, &points).unwrap()
+        };
+
+        // the triangles of a ribbon that folds over itself at a corner go clockwise
+        let area = |mesh: &MeshBuffer, triangle: &[u16]| {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+            (b - a).cross(c - a) / 2.
+        };
+        let (miter, round, bevel) = (
+            joined(JoinStyle::Miter),
+            joined(JoinStyle::Round),
+            joined(JoinStyle::Bevel),
+        );
+        for mesh in [&miter, &round, &bevel] {
+            assert!(mesh
+                .indices
+                .chunks(3)
+                .all(|triangle| area(mesh, triangle) > -1e-6));
+        }
+
+        // miters fill out the corner the most and bevels cut it off
+        let covered = |mesh: &MeshBuffer| {
+            mesh.indices
+                .chunks(3)
+                .map(|triangle| area(mesh, triangle))
+                .sum::<f32>()
+        };
+        assert!(covered(&miter) > covered(&round) && covered(&round) > covered(&bevel));
+
+        // and only miters stick out past the width of the brush
+        let outside = |mesh: &MeshBuffer| {
+            mesh.vertices
+                .iter()
+                .map(|vertex| distance_to_line(*vertex, &sample(0.001, brush_size, &points)))
+                .fold(0., f32::max)
+        };
+        let miter = outside(&miter);
+        assert!(miter > brush_size * 1.05);
+        assert!(outside(&round) < brush_size * 1.01 && outside(&bevel) < brush_size * 1.01);
+        assert!(miter <= brush_size * StrokeOptions::DEFAULT_MITER_LIMIT);
+    }
+
+    #[test]
+    fn coincident_points() {
+        let at = |x: f32, y: f32| StrokeElement { x, y, pressure: 1. };
+
+        let strokes = [
+            // the pen never moved
+            vec![at(1., 1.); 5],
+            // barely moved
+            vec![at(1., 1.), at(1. + 1e-9, 1.), at(1., 1. + 1e-9)],
+            // went out and came straight back
+            vec![at(0., 0.), at(1., 0.), at(0., 0.), at(1., 0.)],
+            // stopped for a while in the middle
+            vec![at(0., 0.), at(1., 1.), at(1., 1.), at(1., 1.), at(2., 0.)],
+        ];
+
+        for points in strokes {
+            let mesh = tessellate(0.001, BRUSH_SIZE, LineStyle::default(), &points).unwrap();
+            assert!(mesh.indices.len() >= 3, "{:?}", points);
+            assert!(
+                mesh.vertices
+                    .iter()
+                    .all(|vertex| vertex.x.is_finite() && vertex.y.is_finite()),
+                "{:?}",
+                points
+            );
+        }
+
+        assert_eq!(unit_or(vector(0., 0.), vector(0., 1.)), vector(0., 1.));
+        assert_eq!(unit_or(vector(3., 4.), vector(0., 1.)), vector(0.6, 0.8));
+    }
+
+    #[test]
+    fn stamps_spaced_by_arc_length() {
+        // an L, 6 across and then 4 down, with the corner in the middle of a segment's worth
+        let points = [(0., 0., 0.), (2.5, 0., 0.25), (6., 0., 0.6), (6., -4., 1.)]
+            .map(|(x, y, pressure)| StrokeElement { x, y, pressure })
+            .to_vec();
+        let (length, spacing) = (10., 0.25);
+
+        let stamps = stamps(&points, spacing);
+        // plus the one at the very start
+        assert_eq!(stamps.len(), (length / spacing) as usize + 1);
+        assert_eq!(stamps.first(), points.first());
+        assert_eq!(stamps.last(), points.last());
+
+        // the corner lands on a stamp, so they're all the same distance apart
+        assert!(stamps.windows(2).all(|pair| {
+            ((pair[1].x - pair[0].x).hypot(pair[1].y - pair[0].y) - spacing).abs() < 1e-5
+        }));
+
+        // the pressure goes up a tenth every unit, like the distance
+        assert!(stamps
+            .iter()
+            .enumerate()
+            .all(|(i, stamp)| (stamp.pressure - i as f32 * spacing / length).abs() < 1e-5));
+
+        // and each circle is sized by its pressure, from nothing at the start to the whole brush at
+        // the end
+        let brush_size = 0.1;
+        let (min, max) = bounds(&circles(0.001, brush_size, spacing, &points, true).unwrap());
+        assert!(min.x > 0.24 && (max.y - 0.06).abs() < 0.01);
+        assert!((max.x - 6.1).abs() < 0.01 && (min.y + 4.1).abs() < 0.01);
+
+        assert!(super::stamps(&[], spacing).is_empty());
+    }
+
+    #[test]
+    fn deterministic_twice() {
+        let bytes = |mesh: &MeshBuffer| {
+            let vertices = mesh
+                .vertices
+                .iter()
+                .flat_map(|vertex| [vertex.x.to_bits(), vertex.y.to_bits()])
+                .collect::<Vec<_>>();
+            (vertices, mesh.indices.clone())
+        };
+
+        let points = wave();
+        let first = deterministic(BRUSH_SIZE, LineStyle::default(), &points).unwrap();
+        let second = deterministic(BRUSH_SIZE, LineStyle::default(), &points).unwrap();
+        assert!(!first.indices.is_empty());
+        assert_eq!(bytes(&first), bytes(&second));
+        assert!(first.vertices.iter().all(|vertex| {
+            (vertex.x / DETERMINISTIC_GRID).fract() == 0.
+                && (vertex.y / DETERMINISTIC_GRID).fract() == 0.
+        }));
+
+        // the stroke options' tolerance doesn't come into it
+        let mesh = |tolerance| {
+            crate::tess::tessellate(
+                crate::tess::TessellatorKind::Deterministic,
+                &mut StrokeTessellator::new(),
+                &StrokeOptions::default()
+                    .with_line_cap(LineCap::Round)
+                    .with_tolerance(tolerance),
+                BRUSH_SIZE,
+                &points,
+                false,
+            )
+            .unwrap()
+        };
+        assert_eq!(bytes(&mesh(0.1)), bytes(&first));
+        assert_eq!(bytes(&mesh(0.0001)), bytes(&first));
+    }
+}
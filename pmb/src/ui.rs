@@ -1,39 +1,65 @@
 use winit::event::MouseButton;
 
 use crate::{
-    config::Config,
+    clip::ClipRect,
+    config::{Config, FingerPolicy},
+    eraser::EraserMode,
     error::{ErrorKind, PmbError, PmbErrorExt},
-    s, CoordinateSystem, Sketch, StrokeBackend, Tool,
+    graphics::{AntiAliasing, RendererKind},
+    grid::GridConfig,
+    s,
+    select::SelectMode,
+    stroke::StrokeStyle,
+    tess::{CapStyle, JoinStyle, TessellatorKind},
+    CoordinateSystem, Sketch, StrokeBackend, Tool,
 };
 use std::path::{Path, PathBuf};
 
+pub mod dialog;
 pub mod undo;
 pub mod widget;
 
-fn prompt_migrate() -> rfd::MessageDialogResult {
-    rfd::MessageDialog::new()
-        .set_title(s!(&MboxTitleMigrateWarning))
-        .set_buttons(rfd::MessageButtons::YesNo)
-        .set_description(s!(&MboxMessageMigrateWarning))
-        .show()
+use dialog::{Answer, Buttons, Level};
+
+fn prompt_migrate() -> Answer {
+    dialog::message(
+        Level::Info,
+        s!(&MboxTitleMigrateWarning),
+        s!(&MboxMessageMigrateWarning),
+        Buttons::YesNo,
+    )
+}
+
+/// whether to open the work saved when the app crashed last time
+pub fn prompt_recover() -> bool {
+    dialog::message(
+        Level::Info,
+        s!(&MboxTitleRecover),
+        s!(&MboxMessageRecover),
+        Buttons::YesNo,
+    ) == Answer::Yes
+}
+
+pub fn prompt_restore_autosave(path: &Path) -> bool {
+    dialog::message(
+        Level::Info,
+        s!(&MboxTitleRestoreAutosave),
+        &format!("{}\n{}", s!(&MboxMessageRestoreAutosave), path.display()),
+        Buttons::YesNo,
+    ) == Answer::Yes
 }
 
-pub fn error(text: &str) -> rfd::MessageDialogResult {
-    rfd::MessageDialog::new()
-        .set_title(s!(&MboxTitleError))
-        .set_description(text)
-        .set_level(rfd::MessageLevel::Error)
-        .set_buttons(rfd::MessageButtons::Ok)
-        .show()
+pub fn error(text: &str) -> Answer {
+    dialog::message(Level::Error, s!(&MboxTitleError), text, Buttons::Ok)
 }
 
-pub fn ask_to_save(why: &str) -> rfd::MessageDialogResult {
-    rfd::MessageDialog::new()
-        .set_level(rfd::MessageLevel::Warning)
-        .set_title(s!(&MboxTitleUnsavedChanges))
-        .set_description(why)
-        .set_buttons(rfd::MessageButtons::YesNoCancel)
-        .show()
+pub fn ask_to_save(why: &str) -> Answer {
+    dialog::message(
+        Level::Warning,
+        s!(&MboxTitleUnsavedChanges),
+        why,
+        Buttons::YesNoCancel,
+    )
 }
 
 pub fn save_dialog(title: &str, filename: Option<&Path>) -> Option<PathBuf> {
@@ -42,18 +68,11 @@ pub fn save_dialog(title: &str, filename: Option<&Path>) -> Option<PathBuf> {
         .and_then(|os| os.to_str())
         .unwrap_or("");
 
-    rfd::FileDialog::new()
-        .set_title(title)
-        .add_filter("PMB", &["pmb"])
-        .set_file_name(filename)
-        .save_file()
+    dialog::save_file(title, ("PMB", &["pmb"]), filename)
 }
 
 pub fn open_dialog() -> Option<PathBuf> {
-    rfd::FileDialog::new()
-        .set_title(s!(&MboxTitleOpen))
-        .add_filter("PMB", &["pmb"])
-        .pick_file()
+    dialog::open_file(s!(&MboxTitleOpen), ("PMB", &["pmb"]))
 }
 
 fn settings_window<S: StrokeBackend>(
@@ -82,6 +101,9 @@ fn settings_window<S: StrokeBackend>(
                                     Tool::Pen => s!(&RadioLabelToolPen),
                                     Tool::Eraser => s!(&RadioLabelToolEraser),
                                     Tool::Pan => s!(&RadioLabelToolPan),
+                                    Tool::Ruler => s!(&RadioLabelToolRuler),
+                                    Tool::Eyedropper => s!(&RadioLabelToolEyedropper),
+                                    Tool::Select => s!(&RadioLabelToolSelect),
                                 })
                                 .show_ui(ui, |ui| {
                                     ui.selectable_value(
@@ -99,16 +121,71 @@ fn settings_window<S: StrokeBackend>(
                                         Tool::Pan,
                                         s!(&RadioLabelToolPan),
                                     );
+                                    ui.selectable_value(
+                                        &mut config.[<tool_for_gesture_ $num>],
+                                        Tool::Ruler,
+                                        s!(&RadioLabelToolRuler),
+                                    );
+                                    ui.selectable_value(
+                                        &mut config.[<tool_for_gesture_ $num>],
+                                        Tool::Eyedropper,
+                                        s!(&RadioLabelToolEyedropper),
+                                    );
+                                    ui.selectable_value(
+                                        &mut config.[<tool_for_gesture_ $num>],
+                                        Tool::Select,
+                                        s!(&RadioLabelToolSelect),
+                                    );
                                 });
                             ui.end_row();
                         }
                     };
                 }
 
+                ui.label(s!(&ConfigLabelToolForGesture1));
+                ComboBox::new("finger policy", "")
+                    .selected_text(match config.finger_policy {
+                        FingerPolicy::UseTool => s!(&ConfigOptionFingerUseTool),
+                        FingerPolicy::Draw => s!(&RadioLabelToolPen),
+                        FingerPolicy::Pan => s!(&RadioLabelToolPan),
+                        FingerPolicy::Ignore => s!(&ConfigOptionFingerIgnore),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut config.finger_policy,
+                            FingerPolicy::UseTool,
+                            s!(&ConfigOptionFingerUseTool),
+                        );
+                        ui.selectable_value(
+                            &mut config.finger_policy,
+                            FingerPolicy::Draw,
+                            s!(&RadioLabelToolPen),
+                        );
+                        ui.selectable_value(
+                            &mut config.finger_policy,
+                            FingerPolicy::Pan,
+                            s!(&RadioLabelToolPan),
+                        );
+                        ui.selectable_value(
+                            &mut config.finger_policy,
+                            FingerPolicy::Ignore,
+                            s!(&ConfigOptionFingerIgnore),
+                        );
+                    });
+                ui.end_row();
+
                 tfg!(2);
                 tfg!(3);
                 tfg!(4);
 
+                ui.label(s!(&ConfigLabelTwoFingerTapUndo));
+                ui.checkbox(&mut config.two_finger_tap_undo, "");
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelPalmRejectionMs));
+                ui.add(Slider::new(&mut config.palm_rejection_ms, 0.0..=2000.0));
+                ui.end_row();
+
                 ui.label(s!(&ConfigLabelUseMouseForPen));
                 ui.checkbox(&mut config.use_mouse_for_pen, "");
                 ui.end_row();
@@ -117,6 +194,234 @@ fn settings_window<S: StrokeBackend>(
                 ui.checkbox(&mut config.stylus_may_be_inverted, "");
                 ui.end_row();
 
+                ui.label(s!(&ConfigLabelEraserMode));
+                ComboBox::new("eraser mode", "")
+                    .selected_text(match config.eraser_mode {
+                        EraserMode::WholeStroke => s!(&ConfigOptionEraserModeWholeStroke),
+                        EraserMode::PointSplit => s!(&ConfigOptionEraserModePointSplit),
+                        EraserMode::Rewind => s!(&ConfigOptionEraserModeRewind),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut config.eraser_mode,
+                            EraserMode::WholeStroke,
+                            s!(&ConfigOptionEraserModeWholeStroke),
+                        );
+                        ui.selectable_value(
+                            &mut config.eraser_mode,
+                            EraserMode::PointSplit,
+                            s!(&ConfigOptionEraserModePointSplit),
+                        );
+                        ui.selectable_value(
+                            &mut config.eraser_mode,
+                            EraserMode::Rewind,
+                            s!(&ConfigOptionEraserModeRewind),
+                        );
+                    });
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelSelectMode));
+                ComboBox::new("select mode", "")
+                    .selected_text(match config.select_mode {
+                        SelectMode::Rectangle => s!(&ConfigOptionSelectModeRectangle),
+                        SelectMode::Lasso => s!(&ConfigOptionSelectModeLasso),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut config.select_mode,
+                            SelectMode::Rectangle,
+                            s!(&ConfigOptionSelectModeRectangle),
+                        );
+                        ui.selectable_value(
+                            &mut config.select_mode,
+                            SelectMode::Lasso,
+                            s!(&ConfigOptionSelectModeLasso),
+                        );
+                    });
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelTessellator));
+                ComboBox::new("tessellator", "")
+                    .selected_text(match config.tessellator {
+                        TessellatorKind::Lyon => s!(&ConfigOptionTessellatorLyon),
+                        TessellatorKind::Native => s!(&ConfigOptionTessellatorNative),
+                        TessellatorKind::Deterministic => {
+                            s!(&ConfigOptionTessellatorDeterministic)
+                        }
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut config.tessellator,
+                            TessellatorKind::Lyon,
+                            s!(&ConfigOptionTessellatorLyon),
+                        );
+                        ui.selectable_value(
+                            &mut config.tessellator,
+                            TessellatorKind::Native,
+                            s!(&ConfigOptionTessellatorNative),
+                        );
+                        ui.selectable_value(
+                            &mut config.tessellator,
+                            TessellatorKind::Deterministic,
+                            s!(&ConfigOptionTessellatorDeterministic),
+                        );
+                    });
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelMeshPixelTolerance));
+                ui.add(
+                    Slider::new(&mut config.mesh_pixel_tolerance, 0.005..=0.5).logarithmic(true),
+                );
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelLineCap));
+                ComboBox::new("line_cap", "")
+                    .selected_text(match config.line_cap {
+                        CapStyle::Round => s!(&ConfigOptionLineCapRound),
+                        CapStyle::Butt => s!(&ConfigOptionLineCapButt),
+                        CapStyle::Square => s!(&ConfigOptionLineCapSquare),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut config.line_cap,
+                            CapStyle::Round,
+                            s!(&ConfigOptionLineCapRound),
+                        );
+                        ui.selectable_value(
+                            &mut config.line_cap,
+                            CapStyle::Butt,
+                            s!(&ConfigOptionLineCapButt),
+                        );
+                        ui.selectable_value(
+                            &mut config.line_cap,
+                            CapStyle::Square,
+                            s!(&ConfigOptionLineCapSquare),
+                        );
+                    });
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelLineJoin));
+                ComboBox::new("line_join", "")
+                    .selected_text(match config.line_join {
+                        JoinStyle::Round => s!(&ConfigOptionLineJoinRound),
+                        JoinStyle::Miter => s!(&ConfigOptionLineJoinMiter),
+                        JoinStyle::Bevel => s!(&ConfigOptionLineJoinBevel),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut config.line_join,
+                            JoinStyle::Round,
+                            s!(&ConfigOptionLineJoinRound),
+                        );
+                        ui.selectable_value(
+                            &mut config.line_join,
+                            JoinStyle::Miter,
+                            s!(&ConfigOptionLineJoinMiter),
+                        );
+                        ui.selectable_value(
+                            &mut config.line_join,
+                            JoinStyle::Bevel,
+                            s!(&ConfigOptionLineJoinBevel),
+                        );
+                    });
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelDefaultStyle));
+                ComboBox::new("default_style", "")
+                    .selected_text(match config.default_style {
+                        StrokeStyle::Lines => s!(&ConfigOptionStyleLines),
+                        StrokeStyle::Circles => s!(&ConfigOptionStyleCircles),
+                        StrokeStyle::CirclesPressure => s!(&ConfigOptionStyleCirclesPressure),
+                        StrokeStyle::Points => s!(&ConfigOptionStylePoints),
+                        StrokeStyle::Spline => s!(&ConfigOptionStyleSpline),
+                    })
+                    .show_ui(ui, |ui| {
+                        for (style, label) in [
+                            (StrokeStyle::Lines, s!(&ConfigOptionStyleLines)),
+                            (StrokeStyle::Circles, s!(&ConfigOptionStyleCircles)),
+                            (
+                                StrokeStyle::CirclesPressure,
+                                s!(&ConfigOptionStyleCirclesPressure),
+                            ),
+                            (StrokeStyle::Points, s!(&ConfigOptionStylePoints)),
+                            (StrokeStyle::Spline, s!(&ConfigOptionStyleSpline)),
+                        ] {
+                            ui.selectable_value(&mut config.default_style, style, label);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelUseIndividualStyle));
+                ui.checkbox(&mut config.use_individual_style, "");
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelStampSpacing));
+                ui.add(Slider::new(&mut config.stamp_spacing, 0.0..=4.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelLazyBrushRadius));
+                ui.add(Slider::new(&mut config.lazy_brush_radius, 0.0..=100.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelPressureCurve));
+                ui.horizontal(|ui| {
+                    for control in config.pressure_curve.iter_mut() {
+                        ui.add(DragValue::new(control).speed(0.01).clamp_range(0.0..=1.0));
+                    }
+                });
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelPressureMin));
+                ui.add(Slider::new(&mut config.pressure_min, 0.0..=1.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelPressureMax));
+                ui.add(Slider::new(&mut config.pressure_max, 0.0..=1.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelAutoStraighten));
+                ui.checkbox(&mut config.auto_straighten, "");
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelAutoShapes));
+                ui.checkbox(&mut config.auto_shapes, "");
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelShapeTolerance));
+                ui.add(Slider::new(&mut config.shape_tolerance, 0.0..=0.25));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelPredictionMs));
+                ui.add(Slider::new(&mut config.prediction_ms, 0.0..=50.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelResizeDebounceMs));
+                ui.add(Slider::new(&mut config.resize_debounce_ms, 0.0..=500.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelMaxFps));
+                ui.add(Slider::new(&mut config.max_fps, 0.0..=240.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelStrokeIdleMs));
+                ui.add(Slider::new(&mut config.stroke_idle_ms, 0.0..=5000.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelAutosaveSecs));
+                ui.add(Slider::new(&mut config.autosave_secs, 0.0..=600.0));
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelRequireMovementToCommit));
+                ui.checkbox(&mut config.require_movement_to_commit, "");
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelCommitMovementPx));
+                ui.add_enabled(
+                    config.require_movement_to_commit,
+                    Slider::new(&mut config.commit_movement_px, 0.0..=20.0),
+                );
+                ui.end_row();
+
                 ui.label(s!(&ConfigLabelPrimaryMouseButton));
                 ComboBox::new("primary button", "")
                     .selected_text(match config.primary_button {
@@ -164,10 +469,124 @@ fn settings_window<S: StrokeBackend>(
                 }
                 ui.end_row();
 
+                ui.label(s!(&ConfigLabelAntiAliasing));
+                ComboBox::new("anti-aliasing", "")
+                    .selected_text(match config.anti_aliasing {
+                        AntiAliasing::None => s!(&ConfigOptionAntiAliasingNone),
+                        AntiAliasing::Smaa => s!(&ConfigOptionAntiAliasingSmaa),
+                        AntiAliasing::Msaa => s!(&ConfigOptionAntiAliasingMsaa),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut config.anti_aliasing,
+                            AntiAliasing::None,
+                            s!(&ConfigOptionAntiAliasingNone),
+                        );
+                        ui.selectable_value(
+                            &mut config.anti_aliasing,
+                            AntiAliasing::Smaa,
+                            s!(&ConfigOptionAntiAliasingSmaa),
+                        );
+                        ui.selectable_value(
+                            &mut config.anti_aliasing,
+                            AntiAliasing::Msaa,
+                            s!(&ConfigOptionAntiAliasingMsaa),
+                        );
+                    });
+                ui.end_row();
+
+                ui.label(s!(&ConfigLabelRenderer));
+                ComboBox::new("renderer", "")
+                    .selected_text(match config.renderer {
+                        RendererKind::Auto => s!(&ConfigOptionRendererAuto),
+                        RendererKind::Wgpu => s!(&ConfigOptionRendererWgpu),
+                        RendererKind::Gl => s!(&ConfigOptionRendererGl),
+                        RendererKind::Software => s!(&ConfigOptionRendererSoftware),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut config.renderer,
+                            RendererKind::Auto,
+                            s!(&ConfigOptionRendererAuto),
+                        );
+                        ui.selectable_value(
+                            &mut config.renderer,
+                            RendererKind::Wgpu,
+                            s!(&ConfigOptionRendererWgpu),
+                        );
+                        ui.selectable_value(
+                            &mut config.renderer,
+                            RendererKind::Gl,
+                            s!(&ConfigOptionRendererGl),
+                        );
+                        ui.selectable_value(
+                            &mut config.renderer,
+                            RendererKind::Software,
+                            s!(&ConfigOptionRendererSoftware),
+                        );
+                    });
+                ui.end_row();
+
                 ui.label(s!(&ConfigLabelStartMaximized));
                 ui.checkbox(&mut config.window_start_maximized, "");
             });
 
+            ui.separator();
+
+            Grid::new("ruler settings").show(ui, |ui| {
+                ui.label(s!(&ConfigLabelRulerCalibrated));
+                let mut calibrated = config.ruler_units_per_stroke_unit.is_some();
+                ui.checkbox(&mut calibrated, "");
+                ui.end_row();
+
+                if calibrated {
+                    let factor = config.ruler_units_per_stroke_unit.get_or_insert(1.0);
+                    ui.label(s!(&ConfigLabelRulerUnitsPerStrokeUnit));
+                    ui.add(
+                        DragValue::new(factor)
+                            .speed(0.01)
+                            .clamp_range(0.0001..=f32::MAX),
+                    );
+                    ui.end_row();
+
+                    ui.label(s!(&ConfigLabelRulerUnitName));
+                    ui.text_edit_singleline(&mut config.ruler_unit_name);
+                    ui.end_row();
+                } else {
+                    config.ruler_units_per_stroke_unit = None;
+                }
+            });
+
+            ui.separator();
+
+            Grid::new("grid settings").show(ui, |ui| {
+                ui.label(s!(&ConfigLabelShowGrid));
+                ui.checkbox(&mut config.grid.show, "");
+                ui.end_row();
+
+                if config.grid.show {
+                    ui.label(s!(&ConfigLabelGridSpacing));
+                    ui.add(
+                        DragValue::new(&mut config.grid.spacing)
+                            .speed(0.01)
+                            .clamp_range(0.01..=f32::MAX),
+                    );
+                    ui.end_row();
+
+                    ui.label(s!(&ConfigLabelGridSubdivisions));
+                    ui.add(DragValue::new(&mut config.grid.subdivisions).clamp_range(1..=100));
+                    ui.end_row();
+
+                    ui.label(s!(&ConfigLabelGridMinorColor));
+                    ui.color_edit_button_rgb(&mut config.grid.minor_color);
+                    ui.end_row();
+
+                    ui.label(s!(&ConfigLabelGridMajorColor));
+                    ui.color_edit_button_rgb(&mut config.grid.major_color);
+                    ui.end_row();
+                }
+            });
+
             ui.separator();
             ctx.settings_ui(ui);
         });
@@ -196,7 +615,7 @@ pub fn egui<C: CoordinateSystem, S: StrokeBackend>(
 
             ui.menu_button(s!(&MenuLabelFile), |ui| {
                 if ui.button(s!(&MenuItemFileNew)).clicked() {
-                    new_file(widget, sketch);
+                    widget.new_document(config, sketch);
                     ui.close_menu();
                 }
                 if ui.button(s!(&MenuItemFileOpen)).clicked() {
@@ -215,6 +634,16 @@ pub fn egui<C: CoordinateSystem, S: StrokeBackend>(
 
                 ui.separator();
 
+                if widget.is_recording() {
+                    if ui.button(s!(&MenuItemFileStopRecording)).clicked() {
+                        save_recording(widget);
+                        ui.close_menu();
+                    }
+                } else if ui.button(s!(&MenuItemFileStartRecording)).clicked() {
+                    widget.start_recording();
+                    ui.close_menu();
+                }
+
                 if ui.button(s!(&MenuItemFileSettings)).clicked() {
                     settings_open = true;
                     ui.close_menu();
@@ -236,7 +665,23 @@ pub fn egui<C: CoordinateSystem, S: StrokeBackend>(
             });
 
             if settings_open {
+                let tessellator = config.tessellator;
+                let line_style = (config.line_cap, config.line_join);
+                let style_override = config.style_override();
+                let stamp_spacing = config.stamp_spacing;
                 settings_window(ui, ctx, settings_id, config, sketch, settings_open);
+                if tessellator != config.tessellator {
+                    widget.use_tessellator(config.tessellator, sketch);
+                }
+                if line_style != (config.line_cap, config.line_join) {
+                    widget.use_line_style(config.line_cap, config.line_join, sketch);
+                }
+                if style_override != config.style_override() {
+                    widget.use_style_override(config.style_override(), sketch);
+                }
+                if stamp_spacing != config.stamp_spacing {
+                    widget.use_stamp_spacing(config.stamp_spacing, sketch);
+                }
             }
 
             ui.menu_button(s!(&MenuLabelEdit), |ui| {
@@ -247,6 +692,50 @@ pub fn egui<C: CoordinateSystem, S: StrokeBackend>(
                 if ui.button(s!(&MenuItemEditRedo)).clicked() {
                     widget.redo(sketch);
                 }
+
+                ui.separator();
+
+                if ui
+                    .add_enabled(
+                        widget.measurement.is_some(),
+                        Button::new(s!(&MenuItemEditClipToRuler)),
+                    )
+                    .clicked()
+                {
+                    sketch.clip = widget
+                        .measurement
+                        .map(|measurement| ClipRect::new(measurement.start, measurement.end));
+                    ui.close_menu();
+                }
+
+                if ui.button(s!(&MenuItemEditClipToLastStroke)).clicked() {
+                    // the one on top, not whichever has the last key
+                    sketch.clip = sketch
+                        .visible_strokes()
+                        .filter_map(ClipRect::from_stroke)
+                        .last();
+                    ui.close_menu();
+                }
+
+                if ui
+                    .add_enabled(
+                        sketch.clip.is_some(),
+                        Button::new(s!(&MenuItemEditClearClip)),
+                    )
+                    .clicked()
+                {
+                    sketch.clip = None;
+                    ui.close_menu();
+                }
+
+                ui.separator();
+
+                ui.checkbox(&mut widget.crisp, s!(&MenuItemEditCrispStrokes));
+
+                ui.horizontal(|ui| {
+                    ui.label(s!(&MenuLabelEditDepth));
+                    ui.add(DragValue::new(&mut widget.depth).speed(0.1));
+                });
             });
 
             ui.separator();
@@ -258,6 +747,21 @@ pub fn egui<C: CoordinateSystem, S: StrokeBackend>(
                 s!(&RadioLabelToolEraser),
             );
             ui.radio_value(&mut widget.active_tool, Tool::Pan, s!(&RadioLabelToolPan));
+            ui.radio_value(
+                &mut widget.active_tool,
+                Tool::Ruler,
+                s!(&RadioLabelToolRuler),
+            );
+            ui.radio_value(
+                &mut widget.active_tool,
+                Tool::Eyedropper,
+                s!(&RadioLabelToolEyedropper),
+            );
+            ui.radio_value(
+                &mut widget.active_tool,
+                Tool::Select,
+                s!(&RadioLabelToolSelect),
+            );
 
             let brush_size_slider = ui.add(
                 Slider::new(&mut widget.brush_size, crate::MIN_BRUSH..=crate::MAX_BRUSH)
@@ -295,6 +799,14 @@ pub fn egui<C: CoordinateSystem, S: StrokeBackend>(
         });
     });
 
+    if config.grid.show {
+        grid_overlay(ctx, sketch, widget, &config.grid);
+    }
+
+    if widget.active_tool == Tool::Ruler {
+        measurement_overlay(ctx, sketch, widget, config);
+    }
+
     if config.debug_show_info {
         Window::new("debug info").show(ctx, |ui| {
             Grid::new("debug info grid").show(ui, |ui| {
@@ -318,6 +830,94 @@ pub fn egui<C: CoordinateSystem, S: StrokeBackend>(
     }
 }
 
+fn grid_overlay<C: CoordinateSystem, S: StrokeBackend>(
+    ctx: &egui::Context,
+    sketch: &Sketch<S>,
+    widget: &widget::SketchWidget<C>,
+    grid: &GridConfig,
+) {
+    use crate::graphics::{Color, StrokePos};
+    use egui::*;
+
+    let ppp = ctx.pixels_per_point();
+    let to_screen = |x, y| {
+        let pixel = C::pos_to_pixel(
+            widget.width,
+            widget.height,
+            sketch.zoom,
+            sketch.origin,
+            StrokePos { x, y },
+        );
+        pos2(pixel.x / ppp, pixel.y / ppp)
+    };
+    let color =
+        |[r, g, b]: Color| Color32::from_rgb((r * 255.) as u8, (g * 255.) as u8, (b * 255.) as u8);
+
+    let (top_left, bottom_right) = sketch.screen_rect::<C>(widget.width, widget.height);
+    let painter = ctx.layer_painter(LayerId::new(Order::Background, Id::new("grid")));
+    for line in grid.lines(top_left, bottom_right, sketch.zoom) {
+        let ends = if line.vertical {
+            [
+                to_screen(line.at, top_left.y),
+                to_screen(line.at, bottom_right.y),
+            ]
+        } else {
+            [
+                to_screen(top_left.x, line.at),
+                to_screen(bottom_right.x, line.at),
+            ]
+        };
+
+        let stroke = if line.major {
+            egui::Stroke {
+                width: 2.,
+                color: color(grid.major_color),
+            }
+        } else {
+            egui::Stroke {
+                width: 1.,
+                color: color(grid.minor_color),
+            }
+        };
+        painter.line_segment(ends, stroke);
+    }
+}
+
+fn measurement_overlay<C: CoordinateSystem, S: StrokeBackend>(
+    ctx: &egui::Context,
+    sketch: &Sketch<S>,
+    widget: &widget::SketchWidget<C>,
+    config: &Config,
+) {
+    use egui::*;
+
+    let Some(measurement) = widget.measurement else {
+        return;
+    };
+
+    let ppp = ctx.pixels_per_point();
+    let to_screen = |pos| {
+        let pixel = C::pos_to_pixel(widget.width, widget.height, sketch.zoom, sketch.origin, pos);
+        pos2(pixel.x / ppp, pixel.y / ppp)
+    };
+
+    let start = to_screen(measurement.start);
+    let end = to_screen(measurement.end);
+    let color = ctx.style().visuals.strong_text_color();
+
+    let painter = ctx.layer_painter(LayerId::new(Order::Background, Id::new("measurement")));
+    painter.line_segment([start, end], egui::Stroke { width: 1.5, color });
+    painter.circle_filled(start, 3., color);
+    painter.circle_filled(end, 3., color);
+    painter.text(
+        end + vec2(8., -8.),
+        Align2::LEFT_BOTTOM,
+        measurement.readout(config.ruler_units_per_stroke_unit, &config.ruler_unit_name),
+        FontId::proportional(14.),
+        color,
+    );
+}
+
 pub fn read_file<S: StrokeBackend, C: CoordinateSystem>(
     widget: &mut widget::SketchWidget<C>,
     path: Option<impl AsRef<std::path::Path>>,
@@ -340,7 +940,11 @@ pub fn read_file<S: StrokeBackend, C: CoordinateSystem>(
                 }
             }
 
-            err => err.problem(s!(MboxMessageCouldNotOpenFile)).display(),
+            err => {
+                // don't throw away the unsaved changes
+                err.problem(s!(MboxMessageCouldNotOpenFile)).display();
+                return;
+            }
         }
     }
 
@@ -392,7 +996,7 @@ pub fn read_file<S: StrokeBackend, C: CoordinateSystem>(
                 },
 
                 UpgradeType::Rocky => match prompt_migrate() {
-                    rfd::MessageDialogResult::Yes => {
+                    Answer::Yes => {
                         let disk = match migrate::from(version, &path) {
                             Ok(disk) => disk,
                             err => {
@@ -402,12 +1006,7 @@ pub fn read_file<S: StrokeBackend, C: CoordinateSystem>(
                         };
 
                         *sketch = disk;
-                        sketch.force_update::<C>(
-                            widget.width,
-                            widget.height,
-                            &mut widget.tesselator,
-                            &widget.stroke_options,
-                        );
+                        widget.force_update(sketch);
 
                         // set the path to none so the user is prompted to save elsewhere
                         widget.path = None;
@@ -433,17 +1032,33 @@ pub fn read_file<S: StrokeBackend, C: CoordinateSystem>(
     };
 
     *sketch = disk;
-    sketch.force_update::<C>(
-        widget.width,
-        widget.height,
-        &mut widget.tesselator,
-        &widget.stroke_options,
-    );
+    widget.force_update(sketch);
 
     widget.modified = false;
-    widget.path = Some(path);
     widget.undo_stack.clear();
 
+    // the app closed without saving the work since
+    if let Some(autosave) = crate::autosave::newer_than(&path) {
+        if prompt_restore_autosave(&path) {
+            match std::fs::File::open(&autosave)
+                .map_err(PmbError::from)
+                .and_then(migrate::read)
+                .problem(format!("{}", autosave.display()))
+            {
+                Ok(restored) => {
+                    *sketch = restored;
+                    widget.force_update(sketch);
+                    widget.modified = true;
+                }
+                err => err.display(),
+            }
+        } else {
+            crate::autosave::remove(&autosave);
+        }
+    }
+
+    widget.path = Some(path);
+
     tracing::info!(
         "success, read from {}",
         widget.path.as_ref().unwrap().display()
@@ -456,29 +1071,41 @@ pub fn ask_to_save_then_save<S: StrokeBackend, C: CoordinateSystem>(
     sketch: &Sketch<S>,
     why: &str,
 ) -> Result<bool, PmbError> {
-    use crate::migrate;
+    save_before(
+        sketch,
+        &mut widget.path,
+        &mut widget.modified,
+        &mut widget.undo_stack,
+        why,
+    )
+}
 
+fn save_before<S: StrokeBackend>(
+    sketch: &Sketch<S>,
+    path: &mut Option<PathBuf>,
+    modified: &mut bool,
+    undo_stack: &mut undo::UndoStack,
+    why: &str,
+) -> Result<bool, PmbError> {
     tracing::info!("asking to save {why:?}");
-    match (ask_to_save(why), widget.path.as_ref()) {
+    match (ask_to_save(why), path.clone()) {
         // if they say yes and the file we're editing has a path
-        (rfd::MessageDialogResult::Yes, Some(path)) => {
+        (Answer::Yes, Some(path)) => {
             tracing::info!("writing as {}", path.display());
-            migrate::write(path, sketch).problem(format!("{}", path.display()))?;
-            widget.modified = false;
+            write_sketch(&path, sketch, modified, undo_stack)?;
             Ok(true)
         }
 
         // they say yes and the file doesn't have a path yet
-        (rfd::MessageDialogResult::Yes, None) => {
+        (Answer::Yes, None) => {
             tracing::info!("asking where to save");
             // ask where to save it
             match save_dialog(s!(&MboxTitleSaveUnnamedFile), None) {
                 Some(new_filename) => {
                     tracing::info!("writing as {}", new_filename.display());
                     // try write to disk
-                    migrate::write(&new_filename, sketch)
-                        .problem(format!("{}", new_filename.display()))?;
-                    widget.modified = false;
+                    write_sketch(&new_filename, sketch, modified, undo_stack)?;
+                    *path = Some(new_filename);
                     Ok(true)
                 }
 
@@ -487,60 +1114,302 @@ pub fn ask_to_save_then_save<S: StrokeBackend, C: CoordinateSystem>(
         }
 
         // they say no, don't write changes
-        (rfd::MessageDialogResult::No, _) => Ok(true),
+        (Answer::No, _) => Ok(true),
 
         _ => Ok(false),
     }
 }
 
+/// returns where the file was saved, if it was saved
 fn save_file<C: CoordinateSystem, S: StrokeBackend>(
     widget: &mut widget::SketchWidget<C>,
     sketch: &Sketch<S>,
-) {
-    use crate::migrate;
+) -> Option<PathBuf> {
+    let path = match widget.path.as_ref() {
+        Some(path) => path.clone(),
+        None => save_dialog(s!(&MboxTitleSaveUnnamedFile), None)?,
+    };
 
-    if let Some(path) = widget.path.as_ref() {
-        match migrate::write(path, sketch) {
-            Ok(()) => {}
-            err => {
-                err.problem(format!("{}", path.display())).display();
-                return;
-            }
-        }
-        widget.modified = false;
-    } else if let Some(path) = save_dialog(s!(&MboxTitleSaveUnnamedFile), None) {
-        let problem = format!("{}", path.display());
-        widget.path = Some(path);
-        match migrate::write(widget.path.as_ref().unwrap(), sketch) {
-            Ok(()) => {}
-            err => {
-                err.problem(problem).display();
-                return;
-            }
-        }
-        widget.modified = false;
+    if let Err(err) = write_sketch(&path, sketch, &mut widget.modified, &mut widget.undo_stack) {
+        err.display();
+        return None;
     }
 
-    tracing::info!("saved file as {}", widget.path.as_ref().unwrap().display());
+    tracing::info!("saved file as {}", path.display());
+    widget.path = Some(path.clone());
+
+    Some(path)
+}
+
+fn save_recording<C: CoordinateSystem>(widget: &mut widget::SketchWidget<C>) {
+    let log = widget.stop_recording();
+    let Some(path) = dialog::save_file(s!(&MboxTitleSaveRecording), ("RON", &["ron"]), "") else {
+        return;
+    };
+
+    match log.write(&path) {
+        Ok(()) => tracing::info!("saved {} events as {}", log.events.len(), path.display()),
+        Err(err) => err.display(),
+    }
+}
+
+/// write the sketch to disk, only marking it as saved if that actually worked
+fn write_sketch<S: StrokeBackend>(
+    path: &Path,
+    sketch: &Sketch<S>,
+    modified: &mut bool,
+    undo_stack: &mut undo::UndoStack,
+) -> Result<(), PmbError> {
+    crate::migrate::write(path, sketch).problem(format!("{}", path.display()))?;
+    *modified = false;
+    undo_stack.set_saved_state();
+    Ok(())
 }
 
 fn new_file<C: CoordinateSystem, S: StrokeBackend>(
     widget: &mut widget::SketchWidget<C>,
     sketch: &mut Sketch<S>,
-) {
-    if widget.modified {
-        match ask_to_save_then_save(widget, sketch, s!(&MboxMessageAskToSaveBeforeOpening)) {
+    template: Option<&Path>,
+) -> bool {
+    reset_document(
+        sketch,
+        template,
+        &mut widget.path,
+        &mut widget.modified,
+        &mut widget.undo_stack,
+        |sketch, path, modified, undo_stack| {
+            save_before(
+                sketch,
+                path,
+                modified,
+                undo_stack,
+                s!(&MboxMessageAskToSaveBeforeOpening),
+            )
+        },
+    )
+}
+
+/// the template sketch, or an empty one if it can't be read
+pub fn template_or_empty<S: StrokeBackend>(template: Option<&Path>) -> Sketch<S> {
+    Sketch::template(template).unwrap_or_else(|err| {
+        let path = template.map(|path| path.display().to_string());
+        err.problem(path.unwrap_or_default()).display();
+        Sketch::empty()
+    })
+}
+
+/// start over with the template or an empty sketch and a fresh view, giving `save_first` a chance
+/// to save the old one if it was modified. returns whether the sketch was actually cleared
+fn reset_document<S: StrokeBackend>(
+    sketch: &mut Sketch<S>,
+    template: Option<&Path>,
+    path: &mut Option<PathBuf>,
+    modified: &mut bool,
+    undo_stack: &mut undo::UndoStack,
+    save_first: impl FnOnce(
+        &Sketch<S>,
+        &mut Option<PathBuf>,
+        &mut bool,
+        &mut undo::UndoStack,
+    ) -> Result<bool, PmbError>,
+) -> bool {
+    if *modified {
+        match save_first(sketch, path, modified, undo_stack) {
             Ok(should_continue) => {
                 if !should_continue {
-                    return;
+                    return false;
                 }
             }
 
-            err => err.problem(s!(MboxMessageCouldNotSaveFile)).display(),
+            err => {
+                err.problem(s!(MboxMessageCouldNotSaveFile)).display();
+                return false;
+            }
         }
     }
 
-    *sketch = Sketch::empty();
-    widget.path = None;
-    widget.modified = false;
+    *sketch = template_or_empty(template);
+    *path = None;
+    *modified = false;
+    undo_stack.clear();
+
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn failed_save_stays_modified() {
+        let dir = crate::scratch::dir("failed-save-stays-modified");
+
+        let original = dir.join("original.pmb");
+        std::fs::write(&original, b"original contents").unwrap();
+
+        let sketch = Sketch::<()>::default();
+        let mut modified = true;
+        let mut undo_stack = undo::UndoStack::new();
+
+        // can't write into a directory that doesn't exist
+        let missing = dir.join("missing").join("original.pmb");
+        assert!(write_sketch(&missing, &sketch, &mut modified, &mut undo_stack).is_err());
+        assert!(modified);
+
+        // can't replace a directory with a file
+        let occupied = dir.join("occupied.pmb");
+        std::fs::create_dir(&occupied).unwrap();
+        assert!(write_sketch(&occupied, &sketch, &mut modified, &mut undo_stack).is_err());
+        assert!(modified);
+        assert!(occupied.is_dir());
+
+        assert_eq!(std::fs::read(&original).unwrap(), b"original contents");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        assert!(write_sketch(&original, &sketch, &mut modified, &mut undo_stack).is_ok());
+        assert!(!modified);
+        assert!(undo_stack.at_saved_state());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn new_document_clears_sketch() {
+        use crate::{
+            graphics::{Color, ColorExt},
+            stroke::{Stroke, StrokeElement},
+        };
+
+        let mut sketch = Sketch::<()>::default();
+        let key = sketch.add_stroke(Stroke::with_points(
+            vec![StrokeElement {
+                x: 1.,
+                y: 2.,
+                pressure: 1.,
+            }],
+            Color::WHITE,
+        ));
+        sketch.zoom *= 2.;
+        sketch.origin.x = 25.;
+
+        let mut path = Some(PathBuf::from("drawing.pmb"));
+        let mut modified = true;
+        let mut undo_stack = undo::UndoStack::new();
+        undo_stack.push(undo::Action::DrawStroke(key));
+
+        // cancelled at the save prompt, nothing changes
+        let mut asked = false;
+        let cleared = reset_document(
+            &mut sketch,
+            None,
+            &mut path,
+            &mut modified,
+            &mut undo_stack,
+            |_, _, _, _| {
+                asked = true;
+                Ok(false)
+            },
+        );
+        assert!(asked);
+        assert!(!cleared);
+        assert_eq!(sketch.strokes.len(), 1);
+        assert_eq!(path, Some(PathBuf::from("drawing.pmb")));
+        assert!(modified);
+        assert_eq!(undo_stack.last(), Some(undo::Action::DrawStroke(key)));
+
+        // told not to save
+        let cleared = reset_document(
+            &mut sketch,
+            None,
+            &mut path,
+            &mut modified,
+            &mut undo_stack,
+            |_, _, _, _| Ok(true),
+        );
+        assert!(cleared);
+        assert!(sketch.strokes.is_empty());
+        assert_eq!(sketch.zoom, crate::DEFAULT_ZOOM);
+        assert_eq!(sketch.origin.x, 0.);
+        assert_eq!(path, None);
+        assert!(!modified);
+        assert_eq!(undo_stack.last(), None);
+
+        // not modified, so there's nothing to save
+        let cleared = reset_document(
+            &mut sketch,
+            None,
+            &mut path,
+            &mut modified,
+            &mut undo_stack,
+            |_, _, _, _| panic!("asked to save an unmodified sketch"),
+        );
+        assert!(cleared);
+    }
+
+    #[test]
+    fn new_document_uses_template() {
+        use crate::{
+            graphics::{Color, ColorExt},
+            stroke::{Stroke, StrokeElement},
+        };
+
+        let rule = |y: f32| {
+            Stroke::with_points(
+                vec![
+                    StrokeElement {
+                        x: -10.,
+                        y,
+                        pressure: 1.,
+                    },
+                    StrokeElement {
+                        x: 10.,
+                        y,
+                        pressure: 1.,
+                    },
+                ],
+                Color::grey(0.5),
+            )
+        };
+        let mut ruled = Sketch::<()>::new((0..5).map(|i| rule(i as f32)).collect());
+        ruled.bg_color = Color::WHITE;
+
+        let dir = crate::scratch::dir("template");
+        let template = dir.join("ruled.pmb");
+        crate::migrate::write(&template, &ruled).unwrap();
+
+        let mut sketch = Sketch::<()>::default();
+        let mut path = Some(PathBuf::from("drawing.pmb"));
+        let mut modified = false;
+        let mut undo_stack = undo::UndoStack::new();
+
+        assert!(reset_document(
+            &mut sketch,
+            Some(&template),
+            &mut path,
+            &mut modified,
+            &mut undo_stack,
+            |_, _, _, _| panic!("asked to save an unmodified sketch"),
+        ));
+        assert_eq!(sketch.strokes.len(), 5);
+        for (a, b) in ruled.strokes.values().zip(sketch.strokes.values()) {
+            assert_eq!(a.points(), b.points());
+            assert_eq!(a.color, b.color);
+        }
+        assert_eq!(sketch.bg_color, Color::WHITE);
+        // a new document, not the template itself
+        assert_eq!(path, None);
+        assert!(!modified);
+
+        assert!(reset_document(
+            &mut sketch,
+            None,
+            &mut path,
+            &mut modified,
+            &mut undo_stack,
+            |_, _, _, _| panic!("asked to save an unmodified sketch"),
+        ));
+        assert!(sketch.strokes.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,130 @@
+//! message boxes and file pickers
+//!
+//! with the `desktop` feature these are the system's own dialogs. without it there's nobody to ask,
+//! so messages only go to the log, questions get whichever answer doesn't lose anything, and no
+//! file ever gets picked.
+
+use std::path::PathBuf;
+
+/// which button closed a message box
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Ok,
+    Yes,
+    No,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Buttons {
+    Ok,
+    YesNo,
+    YesNoCancel,
+}
+
+/// a name for some kind of file and its extensions, like `("PMB", &["pmb"])`
+pub type Filter<'a> = (&'a str, &'a [&'a str]);
+
+#[cfg(feature = "desktop")]
+pub fn message(level: Level, title: &str, text: &str, buttons: Buttons) -> Answer {
+    let answer = rfd::MessageDialog::new()
+        .set_level(match level {
+            Level::Info => rfd::MessageLevel::Info,
+            Level::Warning => rfd::MessageLevel::Warning,
+            Level::Error => rfd::MessageLevel::Error,
+        })
+        .set_title(title)
+        .set_description(text)
+        .set_buttons(match buttons {
+            Buttons::Ok => rfd::MessageButtons::Ok,
+            Buttons::YesNo => rfd::MessageButtons::YesNo,
+            Buttons::YesNoCancel => rfd::MessageButtons::YesNoCancel,
+        })
+        .show();
+
+    match answer {
+        rfd::MessageDialogResult::Yes => Answer::Yes,
+        rfd::MessageDialogResult::No => Answer::No,
+        rfd::MessageDialogResult::Ok => Answer::Ok,
+        _ => Answer::Cancel,
+    }
+}
+
+#[cfg(not(feature = "desktop"))]
+pub fn message(level: Level, title: &str, text: &str, buttons: Buttons) -> Answer {
+    match level {
+        Level::Info => tracing::info!("{title}: {text}"),
+        Level::Warning => tracing::warn!("{title}: {text}"),
+        Level::Error => tracing::error!("{title}: {text}"),
+    }
+
+    unanswered(buttons)
+}
+
+/// what a message box would say if nobody clicked anything. no to yes or no, since saying yes
+/// could mean writing over a file
+#[cfg_attr(feature = "desktop", allow(dead_code))]
+fn unanswered(buttons: Buttons) -> Answer {
+    match buttons {
+        Buttons::Ok => Answer::Ok,
+        Buttons::YesNo => Answer::No,
+        Buttons::YesNoCancel => Answer::Cancel,
+    }
+}
+
+#[cfg(feature = "desktop")]
+pub fn save_file(title: &str, (name, extensions): Filter, filename: &str) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title(title)
+        .add_filter(name, extensions)
+        .set_file_name(filename)
+        .save_file()
+}
+
+#[cfg(not(feature = "desktop"))]
+pub fn save_file(title: &str, _: Filter, _: &str) -> Option<PathBuf> {
+    tracing::warn!("nowhere to ask {title:?}");
+    None
+}
+
+#[cfg(feature = "desktop")]
+pub fn open_file(title: &str, (name, extensions): Filter) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title(title)
+        .add_filter(name, extensions)
+        .pick_file()
+}
+
+#[cfg(not(feature = "desktop"))]
+pub fn open_file(title: &str, _: Filter) -> Option<PathBuf> {
+    tracing::warn!("nowhere to ask {title:?}");
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nobody_to_ask() {
+        assert_eq!(unanswered(Buttons::Ok), Answer::Ok);
+        assert_eq!(unanswered(Buttons::YesNo), Answer::No);
+        assert_eq!(unanswered(Buttons::YesNoCancel), Answer::Cancel);
+
+        #[cfg(not(feature = "desktop"))]
+        {
+            assert_eq!(
+                message(Level::Warning, "title", "text", Buttons::YesNoCancel),
+                Answer::Cancel
+            );
+            assert!(open_file("title", ("PMB", &["pmb"])).is_none());
+        }
+    }
+}
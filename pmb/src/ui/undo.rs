@@ -1,9 +1,37 @@
+use crate::{graphics::Color, stroke::StrokeElement};
 use slotmap::DefaultKey;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Action {
     DrawStroke(DefaultKey),
     EraseStroke(DefaultKey),
+    /// `from` was erased and replaced by the pieces in `into`
+    SplitStroke {
+        from: DefaultKey,
+        into: Vec<DefaultKey>,
+    },
+    /// points were taken off the end of a stroke, most recently removed first
+    RewindStroke {
+        key: DefaultKey,
+        points: Vec<StrokeElement>,
+    },
+    /// the strokes were locked, or unlocked if not `locked`
+    LockStrokes {
+        keys: Vec<DefaultKey>,
+        locked: bool,
+    },
+    /// the strokes were given the brush size `to`, from the ones in `from`
+    ResizeStrokes {
+        keys: Vec<DefaultKey>,
+        from: Vec<f32>,
+        to: f32,
+    },
+    /// the strokes were given the color `to`, from the ones in `from`
+    RecolorStrokes {
+        keys: Vec<DefaultKey>,
+        from: Vec<Color>,
+        to: Color,
+    },
 }
 
 #[derive(Debug)]
@@ -11,6 +39,8 @@ pub struct UndoStack {
     buffer: Vec<Action>,
     cursor: usize,
     saved: usize,
+    /// goes up every time anything could have changed
+    changes: u64,
 }
 
 impl UndoStack {
@@ -19,11 +49,19 @@ impl UndoStack {
             buffer: Vec::new(),
             cursor: 0,
             saved: 0,
+            changes: 0,
         }
     }
 
     pub fn clear(&mut self) {
+        let changes = self.changes + 1;
         *self = Self::new();
+        self.changes = changes;
+    }
+
+    /// different whenever the actions might be different than the last time it was asked
+    pub fn changes(&self) -> u64 {
+        self.changes
     }
 
     pub fn at_saved_state(&self) -> bool {
@@ -40,10 +78,22 @@ impl UndoStack {
             return None;
         }
 
-        self.buffer.get(self.cursor - 1).copied()
+        self.buffer.get(self.cursor - 1).cloned()
+    }
+
+    /// the most recent action, if it can still be changed in place: nothing has been undone since,
+    /// and it hasn't been saved
+    pub fn last_mut(&mut self) -> Option<&mut Action> {
+        if self.cursor == 0 || self.cursor != self.buffer.len() || self.at_saved_state() {
+            return None;
+        }
+
+        self.changes += 1;
+        self.buffer.last_mut()
     }
 
     pub fn push(&mut self, action: Action) {
+        self.changes += 1;
         if self.cursor == self.buffer.len() {
             tracing::debug!("append");
             // [a, b, c, d]
@@ -71,11 +121,43 @@ impl UndoStack {
         self.cursor = self.buffer.len();
     }
 
+    /// forget the most recent action like it never happened, unless it's been undone
+    #[must_use]
+    pub fn pop(&mut self) -> Option<Action> {
+        if self.cursor == 0 || self.cursor != self.buffer.len() {
+            return None;
+        }
+
+        self.cursor -= 1;
+        self.changes += 1;
+        self.buffer.pop()
+    }
+
+    /// forget the most recent time `action` happened like it never did, even if other actions
+    /// came after it. false if it hasn't happened or has been undone
+    pub fn forget(&mut self, action: &Action) -> bool {
+        let Some(i) = self.buffer[..self.cursor]
+            .iter()
+            .rposition(|had| had == action)
+        else {
+            return false;
+        };
+
+        self.buffer.remove(i);
+        self.cursor -= 1;
+        if self.saved > i {
+            self.saved -= 1;
+        }
+        self.changes += 1;
+        true
+    }
+
     #[must_use]
     pub fn undo(&mut self) -> Option<Action> {
         let last = self.last();
         if self.cursor > 0 {
             self.cursor -= 1;
+            self.changes += 1;
         }
         last
     }
@@ -84,6 +166,7 @@ impl UndoStack {
     pub fn redo(&mut self) -> Option<Action> {
         if self.cursor < self.buffer.len() {
             self.cursor += 1;
+            self.changes += 1;
             self.last()
         } else {
             None
@@ -136,3 +219,27 @@ fn undo_stack() {
     stack.push(Action::DrawStroke(a3));
     assert_eq!(stack.last(), Some(Action::DrawStroke(a3)));
 }
+
+#[test]
+fn forget_under_others() {
+    let mut sm = slotmap::SlotMap::new();
+    let mut stack = UndoStack::new();
+    let (a1, a2, a3) = (sm.insert(()), sm.insert(()), sm.insert(()));
+
+    stack.push(Action::DrawStroke(a1));
+    stack.push(Action::DrawStroke(a2));
+    stack.set_saved_state();
+    stack.push(Action::DrawStroke(a3));
+
+    assert!(stack.forget(&Action::DrawStroke(a2)));
+    assert!(!stack.forget(&Action::DrawStroke(a2)));
+    assert_eq!(stack.last(), Some(Action::DrawStroke(a3)));
+    assert_eq!(stack.undo(), Some(Action::DrawStroke(a3)));
+    assert!(stack.at_saved_state());
+    assert_eq!(stack.undo(), Some(Action::DrawStroke(a1)));
+    assert_eq!(stack.undo(), None);
+
+    // undone actions stay where redo can find them
+    assert!(!stack.forget(&Action::DrawStroke(a1)));
+    assert_eq!(stack.redo(), Some(Action::DrawStroke(a1)));
+}
@@ -1,22 +1,41 @@
 use crate::{
+    autosave::Autosave,
+    brush::Brushes,
     config::Config,
-    event::{Event, InputHandler},
+    event::{Effect, Event, InputHandler},
     graphics::{PixelPos, StrokePos},
+    idle::IdleTimer,
     loop_::LoopEvent,
+    measure::Measurement,
+    pinch::{Pinch, PinchMove},
+    predict::Predictor,
+    pressure,
+    select::SelectMode,
+    session::{SessionLog, TimedEvent},
+    shape::Recognizer,
+    stabilizer::LazyBrush,
+    stroke::{StrokeElement, StrokeStyle},
+    tap::{TapDetector, TAP_SLOP},
+    tess::{CapStyle, JoinStyle, TessellatorKind},
     ui::undo::{Action, UndoStack},
     CoordinateSystem, Device, Sketch, Stroke, StrokeBackend, Stylus, StylusPosition, StylusState,
     Tool,
 };
-use lyon::{
-    lyon_tessellation::{StrokeOptions, StrokeTessellator},
-    path::{LineCap, LineJoin},
+use lyon::lyon_tessellation::{StrokeOptions, StrokeTessellator};
+use slotmap::DefaultKey;
+use std::{
+    marker::PhantomData,
+    time::{Duration, Instant},
 };
-use std::marker::PhantomData;
 use winit::{
     event::{ElementState, Touch, TouchPhase, VirtualKeyCode as Keycode},
     event_loop::EventLoopProxy,
 };
 
+/// moves closer than this to the last one, in pixels and in pressure, are the same move reported
+/// twice
+const DUPLICATE_MOVE_EPSILON: f64 = 1e-3;
+
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub enum SketchWidgetState {
     #[default]
@@ -29,6 +48,8 @@ pub enum SketchWidgetState {
     PenErase,
     MouseDraw,
     MouseErase,
+    PenMeasure,
+    MouseMeasure,
     Gesture(u8),
     OpenDialog,
     SaveDialog,
@@ -42,7 +63,8 @@ impl SketchWidgetState {
 }
 
 pub struct SketchWidget<C: CoordinateSystem> {
-    pub proxy: EventLoopProxy<LoopEvent>,
+    /// None when there's no event loop to talk to, like in tests
+    pub proxy: Option<EventLoopProxy<LoopEvent>>,
     pub state: SketchWidgetState,
     pub modified: bool,
     pub path: Option<std::path::PathBuf>,
@@ -53,25 +75,89 @@ pub struct SketchWidget<C: CoordinateSystem> {
     pub stylus: Stylus,
     pub brush_size: usize,
     pub active_tool: Tool,
+    /// what the fingers on the screen are doing
+    pub gesture_tool: Tool,
     pub undo_stack: UndoStack,
+    pub measurement: Option<Measurement>,
+    /// where the pen has been since it went down with the select tool
+    pub selection_path: Vec<StrokePos>,
+    pub lazy_brush: LazyBrush,
+    /// straightens lines and tidies shapes once they're finished
+    pub recognizer: Recognizer,
+    pub predictor: Predictor,
 
     pub width: u32,
     pub height: u32,
     pub tesselator: StrokeTessellator,
     pub stroke_options: StrokeOptions,
-
+    pub tessellator_kind: TessellatorKind,
+    /// how far meshes can stray from their strokes on the screen, in pixels
+    pub pixel_tolerance: f32,
+    /// the ends and corners of lines, which the cursor shows too
+    pub line_cap: CapStyle,
+    pub line_join: JoinStyle,
+    /// the engines that build each style's meshes
+    pub brushes: Brushes,
+    /// draw every stroke in this style instead of its own, which is still what gets saved
+    pub style_override: Option<StrokeStyle>,
+    /// brush sizes between the circles of a circles style
+    pub stamp_spacing: f32,
+    /// whether strokes drawn from now on are crisp
+    pub crisp: bool,
+    /// depth of strokes drawn from now on
+    pub depth: f32,
+    /// finishes strokes the pen was lifted from without saying so
+    pub idle: IdleTimer,
+    /// writes a copy of the sketch when it has changes that haven't been saved
+    pub autosave: Autosave,
+    /// notices fingers tapping without moving
+    pub taps: TapDetector,
+    /// follows the fingers to move and zoom the view
+    pub pinch: Pinch,
+    /// the last time the pen said anything, to tell a palm resting on the screen from a finger
+    pub last_pen_instant: Option<Instant>,
+    /// fingers that touched while the pen was in use, which are ignored until they lift
+    palms: Vec<u64>,
+
+    /// the stroke the first finger of a gesture is drawing, which gets thrown away if another
+    /// finger joins in
+    gesture_stroke: Option<DefaultKey>,
+    /// where the stroke being drawn started, and the furthest it's gotten from there in pixels
+    stroke_travel: (PixelPos, f32),
+    /// the zoom level and pixel tolerance the meshes were built for
+    meshed_for: Option<(i32, f32)>,
+    last_move: Option<Touch>,
+    /// the last key press came from a key being held down, not pressed again
+    key_repeat: bool,
+    recording: Option<(Instant, SessionLog)>,
     coords: PhantomData<C>,
 }
 
 impl<C: CoordinateSystem> SketchWidget<C> {
     pub fn new(proxy: EventLoopProxy<LoopEvent>, width: u32, height: u32) -> Self {
+        Self::with_proxy(Some(proxy), width, height)
+    }
+
+    /// a widget that isn't attached to a window, for driving it from tests
+    #[cfg(any(test, feature = "test-backend"))]
+    pub fn headless(width: u32, height: u32) -> Self {
+        Self::with_proxy(None, width, height)
+    }
+
+    fn with_proxy(proxy: Option<EventLoopProxy<LoopEvent>>, width: u32, height: u32) -> Self {
         Self {
             proxy,
             state: SketchWidgetState::default(),
             stylus: Stylus::default(),
             prev_device: Device::Mouse,
             active_tool: Tool::Pen,
+            gesture_tool: Tool::Pen,
             undo_stack: UndoStack::new(),
+            measurement: None,
+            selection_path: Vec::new(),
+            lazy_brush: LazyBrush::default(),
+            recognizer: Recognizer::default(),
+            predictor: Predictor::new(),
             brush_size: crate::DEFAULT_BRUSH,
             modified: false,
             path: None,
@@ -80,10 +166,31 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             height,
             tesselator: StrokeTessellator::new(),
             stroke_options: StrokeOptions::default()
-                .with_line_cap(LineCap::Round)
-                .with_line_join(LineJoin::Round)
+                .with_line_cap(CapStyle::default().into())
+                .with_line_join(JoinStyle::default().into())
                 .with_tolerance(0.001)
                 .with_variable_line_width(0),
+            tessellator_kind: TessellatorKind::default(),
+            pixel_tolerance: crate::tess::PIXEL_TOLERANCE,
+            line_cap: CapStyle::default(),
+            line_join: JoinStyle::default(),
+            brushes: Brushes::default(),
+            style_override: None,
+            stamp_spacing: 0.,
+            crisp: false,
+            depth: 0.,
+            idle: IdleTimer::default(),
+            autosave: Autosave::default(),
+            taps: TapDetector::new(TAP_SLOP),
+            pinch: Pinch::default(),
+            last_pen_instant: None,
+            palms: Vec::new(),
+            gesture_stroke: None,
+            stroke_travel: (PixelPos::default(), 0.),
+            meshed_for: None,
+            last_move: None,
+            key_repeat: false,
+            recording: None,
             coords: Default::default(),
         }
     }
@@ -96,6 +203,17 @@ impl<C: CoordinateSystem> SketchWidget<C> {
     }
 
     pub fn force_update<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
+        let level = crate::tess::zoom_level(sketch.zoom);
+        self.meshed_for = Some((level, self.pixel_tolerance));
+        self.stroke_options = self
+            .stroke_options
+            .with_line_cap(self.line_cap.into())
+            .with_line_join(self.line_join.into())
+            .with_tolerance(crate::tess::level_tolerance(level, self.pixel_tolerance));
+        sketch.use_tessellator(self.tessellator_kind);
+        sketch.use_brushes(&self.brushes);
+        sketch.use_stamp_spacing(self.stamp_spacing);
+        sketch.use_style_override(self.style_override);
         sketch.force_update::<C>(
             self.width,
             self.height,
@@ -104,28 +222,133 @@ impl<C: CoordinateSystem> SketchWidget<C> {
         );
     }
 
-    fn start_stroke<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
-        self.modified = true;
-        let stroke_brush_size = self.brush_size as f32 / sketch.zoom;
-        let key = sketch
-            .strokes
-            .insert(Stroke::new(sketch.fg_color, stroke_brush_size, true));
-        self.undo_stack.push(Action::DrawStroke(key));
+    /// tessellate every stroke again if the zoom has crossed into another
+    /// [level](crate::tess::zoom_level) since they were, so they look as smooth zoomed in as they
+    /// do zoomed out. waits for the stroke being drawn to be finished. true if it did
+    pub fn follow_zoom<S: StrokeBackend>(
+        &mut self,
+        config: &Config,
+        sketch: &mut Sketch<S>,
+    ) -> bool {
+        self.pixel_tolerance = config.mesh_pixel_tolerance;
+        let wanted = (crate::tess::zoom_level(sketch.zoom), self.pixel_tolerance);
+        // the stroke being drawn is meshed a bit at a time, so wait until it's finished
+        if self.meshed_for == Some(wanted) || self.drawing_stroke() {
+            return false;
+        }
+
+        tracing::debug!("tessellating for zoom level {}", wanted.0);
+        self.force_update(sketch);
+        true
+    }
+
+    /// switch tessellators and tessellate every stroke again with the new one
+    pub fn use_tessellator<S: StrokeBackend>(
+        &mut self,
+        kind: TessellatorKind,
+        sketch: &mut Sketch<S>,
+    ) {
+        self.tessellator_kind = kind;
+        self.force_update(sketch);
+    }
+
+    /// change the ends and corners of lines, and tessellate every stroke again with them
+    pub fn use_line_style<S: StrokeBackend>(
+        &mut self,
+        cap: CapStyle,
+        join: JoinStyle,
+        sketch: &mut Sketch<S>,
+    ) {
+        self.line_cap = cap;
+        self.line_join = join;
+        self.force_update(sketch);
     }
 
-    fn continue_stroke<S: StrokeBackend>(
+    /// draw every stroke in one style, or each in their own if `None`
+    pub fn use_style_override<S: StrokeBackend>(
         &mut self,
+        style: Option<StrokeStyle>,
         sketch: &mut Sketch<S>,
-        max_points: Option<usize>,
     ) {
+        self.style_override = style;
+        self.force_update(sketch);
+    }
+
+    /// space out the circles of circle strokes differently, and tessellate them again
+    pub fn use_stamp_spacing<S: StrokeBackend>(&mut self, spacing: f32, sketch: &mut Sketch<S>) {
+        self.stamp_spacing = spacing;
+        self.force_update(sketch);
+    }
+
+    fn start_stroke<S: StrokeBackend>(&mut self, config: &Config, sketch: &mut Sketch<S>) {
+        self.modified = true;
+        self.stroke_travel = (self.stylus.pixel, 0.);
+        self.lazy_brush.reset(self.stylus.pixel);
+        self.predictor.reset();
+        self.recognizer = Recognizer {
+            lines: config.auto_straighten,
+            shapes: config.auto_shapes,
+            tolerance: config.shape_tolerance,
+        };
+        let stroke_brush_size = self.brush_size as f32 / sketch.zoom;
+        let mut stroke = Stroke::new(sketch.fg_color, stroke_brush_size, true);
+        stroke.tessellator = self.tessellator_kind;
+        stroke.stamp_spacing = self.stamp_spacing;
+        stroke.brushes = self.brushes.clone();
+        stroke.style = config.default_style;
+        stroke.style_override = self.style_override;
+        stroke.update_primitive(sketch.zoom);
+        stroke.clip = sketch.clip;
+        stroke.crisp = self.crisp;
+        stroke.depth = self.depth;
+        let key = sketch.add_stroke(stroke);
+        self.undo_stack.push(Action::DrawStroke(key));
+    }
+
+    fn continue_stroke<S: StrokeBackend>(&mut self, config: &Config, sketch: &mut Sketch<S>) {
+        self.travel();
+        let mut stylus = self.stylus;
+
+        self.lazy_brush.radius = config.lazy_brush_radius;
+        if self.lazy_brush.enabled() {
+            match self.lazy_brush.update(self.stylus.pixel) {
+                Some(brush) => {
+                    stylus.pixel = brush;
+                    stylus.point = C::pixel_to_stroke(self.width, self.height, sketch.zoom, brush);
+                    stylus.pos = crate::graphics::xform_point_to_pos(sketch.origin, stylus.point);
+                }
+
+                // still inside the leash
+                None => return,
+            }
+        }
+
         if let Some(Action::DrawStroke(key)) = self.undo_stack.last() {
             if let Some(stroke) = sketch.strokes.get_mut(key) {
                 stroke.add_point(
-                    &self.stylus,
+                    &stylus,
                     &mut self.tesselator,
                     &self.stroke_options,
-                    max_points,
+                    config.max_points_before_split_stroke,
                 );
+
+                self.predictor.push(stylus.pos, Instant::now());
+                if config.prediction_ms > 0. {
+                    let ahead = Duration::from_secs_f32(config.prediction_ms / 1000.);
+                    if let Some(predicted) = self.predictor.predict(ahead) {
+                        stroke.predict(
+                            StrokeElement {
+                                x: predicted.x,
+                                y: predicted.y,
+                                pressure: stylus.pressure,
+                            },
+                            &mut self.tesselator,
+                            &self.stroke_options,
+                        );
+                    }
+                }
+
+                sketch.stroke_grew(key);
             } else {
                 tracing::error!("no stroke for key of last action");
             }
@@ -138,6 +361,11 @@ impl<C: CoordinateSystem> SketchWidget<C> {
         if let Some(Action::DrawStroke(key)) = self.undo_stack.last() {
             if let Some(stroke) = sketch.strokes.get_mut(key) {
                 stroke.finish();
+                if let Some(points) = self.recognizer.recognize(stroke.points()) {
+                    stroke.points = points;
+                    stroke.remesh(&mut self.tesselator, &self.stroke_options);
+                    sketch.index.invalidate();
+                }
             } else {
                 tracing::error!("no stroke for key of last action");
             }
@@ -146,7 +374,134 @@ impl<C: CoordinateSystem> SketchWidget<C> {
         }
     }
 
-    fn erase_strokes<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
+    /// end the stroke, unless it's a tap that didn't go anywhere and
+    /// [taps don't draw](Config::require_movement_to_commit)
+    fn commit_stroke<S: StrokeBackend>(&mut self, config: &Config, sketch: &mut Sketch<S>) {
+        self.travel();
+        if config.require_movement_to_commit && self.stroke_travel.1 < config.commit_movement_px {
+            tracing::debug!("discarding tap moved {} pixels", self.stroke_travel.1);
+            self.discard_stroke(sketch);
+        } else {
+            self.end_stroke(sketch);
+        }
+    }
+
+    fn travel(&mut self) {
+        let (start, furthest) = self.stroke_travel;
+        let distance = (self.stylus.pixel.x - start.x).hypot(self.stylus.pixel.y - start.y);
+        self.stroke_travel.1 = furthest.max(distance);
+    }
+
+    /// throw away the stroke being drawn, along with its undo
+    fn discard_stroke<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
+        if let Some(Action::DrawStroke(key)) = self.undo_stack.last() {
+            let _ = self.undo_stack.pop();
+            sketch.remove_stroke(key);
+        } else {
+            tracing::error!("last action not draw stroke in discard stroke or empty undo stack");
+        }
+
+        self.modified = !self.undo_stack.at_saved_state();
+    }
+
+    /// start using `tool` with another finger. the pen keeps its own tool
+    fn start_gesture<S: StrokeBackend>(
+        &mut self,
+        config: &Config,
+        sketch: &mut Sketch<S>,
+        tool: Tool,
+        touch: Touch,
+    ) {
+        self.gesture_tool = tool;
+        match tool {
+            Tool::Pen => {
+                self.update_stylus_from_touch(config, sketch, touch);
+                self.start_stroke(config, sketch);
+            }
+            Tool::Ruler => {
+                self.update_stylus_from_touch(config, sketch, touch);
+                self.start_measurement();
+            }
+            Tool::Eyedropper => {
+                self.update_stylus_from_touch(config, sketch, touch);
+                self.pick_color(sketch);
+            }
+            Tool::Select => {
+                self.update_stylus_from_touch(config, sketch, touch);
+                self.start_selection();
+            }
+            _ => {
+                // TODO
+                self.input.handle_mouse_move(touch.location.into());
+            }
+        }
+    }
+
+    /// throw away a stroke being drawn along with its undo, even if it isn't the last thing that
+    /// happened
+    fn forget_stroke<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>, key: DefaultKey) {
+        if self.undo_stack.forget(&Action::DrawStroke(key)) {
+            sketch.remove_stroke(key);
+        } else {
+            tracing::error!("no undo to forget for gesture stroke");
+        }
+
+        self.modified = !self.undo_stack.at_saved_state();
+    }
+
+    /// move and zoom the view so what was under the fingers stays under them
+    fn pinch_view<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>, pinch: PinchMove) {
+        let (width, height) = (self.width, self.height);
+        let pos = |sketch: &Sketch<S>, pixel| {
+            C::pixel_to_pos(width, height, sketch.zoom, sketch.origin, pixel)
+        };
+
+        let prev = pos(sketch, pinch.from);
+        let next = pos(sketch, pinch.to);
+        sketch.move_origin::<C>(width, height, prev, next);
+
+        if pinch.scale != 1. {
+            // zooming is around the middle of the screen, so put back what was under the fingers
+            let before = pos(sketch, pinch.to);
+            sketch.update_zoom::<C>(width, height, sketch.zoom * pinch.scale);
+            let after = pos(sketch, pinch.to);
+            sketch.move_origin::<C>(width, height, before, after);
+        }
+    }
+
+    fn start_measurement(&mut self) {
+        self.measurement = Some(Measurement::new(self.stylus.pos));
+    }
+
+    fn continue_measurement(&mut self) {
+        if let Some(measurement) = self.measurement.as_mut() {
+            measurement.end = self.stylus.pos;
+        }
+    }
+
+    fn start_selection(&mut self) {
+        self.selection_path = vec![self.stylus.pos];
+    }
+
+    fn continue_selection(&mut self) {
+        self.selection_path.push(self.stylus.pos);
+    }
+
+    /// select what the path went around, or the rectangle from its start to its end
+    fn finish_selection<S: StrokeBackend>(&mut self, config: &Config, sketch: &mut Sketch<S>) {
+        self.continue_selection();
+        let path = std::mem::take(&mut self.selection_path);
+        match config.select_mode {
+            SelectMode::Rectangle => sketch.select_rect(path[0], path[path.len() - 1]),
+            SelectMode::Lasso => sketch.select_lasso(&path),
+        };
+    }
+
+    /// the box around the cursor, and whether a point is close enough to it to be touched
+    fn cursor_area<S: StrokeBackend>(
+        &self,
+        sketch: &Sketch<S>,
+    ) -> (StrokePos, StrokePos, impl Fn(StrokePos) -> bool) {
         let stylus_pos_pix = C::pos_to_pixel(
             self.width,
             self.height,
@@ -177,46 +532,200 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             },
         );
 
+        let (width, height, zoom, origin) = (self.width, self.height, sketch.zoom, sketch.origin);
+        let brush_size = self.brush_size as f32;
+        let under_cursor = move |pos| {
+            let point_pix = C::pos_to_pixel(width, height, zoom, origin, pos);
+
+            ((stylus_pos_pix.x - point_pix.x).powi(2) + (stylus_pos_pix.y - point_pix.y).powi(2))
+                .sqrt()
+                <= brush_size
+        };
+
+        (top_left_cursor, bottom_right_cursor, under_cursor)
+    }
+
+    fn erase_strokes<S: StrokeBackend>(&mut self, config: &Config, sketch: &mut Sketch<S>) {
+        let (top_left_cursor, bottom_right_cursor, under_eraser) = self.cursor_area(sketch);
+
+        if crate::eraser::erase(
+            config.eraser_mode,
+            sketch,
+            &mut self.undo_stack,
+            &mut self.tesselator,
+            &self.stroke_options,
+            top_left_cursor,
+            bottom_right_cursor,
+            under_eraser,
+        ) {
+            self.modified = true;
+        }
+    }
+
+    /// lock the strokes under the cursor, or unlock them if they're all locked already
+    /// visible strokes with a point under the cursor, oldest first
+    fn strokes_under_cursor<S: StrokeBackend>(&self, sketch: &Sketch<S>) -> Vec<DefaultKey> {
+        let (top_left, bottom_right, under_cursor) = self.cursor_area(sketch);
         sketch
             .strokes
-            .iter_mut()
+            .iter()
             .filter(|(_, stroke)| {
                 stroke.visible
                     && !stroke.erased
-                    && stroke.aabb(top_left_cursor, bottom_right_cursor)
+                    && stroke.aabb(top_left, bottom_right)
+                    && stroke
+                        .points()
+                        .iter()
+                        .any(|point| under_cursor(point.into()))
             })
-            .for_each(|(key, stroke)| {
-                if stroke.vertices().any(|point| {
-                    let point_pix = C::pos_to_pixel(
-                        self.width,
-                        self.height,
-                        sketch.zoom,
-                        sketch.origin,
-                        StrokePos {
-                            x: point.x,
-                            y: point.y,
-                        },
-                    );
+            .map(|(key, _)| key)
+            .collect()
+    }
 
-                    ((stylus_pos_pix.x - point_pix.x).powi(2)
-                        + (stylus_pos_pix.y - point_pix.y).powi(2))
-                    .sqrt()
-                        <= self.brush_size as f32
-                }) {
-                    stroke.erase();
-                    self.undo_stack.push(Action::EraseStroke(key));
-                    self.modified = true;
-                }
-            });
+    /// use the color of the stroke under the cursor that's drawn on top. true if there was one
+    pub fn pick_color<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) -> bool {
+        let Some(key) = self
+            .strokes_under_cursor(sketch)
+            .into_iter()
+            .max_by(|a, b| {
+                sketch.strokes[*a]
+                    .depth
+                    .total_cmp(&sketch.strokes[*b].depth)
+            })
+        else {
+            return false;
+        };
+
+        sketch.fg_color = sketch.strokes[key].color;
+        tracing::debug!("picked {:?}", sketch.fg_color);
+        true
+    }
+
+    pub fn toggle_lock<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
+        let touched = self.strokes_under_cursor(sketch);
+
+        let locked = !touched.iter().all(|key| sketch.strokes[*key].locked);
+        let keys = sketch.set_locked(&touched, locked);
+        if keys.is_empty() {
+            return;
+        }
+
+        tracing::debug!("locked={} {} strokes", locked, keys.len());
+        self.undo_stack.push(Action::LockStrokes { keys, locked });
+        self.modified = true;
+    }
+
+    /// give the selected strokes the brush size, as big as it would draw at this zoom
+    fn resize_selection<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
+        let to = self.brush_size as f32 / sketch.zoom;
+        let from = sketch
+            .selection
+            .iter()
+            .filter_map(|key| Some((*key, sketch.strokes.get(*key)?.brush_size)))
+            .collect::<Vec<_>>();
+        let keys = sketch.set_stroke_brush_size(
+            &sketch.selection.clone(),
+            to,
+            &mut self.tesselator,
+            &self.stroke_options,
+        );
+        if keys.is_empty() {
+            return;
+        }
+
+        let repeat = self.key_repeat;
+        match self.undo_stack.last_mut() {
+            // holding the key down is one change, pressing it again is another
+            Some(Action::ResizeStrokes {
+                keys: last,
+                to: last_to,
+                ..
+            }) if repeat && *last == keys => *last_to = to,
+
+            _ => {
+                let from = keys
+                    .iter()
+                    .filter_map(|key| from.iter().find(|(had, _)| had == key))
+                    .map(|(_, size)| *size)
+                    .collect();
+                self.undo_stack
+                    .push(Action::ResizeStrokes { keys, from, to });
+            }
+        }
+        self.modified = true;
+    }
+
+    /// give the selected strokes the foreground color
+    pub fn recolor_selection<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
+        let to = sketch.fg_color;
+        let from = sketch
+            .selection
+            .iter()
+            .filter_map(|key| Some((*key, sketch.strokes.get(*key)?.color)))
+            .collect::<Vec<_>>();
+        let keys = sketch.set_stroke_color(&sketch.selection.clone(), to);
+        if keys.is_empty() {
+            return;
+        }
+
+        let from = keys
+            .iter()
+            .filter_map(|key| from.iter().find(|(had, _)| had == key))
+            .map(|(_, color)| *color)
+            .collect();
+        self.undo_stack
+            .push(Action::RecolorStrokes { keys, from, to });
+        self.modified = true;
+    }
+
+    /// start over with the configured template or an empty sketch, asking to save the current one
+    /// first if it's modified
+    pub fn new_document<S: StrokeBackend>(&mut self, config: &Config, sketch: &mut Sketch<S>) {
+        if super::new_file(self, sketch, config.template.as_deref()) {
+            self.state = SketchWidgetState::Ready;
+            self.measurement = None;
+            self.force_update(sketch);
+        }
     }
 
     pub fn undo<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
+        sketch.index.invalidate();
         match self.undo_stack.undo() {
             Some(Action::DrawStroke(stroke)) => sketch.strokes[stroke].erase(),
             Some(Action::EraseStroke(stroke)) => {
                 sketch.strokes[stroke].erased = false;
                 sketch.update_visible_strokes::<C>(self.width, self.height);
             }
+            Some(Action::SplitStroke { from, into }) => {
+                into.iter().for_each(|piece| sketch.strokes[*piece].erase());
+                sketch.strokes[from].erased = false;
+                sketch.update_visible_strokes::<C>(self.width, self.height);
+            }
+            Some(Action::RewindStroke { key, points }) => {
+                let stroke = &mut sketch.strokes[key];
+                stroke.points.extend(points.into_iter().rev());
+                stroke.erased = false;
+                stroke.remesh(&mut self.tesselator, &self.stroke_options);
+                sketch.update_visible_strokes::<C>(self.width, self.height);
+            }
+            Some(Action::LockStrokes { keys, locked }) => {
+                sketch.set_locked(&keys, !locked);
+            }
+            Some(Action::ResizeStrokes { keys, from, .. }) => {
+                for (key, size) in keys.iter().zip(from) {
+                    sketch.set_stroke_brush_size(
+                        &[*key],
+                        size,
+                        &mut self.tesselator,
+                        &self.stroke_options,
+                    );
+                }
+            }
+            Some(Action::RecolorStrokes { keys, from, .. }) => {
+                for (key, color) in keys.iter().zip(from) {
+                    sketch.set_stroke_color(&[*key], color);
+                }
+            }
             None => {}
         }
 
@@ -224,12 +733,39 @@ impl<C: CoordinateSystem> SketchWidget<C> {
     }
 
     pub fn redo<S: StrokeBackend>(&mut self, sketch: &mut Sketch<S>) {
+        sketch.index.invalidate();
         match self.undo_stack.redo() {
             Some(Action::DrawStroke(stroke)) => {
                 sketch.strokes[stroke].erased = false;
                 sketch.update_visible_strokes::<C>(self.width, self.height);
             }
             Some(Action::EraseStroke(stroke)) => sketch.strokes[stroke].erase(),
+            Some(Action::SplitStroke { from, into }) => {
+                sketch.strokes[from].erase();
+                into.iter()
+                    .for_each(|piece| sketch.strokes[*piece].erased = false);
+                sketch.update_visible_strokes::<C>(self.width, self.height);
+            }
+            Some(Action::RewindStroke { key, points }) => {
+                let stroke = &mut sketch.strokes[key];
+                let len = stroke.points.len().saturating_sub(points.len());
+                stroke.points.truncate(len);
+                if stroke.points.is_empty() {
+                    stroke.erase();
+                } else {
+                    stroke.remesh(&mut self.tesselator, &self.stroke_options);
+                    sketch.update_visible_strokes::<C>(self.width, self.height);
+                }
+            }
+            Some(Action::LockStrokes { keys, locked }) => {
+                sketch.set_locked(&keys, locked);
+            }
+            Some(Action::ResizeStrokes { keys, to, .. }) => {
+                sketch.set_stroke_brush_size(&keys, to, &mut self.tesselator, &self.stroke_options);
+            }
+            Some(Action::RecolorStrokes { keys, to, .. }) => {
+                sketch.set_stroke_color(&keys, to);
+            }
             None => {}
         }
 
@@ -266,13 +802,23 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             ..
         } = touch;
 
-        let pressure = force.map(|force| force.normalized()).unwrap_or(1.0);
+        let pressure = force
+            .map(|force| {
+                pressure::remap(
+                    config.pressure_curve,
+                    config.pressure_min,
+                    config.pressure_max,
+                    force.normalized() as f32,
+                ) as f64
+            })
+            .unwrap_or(1.0);
 
         if let Some(pen_info) = pen_info {
             if config.stylus_may_be_inverted {
+                // flipping the pen back over shouldn't drop other tools like the eyedropper
                 if pen_info.inverted || pen_info.eraser {
                     self.active_tool = Tool::Eraser;
-                } else {
+                } else if self.active_tool == Tool::Eraser {
                     self.active_tool = Tool::Pen;
                 }
             }
@@ -321,19 +867,154 @@ impl<C: CoordinateSystem> SketchWidget<C> {
     }
 
     fn increase_brush(&mut self, by: usize) {
-        self.brush_size += by;
+        self.brush_size = self.brush_size.saturating_add(by);
         self.brush_size = self.brush_size.clamp(crate::MIN_BRUSH, crate::MAX_BRUSH);
 
         tracing::debug!("increase brush {}", self.brush_size);
     }
 
     fn decrease_brush(&mut self, by: usize) {
-        self.brush_size -= by;
+        self.brush_size = self.brush_size.saturating_sub(by);
         self.brush_size = self.brush_size.clamp(crate::MIN_BRUSH, crate::MAX_BRUSH);
 
         tracing::debug!("decrease brush {}", self.brush_size);
     }
 
+    /// whether the event is from a finger that touched while the pen was in use, which is
+    /// probably the hand holding it
+    fn palm(&mut self, config: &Config, event: &Event, now: Instant) -> bool {
+        match event {
+            Event::PenDown(_) | Event::PenMove(_) | Event::PenUp(_) => {
+                self.last_pen_instant = Some(now);
+                false
+            }
+
+            Event::Touch(touch) if touch.pen_info.is_none() => {
+                let palm = match (config.palm_rejection_window(), self.last_pen_instant) {
+                    (Some(window), Some(last)) => now.saturating_duration_since(last) < window,
+                    _ => false,
+                };
+                if palm {
+                    self.palms.push(touch.id);
+                }
+                palm
+            }
+
+            Event::TouchMove(touch) => self.palms.contains(&touch.id),
+
+            Event::Release(touch) => {
+                let palm = self.palms.contains(&touch.id);
+                self.palms.retain(|id| *id != touch.id);
+                palm
+            }
+
+            _ => false,
+        }
+    }
+
+    /// whether the event is a touch that moved to exactly where the last one did, which some
+    /// drivers send more than once
+    fn duplicate_move(&mut self, event: &Event) -> bool {
+        let touch = match *event {
+            Event::Touch(touch)
+            | Event::TouchMove(touch)
+            | Event::Release(touch)
+            | Event::PenDown(touch)
+            | Event::PenMove(touch)
+            | Event::PenUp(touch) => touch,
+            _ => return false,
+        };
+
+        if touch.phase != TouchPhase::Moved {
+            self.last_move = None;
+            return false;
+        }
+
+        let pressure = |touch: &Touch| touch.force.map(|force| force.normalized());
+        let duplicate = self.last_move.map_or(false, |last| {
+            last.id == touch.id
+                && (last.location.x - touch.location.x).abs() < DUPLICATE_MOVE_EPSILON
+                && (last.location.y - touch.location.y).abs() < DUPLICATE_MOVE_EPSILON
+                && match (pressure(&last), pressure(&touch)) {
+                    (Some(a), Some(b)) => (a - b).abs() < DUPLICATE_MOVE_EPSILON,
+                    (a, b) => a == b,
+                }
+        });
+
+        self.last_move = Some(touch);
+        duplicate
+    }
+
+    /// remember every event from now on, until [`stop_recording`](Self::stop_recording). starting
+    /// again throws away what was recorded so far
+    pub fn start_recording(&mut self) {
+        tracing::info!("start recording");
+        self.recording = Some((Instant::now(), SessionLog::new(self.width, self.height)));
+    }
+
+    /// the events since [`start_recording`](Self::start_recording), or none if it wasn't recording
+    pub fn stop_recording(&mut self) -> SessionLog {
+        tracing::info!("stop recording");
+        self.recording
+            .take()
+            .map(|(_, log)| log)
+            .unwrap_or_else(|| SessionLog::new(self.width, self.height))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// like [`next`](Self::next), but also says what the event did. the same events applied to the
+    /// same sketch always have the same effects, so a session can be recorded and replayed
+    pub fn apply<S: StrokeBackend>(
+        &mut self,
+        config: &Config,
+        sketch: &mut Sketch<S>,
+        event: Event,
+    ) -> Vec<Effect> {
+        let before = sketch
+            .strokes
+            .iter()
+            .map(|(key, stroke)| (key, (stroke.points().len(), stroke.erased())))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let view = (sketch.zoom, sketch.origin);
+
+        self.next(config, sketch, event);
+
+        let mut effects = Vec::new();
+        for (key, stroke) in sketch.strokes.iter() {
+            match before.get(&key) {
+                None => effects.push(Effect::StrokeAdded(key)),
+                Some(&(_, false)) if stroke.erased() => effects.push(Effect::StrokeErased(key)),
+                Some(&(points, _)) if points != stroke.points().len() => {
+                    effects.push(Effect::StrokeChanged(key))
+                }
+                Some(_) => {}
+            }
+        }
+        effects.extend(
+            before
+                .keys()
+                .filter(|key| !sketch.strokes.contains_key(**key))
+                .map(|key| Effect::StrokeRemoved(*key)),
+        );
+
+        if view != (sketch.zoom, sketch.origin) {
+            effects.push(Effect::ViewChanged);
+        }
+
+        if !effects.is_empty() || self.state.redraw() {
+            effects.push(Effect::Redraw);
+        }
+
+        if matches!(event, Event::Exit) {
+            effects.push(Effect::Quit);
+        }
+
+        effects
+    }
+
     pub fn next<S: StrokeBackend>(
         &mut self,
         config: &Config,
@@ -345,19 +1026,38 @@ impl<C: CoordinateSystem> SketchWidget<C> {
 
         tracing::trace!("WIDGET STATE {:?} NEXT {:?}", self.state, event);
 
+        if let Some((start, log)) = self.recording.as_mut() {
+            log.events.push(TimedEvent {
+                ms: start.elapsed().as_millis() as u64,
+                event: event.into(),
+            });
+        }
+
+        if self.duplicate_move(&event) {
+            tracing::trace!("skipping duplicate move");
+            return;
+        }
+
+        if self.palm(config, &event, Instant::now()) {
+            tracing::trace!("ignoring palm");
+            return;
+        }
+
         self.state = match (self.state, event) {
             (state, E::Exit) => {
-                self.proxy.send_event(LoopEvent::Quit).unwrap();
+                self.send(LoopEvent::Quit);
                 state
             }
 
             (S::Ready, E::IncreaseBrush(change)) => {
                 self.increase_brush(change);
+                self.resize_selection(sketch);
                 S::Ready
             }
 
             (S::Ready, E::DecreaseBrush(change)) => {
                 self.decrease_brush(change);
+                self.resize_selection(sketch);
                 S::Ready
             }
 
@@ -384,17 +1084,36 @@ impl<C: CoordinateSystem> SketchWidget<C> {
                     self.update_stylus_from_mouse(config, sketch, TouchPhase::Started);
                     match self.active_tool {
                         Tool::Pen => {
-                            self.start_stroke(sketch);
+                            self.start_stroke(config, sketch);
                             S::MouseDraw
                         }
                         Tool::Eraser => S::MouseErase,
                         Tool::Pan => S::Pan,
+                        Tool::Eyedropper => {
+                            self.pick_color(sketch);
+                            S::Ready
+                        }
+                        Tool::Ruler => {
+                            self.start_measurement();
+                            S::MouseMeasure
+                        }
+                        Tool::Select => {
+                            self.start_selection();
+                            S::Select
+                        }
                     }
                 } else {
                     S::Pan
                 }
             }
 
+            // after a tap with the eyedropper
+            (S::Ready, E::MouseUp(button)) => {
+                self.input
+                    .handle_mouse_button(button, ElementState::Released);
+                S::Ready
+            }
+
             (S::Pan, E::MouseUp(button)) => {
                 self.input
                     .handle_mouse_button(button, ElementState::Released);
@@ -476,29 +1195,47 @@ impl<C: CoordinateSystem> SketchWidget<C> {
                 self.update_stylus_from_touch(config, sketch, touch);
                 match self.active_tool {
                     Tool::Pen => {
-                        self.start_stroke(sketch);
+                        self.start_stroke(config, sketch);
                         S::PenDraw
                     }
                     Tool::Eraser => S::PenErase,
                     Tool::Pan => S::Pan,
+                    Tool::Eyedropper => {
+                        self.pick_color(sketch);
+                        S::Ready
+                    }
+                    Tool::Ruler => {
+                        self.start_measurement();
+                        S::PenMeasure
+                    }
+                    Tool::Select => {
+                        self.start_selection();
+                        S::Select
+                    }
                 }
             }
 
             (S::PenDraw, E::PenMove(touch)) => {
                 self.update_stylus_from_touch(config, sketch, touch);
-                self.continue_stroke(sketch, config.max_points_before_split_stroke);
+                self.continue_stroke(config, sketch);
                 S::PenDraw
             }
 
             (S::PenDraw, E::PenUp(touch)) => {
                 self.update_stylus_from_touch(config, sketch, touch);
-                self.end_stroke(sketch);
+                // the system took the touch away, usually for a palm or a gesture, so it wasn't
+                // meant to draw anything
+                if touch.phase == TouchPhase::Cancelled {
+                    self.discard_stroke(sketch);
+                } else {
+                    self.commit_stroke(config, sketch);
+                }
                 S::Ready
             }
 
             (S::PenErase, E::PenMove(touch)) => {
                 self.update_stylus_from_touch(config, sketch, touch);
-                self.erase_strokes(sketch);
+                self.erase_strokes(config, sketch);
                 S::PenErase
             }
 
@@ -507,117 +1244,168 @@ impl<C: CoordinateSystem> SketchWidget<C> {
                 S::Ready
             }
 
-            // mouse input
-            (S::Ready, E::MouseMove(location)) => {
-                self.input.handle_mouse_move(location);
-
-                if config.use_mouse_for_pen {
-                    self.update_stylus_from_mouse(config, sketch, TouchPhase::Moved);
-                }
+            (S::PenMeasure, E::PenMove(touch)) => {
+                self.update_stylus_from_touch(config, sketch, touch);
+                self.continue_measurement();
+                S::PenMeasure
+            }
 
+            (S::PenMeasure, E::PenUp(touch)) => {
+                self.update_stylus_from_touch(config, sketch, touch);
+                self.continue_measurement();
                 S::Ready
             }
 
-            (S::MouseDraw, E::MouseMove(location)) => {
-                self.input.handle_mouse_move(location);
-                self.update_stylus_from_mouse(config, sketch, TouchPhase::Moved);
-                self.continue_stroke(sketch, config.max_points_before_split_stroke);
-                S::MouseDraw
+            // selection, from the pen or the mouse
+            (S::Select, E::PenMove(touch)) => {
+                self.update_stylus_from_touch(config, sketch, touch);
+                self.continue_selection();
+                S::Select
             }
 
-            (S::MouseDraw, E::MouseUp(button)) => {
-                self.input
-                    .handle_mouse_button(button, ElementState::Released);
-                self.update_stylus_from_mouse(config, sketch, TouchPhase::Ended);
+            (S::Select, E::PenUp(touch)) => {
+                self.update_stylus_from_touch(config, sketch, touch);
+                self.finish_selection(config, sketch);
                 S::Ready
             }
 
-            (S::MouseErase, E::MouseMove(location)) => {
+            (S::Select, E::MouseMove(location)) => {
                 self.input.handle_mouse_move(location);
                 self.update_stylus_from_mouse(config, sketch, TouchPhase::Moved);
-                self.erase_strokes(sketch);
-                S::MouseErase
+                self.continue_selection();
+                S::Select
             }
 
-            (S::MouseErase, E::MouseUp(button)) => {
+            (S::Select, E::MouseUp(button)) => {
                 self.input
                     .handle_mouse_button(button, ElementState::Released);
                 self.update_stylus_from_mouse(config, sketch, TouchPhase::Ended);
+                self.finish_selection(config, sketch);
                 S::Ready
             }
 
-            // TODO: touch input, pan & zoom
-            (S::Ready, E::Touch(touch)) => {
-                let tool = config.tool_for_gesture(self.active_tool, 1);
-                self.active_tool = tool;
-                match self.active_tool {
-                    Tool::Pen => {
-                        self.update_stylus_from_touch(config, sketch, touch);
-                        self.start_stroke(sketch);
-                    }
-                    _ => {
-                        // TODO
-                        self.input.handle_mouse_move(touch.location.into());
-                    }
+            // mouse input
+            (S::Ready, E::MouseMove(location)) => {
+                self.input.handle_mouse_move(location);
+
+                if config.use_mouse_for_pen {
+                    self.update_stylus_from_mouse(config, sketch, TouchPhase::Moved);
                 }
 
-                S::Gesture(1)
+                S::Ready
+            }
+
+            (S::MouseDraw, E::MouseMove(location)) => {
+                self.input.handle_mouse_move(location);
+                self.update_stylus_from_mouse(config, sketch, TouchPhase::Moved);
+                self.continue_stroke(config, sketch);
+                S::MouseDraw
             }
 
+            (S::MouseDraw, E::MouseUp(button)) => {
+                self.input
+                    .handle_mouse_button(button, ElementState::Released);
+                self.update_stylus_from_mouse(config, sketch, TouchPhase::Ended);
+                self.commit_stroke(config, sketch);
+                S::Ready
+            }
+
+            (S::MouseErase, E::MouseMove(location)) => {
+                self.input.handle_mouse_move(location);
+                self.update_stylus_from_mouse(config, sketch, TouchPhase::Moved);
+                self.erase_strokes(config, sketch);
+                S::MouseErase
+            }
+
+            (S::MouseErase, E::MouseUp(button)) => {
+                self.input
+                    .handle_mouse_button(button, ElementState::Released);
+                self.update_stylus_from_mouse(config, sketch, TouchPhase::Ended);
+                S::Ready
+            }
+
+            (S::MouseMeasure, E::MouseMove(location)) => {
+                self.input.handle_mouse_move(location);
+                self.update_stylus_from_mouse(config, sketch, TouchPhase::Moved);
+                self.continue_measurement();
+                S::MouseMeasure
+            }
+
+            (S::MouseMeasure, E::MouseUp(button)) => {
+                self.input
+                    .handle_mouse_button(button, ElementState::Released);
+                self.update_stylus_from_mouse(config, sketch, TouchPhase::Ended);
+                S::Ready
+            }
+
+            (S::Ready, E::Touch(touch)) => match config.tool_for_gesture(self.active_tool, 1) {
+                Some(tool) => {
+                    self.taps.touch(touch.id, touch.location.into());
+                    self.pinch.touch(touch.id, touch.location.into());
+                    self.start_gesture(config, sketch, tool, touch);
+                    self.gesture_stroke = match (tool, self.undo_stack.last()) {
+                        (Tool::Pen, Some(Action::DrawStroke(key))) => Some(key),
+                        _ => None,
+                    };
+                    S::Gesture(1)
+                }
+
+                None => S::Ready,
+            },
+
             (S::Gesture(i), E::Touch(touch)) => {
-                // TODO dedup, more movement tolerance for gesture state transition
-                let tool = config.tool_for_gesture(self.active_tool, i + 1);
-                self.active_tool = tool;
-                match self.active_tool {
-                    Tool::Pen => {
-                        self.update_stylus_from_touch(config, sketch, touch);
-                        self.start_stroke(sketch);
-                    }
-                    _ => {
-                        // TODO
-                        self.input.handle_mouse_move(touch.location.into());
-                    }
+                self.taps.touch(touch.id, touch.location.into());
+                self.pinch.touch(touch.id, touch.location.into());
+
+                // the first finger was starting a gesture, not drawing. its stroke goes by key
+                // since it might not be the last thing that happened anymore
+                if let Some(key) = self.gesture_stroke.take() {
+                    self.forget_stroke(sketch, key);
                 }
 
+                // TODO more movement tolerance for gesture state transition
+                let tool = config
+                    .tool_for_gesture(self.active_tool, i + 1)
+                    .unwrap_or(self.gesture_tool);
+                self.start_gesture(config, sketch, tool, touch);
                 S::Gesture(i + 1)
             }
 
             (S::Gesture(i), E::TouchMove(touch)) => {
-                let tool = config.tool_for_gesture(self.active_tool, i);
-                self.active_tool = tool;
+                self.taps.move_to(touch.id, touch.location.into());
+                let pinch = self.pinch.move_to(touch.id, touch.location.into());
 
-                match tool {
+                match self.gesture_tool {
                     Tool::Pen => {
                         // TODO dedup, logic???
                         self.update_stylus_from_touch(config, sketch, touch);
-                        self.continue_stroke(sketch, config.max_points_before_split_stroke);
+                        self.continue_stroke(config, sketch);
                     }
 
                     Tool::Eraser => {
                         self.update_stylus_from_touch(config, sketch, touch);
-                        self.erase_strokes(sketch);
+                        self.erase_strokes(config, sketch);
                     }
 
-                    Tool::Pan => {
-                        let prev = C::pixel_to_pos(
-                            self.width,
-                            self.height,
-                            sketch.zoom,
-                            sketch.origin,
-                            self.input.cursor_pos(),
-                        );
+                    Tool::Ruler => {
+                        self.update_stylus_from_touch(config, sketch, touch);
+                        self.continue_measurement();
+                    }
 
-                        self.input.handle_mouse_move(touch.location.into());
+                    Tool::Eyedropper => {
+                        self.update_stylus_from_touch(config, sketch, touch);
+                        self.pick_color(sketch);
+                    }
 
-                        let next = C::pixel_to_pos(
-                            self.width,
-                            self.height,
-                            sketch.zoom,
-                            sketch.origin,
-                            self.input.cursor_pos(),
-                        );
+                    Tool::Select => {
+                        self.update_stylus_from_touch(config, sketch, touch);
+                        self.continue_selection();
+                    }
 
-                        sketch.move_origin::<C>(self.width, self.height, prev, next);
+                    Tool::Pan => {
+                        if let Some(pinch) = pinch {
+                            self.pinch_view(sketch, pinch);
+                        }
                     }
                 }
 
@@ -625,21 +1413,38 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             }
 
             (S::Gesture(i), E::Release(touch)) => {
+                let tapped = self.taps.release(touch.id, touch.location.into());
+                self.pinch.release(touch.id);
+
                 #[allow(clippy::single_match)]
-                match self.active_tool {
+                match self.gesture_tool {
                     Tool::Pen => {
                         self.update_stylus_from_touch(config, sketch, touch);
-                        self.end_stroke(sketch);
+                        // a palm, or a system gesture taking over
+                        if touch.phase == TouchPhase::Cancelled {
+                            self.discard_stroke(sketch);
+                        } else {
+                            self.commit_stroke(config, sketch);
+                        }
                     }
 
-                    Tool::Eraser => {
+                    Tool::Eraser | Tool::Ruler | Tool::Eyedropper => {
+                        self.update_stylus_from_touch(config, sketch, touch);
+                    }
+
+                    Tool::Select => {
                         self.update_stylus_from_touch(config, sketch, touch);
+                        self.finish_selection(config, sketch);
                     }
 
                     _ => {}
                 }
 
                 if i == 1 {
+                    self.gesture_stroke = None;
+                    if tapped == Some(2) && config.two_finger_tap_undo {
+                        self.undo(sketch);
+                    }
                     S::Ready
                 } else {
                     S::Gesture(i - 1)
@@ -648,6 +1453,51 @@ impl<C: CoordinateSystem> SketchWidget<C> {
 
             (any, _) => any,
         };
+
+        self.idle.timeout = config.stroke_idle_timeout();
+        if !self.drawing_stroke() {
+            self.idle.stop();
+        } else if matches!(
+            event,
+            E::PenDown(_) | E::PenMove(_) | E::Touch(_) | E::TouchMove(_)
+        ) {
+            self.idle.push(Instant::now());
+        }
+
+        self.follow_zoom(config, sketch);
+    }
+
+    /// tell the loop about something, if there is one
+    fn send(&self, event: LoopEvent) {
+        if let Some(proxy) = self.proxy.as_ref() {
+            proxy.send_event(event).unwrap();
+        }
+    }
+
+    /// whether a pen or a finger is in the middle of a stroke
+    fn drawing_stroke(&self) -> bool {
+        match self.state {
+            SketchWidgetState::PenDraw => true,
+            SketchWidgetState::Gesture(1) => self.gesture_tool == Tool::Pen,
+            _ => false,
+        }
+    }
+
+    /// finish the stroke being drawn if it hasn't gotten any input for
+    /// [`stroke_idle_ms`](Config::stroke_idle_ms). true if it did
+    pub fn finish_idle_stroke<S: StrokeBackend>(
+        &mut self,
+        sketch: &mut Sketch<S>,
+        now: Instant,
+    ) -> bool {
+        if !self.drawing_stroke() || !self.idle.take(now) {
+            return false;
+        }
+
+        tracing::debug!("finishing idle stroke");
+        self.end_stroke(sketch);
+        self.state = SketchWidgetState::Ready;
+        true
     }
 
     pub fn handle_key<S: StrokeBackend>(
@@ -658,6 +1508,8 @@ impl<C: CoordinateSystem> SketchWidget<C> {
         state: ElementState,
     ) {
         tracing::debug!("handle key {key:?} {state:?}");
+        // held keys keep saying they were pressed without being released in between
+        self.key_repeat = state == ElementState::Pressed && self.input.is_down(key);
         self.input.handle_key(key, state);
 
         if self.input.combo_just_pressed(&config.brush_increase) {
@@ -668,13 +1520,17 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             self.next(config, sketch, Event::DecreaseBrush(crate::BRUSH_DELTA));
         }
 
-        if dbg!(self
+        if self
             .input
-            .combo_just_pressed(&config.debug_toggle_show_info))
+            .combo_just_pressed(&config.debug_toggle_show_info)
         {
             config.debug_show_info = !config.debug_show_info;
         }
 
+        if self.input.combo_just_pressed(&config.debug_quit) {
+            self.next(config, sketch, Event::Exit);
+        }
+
         if self.input.combo_just_pressed(&config.debug_clear_strokes) {
             sketch.clear_strokes();
             self.undo_stack.clear();
@@ -734,7 +1590,9 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             self.undo(sketch);
         }
 
-        if self.input.combo_just_pressed(&config.redo) {
+        if self.input.combo_just_pressed(&config.redo)
+            || self.input.combo_just_pressed(&config.redo_alt)
+        {
             self.redo(sketch);
         }
 
@@ -760,7 +1618,15 @@ impl<C: CoordinateSystem> SketchWidget<C> {
         }
 
         if self.input.combo_just_pressed(&config.new) {
-            super::new_file(self, sketch);
+            self.new_document(config, sketch);
+        }
+
+        if self.input.combo_just_pressed(&config.open_document) {
+            self.send(LoopEvent::NewDocument);
+        }
+
+        if self.input.combo_just_pressed(&config.next_document) {
+            self.send(LoopEvent::NextDocument);
         }
 
         if self.input.combo_just_pressed(&config.zoom_out) {
@@ -798,19 +1664,33 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             }
         }
 
+        if self.input.combo_just_pressed(&config.recolor_selection) {
+            self.recolor_selection(sketch);
+        }
+
+        if self.input.combo_just_pressed(&config.toggle_lock) {
+            self.toggle_lock(sketch);
+        }
+
+        if self.input.combo_just_pressed(&config.cycle_eraser_mode) {
+            config.eraser_mode = config.eraser_mode.next();
+            tracing::info!("eraser mode {:?}", config.eraser_mode);
+        }
+
         if self
             .input
             .combo_just_pressed(&config.debug_toggle_use_mouse_for_pen)
         {
             config.use_mouse_for_pen = !config.use_mouse_for_pen;
-            println!("using mouse for pen? {}", config.use_mouse_for_pen);
+            tracing::info!("using mouse for pen? {}", config.use_mouse_for_pen);
         }
 
         if self
             .input
             .combo_just_pressed(&config.debug_toggle_use_finger_for_pen)
         {
-            // meh
+            config.finger_policy = config.finger_policy.next();
+            tracing::info!("finger policy {:?}", config.finger_policy);
         }
 
         if self
@@ -818,7 +1698,7 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             .combo_just_pressed(&config.debug_toggle_stylus_invertability)
         {
             config.stylus_may_be_inverted = !config.stylus_may_be_inverted;
-            println!("stylus invertable? {}", config.stylus_may_be_inverted);
+            tracing::info!("stylus invertable? {}", config.stylus_may_be_inverted);
         }
 
         if self
@@ -829,7 +1709,7 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             self.force_update(sketch);
         }
 
-        if self.input.combo_just_pressed(&Keycode::L.into()) {
+        if self.input.combo_just_pressed(&config.debug_toggle_language) {
             use crate::i18n::*;
             if get_lang() == "es" {
                 set_lang("en");
@@ -838,6 +1718,1158 @@ impl<C: CoordinateSystem> SketchWidget<C> {
             }
         }
 
+        self.follow_zoom(config, sketch);
         self.input.pump_key_state();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::FingerPolicy,
+        graphics::{Color, ColorExt},
+        null::{NullCoords, NullStrokeBackend},
+    };
+    use winit::{
+        dpi::PhysicalPosition,
+        event::{DeviceId, MouseButton, PenInfo},
+    };
+
+    fn pen(phase: TouchPhase, x: f64, y: f64) -> Touch {
+        Touch {
+            // never compared with a real device
+            device_id: unsafe { DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition { x, y },
+            force: None,
+            id: 0,
+            pen_info: Some(PenInfo {
+                barrel: false,
+                inverted: false,
+                eraser: false,
+            }),
+        }
+    }
+
+    fn finger(phase: TouchPhase, x: f64, y: f64) -> Touch {
+        Touch {
+            pen_info: None,
+            ..pen(phase, x, y)
+        }
+    }
+
+    /// down at the first point, through the rest, and up at the last
+    fn draw(
+        widget: &mut SketchWidget<NullCoords>,
+        sketch: &mut Sketch<NullStrokeBackend>,
+        config: &Config,
+        path: &[(f64, f64)],
+    ) {
+        let (first, rest) = path.split_first().unwrap();
+        widget.next(
+            config,
+            sketch,
+            Event::PenDown(pen(TouchPhase::Started, first.0, first.1)),
+        );
+        for (x, y) in rest {
+            widget.next(
+                config,
+                sketch,
+                Event::PenMove(pen(TouchPhase::Moved, *x, *y)),
+            );
+        }
+        let (x, y) = path.last().unwrap();
+        widget.next(config, sketch, Event::PenUp(pen(TouchPhase::Ended, *x, *y)));
+    }
+
+    fn press(
+        widget: &mut SketchWidget<NullCoords>,
+        sketch: &mut Sketch<NullStrokeBackend>,
+        config: &mut Config,
+        keys: &[Keycode],
+    ) {
+        for key in keys {
+            widget.handle_key(config, sketch, *key, ElementState::Pressed);
+        }
+        for key in keys.iter().rev() {
+            widget.handle_key(config, sketch, *key, ElementState::Released);
+        }
+    }
+
+    #[test]
+    fn pen_draws_stroke() {
+        let config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+
+        let path = (0..=10)
+            .map(|i| (100. + i as f64 * 20., 100. + i as f64 * 5.))
+            .collect::<Vec<_>>();
+        draw(&mut widget, &mut sketch, &config, &path);
+        assert_eq!(widget.state, SketchWidgetState::Ready);
+        assert!(widget.modified);
+
+        assert_eq!(sketch.strokes.len(), 1);
+        let (key, stroke) = sketch.strokes.iter().next().unwrap();
+        assert!(stroke.points().len() >= 9);
+        assert!(stroke.vertices().count() > 0);
+        assert!(stroke.done);
+        assert_eq!(widget.undo_stack.last(), Some(Action::DrawStroke(key)));
+
+        // the stroke starts under where the pen first moved
+        let first = stroke.points()[0];
+        let pixel = NullCoords::pos_to_pixel(
+            widget.width,
+            widget.height,
+            sketch.zoom,
+            sketch.origin,
+            first.into(),
+        );
+        assert!((pixel.x - 120.).abs() < 1. && (pixel.y - 105.).abs() < 1.);
+    }
+
+    #[test]
+    fn new_strokes_use_default_style() {
+        let mut config = Config::new();
+        config.default_style = StrokeStyle::Circles;
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+
+        let path = [(100., 100.), (200., 100.)];
+        draw(&mut widget, &mut sketch, &config, &path);
+        config.default_style = StrokeStyle::Points;
+        draw(&mut widget, &mut sketch, &config, &path);
+
+        let mut styles = sketch
+            .strokes
+            .values()
+            .map(|stroke| stroke.style)
+            .collect::<Vec<_>>();
+        styles.sort_by_key(|style| *style as u8);
+        assert_eq!(styles, vec![StrokeStyle::Circles, StrokeStyle::Points]);
+
+        // all drawn in one style when they don't get their own, which isn't saved
+        let hash = sketch.content_hash();
+        let saved = |sketch: &Sketch<NullStrokeBackend>| {
+            let mut styles = sketch
+                .strokes
+                .values()
+                .map(|stroke| stroke.style)
+                .collect::<Vec<_>>();
+            styles.sort_by_key(|style| *style as u8);
+            styles
+        };
+        config.use_individual_style = false;
+        config.default_style = StrokeStyle::Spline;
+        widget.use_style_override(config.style_override(), &mut sketch);
+        assert!(sketch
+            .strokes
+            .values()
+            .all(|stroke| stroke.drawn_style() == StrokeStyle::Spline));
+        assert_eq!(saved(&sketch), styles);
+        assert_eq!(sketch.content_hash(), hash);
+
+        config.use_individual_style = true;
+        widget.use_style_override(config.style_override(), &mut sketch);
+        assert!(sketch
+            .strokes
+            .values()
+            .all(|stroke| stroke.drawn_style() == stroke.style));
+        assert_eq!(saved(&sketch), styles);
+        assert_eq!(sketch.content_hash(), hash);
+
+        // strokes drawn meanwhile are too
+        config.use_individual_style = false;
+        config.default_style = StrokeStyle::Points;
+        widget.use_style_override(Some(StrokeStyle::Spline), &mut sketch);
+        draw(&mut widget, &mut sketch, &config, &path);
+        assert!(sketch
+            .strokes
+            .values()
+            .all(|stroke| stroke.drawn_style() == StrokeStyle::Spline));
+        assert_eq!(
+            saved(&sketch),
+            vec![
+                StrokeStyle::Circles,
+                StrokeStyle::Points,
+                StrokeStyle::Points
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_moves_skipped() {
+        let config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenDown(pen(TouchPhase::Started, 100., 100.)),
+        );
+        for _ in 0..5 {
+            widget.next(
+                &config,
+                &mut sketch,
+                Event::PenMove(pen(TouchPhase::Moved, 200., 100.)),
+            );
+        }
+        let stroke = sketch.strokes.values().next().unwrap();
+        assert_eq!(stroke.points().len(), 1);
+
+        // moving somewhere else still counts
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenMove(pen(TouchPhase::Moved, 250., 100.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenMove(pen(TouchPhase::Moved, 200., 100.)),
+        );
+        let stroke = sketch.strokes.values().next().unwrap();
+        assert_eq!(stroke.points().len(), 3);
+    }
+
+    #[test]
+    fn cancelled_stroke_discarded() {
+        fn event(with_finger: bool, phase: TouchPhase, x: f64, y: f64) -> Event {
+            match (with_finger, phase) {
+                (false, TouchPhase::Started) => Event::PenDown(pen(phase, x, y)),
+                (false, TouchPhase::Moved) => Event::PenMove(pen(phase, x, y)),
+                (false, _) => Event::PenUp(pen(phase, x, y)),
+                (true, TouchPhase::Started) => Event::Touch(finger(phase, x, y)),
+                (true, TouchPhase::Moved) => Event::TouchMove(finger(phase, x, y)),
+                (true, _) => Event::Release(finger(phase, x, y)),
+            }
+        }
+
+        let mut config = Config::new();
+        config.finger_policy = FingerPolicy::Draw;
+
+        for with_finger in [false, true] {
+            let mut sketch = Sketch::<NullStrokeBackend>::empty();
+            let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+
+            widget.next(
+                &config,
+                &mut sketch,
+                event(with_finger, TouchPhase::Started, 100., 100.),
+            );
+            for i in 1..5 {
+                let x = 100. + i as f64 * 20.;
+                widget.next(
+                    &config,
+                    &mut sketch,
+                    event(with_finger, TouchPhase::Moved, x, 100.),
+                );
+            }
+            assert_eq!(sketch.strokes.len(), 1);
+
+            widget.next(
+                &config,
+                &mut sketch,
+                event(with_finger, TouchPhase::Cancelled, 180., 100.),
+            );
+            assert_eq!(
+                widget.state,
+                SketchWidgetState::Ready,
+                "finger: {with_finger}"
+            );
+            assert!(sketch.strokes.is_empty(), "finger: {with_finger}");
+            assert_eq!(widget.undo_stack.last(), None);
+            assert!(!widget.modified);
+        }
+    }
+
+    #[test]
+    fn finger_policies() {
+        // what a one finger swipe did: how many strokes it drew, and whether it moved the view
+        let swipe = |policy, tool| {
+            let mut config = Config::new();
+            config.finger_policy = policy;
+            let mut sketch = Sketch::<NullStrokeBackend>::empty();
+            let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+            widget.active_tool = tool;
+            let origin = sketch.origin;
+
+            widget.next(
+                &config,
+                &mut sketch,
+                Event::Touch(finger(TouchPhase::Started, 100., 100.)),
+            );
+            for i in 1..5 {
+                widget.next(
+                    &config,
+                    &mut sketch,
+                    Event::TouchMove(finger(TouchPhase::Moved, 100. + i as f64 * 20., 100.)),
+                );
+            }
+            widget.next(
+                &config,
+                &mut sketch,
+                Event::Release(finger(TouchPhase::Ended, 180., 100.)),
+            );
+
+            assert_eq!(widget.state, SketchWidgetState::Ready);
+            // the finger never changes the pen's tool
+            assert_eq!(widget.active_tool, tool);
+            (sketch.strokes.len(), sketch.origin != origin)
+        };
+
+        assert_eq!(swipe(FingerPolicy::UseTool, Tool::Pen), (1, false));
+        assert_eq!(swipe(FingerPolicy::UseTool, Tool::Pan), (0, true));
+        assert_eq!(swipe(FingerPolicy::Draw, Tool::Eraser), (1, false));
+        assert_eq!(swipe(FingerPolicy::Pan, Tool::Pen), (0, true));
+        assert_eq!(swipe(FingerPolicy::Ignore, Tool::Pen), (0, false));
+    }
+
+    #[test]
+    fn palms_ignored_near_pen() {
+        let mut config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        let swipe = |widget: &mut SketchWidget<NullCoords>,
+                     config: &Config,
+                     sketch: &mut Sketch<NullStrokeBackend>| {
+            let strokes = sketch.strokes.len();
+            widget.next(
+                config,
+                sketch,
+                Event::Touch(finger(TouchPhase::Started, 100., 300.)),
+            );
+            for i in 1..5 {
+                widget.next(
+                    config,
+                    sketch,
+                    Event::TouchMove(finger(TouchPhase::Moved, 100. + i as f64 * 20., 300.)),
+                );
+            }
+            widget.next(
+                config,
+                sketch,
+                Event::Release(finger(TouchPhase::Ended, 180., 300.)),
+            );
+            sketch.strokes.len() - strokes
+        };
+
+        // no pen, so it's a finger
+        assert_eq!(swipe(&mut widget, &config, &mut sketch), 1);
+
+        // the hand resting while the pen draws
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenDown(pen(TouchPhase::Started, 100., 100.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenMove(pen(TouchPhase::Moved, 150., 100.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::Touch(finger(TouchPhase::Started, 300., 300.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenMove(pen(TouchPhase::Moved, 200., 100.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenUp(pen(TouchPhase::Ended, 200., 100.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::Release(finger(TouchPhase::Ended, 300., 300.)),
+        );
+        assert_eq!(widget.state, SketchWidgetState::Ready);
+        assert_eq!(sketch.strokes.len(), 2);
+
+        // and just after lifting it
+        assert_eq!(swipe(&mut widget, &config, &mut sketch), 0);
+        assert_eq!(widget.state, SketchWidgetState::Ready);
+
+        widget.last_pen_instant = Some(Instant::now() - Duration::from_secs(1));
+        assert_eq!(swipe(&mut widget, &config, &mut sketch), 1);
+
+        widget.last_pen_instant = Some(Instant::now());
+        config.palm_rejection_ms = 0.;
+        assert_eq!(swipe(&mut widget, &config, &mut sketch), 1);
+    }
+
+    #[test]
+    fn idle_stroke_finished() {
+        let mut config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let later = || std::time::Instant::now() + std::time::Duration::from_secs(60);
+
+        // the pen is lifted without an up event
+        let lift = |widget: &mut SketchWidget<NullCoords>,
+                    config: &Config,
+                    sketch: &mut Sketch<NullStrokeBackend>| {
+            widget.next(
+                config,
+                sketch,
+                Event::PenDown(pen(TouchPhase::Started, 100., 100.)),
+            );
+            widget.next(
+                config,
+                sketch,
+                Event::PenMove(pen(TouchPhase::Moved, 200., 100.)),
+            );
+            assert_eq!(widget.state, SketchWidgetState::PenDraw);
+            widget.finish_idle_stroke(sketch, later())
+        };
+
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        // off by default
+        assert!(!lift(&mut widget, &config, &mut sketch));
+        assert!(!sketch.strokes.values().next().unwrap().done);
+
+        // stuck
+        assert_eq!(widget.state, SketchWidgetState::PenDraw);
+
+        sketch = Sketch::empty();
+        widget = SketchWidget::headless(800, 600);
+        config.stroke_idle_ms = 500.;
+        assert!(!widget.finish_idle_stroke(&mut sketch, std::time::Instant::now()));
+        assert!(lift(&mut widget, &config, &mut sketch));
+        assert_eq!(widget.state, SketchWidgetState::Ready);
+        assert_eq!(sketch.strokes.len(), 1);
+        assert!(sketch.strokes.values().next().unwrap().done);
+        assert!(widget.idle.deadline().is_none());
+
+        // the pen coming back starts a new stroke
+        draw(
+            &mut widget,
+            &mut sketch,
+            &config,
+            &[(300., 100.), (300., 150.)],
+        );
+        assert_eq!(sketch.strokes.len(), 2);
+        assert!(!widget.finish_idle_stroke(&mut sketch, later()));
+    }
+
+    #[test]
+    fn drawing_keeps_index() {
+        let config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenDown(pen(TouchPhase::Started, 100., 100.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::PenMove(pen(TouchPhase::Moved, 120., 100.)),
+        );
+        sketch.update_visible_strokes::<NullCoords>(800, 600);
+        assert!(sketch.index.is_fresh());
+
+        // every point doesn't build the index again
+        for i in 2..30 {
+            widget.next(
+                &config,
+                &mut sketch,
+                Event::PenMove(pen(TouchPhase::Moved, 100. + i as f64 * 20., 300.)),
+            );
+            assert!(sketch.index.is_fresh());
+        }
+        assert_eq!(sketch.visible_strokes().count(), 1);
+
+        let key = sketch.strokes.keys().next().unwrap();
+        let end = NullCoords::pixel_to_pos(
+            800,
+            600,
+            sketch.zoom,
+            sketch.origin,
+            PixelPos { x: 680., y: 300. },
+        );
+        assert_eq!(sketch.strokes_near(end, end), vec![key]);
+        assert!(sketch.index.is_fresh());
+    }
+
+    #[test]
+    fn lazy_brush_trails_cursor() {
+        let mut config = Config::new();
+        let trail = |config: &Config| {
+            let mut sketch = Sketch::<NullStrokeBackend>::empty();
+            let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+            widget.next(
+                config,
+                &mut sketch,
+                Event::PenDown(pen(TouchPhase::Started, 100., 100.)),
+            );
+
+            // shaking in place, then a wobbly line to the right
+            let path = [(105., 95.), (95., 105.), (110., 100.)]
+                .into_iter()
+                .chain((1..=10).map(|i| (100. + i as f64 * 20., 100. + (i % 2) as f64 * 10.)));
+            // how far each move's new point is from the pen, if it made one
+            let (mut points, mut added) = (0, Vec::new());
+            for (x, y) in path {
+                widget.next(
+                    config,
+                    &mut sketch,
+                    Event::PenMove(pen(TouchPhase::Moved, x, y)),
+                );
+                let stroke = sketch.strokes.values().next().unwrap();
+                if stroke.points().len() > points {
+                    let pixel = NullCoords::pos_to_pixel(
+                        widget.width,
+                        widget.height,
+                        sketch.zoom,
+                        sketch.origin,
+                        stroke.points()[points].into(),
+                    );
+                    let distance = (pixel.x - x as f32).hypot(pixel.y - y as f32);
+                    added.push(distance);
+                    points += 1;
+                } else {
+                    added.push(f32::NAN);
+                }
+            }
+            added
+        };
+
+        // without it every point is right under the pen
+        let plain = trail(&config);
+        assert!(plain.iter().all(|distance| *distance < 0.01));
+
+        config.lazy_brush_radius = 20.;
+        let lazy = trail(&config);
+        assert!(lazy[..3].iter().all(|distance| distance.is_nan()));
+        assert!(lazy[3..].iter().any(|distance| !distance.is_nan()));
+        assert!(lazy
+            .iter()
+            .filter(|distance| !distance.is_nan())
+            .all(|distance| (distance - 20.).abs() < 0.01));
+    }
+
+    #[test]
+    fn straightened_when_finished() {
+        let mut config = Config::new();
+        let straighten = |config: &Config, wobble: f64| {
+            let mut sketch = Sketch::<NullStrokeBackend>::empty();
+            let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+            let path = std::iter::once((100., 100.))
+                .chain((0..=10).map(|i| {
+                    let y = 100. + if i % 2 == 0 { wobble } else { -wobble };
+                    (100. + i as f64 * 40., y)
+                }))
+                .collect::<Vec<_>>();
+            draw(&mut widget, &mut sketch, config, &path);
+            sketch.strokes.values().next().unwrap().points().len()
+        };
+
+        // off by default
+        assert!(straighten(&config, 3.) > 2);
+
+        config.auto_straighten = true;
+        assert_eq!(straighten(&config, 3.), 2);
+        assert!(straighten(&config, 60.) > 2);
+    }
+
+    #[test]
+    fn finer_meshes_zoomed_in() {
+        let config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::new(vec![Stroke::with_points(
+            (0..40)
+                .map(|i| {
+                    let angle = i as f32 / 10.;
+                    StrokeElement {
+                        x: angle.cos() * 2.,
+                        y: angle.sin() * 2.,
+                        pressure: 1.,
+                    }
+                })
+                .collect(),
+            Color::WHITE,
+        )]);
+        sketch
+            .strokes
+            .values_mut()
+            .for_each(|stroke| stroke.brush_size = 0.2);
+        let vertices = |sketch: &Sketch<NullStrokeBackend>| {
+            sketch.strokes.values().next().unwrap().vertices().count()
+        };
+
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        sketch.update_zoom::<NullCoords>(800, 600, 4.);
+        assert!(widget.follow_zoom(&config, &mut sketch));
+        let zoomed_out = vertices(&sketch);
+
+        // nothing to do until the zoom changes
+        assert!(!widget.follow_zoom(&config, &mut sketch));
+
+        sketch.update_zoom::<NullCoords>(800, 600, 300.);
+        assert!(widget.follow_zoom(&config, &mut sketch));
+        let zoomed_in = vertices(&sketch);
+        assert!(zoomed_in > zoomed_out);
+
+        // or only a little, without leaving the level
+        sketch.update_zoom::<NullCoords>(800, 600, 310.);
+        assert!(!widget.follow_zoom(&config, &mut sketch));
+        assert_eq!(vertices(&sketch), zoomed_in);
+
+        // events that zoom do it on their own
+        widget.next(&config, &mut sketch, Event::ScrollZoom(4. - 310.));
+        assert_eq!(sketch.zoom, 4.);
+        assert_eq!(vertices(&sketch), zoomed_out);
+    }
+
+    #[test]
+    fn eyedropper_picks_color() {
+        let config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+
+        let red = [1., 0., 0.];
+        sketch.fg_color = red;
+        draw(
+            &mut widget,
+            &mut sketch,
+            &config,
+            &[(100., 100.), (150., 100.), (200., 100.)],
+        );
+
+        let blue = [0., 0., 1.];
+        sketch.fg_color = blue;
+        widget.active_tool = Tool::Eyedropper;
+        let tap = |widget: &mut SketchWidget<NullCoords>,
+                   sketch: &mut Sketch<NullStrokeBackend>,
+                   x,
+                   y| {
+            draw(widget, sketch, &config, &[(x, y)]);
+            assert_eq!(widget.state, SketchWidgetState::Ready);
+        };
+
+        // nothing there
+        tap(&mut widget, &mut sketch, 500., 400.);
+        assert_eq!(sketch.fg_color, blue);
+
+        tap(&mut widget, &mut sketch, 150., 100.);
+        assert_eq!(sketch.fg_color, red);
+        assert_eq!(sketch.strokes.len(), 1);
+    }
+
+    #[test]
+    fn replay_reconstructs_sketch() {
+        let config = Config::new();
+        let mut log = Vec::new();
+        for stroke in 0..3 {
+            let y = 100. + stroke as f64 * 50.;
+            log.push(Event::PenDown(pen(TouchPhase::Started, 100., y)));
+            for i in 1..10 {
+                log.push(Event::PenMove(pen(
+                    TouchPhase::Moved,
+                    100. + i as f64 * 15.,
+                    y + (i as f64).sin() * 10.,
+                )));
+            }
+            log.push(Event::PenUp(pen(
+                if stroke == 1 {
+                    TouchPhase::Cancelled
+                } else {
+                    TouchPhase::Ended
+                },
+                250.,
+                y,
+            )));
+        }
+        log.push(Event::ScrollZoom(5.));
+
+        let session = |log: &[Event]| {
+            let mut sketch = Sketch::<NullStrokeBackend>::empty();
+            let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+            let effects = log
+                .iter()
+                .map(|event| widget.apply(&config, &mut sketch, *event))
+                .collect::<Vec<_>>();
+            (sketch, effects)
+        };
+
+        let (sketch, effects) = session(&log);
+        assert_eq!(sketch.strokes.len(), 2);
+        assert!(matches!(
+            effects[0][..],
+            [Effect::StrokeAdded(_), Effect::Redraw]
+        ));
+        assert!(matches!(
+            effects[1][..],
+            [Effect::StrokeChanged(_), Effect::Redraw]
+        ));
+        // the cancelled stroke
+        assert!(matches!(
+            effects[21][..],
+            [Effect::StrokeRemoved(_), Effect::Redraw]
+        ));
+        assert_eq!(
+            effects.last().unwrap(),
+            &vec![Effect::ViewChanged, Effect::Redraw]
+        );
+
+        let (replayed, replayed_effects) = session(&log);
+        assert_eq!(replayed.content_hash(), sketch.content_hash());
+        assert_eq!(replayed_effects, effects);
+
+        // and a different session gives a different sketch
+        let (shorter, _) = session(&log[..11]);
+        assert_ne!(shorter.content_hash(), sketch.content_hash());
+    }
+
+    // what the tests of the old State checked, now that Sketch and SketchWidget are all there is
+    #[test]
+    fn one_model() {
+        let config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        widget.force_update(&mut sketch);
+
+        let line = |y| {
+            (0..=10)
+                .map(|i| (100. + i as f64 * 20., y))
+                .collect::<Vec<_>>()
+        };
+
+        // undo
+        draw(&mut widget, &mut sketch, &config, &line(100.));
+        draw(&mut widget, &mut sketch, &config, &line(200.));
+        assert_eq!(sketch.visible_strokes().count(), 2);
+        widget.undo(&mut sketch);
+        assert_eq!(sketch.visible_strokes().count(), 1);
+        assert_eq!(sketch.strokes.values().filter(|s| s.erased).count(), 1);
+        widget.redo(&mut sketch);
+        assert_eq!(sketch.visible_strokes().count(), 2);
+
+        // clear
+        sketch.clear_strokes();
+        assert!(sketch.strokes.is_empty());
+        assert_eq!(sketch.visible_strokes().count(), 0);
+
+        // brush clamping
+        widget.next(&config, &mut sketch, Event::IncreaseBrush(100));
+        assert_eq!(widget.brush_size, crate::MAX_BRUSH);
+        widget.next(&config, &mut sketch, Event::DecreaseBrush(100));
+        assert_eq!(widget.brush_size, crate::MIN_BRUSH);
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::IncreaseBrush(crate::BRUSH_DELTA),
+        );
+        assert_eq!(widget.brush_size, crate::MIN_BRUSH + crate::BRUSH_DELTA);
+
+        // zoom clamping
+        sketch.update_zoom::<NullCoords>(800, 600, crate::MAX_ZOOM * 2.);
+        assert_eq!(sketch.zoom, crate::MAX_ZOOM);
+        sketch.update_zoom::<NullCoords>(800, 600, 0.);
+        assert_eq!(sketch.zoom, crate::MIN_ZOOM);
+
+        // move_origin
+        let origin = sketch.origin;
+        sketch.move_origin::<NullCoords>(
+            800,
+            600,
+            StrokePos { x: 1., y: 2. },
+            StrokePos { x: 4., y: -2. },
+        );
+        assert_eq!(sketch.origin.x, origin.x + 3.);
+        assert_eq!(sketch.origin.y, origin.y - 4.);
+    }
+
+    #[test]
+    fn undo_redo_keys() {
+        let mut config = Config::new();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let line = |y| [(100., y), (200., y), (300., y)];
+        let drawn = |sketch: &Sketch<_>| sketch.strokes.values().filter(|s| !s.erased).count();
+        let (undo, redo) = (
+            [Keycode::LControl, Keycode::Z],
+            [Keycode::LControl, Keycode::Y],
+        );
+
+        draw(&mut widget, &mut sketch, &config, &line(100.));
+        draw(&mut widget, &mut sketch, &config, &line(200.));
+        let keys = sketch.strokes.keys().collect::<Vec<_>>();
+
+        press(&mut widget, &mut sketch, &mut config, &undo);
+        assert_eq!(drawn(&sketch), 1);
+        press(&mut widget, &mut sketch, &mut config, &redo);
+        assert_eq!(drawn(&sketch), 2);
+        press(
+            &mut widget,
+            &mut sketch,
+            &mut config,
+            &[Keycode::LControl, Keycode::LShift, Keycode::Z],
+        );
+        assert_eq!(drawn(&sketch), 2);
+
+        // strokes come back as themselves, under the same keys
+        press(&mut widget, &mut sketch, &mut config, &undo);
+        press(&mut widget, &mut sketch, &mut config, &undo);
+        assert_eq!(drawn(&sketch), 0);
+        press(&mut widget, &mut sketch, &mut config, &redo);
+        assert_eq!(sketch.strokes.keys().collect::<Vec<_>>(), keys);
+        assert!(!sketch.strokes[keys[0]].erased && sketch.strokes[keys[1]].erased);
+
+        // drawing something new means there's nothing to redo
+        draw(&mut widget, &mut sketch, &config, &line(300.));
+        press(&mut widget, &mut sketch, &mut config, &redo);
+        assert!(sketch.strokes[keys[1]].erased);
+        assert_eq!(drawn(&sketch), 2);
+    }
+
+    #[test]
+    fn restyle_selection() {
+        let mut config = Config::new();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        for y in [100., 200.] {
+            draw(&mut widget, &mut sketch, &config, &[(100., y), (300., y)]);
+        }
+        let keys = sketch.strokes.keys().collect::<Vec<_>>();
+        let (size, color) = (sketch.strokes[keys[0]].brush_size, sketch.fg_color);
+
+        // nothing selected only changes the brush
+        press(&mut widget, &mut sketch, &mut config, &[Keycode::RBracket]);
+        assert_eq!(sketch.strokes[keys[0]].brush_size, size);
+        let undo = widget.undo_stack.last();
+
+        sketch.selection = vec![keys[0]];
+        widget.modified = false;
+        press(&mut widget, &mut sketch, &mut config, &[Keycode::RBracket]);
+        let big = sketch.strokes[keys[0]].brush_size;
+        press(&mut widget, &mut sketch, &mut config, &[Keycode::RBracket]);
+        let bigger = sketch.strokes[keys[0]].brush_size;
+        assert!(bigger > big && big > size);
+        assert_eq!(sketch.strokes[keys[1]].brush_size, size);
+        assert!(widget.modified);
+
+        sketch.fg_color = Color::NICE_RED;
+        press(
+            &mut widget,
+            &mut sketch,
+            &mut config,
+            &[Keycode::LAlt, Keycode::C],
+        );
+        assert_eq!(sketch.strokes[keys[0]].color, Color::NICE_RED);
+        assert_eq!(sketch.strokes[keys[1]].color, color);
+
+        // pressing the brush key twice is two undos
+        let undo_keys = [Keycode::LControl, Keycode::Z];
+        press(&mut widget, &mut sketch, &mut config, &undo_keys);
+        assert_eq!(sketch.strokes[keys[0]].color, color);
+        assert_eq!(sketch.strokes[keys[0]].brush_size, bigger);
+        press(&mut widget, &mut sketch, &mut config, &undo_keys);
+        assert_eq!(sketch.strokes[keys[0]].brush_size, big);
+        press(&mut widget, &mut sketch, &mut config, &undo_keys);
+        assert_eq!(sketch.strokes[keys[0]].brush_size, size);
+        assert_eq!(widget.undo_stack.last(), undo);
+
+        // and holding it is one
+        widget.handle_key(
+            &mut config,
+            &mut sketch,
+            Keycode::RBracket,
+            ElementState::Pressed,
+        );
+        for _ in 0..3 {
+            widget.handle_key(
+                &mut config,
+                &mut sketch,
+                Keycode::RBracket,
+                ElementState::Pressed,
+            );
+        }
+        widget.handle_key(
+            &mut config,
+            &mut sketch,
+            Keycode::RBracket,
+            ElementState::Released,
+        );
+        assert!(sketch.strokes[keys[0]].brush_size > bigger);
+        press(&mut widget, &mut sketch, &mut config, &undo_keys);
+        assert_eq!(sketch.strokes[keys[0]].brush_size, size);
+        assert_eq!(widget.undo_stack.last(), undo);
+    }
+
+    #[test]
+    fn two_finger_tap_undoes() {
+        let config = Config::new();
+        let touch = |phase, id, x| Touch {
+            id,
+            ..finger(phase, x, 300.)
+        };
+        let tap = |widget: &mut SketchWidget<NullCoords>, sketch: &mut Sketch<_>, drag: f64| {
+            widget.next(
+                &config,
+                sketch,
+                Event::Touch(touch(TouchPhase::Started, 1, 100.)),
+            );
+            // the first finger gets a stroke going before the second one lands
+            widget.next(
+                &config,
+                sketch,
+                Event::TouchMove(touch(TouchPhase::Moved, 1, 104.)),
+            );
+            widget.next(
+                &config,
+                sketch,
+                Event::Touch(touch(TouchPhase::Started, 2, 200.)),
+            );
+            widget.next(
+                &config,
+                sketch,
+                Event::TouchMove(touch(TouchPhase::Moved, 2, 200. + drag)),
+            );
+            widget.next(
+                &config,
+                sketch,
+                Event::Release(touch(TouchPhase::Ended, 1, 104.)),
+            );
+            widget.next(
+                &config,
+                sketch,
+                Event::Release(touch(TouchPhase::Ended, 2, 200. + drag)),
+            );
+            assert_eq!(widget.state, SketchWidgetState::Ready);
+        };
+
+        // nothing to undo, and the first finger's stroke is gone
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        tap(&mut widget, &mut sketch, 0.);
+        assert!(sketch.strokes.is_empty());
+        assert_eq!(widget.undo_stack.last(), None);
+        assert!(!widget.modified);
+
+        // the last stroke that was really drawn is undone, and nothing else
+        for y in [100., 200.] {
+            draw(&mut widget, &mut sketch, &config, &[(100., y), (300., y)]);
+        }
+        let keys = sketch.strokes.keys().collect::<Vec<_>>();
+        // long enough after the pen that the fingers aren't a palm
+        widget.last_pen_instant = Some(Instant::now() - Duration::from_secs(1));
+        tap(&mut widget, &mut sketch, 0.);
+        assert_eq!(sketch.strokes.keys().collect::<Vec<_>>(), keys);
+        assert!(!sketch.strokes[keys[0]].erased);
+        assert!(sketch.strokes[keys[1]].erased);
+        assert_eq!(widget.undo_stack.last(), Some(Action::DrawStroke(keys[0])));
+
+        // dragging is a pan, not a tap
+        let origin = sketch.origin;
+        tap(&mut widget, &mut sketch, 100.);
+        assert_ne!(sketch.origin, origin);
+        assert!(!sketch.strokes[keys[0]].erased);
+        assert_eq!(sketch.strokes.len(), 2);
+    }
+
+    #[test]
+    fn pinch_zoom() {
+        let config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        let touch = |phase, id, x| Touch {
+            id,
+            ..finger(phase, x, 300.)
+        };
+        let under = |widget: &SketchWidget<NullCoords>, sketch: &Sketch<_>, x| {
+            NullCoords::pixel_to_pos(
+                widget.width,
+                widget.height,
+                sketch.zoom,
+                sketch.origin,
+                PixelPos { x, y: 300. },
+            )
+        };
+        let close =
+            |a: StrokePos, b: StrokePos| (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3;
+
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::Touch(touch(TouchPhase::Started, 1, 300.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::TouchMove(touch(TouchPhase::Moved, 1, 304.)),
+        );
+        let (zoom, origin) = (sketch.zoom, sketch.origin);
+        let left = under(&widget, &sketch, 304.);
+        let right = under(&widget, &sketch, 500.);
+
+        // the second finger landing doesn't move the view
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::Touch(touch(TouchPhase::Started, 2, 500.)),
+        );
+        assert_eq!((sketch.zoom, sketch.origin), (zoom, origin));
+        assert!(sketch.strokes.is_empty());
+
+        // spreading them zooms in, keeping what was under each finger there
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::TouchMove(touch(TouchPhase::Moved, 2, 598.)),
+        );
+        assert!((sketch.zoom / zoom - 1.5).abs() < 1e-4);
+        assert!(close(under(&widget, &sketch, 304.), left));
+        assert!(close(under(&widget, &sketch, 598.), right));
+
+        // the finger left behind pans without jumping
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::Release(touch(TouchPhase::Ended, 2, 598.)),
+        );
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::TouchMove(touch(TouchPhase::Moved, 1, 354.)),
+        );
+        assert!((sketch.zoom / zoom - 1.5).abs() < 1e-4);
+        assert!(close(under(&widget, &sketch, 354.), left));
+
+        widget.next(
+            &config,
+            &mut sketch,
+            Event::Release(touch(TouchPhase::Ended, 1, 354.)),
+        );
+        assert_eq!(widget.state, SketchWidgetState::Ready);
+        assert_eq!(widget.pinch.fingers(), 0);
+    }
+
+    #[test]
+    fn taps_discarded_when_movement_required() {
+        let mut config = Config::new();
+        let stroke = |config: &Config, to: f64| {
+            let mut sketch = Sketch::<NullStrokeBackend>::empty();
+            let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+            draw(
+                &mut widget,
+                &mut sketch,
+                config,
+                &[(100., 100.), (to, 100.)],
+            );
+            assert_eq!(widget.state, SketchWidgetState::Ready);
+            (sketch.strokes.len(), widget.undo_stack.last().is_some())
+        };
+
+        // taps draw by default
+        assert_eq!(stroke(&config, 100.), (1, true));
+
+        config.require_movement_to_commit = true;
+        assert_eq!(stroke(&config, 100.), (0, false));
+        assert_eq!(stroke(&config, 101.), (0, false));
+        assert_eq!(stroke(&config, 200.), (1, true));
+
+        config.commit_movement_px = 200.;
+        assert_eq!(stroke(&config, 200.), (0, false));
+    }
+
+    #[test]
+    fn mouse_taps_discarded_when_movement_required() {
+        let mut config = Config::new();
+        config.require_movement_to_commit = true;
+        let stroke = |to: f32| {
+            let mut sketch = Sketch::<NullStrokeBackend>::empty();
+            let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+            widget.next(
+                &config,
+                &mut sketch,
+                Event::MouseMove(PixelPos { x: 100., y: 100. }),
+            );
+            widget.next(&config, &mut sketch, Event::MouseDown(MouseButton::Left));
+            widget.next(
+                &config,
+                &mut sketch,
+                Event::MouseMove(PixelPos { x: to, y: 100. }),
+            );
+            widget.next(&config, &mut sketch, Event::MouseUp(MouseButton::Left));
+            assert_eq!(widget.state, SketchWidgetState::Ready);
+            (sketch.strokes.len(), widget.undo_stack.last().is_some())
+        };
+
+        assert_eq!(stroke(100.), (0, false));
+        assert_eq!(stroke(200.), (1, true));
+    }
+
+    #[test]
+    fn select_tool() {
+        let mut config = Config::new();
+        let mut sketch = Sketch::<NullStrokeBackend>::empty();
+        let mut widget = SketchWidget::<NullCoords>::headless(800, 600);
+        let drag = |widget: &mut SketchWidget<NullCoords>,
+                    config: &Config,
+                    sketch: &mut Sketch<NullStrokeBackend>,
+                    path: &[(f64, f64)]| {
+            draw(widget, sketch, config, path);
+            assert_eq!(widget.state, SketchWidgetState::Ready);
+        };
+
+        drag(
+            &mut widget,
+            &config,
+            &mut sketch,
+            &[(100., 100.), (200., 100.), (300., 100.)],
+        );
+        drag(
+            &mut widget,
+            &config,
+            &mut sketch,
+            &[(100., 300.), (200., 300.), (300., 300.)],
+        );
+        let keys = sketch.strokes.keys().collect::<Vec<_>>();
+
+        widget.active_tool = Tool::Select;
+        drag(
+            &mut widget,
+            &config,
+            &mut sketch,
+            &[(50., 50.), (400., 200.), (350., 150.)],
+        );
+        assert_eq!(sketch.selection, vec![keys[0]]);
+        assert!(widget.selection_path.is_empty());
+
+        // only partly inside doesn't count
+        drag(
+            &mut widget,
+            &config,
+            &mut sketch,
+            &[(50., 50.), (150., 350.)],
+        );
+        assert!(sketch.selection.is_empty());
+
+        config.select_mode = SelectMode::Lasso;
+        let around_second = [
+            (50., 250.),
+            (350., 250.),
+            (350., 350.),
+            (50., 350.),
+            (50., 260.),
+        ];
+        drag(&mut widget, &config, &mut sketch, &around_second);
+        assert_eq!(sketch.selection, vec![keys[1]]);
+
+        // nothing drawn
+        assert_eq!(sketch.strokes.len(), 2);
+
+        sketch.strokes[keys[1]].locked = true;
+        drag(&mut widget, &config, &mut sketch, &around_second);
+        assert!(sketch.selection.is_empty());
+    }
+}